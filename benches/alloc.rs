@@ -0,0 +1,69 @@
+//! Counts heap allocations per simulated path. Run with
+//! `cargo bench --features bench --bench alloc`.
+
+use alloy_primitives::U256;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use wayfinder::{
+    Engine, Scanner, TokenId,
+    synth::{SyntheticConfig, Topology, generate, random_plans},
+};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn count<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn main() {
+    let market = generate(&SyntheticConfig {
+        tokens: 30,
+        pools: 120,
+        topology: Topology::HubAndSpoke { hubs: 3 },
+        ..SyntheticConfig::default()
+    });
+    let engine = Engine::new(&market.pools);
+    let amount = U256::from(10u64).pow(U256::from(18u64));
+
+    for hops in [1, 2, 3, 4, 5] {
+        let plans = random_plans(&market, 256, hops, 42);
+        let allocs = count(|| {
+            for plan in &plans {
+                black_box(engine.simulate_chained(&market.world, plan, amount));
+            }
+        });
+        println!(
+            "simulate_chained/{hops}: {:.2} allocations per path",
+            allocs as f64 / plans.len() as f64
+        );
+    }
+
+    let cycles = Scanner::new(&engine, &market.graph).cycles(&market.world, TokenId(0));
+    let allocs = count(|| {
+        black_box(engine.simulate_many(&market.world, &cycles, amount));
+    });
+    println!(
+        "simulate_many/{} cycles: {:.2} allocations per path",
+        cycles.len(),
+        allocs as f64 / cycles.len().max(1) as f64
+    );
+}
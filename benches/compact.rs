@@ -0,0 +1,87 @@
+//! Snapshot size and codec speed for a V3-heavy world. Run with
+//! `cargo bench --features bench,rpc,zstd --bench compact`.
+
+use alloy_primitives::U256;
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+use wayfinder::{
+    PoolId, World,
+    compact::Compression,
+    rng::SplitMix64,
+    v3storage::{TickInfo, TickMap},
+};
+
+const POOLS: u64 = 500;
+const TICKS_PER_POOL: i32 = 800;
+
+fn tick_world() -> World<TickMap> {
+    let mut rng = SplitMix64::new(7);
+    let mut world = World::default();
+    for pid in 0..POOLS {
+        let spacing = [1, 10, 60, 200][(pid % 4) as usize];
+        let mut tick = -(TICKS_PER_POOL / 2) * spacing;
+        let ticks = (0..TICKS_PER_POOL)
+            .map(|_| {
+                tick += spacing * (1 + rng.below(3) as i32);
+                let liquidity = u128::from(rng.below(1 << 40));
+                let info = TickInfo {
+                    liquidity_gross: liquidity,
+                    liquidity_net: if rng.below(2) == 0 {
+                        liquidity as i128
+                    } else {
+                        -(liquidity as i128)
+                    },
+                };
+                (tick, info)
+            })
+            .collect();
+        world.set_pool_state(
+            PoolId(pid),
+            TickMap {
+                tick_spacing: spacing,
+                sqrt_price_x96: U256::from(rng.next_u64()) << 64,
+                tick: 0,
+                liquidity: u128::from(rng.next_u64()),
+                ticks,
+                words: (-4, 4),
+            },
+        );
+    }
+    world
+}
+
+fn snapshot(c: &mut Criterion) {
+    let world = tick_world();
+    // i32 tick plus two 16-byte liquidities, before any map overhead.
+    let fixed_width = POOLS as usize * TICKS_PER_POOL as usize * 36;
+
+    let mut compressions = vec![("plain", Compression::None)];
+    #[cfg(feature = "zstd")]
+    compressions.push(("zstd", Compression::Zstd(3)));
+
+    let mut group = c.benchmark_group("compact_snapshot");
+    for (name, compression) in compressions {
+        let mut buf = Vec::new();
+        world.write_compact(&mut buf, 0, compression).unwrap();
+        println!(
+            "{name}: {} bytes ({:.1}% of fixed-width ticks)",
+            buf.len(),
+            buf.len() as f64 * 100.0 / fixed_width as f64
+        );
+        group.throughput(Throughput::Bytes(buf.len() as u64));
+        group.bench_function(format!("write_{name}"), |b| {
+            b.iter(|| {
+                let mut out = Vec::with_capacity(buf.len());
+                world.write_compact(&mut out, 0, compression).unwrap();
+                black_box(out)
+            })
+        });
+        group.bench_function(format!("read_{name}"), |b| {
+            b.iter(|| black_box(World::<TickMap>::read_compact(&buf[..]).unwrap()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, snapshot);
+criterion_main!(benches);
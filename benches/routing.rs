@@ -0,0 +1,131 @@
+use alloy_primitives::U256;
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+use wayfinder::{
+    Engine, Router, Scanner, TokenId,
+    synth::{SyntheticConfig, Topology, generate, random_plans},
+    trie::PlanTrie,
+};
+
+fn simulate_chained(c: &mut Criterion) {
+    let market = generate(&SyntheticConfig::default());
+    let engine = Engine::new(&market.pools);
+    let amount = U256::from(10u64).pow(U256::from(18u64));
+
+    let mut group = c.benchmark_group("simulate_chained");
+    for hops in [1, 2, 3, 4] {
+        let plans = random_plans(&market, 256, hops, 42);
+        group.throughput(Throughput::Elements(plans.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(hops), &plans, |b, plans| {
+            b.iter(|| {
+                for plan in plans {
+                    black_box(engine.simulate_chained(&market.world, plan, amount));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn cycle_enumeration(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cycle_enumeration");
+    for (name, topology) in [
+        ("random", Topology::Random),
+        ("hub_and_spoke", Topology::HubAndSpoke { hubs: 4 }),
+    ] {
+        let market = generate(&SyntheticConfig {
+            tokens: 60,
+            pools: 240,
+            topology,
+            ..SyntheticConfig::default()
+        });
+        let engine = Engine::new(&market.pools);
+        let scanner = Scanner::new(&engine, &market.graph);
+        group.bench_function(name, |b| {
+            b.iter(|| black_box(scanner.cycles(&market.world, TokenId(0))))
+        });
+    }
+    group.finish();
+}
+
+fn simulate_many(c: &mut Criterion) {
+    let market = generate(&SyntheticConfig {
+        tokens: 30,
+        pools: 120,
+        topology: Topology::HubAndSpoke { hubs: 3 },
+        ..SyntheticConfig::default()
+    });
+    let engine = Engine::new(&market.pools);
+    let plans = Scanner::new(&engine, &market.graph).cycles(&market.world, TokenId(0));
+    let amount = U256::from(10u64).pow(U256::from(18u64));
+
+    let mut group = c.benchmark_group("simulate_many");
+    group.throughput(Throughput::Elements(plans.len() as u64));
+    group.bench_function("independent", |b| {
+        b.iter(|| {
+            for plan in &plans {
+                black_box(engine.simulate_chained(&market.world, plan, amount));
+            }
+        })
+    });
+    group.bench_function("trie", |b| {
+        b.iter(|| black_box(engine.simulate_many(&market.world, &plans, amount)))
+    });
+    let trie = PlanTrie::new(&plans);
+    group.bench_function("trie_prebuilt", |b| {
+        b.iter(|| black_box(engine.simulate_trie(&market.world, &trie, amount)))
+    });
+    group.finish();
+}
+
+fn first_quote(c: &mut Criterion) {
+    let market = generate(&SyntheticConfig {
+        tokens: 30,
+        pools: 120,
+        topology: Topology::HubAndSpoke { hubs: 3 },
+        ..SyntheticConfig::default()
+    });
+    let router = || {
+        Router::new(
+            market.registry.clone(),
+            market.graph.clone(),
+            market.pools.clone(),
+            market.world.clone(),
+        )
+    };
+    let mut warm = router();
+    warm.warm_up();
+    let connectors = warm.index().expect("warmed").connectors.clone();
+    let (a, b) = (connectors[0], connectors[1]);
+    let amount = warm.whole_token(a);
+
+    let mut group = c.benchmark_group("first_quote");
+    group.bench_function("cold", |bench| {
+        bench.iter_batched(
+            router,
+            |r| black_box(r.quote(a, b, amount)),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("warmed", |bench| {
+        bench.iter_batched(
+            || {
+                let mut r = router();
+                r.warm_up();
+                r
+            },
+            |r| black_box(r.quote(a, b, amount)),
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    simulate_chained,
+    cycle_enumeration,
+    simulate_many,
+    first_quote
+);
+criterion_main!(benches);
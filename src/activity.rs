@@ -0,0 +1,194 @@
+//! Recent trading per pool: when it last swapped and how much it moved over
+//! a rolling window of blocks. Pools nobody trades are the likeliest to have
+//! cached state the chain has moved past, so searches can leave them out.
+
+use crate::{
+    decode::{Coin, StateUpdate},
+    graph::AMMGraph,
+    ids::PoolId,
+};
+use alloy_primitives::U256;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoolActivity {
+    pub last_swap_block: u64,
+    pub swaps: u64,
+    /// Per-block volume, oldest first, within the tracker's window.
+    blocks: VecDeque<(u64, [U256; 2])>,
+}
+
+impl PoolActivity {
+    /// Token0 and token1 volume across the window.
+    pub fn volume(&self) -> [U256; 2] {
+        self.blocks.iter().fold([U256::ZERO; 2], |[a, b], (_, v)| {
+            [a.saturating_add(v[0]), b.saturating_add(v[1])]
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ActivityTracker {
+    /// Blocks of volume kept per pool.
+    pub window: u64,
+    pools: HashMap<PoolId, PoolActivity>,
+}
+
+impl ActivityTracker {
+    pub fn new(window: u64) -> Self {
+        Self {
+            window: window.max(1),
+            pools: HashMap::new(),
+        }
+    }
+
+    /// Records a swap moving `volume` of token0 and token1 at `block`.
+    pub fn record_swap(&mut self, pid: PoolId, block: u64, volume: [U256; 2]) {
+        let window = self.window;
+        let a = self.pools.entry(pid).or_default();
+        a.last_swap_block = a.last_swap_block.max(block);
+        a.swaps += 1;
+        match a.blocks.back_mut() {
+            Some((b, v)) if *b == block => {
+                v[0] = v[0].saturating_add(volume[0]);
+                v[1] = v[1].saturating_add(volume[1]);
+            }
+            _ => a.blocks.push_back((block, volume)),
+        }
+        let oldest = a.last_swap_block.saturating_sub(window - 1);
+        while a.blocks.front().is_some_and(|(b, _)| *b < oldest) {
+            a.blocks.pop_front();
+        }
+    }
+
+    /// Records the swaps among decoded `updates` from `block`. Volume is
+    /// counted on the input side; Curve and Balancer exchanges, whose coins
+    /// are not ordered by pair, count toward token0.
+    pub fn record_updates<'u>(
+        &mut self,
+        block: u64,
+        updates: impl IntoIterator<Item = &'u (PoolId, StateUpdate)>,
+    ) {
+        for &(pid, update) in updates {
+            let volume = match update {
+                StateUpdate::Swap { amount0, amount1 }
+                | StateUpdate::V3Swap {
+                    amount0, amount1, ..
+                } => [
+                    amount0.max(Default::default()).into_raw(),
+                    amount1.max(Default::default()).into_raw(),
+                ],
+                StateUpdate::Exchange {
+                    sold, amount_in, ..
+                } => match sold {
+                    Coin::Index(1) => [U256::ZERO, amount_in],
+                    _ => [amount_in, U256::ZERO],
+                },
+                _ => continue,
+            };
+            self.record_swap(pid, block, volume);
+        }
+    }
+
+    pub fn get(&self, pid: PoolId) -> Option<&PoolActivity> {
+        self.pools.get(&pid)
+    }
+
+    /// Blocks since `pid` last swapped, or `None` if it was never seen to.
+    pub fn idle_blocks(&self, pid: PoolId, block: u64) -> Option<u64> {
+        self.get(pid)
+            .map(|a| block.saturating_sub(a.last_swap_block))
+    }
+
+    /// Whether `pid` swapped within `max_idle` blocks of `block`.
+    pub fn is_active(&self, pid: PoolId, block: u64, max_idle: u64) -> bool {
+        self.idle_blocks(pid, block)
+            .is_some_and(|idle| idle <= max_idle)
+    }
+
+    /// Window volume of `pid` as of `block`; blocks older than the window
+    /// no longer count.
+    pub fn volume(&self, pid: PoolId, block: u64) -> [U256; 2] {
+        let oldest = block.saturating_sub(self.window - 1);
+        self.get(pid).map_or([U256::ZERO; 2], |a| {
+            a.blocks
+                .iter()
+                .filter(|(b, _)| *b >= oldest)
+                .fold([U256::ZERO; 2], |[x, y], (_, v)| {
+                    [x.saturating_add(v[0]), y.saturating_add(v[1])]
+                })
+        })
+    }
+}
+
+impl AMMGraph {
+    /// A copy without the pools `keep` rejects, e.g.
+    /// `|p| activity.is_active(p, block, 300)`. Tokens are kept, so lookups
+    /// of an unrouted token still succeed and just find no pools.
+    pub fn filter_pools(&self, keep: impl Fn(PoolId) -> bool) -> AMMGraph {
+        let mut graph = self.clone();
+        graph.pool_idx.retain(|&pid, &mut ix| {
+            let kept = keep(pid);
+            if !kept {
+                graph.g.remove_node(ix);
+            }
+            kept
+        });
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arb::Scanner;
+    use crate::engine::Engine;
+    use crate::ids::TokenId;
+    use crate::test_utils::{Cp, reserves};
+    use crate::world::World;
+    use alloy_primitives::I256;
+
+    #[test]
+    fn tracks_rolling_volume_and_filters_idle_pools_from_the_graph() {
+        let mut activity = ActivityTracker::new(10);
+        let swap = |a0: i64, a1: i64| StateUpdate::Swap {
+            amount0: I256::try_from(a0).unwrap(),
+            amount1: I256::try_from(a1).unwrap(),
+        };
+        activity.record_updates(
+            100,
+            &[(PoolId(1), swap(100, -90)), (PoolId(1), swap(-45, 50))],
+        );
+        activity.record_updates(105, &[(PoolId(1), swap(10, -9))]);
+        activity.record_updates(120, &[(PoolId(2), swap(5, -4))]);
+
+        let one = activity.get(PoolId(1)).unwrap();
+        assert_eq!((one.last_swap_block, one.swaps), (105, 3));
+        let v = |x: u64| U256::from(x);
+        assert_eq!(one.volume(), [v(110), v(50)]);
+        assert_eq!(activity.volume(PoolId(1), 110), [v(10), v(0)]);
+        assert_eq!(activity.idle_blocks(PoolId(1), 120), Some(15));
+        assert!(!activity.is_active(PoolId(1), 120, 10));
+        assert!(activity.is_active(PoolId(2), 120, 10));
+        assert_eq!(activity.idle_blocks(PoolId(3), 120), None);
+
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2)), (PoolId(2), Cp::new(2, 1, 2))]);
+        let mut graph = AMMGraph::new();
+        let mut world = World::default();
+        for id in [1, 2] {
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(1), TokenId(2));
+            world.set_pool_state(PoolId(id), reserves(1_000, 1_000));
+        }
+        let live = graph.filter_pools(|p| activity.is_active(p, 120, 10));
+        let engine = Engine::new(&pools);
+        let routes = Scanner::new(&engine, &live).routes(&world, TokenId(1), TokenId(2));
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0][0].pool, PoolId(2));
+        assert_eq!(
+            Scanner::new(&engine, &graph)
+                .routes(&world, TokenId(1), TokenId(2))
+                .len(),
+            2
+        );
+    }
+}
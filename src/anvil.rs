@@ -0,0 +1,321 @@
+//! Fork-mode integration harness: runs `anvil --fork-url`, locates pools on
+//! the fork, loads them into a [`World`] and checks engine quotes against
+//! on-chain ones.
+
+use crate::{
+    engine::{Engine, Hop},
+    error::{RegistryError, WayfinderError},
+    ids::{ChainId, PoolId, TokenId},
+    registry::{PoolKind, PoolMeta, Registry},
+    sync::WorldSync,
+    univ2::{UniV2Pool, UniV2State},
+    world::World,
+};
+use alloy_primitives::{Address, U256, address};
+use alloy_provider::{
+    DynProvider, Provider, ProviderBuilder,
+    network::{Ethereum, Network, TransactionBuilder},
+};
+use alloy_sol_types::{SolCall, sol};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader};
+use std::net::TcpListener;
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+
+pub const UNIV2_FACTORY: Address = address!("5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f");
+pub const UNIV2_ROUTER: Address = address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D");
+
+sol! {
+    function getPair(address tokenA, address tokenB) external view returns (address pair);
+    function token0() external view returns (address);
+    function getAmountsOut(uint256 amountIn, address[] path) external view returns (uint256[] amounts);
+}
+
+/// A running `anvil` fork, killed on drop. The binary is taken from
+/// `ANVIL_BIN`, defaulting to `anvil` on the `PATH`. Its output is drained
+/// on a background thread so logging never blocks it.
+pub struct AnvilFork {
+    child: Child,
+    pub endpoint: String,
+}
+
+impl AnvilFork {
+    pub fn spawn(fork_url: &str, block: Option<u64>) -> io::Result<Self> {
+        let bin = std::env::var("ANVIL_BIN").unwrap_or_else(|_| "anvil".into());
+        Self::spawn_bin(&bin, fork_url, block)
+    }
+
+    fn spawn_bin(bin: &str, fork_url: &str, block: Option<u64>) -> io::Result<Self> {
+        let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let mut cmd = Command::new(bin);
+        cmd.args(["--fork-url", fork_url, "--port", &port.to_string()])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+        if let Some(block) = block {
+            cmd.args(["--fork-block-number", &block.to_string()]);
+        }
+        let mut child = cmd.spawn()?;
+
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let mut lines = BufReader::new(stdout).lines();
+        let listening = loop {
+            match lines.next() {
+                Some(Ok(line)) if line.contains("Listening on") => break Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => break Err(e),
+                None => break Err(io::Error::other("anvil exited before listening")),
+            }
+        };
+        if let Err(e) = listening {
+            child.kill().ok();
+            child.wait().ok();
+            return Err(e);
+        }
+        std::thread::spawn(move || lines.for_each(drop));
+        Ok(Self {
+            child,
+            endpoint: format!("http://127.0.0.1:{port}"),
+        })
+    }
+}
+
+impl Drop for AnvilFork {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+/// One plan quoted both ways, per hop.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrossCheck {
+    pub engine: Vec<U256>,
+    pub onchain: Vec<U256>,
+}
+
+impl CrossCheck {
+    pub fn matches(&self) -> bool {
+        self.engine == self.onchain
+    }
+}
+
+pub struct ForkHarness {
+    pub fork: AnvilFork,
+    pub provider: DynProvider,
+    pub chain: ChainId,
+    pub block: u64,
+    pub registry: Registry,
+    pub factory: Address,
+    pub router: Address,
+    /// Fee, in hundredths of a bip, of pairs `factory` deploys; pairs the
+    /// registry already holds keep their own.
+    pub pair_fee: u32,
+}
+
+impl ForkHarness {
+    /// Forks `fork_url` at `block` (or its head) with the mainnet UniswapV2
+    /// factory and router; `registry` must hold every token tests refer to.
+    pub async fn start(
+        fork_url: &str,
+        block: Option<u64>,
+        registry: Registry,
+    ) -> Result<Self, WayfinderError> {
+        let fork = AnvilFork::spawn(fork_url, block)?;
+        let provider = ProviderBuilder::new()
+            .connect(&fork.endpoint)
+            .await
+            .map_err(rpc)?
+            .erased();
+        let chain = ChainId(provider.get_chain_id().await.map_err(rpc)?);
+        let block = provider.get_block_number().await.map_err(rpc)?;
+        Ok(Self {
+            fork,
+            provider,
+            chain,
+            block,
+            registry,
+            factory: UNIV2_FACTORY,
+            router: UNIV2_ROUTER,
+            pair_fee: 3000,
+        })
+    }
+
+    /// Finds the factory's pair for `a`/`b` and registers it.
+    pub async fn locate_v2_pair(
+        &mut self,
+        a: TokenId,
+        b: TokenId,
+    ) -> Result<PoolId, WayfinderError> {
+        let addr = |t| self.registry.require_token(t).map(|m| m.address);
+        let (a_addr, b_addr) = (addr(a)?, addr(b)?);
+        let pair = self
+            .call(
+                self.factory,
+                getPairCall {
+                    tokenA: a_addr,
+                    tokenB: b_addr,
+                },
+            )
+            .await?;
+        if pair.is_zero() {
+            return Err(WayfinderError::Rpc(format!("no pair for {a}/{b}")));
+        }
+        let (token0, token1) = if self.call(pair, token0Call {}).await? == a_addr {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        let fee = self
+            .registry
+            .pool_by_addr
+            .get(&pair)
+            .and_then(|&pid| self.registry.pool(pid))
+            .map_or(self.pair_fee, |m| m.fee);
+        Ok(self.registry.insert_pool_hashed(
+            self.chain,
+            PoolMeta {
+                address: pair,
+                kind: PoolKind::UniV2,
+                token0,
+                token1,
+                fee,
+            },
+        ))
+    }
+
+    /// Loads `pools` at the fork block, plus matching pool models.
+    pub async fn load_world(
+        &self,
+        pools: &[PoolId],
+    ) -> Result<(World<UniV2State>, HashMap<PoolId, UniV2Pool>), WayfinderError> {
+        let mut sync = WorldSync::new(self.provider.clone(), Arc::new(self.registry.clone()));
+        let mut world = World::default();
+        let report = sync.refresh(&mut world, pools, self.block).await?;
+        if let Some(&pid) = report.failed.first() {
+            return Err(RegistryError::UnknownPool(pid).into());
+        }
+        let models = pools
+            .iter()
+            .map(|&pid| {
+                let meta = self.registry.require_pool(pid)?;
+                let pool =
+                    UniV2Pool::new(pid, meta.token0, meta.token1).with_fee_bps(meta.fee / 100);
+                Ok((pid, pool))
+            })
+            .collect::<Result<_, WayfinderError>>()?;
+        Ok((world, models))
+    }
+
+    /// Quotes `plan` with the engine and with the router's `getAmountsOut`.
+    pub async fn cross_check(
+        &self,
+        engine: &Engine<'_, UniV2Pool>,
+        world: &World<UniV2State>,
+        plan: &[Hop],
+        amt_in: U256,
+    ) -> Result<CrossCheck, WayfinderError> {
+        let path = engine.try_simulate(world, plan, amt_in)?;
+        let mut tokens = vec![self.registry.require_token(plan[0].dir.from)?.address];
+        for h in plan {
+            tokens.push(self.registry.require_token(h.dir.to)?.address);
+        }
+        let amounts = self
+            .call(
+                self.router,
+                getAmountsOutCall {
+                    amountIn: amt_in,
+                    path: tokens,
+                },
+            )
+            .await?;
+        Ok(CrossCheck {
+            engine: path.steps.iter().map(|s| s.amt_out).collect(),
+            onchain: amounts.into_iter().skip(1).collect(),
+        })
+    }
+
+    async fn call<C: SolCall>(&self, to: Address, call: C) -> Result<C::Return, WayfinderError> {
+        let tx = <Ethereum as Network>::TransactionRequest::default()
+            .with_to(to)
+            .with_input(call.abi_encode());
+        let data = self
+            .provider
+            .call(tx)
+            .block(self.block.into())
+            .await
+            .map_err(rpc)?;
+        C::abi_decode_returns(&data).map_err(rpc)
+    }
+}
+
+fn rpc(e: impl std::fmt::Display) -> WayfinderError {
+    WayfinderError::Rpc(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::TokenMeta;
+
+    #[test]
+    fn reports_an_anvil_that_never_listens() {
+        let err = AnvilFork::spawn_bin("true", "http://unused", None)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("before listening"), "{err}");
+    }
+
+    #[tokio::test]
+    #[ignore = "needs anvil and WAYFINDER_FORK_URL"]
+    async fn univ2_quotes_match_mainnet_router() {
+        let url = std::env::var("WAYFINDER_FORK_URL").unwrap();
+        let mut reg = Registry::default();
+        let mut token = |addr: Address, symbol: &str, decimals| {
+            reg.insert_token_hashed(
+                ChainId::MAINNET,
+                TokenMeta {
+                    address: addr,
+                    symbol: symbol.into(),
+                    decimals,
+                },
+            )
+        };
+        let weth = token(
+            address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+            "WETH",
+            18,
+        );
+        let usdc = token(
+            address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+            "USDC",
+            6,
+        );
+        let dai = token(
+            address!("6B175474E89094C44Da98b954EedeAC495271d0F"),
+            "DAI",
+            18,
+        );
+
+        let mut h = ForkHarness::start(&url, None, reg).await.unwrap();
+        let p1 = h.locate_v2_pair(weth, usdc).await.unwrap();
+        let p2 = h.locate_v2_pair(usdc, dai).await.unwrap();
+        let (world, pools) = h.load_world(&[p1, p2]).await.unwrap();
+        let engine = Engine::new(&pools);
+
+        let plan = [
+            Hop::new(p1, crate::ids::SwapDirection::new(weth, usdc).unwrap()),
+            Hop::new(p2, crate::ids::SwapDirection::new(usdc, dai).unwrap()),
+        ];
+        let check = h
+            .cross_check(
+                &engine,
+                &world,
+                &plan,
+                U256::from(10u64).pow(U256::from(18u64)),
+            )
+            .await
+            .unwrap();
+        assert!(check.matches(), "{check:?}");
+    }
+}
@@ -0,0 +1,193 @@
+use crate::{
+    arb::Scanner,
+    engine::{Hop, Path},
+    ids::TokenId,
+    pool::Pool,
+    world::StateView,
+};
+use alloy_primitives::U256;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchBudget {
+    /// Stop after expanding this many partial routes.
+    Expansions(usize),
+    Until(Instant),
+}
+
+impl SearchBudget {
+    pub fn for_duration(d: Duration) -> Self {
+        Self::Until(Instant::now() + d)
+    }
+}
+
+/// Resumable best-route search. Routes are explored shortest first, so even
+/// a tiny budget yields the direct pools before longer detours.
+pub struct RouteSearch<'s, 'a, P: Pool, V> {
+    scanner: &'s Scanner<'a, P>,
+    world: &'s V,
+    from: TokenId,
+    to: TokenId,
+    amt_in: U256,
+    frontier: VecDeque<Vec<Hop>>,
+    best: Option<Path>,
+    evaluated: usize,
+}
+
+impl<'a, P: Pool> Scanner<'a, P> {
+    pub fn search_incremental<'s, V: StateView<P::State>>(
+        &'s self,
+        world: &'s V,
+        from: TokenId,
+        to: TokenId,
+        amt_in: U256,
+    ) -> RouteSearch<'s, 'a, P, V> {
+        let mut frontier = VecDeque::new();
+        if from != to && self.graph.token_idx.contains_key(&from) {
+            frontier.push_back(Vec::new());
+        }
+        RouteSearch {
+            scanner: self,
+            world,
+            from,
+            to,
+            amt_in,
+            frontier,
+            best: None,
+            evaluated: 0,
+        }
+    }
+}
+
+impl<P: Pool, V: StateView<P::State>> RouteSearch<'_, '_, P, V> {
+    /// Continues the search until `budget` runs out or every route within
+    /// `max_hops` has been tried, returning the best route so far.
+    pub fn search(&mut self, budget: SearchBudget) -> Option<&Path> {
+        let mut expansions = 0;
+        while let Some(plan) = self.frontier.pop_front() {
+            let at = plan.last().map_or(self.from, |h| h.dir.to);
+            for hop in self.scanner.next_hops(self.world, at, self.to, &plan) {
+                let next = hop.dir.to;
+                if next == self.from || plan.iter().any(|h| h.dir.from == next) {
+                    continue;
+                }
+                let mut longer = plan.clone();
+                longer.push(hop);
+                if next == self.to {
+                    self.evaluate(&longer);
+                } else if longer.len() < self.scanner.config.max_hops {
+                    self.frontier.push_back(longer);
+                }
+            }
+
+            expansions += 1;
+            let spent = match budget {
+                SearchBudget::Expansions(n) => expansions >= n,
+                SearchBudget::Until(deadline) => Instant::now() >= deadline,
+            };
+            if spent {
+                break;
+            }
+        }
+        self.best.as_ref()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.frontier.is_empty()
+    }
+
+    pub fn best(&self) -> Option<&Path> {
+        self.best.as_ref()
+    }
+
+    pub fn into_best(self) -> Option<Path> {
+        self.best
+    }
+
+    /// Complete routes simulated so far.
+    pub fn evaluated(&self) -> usize {
+        self.evaluated
+    }
+
+    fn evaluate(&mut self, plan: &[Hop]) {
+        self.evaluated += 1;
+        let path = self
+            .scanner
+            .engine
+            .simulate_chained(self.world, plan, self.amt_in);
+        let out = |p: &Path| p.steps.last().map(|s| s.amt_out).unwrap_or_default();
+        if self.best.as_ref().is_none_or(|b| out(&path) > out(b)) {
+            self.best = Some(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arb::ScanConfig;
+    use crate::engine::Engine;
+    use crate::graph::AMMGraph;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, reserves};
+    use crate::world::World;
+    use std::collections::HashMap;
+
+    #[test]
+    fn resumed_search_improves_to_exhaustive_best() {
+        // Thin direct pool 1 -> 4, deeper two- and three-hop detours.
+        let mut pools = HashMap::new();
+        let mut graph = AMMGraph::new();
+        let mut world = World::default();
+        for (id, t0, t1, r) in [
+            (1, 1, 4, 1_000),
+            (2, 1, 2, 1_000_000),
+            (3, 2, 4, 1_000_000),
+            (4, 2, 3, 100_000_000),
+            (5, 3, 4, 100_000_000),
+        ] {
+            pools.insert(PoolId(id), Cp::new(id, t0, t1));
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(t0), TokenId(t1));
+            world.pool_states.insert(PoolId(id), reserves(r, r));
+        }
+        let engine = Engine::new(&pools);
+        let scanner = Scanner::new(&engine, &graph).with_config(ScanConfig {
+            max_hops: 3,
+            ..Default::default()
+        });
+        let amt = U256::from(10_000u64);
+        let out = |p: &Path| p.steps.last().unwrap().amt_out;
+
+        let mut search = scanner.search_incremental(&world, TokenId(1), TokenId(4), amt);
+        let first = search.search(SearchBudget::Expansions(1)).cloned().unwrap();
+        assert_eq!(first.steps.len(), 1);
+        assert!(!search.is_done());
+
+        while !search.is_done() {
+            search.search(SearchBudget::Expansions(1));
+        }
+        let best = search.into_best().unwrap();
+        assert!(out(&best) > out(&first));
+        let exhaustive = scanner
+            .best_route(&world, TokenId(1), TokenId(4), amt)
+            .unwrap();
+        assert_eq!(out(&best), out(&exhaustive));
+    }
+
+    #[test]
+    fn expired_deadline_still_returns_usable_route() {
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2))]);
+        let mut graph = AMMGraph::new();
+        graph.connect_bidirectional_pair(PoolId(1), TokenId(1), TokenId(2));
+        let mut world = World::default();
+        world.pool_states.insert(PoolId(1), reserves(1_000, 1_000));
+        let engine = Engine::new(&pools);
+        let scanner = Scanner::new(&engine, &graph);
+
+        let mut search =
+            scanner.search_incremental(&world, TokenId(1), TokenId(2), U256::from(10u64));
+        assert!(search.search(SearchBudget::Until(Instant::now())).is_some());
+        assert_eq!(search.evaluated(), 1);
+    }
+}
@@ -0,0 +1,455 @@
+use crate::{
+    engine::{Engine, Hop, Path},
+    funding::Funding,
+    gas::GasModel,
+    graph::{AMMGraph, NodeKind},
+    ids::{PoolId, SwapDirection, TokenId, TokenSet},
+    pool::Pool,
+    ranking::{RankingPolicy, WeightedScore},
+    telemetry::{self, Timer},
+    world::StateView,
+};
+use alloy_primitives::U256;
+use std::cmp::Reverse;
+
+#[derive(Clone, Debug)]
+pub struct ArbOpportunity {
+    pub plan: Vec<Hop>,
+    pub optimal_in: U256,
+    pub gross: U256,
+    pub gas: U256,
+    pub net: U256,
+}
+
+impl ArbOpportunity {
+    pub fn base(&self) -> TokenId {
+        self.plan[0].dir.from
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ScanConfig {
+    pub max_hops: usize,
+    pub max_in: U256,
+    pub gas_per_hop: U256,
+    pub max_iters: usize,
+    /// With a flash loan, only cycles in the loan token are scanned and the
+    /// fee is charged against gross profit.
+    pub funding: Funding,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            max_hops: 3,
+            max_in: U256::from(10u64).pow(U256::from(24u64)),
+            gas_per_hop: U256::ZERO,
+            max_iters: 160,
+            funding: Funding::Inventory,
+        }
+    }
+}
+
+pub struct Scanner<'a, P: Pool> {
+    pub engine: &'a Engine<'a, P>,
+    pub graph: &'a AMMGraph,
+    pub config: ScanConfig,
+    /// Orders `best_route` candidates; [`WeightedScore::default`] if unset.
+    pub ranking: Option<&'a dyn RankingPolicy>,
+    /// Prices each plan's gas; `config.gas_per_hop` per hop if unset.
+    pub gas_model: Option<&'a dyn GasModel>,
+    /// Tokens routes and cycles may pass through; any token if unset. The
+    /// endpoints are always allowed.
+    pub intermediates: Option<&'a TokenSet>,
+}
+
+impl<'a, P: Pool> Scanner<'a, P> {
+    pub fn new(engine: &'a Engine<'a, P>, graph: &'a AMMGraph) -> Self {
+        Self {
+            engine,
+            graph,
+            config: ScanConfig::default(),
+            ranking: None,
+            gas_model: None,
+            intermediates: None,
+        }
+    }
+
+    pub fn with_config(mut self, config: ScanConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_ranking(mut self, ranking: &'a dyn RankingPolicy) -> Self {
+        self.ranking = Some(ranking);
+        self
+    }
+
+    pub fn with_gas_model(mut self, gas_model: &'a dyn GasModel) -> Self {
+        self.gas_model = Some(gas_model);
+        self
+    }
+
+    pub fn with_intermediates(mut self, intermediates: &'a TokenSet) -> Self {
+        self.intermediates = Some(intermediates);
+        self
+    }
+
+    pub fn gas_cost(&self, plan: &[Hop]) -> U256 {
+        match self.gas_model {
+            Some(model) => model.cost(plan),
+            None => self.config.gas_per_hop * U256::from(plan.len()),
+        }
+    }
+
+    pub fn cycles<V: StateView<P::State>>(&self, world: &V, base: TokenId) -> Vec<Vec<Hop>> {
+        let mut out = Vec::new();
+        if !self.graph.token_idx.contains_key(&base) {
+            return out;
+        }
+        let mut plan = Vec::with_capacity(self.config.max_hops);
+        self.extend(world, base, base, &mut plan, &mut out);
+        out
+    }
+
+    pub fn routes<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        from: TokenId,
+        to: TokenId,
+    ) -> Vec<Vec<Hop>> {
+        let mut out = Vec::new();
+        if from == to || !self.graph.token_idx.contains_key(&from) {
+            return out;
+        }
+        let mut plan = Vec::with_capacity(self.config.max_hops);
+        self.extend(world, to, from, &mut plan, &mut out);
+        out
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", name = "route_search", skip_all, fields(%from, %to, %amt_in))
+    )]
+    pub fn best_route<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        from: TokenId,
+        to: TokenId,
+        amt_in: U256,
+    ) -> Option<Path> {
+        let timer = Timer::start();
+        let best = self.rank(world, &self.routes(world, from, to), amt_in);
+        timer.observe(telemetry::QUOTE_SECONDS);
+        best
+    }
+
+    /// Simulates each plan with `amt_in` and keeps the one the ranking
+    /// policy scores highest.
+    pub(crate) fn rank<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plans: &[Vec<Hop>],
+        amt_in: U256,
+    ) -> Option<Path> {
+        let default;
+        let policy = match self.ranking {
+            Some(policy) => policy,
+            None => {
+                default = WeightedScore::default();
+                &default
+            }
+        };
+        plans
+            .iter()
+            .map(|plan| {
+                #[cfg(feature = "tracing")]
+                let _candidate = tracing::debug_span!("candidate", hops = plan.len()).entered();
+                let path = self.engine.simulate_chained(world, plan, amt_in);
+                #[cfg(feature = "tracing")]
+                tracing::debug!(amt_out = %path.steps.last().map(|s| s.amt_out).unwrap_or_default());
+                let gas = self.gas_cost(plan);
+                let out = path.steps.last().map(|s| s.amt_out).unwrap_or_default();
+                (policy.score(&path, gas), out, path)
+            })
+            // Exact output breaks ties the float score can't resolve.
+            .max_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)))
+            .map(|(_, _, path)| path)
+    }
+
+    fn extend<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        base: TokenId,
+        at: TokenId,
+        plan: &mut Vec<Hop>,
+        out: &mut Vec<Vec<Hop>>,
+    ) {
+        if plan.len() == self.config.max_hops {
+            return;
+        }
+        for hop in self.next_hops(world, at, base, plan) {
+            let next = hop.dir.to;
+            if next != base && plan.iter().any(|h| h.dir.from == next) {
+                continue;
+            }
+
+            plan.push(hop);
+            if next == base {
+                if plan.len() >= 2 || plan[0].dir.from != base {
+                    out.push(plan.clone());
+                }
+            } else {
+                self.extend(world, base, next, plan, out);
+            }
+            plan.pop();
+        }
+    }
+
+    /// Usable hops out of `at` through pools not already in `plan`, into
+    /// `target` or an allowed intermediate.
+    pub(crate) fn next_hops<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        at: TokenId,
+        target: TokenId,
+        plan: &[Hop],
+    ) -> Vec<Hop> {
+        let mut hops = Vec::new();
+        let Ok(pools) = self.graph.pools_accepting(at) else {
+            return hops;
+        };
+        for pix in pools {
+            let NodeKind::Pool(pid) = self.graph.g[pix] else {
+                continue;
+            };
+            if plan.iter().any(|h| h.pool == pid) || !self.usable(world, pid) {
+                continue;
+            }
+            for tix in self.graph.tokens_emitted_by(pid).into_iter().flatten() {
+                let NodeKind::Token(next) = self.graph.g[tix] else {
+                    continue;
+                };
+                if next != target && self.intermediates.is_some_and(|set| !set.contains(next)) {
+                    continue;
+                }
+                let Some(dir) = SwapDirection::new(at, next) else {
+                    continue;
+                };
+                if self.engine.pools[&pid].supports(dir) {
+                    hops.push(Hop::new(pid, dir));
+                }
+            }
+        }
+        hops
+    }
+
+    fn usable<V: StateView<P::State>>(&self, world: &V, pid: PoolId) -> bool {
+        self.engine.pools.contains_key(&pid) && world.pool_state(pid).is_some()
+    }
+
+    /// Input maximizing profit, and the output net of any flash-loan fee.
+    pub fn size<V: StateView<P::State>>(&self, world: &V, plan: &[Hop]) -> (U256, U256) {
+        optimal_input(
+            |x| {
+                let path = self.engine.simulate_chained(world, plan, x);
+                let out = path.steps.last().map(|s| s.amt_out).unwrap_or_default();
+                out.saturating_sub(self.config.funding.fee(x))
+            },
+            self.config.max_in,
+            self.config.max_iters,
+        )
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", name = "arb_scan", skip_all, fields(bases = bases.len()))
+    )]
+    pub fn scan<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        bases: &[TokenId],
+    ) -> Vec<ArbOpportunity> {
+        let timer = Timer::start();
+        let mut opps = Vec::new();
+        for &base in bases {
+            if let Funding::FlashLoan { token, .. } = self.config.funding
+                && token != base
+            {
+                continue;
+            }
+            for plan in self.cycles(world, base) {
+                #[cfg(feature = "tracing")]
+                let _candidate =
+                    tracing::debug_span!("candidate", %base, hops = plan.len()).entered();
+                opps.extend(self.evaluate(world, plan));
+            }
+        }
+        opps.sort_by_key(|o| Reverse(o.net));
+        timer.observe(telemetry::SCAN_SECONDS);
+        opps
+    }
+
+    /// Sizes a single cycle; `None` unless it clears gas.
+    pub fn evaluate<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plan: Vec<Hop>,
+    ) -> Option<ArbOpportunity> {
+        let (optimal_in, out) = self.size(world, &plan);
+        let gross = out.saturating_sub(optimal_in);
+        let gas = self.gas_cost(&plan);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(%optimal_in, %gross, %gas);
+        (gross > gas).then(|| ArbOpportunity {
+            plan,
+            optimal_in,
+            gross,
+            gas,
+            net: gross - gas,
+        })
+    }
+}
+
+pub fn optimal_input<F: FnMut(U256) -> U256>(
+    mut out_for: F,
+    max_in: U256,
+    max_iters: usize,
+) -> (U256, U256) {
+    let (mut lo, mut hi) = (U256::ZERO, max_in);
+    let three = U256::from(3u64);
+
+    for _ in 0..max_iters {
+        if hi - lo <= U256::from(2u64) {
+            break;
+        }
+        let third = (hi - lo) / three;
+        let (m1, m2) = (lo + third, hi - third);
+        let (o1, o2) = (out_for(m1), out_for(m2));
+        if o1.saturating_add(m2) < o2.saturating_add(m1) {
+            lo = m1;
+        } else {
+            hi = m2;
+        }
+    }
+
+    let mut best = (lo, out_for(lo));
+    for x in [lo + (hi - lo) / U256::from(2u64), hi] {
+        let o = out_for(x);
+        if o.saturating_add(best.0) > best.1.saturating_add(x) {
+            best = (x, o);
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gas::{GasSchedule, L1Gas};
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::World;
+    use std::collections::HashMap;
+
+    fn setup() -> (HashMap<PoolId, Cp>, AMMGraph, World<(U256, U256)>) {
+        let mut pools = HashMap::new();
+        let mut graph = AMMGraph::new();
+        let mut world = World::default();
+        for (id, t0, t1, r0, r1) in [
+            (1, 1, 2, 1_000_000, 2_000_000),
+            (2, 1, 2, 1_000_000, 2_200_000),
+            (3, 2, 3, 1_000_000, 1_000_000),
+            (4, 3, 1, 1_000_000, 500_000),
+        ] {
+            pools.insert(PoolId(id), Cp::new(id, t0, t1).with_fee(30));
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(t0), TokenId(t1));
+            world.pool_states.insert(PoolId(id), reserves(r0, r1));
+        }
+        (pools, graph, world)
+    }
+
+    #[test]
+    fn enumerates_two_and_three_cycles() {
+        let (pools, graph, world) = setup();
+        let engine = Engine::new(&pools);
+        let scanner = Scanner::new(&engine, &graph);
+
+        let cycles = scanner.cycles(&world, TokenId(1));
+        assert!(cycles.contains(&vec![hop(2, 1, 2), hop(1, 2, 1)]));
+        assert!(cycles.contains(&vec![hop(1, 1, 2), hop(3, 2, 3), hop(4, 3, 1)]));
+        assert!(cycles.iter().all(|c| c.len() >= 2 && c.len() <= 3));
+        assert!(
+            cycles
+                .iter()
+                .all(|c| c[0].dir.from == TokenId(1) && c.last().unwrap().dir.to == TokenId(1))
+        );
+
+        let two_hop = scanner
+            .with_config(ScanConfig {
+                max_hops: 2,
+                ..ScanConfig::default()
+            })
+            .cycles(&world, TokenId(1));
+        assert!(two_hop.iter().all(|c| c.len() == 2));
+    }
+
+    #[test]
+    fn routes_reach_target_and_best_route_maximizes_output() {
+        let (pools, graph, world) = setup();
+        let engine = Engine::new(&pools);
+        let scanner = Scanner::new(&engine, &graph);
+
+        let routes = scanner.routes(&world, TokenId(1), TokenId(2));
+        assert!(routes.contains(&vec![hop(1, 1, 2)]));
+        assert!(routes.contains(&vec![hop(4, 1, 3), hop(3, 3, 2)]));
+        assert!(
+            routes
+                .iter()
+                .all(|r| r.last().unwrap().dir.to == TokenId(2))
+        );
+
+        let best = scanner
+            .best_route(&world, TokenId(1), TokenId(2), U256::from(1_000u64))
+            .unwrap();
+        assert_eq!(best.steps.len(), 1);
+        assert_eq!(best.steps[0].pool, PoolId(2));
+    }
+
+    #[test]
+    fn scan_ranks_profitable_cycles_by_net() {
+        let (pools, graph, world) = setup();
+        let engine = Engine::new(&pools);
+        let opps = Scanner::new(&engine, &graph)
+            .with_config(ScanConfig {
+                gas_per_hop: U256::from(100u64),
+                ..ScanConfig::default()
+            })
+            .scan(&world, &[TokenId(1)]);
+
+        assert!(!opps.is_empty());
+        assert!(opps.windows(2).all(|w| w[0].net >= w[1].net));
+        let best = &opps[0];
+        assert_eq!(best.net, best.gross - best.gas);
+        assert_eq!(best.base(), TokenId(1));
+
+        let path = engine.simulate_chained(&world, &best.plan, best.optimal_in);
+        let out = path.steps.last().unwrap().amt_out;
+        assert_eq!(out - best.optimal_in, best.gross);
+        for probe in [
+            best.optimal_in / U256::from(2u64),
+            best.optimal_in * U256::from(2u64),
+        ] {
+            let p = engine.simulate_chained(&world, &best.plan, probe);
+            let o = p.steps.last().unwrap().amt_out;
+            assert!(o.saturating_sub(probe) <= best.gross);
+        }
+
+        let l1 = L1Gas {
+            schedule: GasSchedule::default(),
+            gas_price: 1,
+        };
+        let priced = Scanner::new(&engine, &graph).with_gas_model(&l1);
+        assert!(priced.gas_cost(&best.plan) > U256::from(200_000u64));
+        assert!(priced.scan(&world, &[TokenId(1)]).is_empty());
+    }
+}
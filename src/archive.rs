@@ -0,0 +1,223 @@
+//! Historical state from an archive node: the nearest earlier checkpoint,
+//! rolled forward by replaying the pools' `Sync` logs.
+
+use crate::{
+    decode::{self, IUniswapV2Pair},
+    error::WayfinderError,
+    ids::PoolId,
+    provider::StateProvider,
+    registry::Registry,
+    univ2::UniV2State,
+    world::{BlockContext, World},
+};
+use alloy_primitives::Address;
+use alloy_provider::Provider;
+use alloy_rpc_types_eth::Filter;
+use alloy_sol_types::SolEvent;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+pub struct ArchiveStateProvider<P> {
+    pub provider: P,
+    pub registry: Arc<Registry>,
+    /// Blocks per `eth_getLogs` request.
+    pub log_range: u64,
+    checkpoints: BTreeMap<u64, World<UniV2State>>,
+}
+
+impl<P: Provider> ArchiveStateProvider<P> {
+    pub fn new(provider: P, registry: Arc<Registry>) -> Self {
+        Self {
+            provider,
+            registry,
+            log_range: 2_000,
+            checkpoints: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_log_range(mut self, log_range: u64) -> Self {
+        self.log_range = log_range.max(1);
+        self
+    }
+
+    /// Registers `world` as the state at the end of its block.
+    pub fn add_checkpoint(&mut self, world: World<UniV2State>) {
+        self.checkpoints.insert(world.block.number, world);
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn add_saved_checkpoint(&mut self, ckpt: crate::checkpoint::Checkpoint<UniV2State>) {
+        let mut world = ckpt.world;
+        world.block.number = ckpt.block;
+        self.add_checkpoint(world);
+    }
+
+    /// Materializes the world at the end of `block`, with that block's
+    /// header as its context. Pools created after the checkpoint are
+    /// missing until their first `Sync` is replayed.
+    pub async fn world_at(&self, block: u64) -> Result<World<UniV2State>, WayfinderError> {
+        let (&from, ckpt) = self
+            .checkpoints
+            .range(..=block)
+            .next_back()
+            .ok_or(WayfinderError::NoCheckpoint(block))?;
+        let mut world = ckpt.clone();
+        if from == block {
+            return Ok(world);
+        }
+        let pools: Vec<Address> = self.registry.pool_by_addr.keys().copied().collect();
+        self.replay(&mut world, pools, from, block).await?;
+        world.block = self.block_context(block).await?;
+        Ok(world)
+    }
+
+    async fn block_context(&self, number: u64) -> Result<BlockContext, WayfinderError> {
+        let header = self
+            .provider
+            .get_block_by_number(number.into())
+            .await
+            .map_err(|e| WayfinderError::Rpc(e.to_string()))?
+            .ok_or_else(|| WayfinderError::Rpc(format!("no block {number}")))?
+            .header;
+        Ok(BlockContext {
+            number,
+            timestamp: header.timestamp,
+            basefee: header.base_fee_per_gas.unwrap_or_default(),
+        })
+    }
+
+    async fn replay(
+        &self,
+        world: &mut World<UniV2State>,
+        pools: Vec<Address>,
+        from: u64,
+        to: u64,
+    ) -> Result<(), WayfinderError> {
+        let mut start = from + 1;
+        while start <= to {
+            let end = to.min(start + self.log_range - 1);
+            let filter = Filter::new()
+                .address(pools.clone())
+                .event_signature(IUniswapV2Pair::Sync::SIGNATURE_HASH)
+                .from_block(start)
+                .to_block(end);
+            let logs = self
+                .provider
+                .get_logs(&filter)
+                .await
+                .map_err(|e| WayfinderError::Rpc(e.to_string()))?;
+            let updates = decode::decode_logs(&self.registry, logs.iter().map(|l| &l.inner));
+            let diff = decode::univ2_diff(world, updates);
+            world.apply(diff);
+            start = end + 1;
+        }
+        world.block.number = to;
+        Ok(())
+    }
+}
+
+impl<P: Provider> StateProvider<UniV2State> for ArchiveStateProvider<P> {
+    async fn pool_state(&self, pid: PoolId, block: u64) -> Result<UniV2State, WayfinderError> {
+        let meta = self.registry.require_pool(pid)?;
+        let (&from, ckpt) = self
+            .checkpoints
+            .range(..=block)
+            .next_back()
+            .ok_or(WayfinderError::NoCheckpoint(block))?;
+        let mut world = World::default();
+        if let Some(&st) = ckpt.pool_states.get(&pid) {
+            world.set_pool_state(pid, st);
+        }
+        self.replay(&mut world, vec![meta.address], from, block)
+            .await?;
+        world
+            .pool_states
+            .remove(&pid)
+            .ok_or(WayfinderError::Unavailable { pool: pid, block })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ids::TokenId,
+        registry::{PoolKind, PoolMeta},
+    };
+    use alloy_primitives::{U256, aliases::U112};
+    use alloy_provider::{ProviderBuilder, mock::Asserter};
+    use alloy_rpc_types_eth::{Block, Log};
+
+    fn sync_log(addr: u8, block: u64, r: u64) -> Log {
+        let e = IUniswapV2Pair::Sync {
+            reserve0: U112::from(r),
+            reserve1: U112::from(r),
+        };
+        Log {
+            inner: alloy_primitives::Log {
+                address: Address::repeat_byte(addr),
+                data: e.encode_log_data(),
+            },
+            block_number: Some(block),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn replays_logs_on_top_of_the_nearest_checkpoint() {
+        let mut reg = Registry::default();
+        for i in 1..=2u8 {
+            reg.upsert_pool(
+                PoolId(i as u64),
+                PoolMeta {
+                    address: Address::repeat_byte(i),
+                    kind: PoolKind::UniV2,
+                    token0: TokenId(1),
+                    token1: TokenId(2),
+                    fee: 3000,
+                },
+            );
+        }
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+        let mut archive = ArchiveStateProvider::new(provider, Arc::new(reg)).with_log_range(5);
+
+        for (number, r) in [(100, 1u64), (110, 50)] {
+            let mut world = World::default();
+            world.block.number = number;
+            world.set_pool_state(PoolId(1), UniV2State::new(U256::from(r), U256::from(r)));
+            archive.add_checkpoint(world);
+        }
+
+        // Blocks 101..=105, then 106..=108.
+        asserter.push_success(&vec![sync_log(1, 102, 7), sync_log(2, 104, 3)]);
+        asserter.push_success(&vec![sync_log(1, 107, 9)]);
+        let mut block: Block = Block::default();
+        block.header.inner.number = 108;
+        block.header.inner.timestamp = 1_296;
+        block.header.inner.base_fee_per_gas = Some(7);
+        asserter.push_success(&block);
+        let world = archive.world_at(108).await.unwrap();
+        assert_eq!(
+            world.block,
+            BlockContext {
+                number: 108,
+                timestamp: 1_296,
+                basefee: 7
+            }
+        );
+        assert_eq!(world.pool_states[&PoolId(1)].reserve0, U256::from(9u64));
+        assert_eq!(world.pool_states[&PoolId(2)].reserve0, U256::from(3u64));
+
+        // At a checkpoint block nothing is replayed.
+        let st = StateProvider::pool_state(&archive, PoolId(1), 110)
+            .await
+            .unwrap();
+        assert_eq!(st.reserve0, U256::from(50u64));
+
+        assert!(matches!(
+            archive.world_at(99).await,
+            Err(WayfinderError::NoCheckpoint(99))
+        ));
+    }
+}
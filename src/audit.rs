@@ -0,0 +1,210 @@
+//! Consistency checks on cached pool state: the price a pool reports from its
+//! state should match what a tiny swap through that state pays. They drift
+//! apart when a sync writes some fields of a pool but not others, e.g. a V3
+//! price without the liquidity at it.
+
+use crate::{
+    engine::Engine,
+    graph::{AMMGraph, NodeKind},
+    ids::{PoolId, SwapDirection, TokenId},
+    pool::{DEPTH_PRECISION, Pool},
+    validation::deviation_bps,
+    world::{BlockContext, StateView, World},
+};
+use alloy_primitives::U256;
+
+pub const DEFAULT_AUDIT_TOLERANCE_BPS: u64 = 50;
+
+/// Probes stop doubling at this many bits, so a pool that pays nothing is
+/// judged on a large but finite input.
+const PROBE_MAX_BITS: usize = 128;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Inconsistency {
+    pub pool: PoolId,
+    pub dir: SwapDirection,
+    pub probe_in: U256,
+    /// What the reported marginal price says `probe_in` buys.
+    pub expected_out: U256,
+    pub probe_out: U256,
+    pub dev_bps: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AuditReport {
+    /// Directions whose reported price was checked against a probe.
+    pub checked: usize,
+    /// Directions skipped: no pool, no state, or no reported price.
+    pub skipped: usize,
+    /// Worst first.
+    pub inconsistent: Vec<Inconsistency>,
+}
+
+impl AuditReport {
+    pub fn is_clean(&self) -> bool {
+        self.inconsistent.is_empty()
+    }
+}
+
+impl<S> World<S> {
+    /// Checks every direction of every pool in `graph`: a probe just large
+    /// enough to resolve the output to a part in [`DEPTH_PRECISION`] must get
+    /// within `tolerance_bps` of what [`Pool::marginal_price`] predicts.
+    pub fn audit<P: Pool<State = S>>(
+        &self,
+        engine: &Engine<'_, P>,
+        graph: &AMMGraph,
+        tolerance_bps: u64,
+    ) -> AuditReport {
+        let ctx = self.block();
+        let mut report = AuditReport::default();
+        let mut pools: Vec<_> = graph.pool_idx.keys().copied().collect();
+        pools.sort();
+        for pid in pools {
+            let (ins, outs) = (tokens(graph, pid, false), tokens(graph, pid, true));
+            for &from in &ins {
+                for &to in &outs {
+                    let Some(dir) = SwapDirection::new(from, to) else {
+                        continue;
+                    };
+                    match check(engine, self.pool_state(pid), &ctx, pid, dir) {
+                        None => report.skipped += 1,
+                        Some(found) => {
+                            report.checked += 1;
+                            if found.dev_bps > tolerance_bps {
+                                report.inconsistent.push(found);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        report
+            .inconsistent
+            .sort_by(|a, b| b.dev_bps.cmp(&a.dev_bps).then(a.pool.cmp(&b.pool)));
+        report
+    }
+}
+
+fn tokens(graph: &AMMGraph, pid: PoolId, emitted: bool) -> Vec<TokenId> {
+    let nodes = if emitted {
+        graph.tokens_emitted_by(pid).map(|n| n.collect::<Vec<_>>())
+    } else {
+        graph.tokens_accepted_by(pid).map(|n| n.collect::<Vec<_>>())
+    };
+    let mut out: Vec<_> = nodes
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|n| match graph.g[n] {
+            NodeKind::Token(t) => Some(t),
+            NodeKind::Pool(_) => None,
+        })
+        .collect();
+    out.sort();
+    out
+}
+
+fn check<P: Pool>(
+    engine: &Engine<'_, P>,
+    st: Option<&P::State>,
+    ctx: &BlockContext,
+    pid: PoolId,
+    dir: SwapDirection,
+) -> Option<Inconsistency> {
+    let pool = engine.pools.get(&pid).filter(|p| p.supports(dir))?;
+    let st = st?;
+    let price = pool.marginal_price(st, dir)?;
+    let out = |amt: U256| {
+        pool.swap(&mut st.clone(), ctx, dir, amt)
+            .unwrap_or_default()
+    };
+
+    let mut probe_in = U256::from(1u64);
+    let mut probe_out = out(probe_in);
+    while probe_out < U256::from(DEPTH_PRECISION) && probe_in.bit_len() < PROBE_MAX_BITS {
+        probe_in <<= 1;
+        probe_out = out(probe_in);
+    }
+    let expected_out = price.quote(probe_in)?;
+    Some(Inconsistency {
+        pool: pid,
+        dir,
+        probe_in,
+        expected_out,
+        probe_out,
+        dev_bps: deviation_bps(expected_out, probe_out),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::num::{MathResult, Price};
+    use crate::test_utils::Cp;
+    use crate::univ2::{UniV2Pool, UniV2State};
+    use std::collections::HashMap;
+
+    /// Keeps its price apart from the reserves it swaps against, as a V3
+    /// pool keeps `sqrtPriceX96` apart from its liquidity.
+    struct Priced(Cp);
+
+    impl Pool for Priced {
+        type State = (Price, (U256, U256));
+
+        fn id(&self) -> PoolId {
+            self.0.id
+        }
+
+        fn supports(&self, dir: SwapDirection) -> bool {
+            self.0.supports(dir)
+        }
+
+        fn swap(
+            &self,
+            st: &mut Self::State,
+            ctx: &BlockContext,
+            dir: SwapDirection,
+            amt_in: U256,
+        ) -> MathResult<U256> {
+            self.0.swap(&mut st.1, ctx, dir, amt_in)
+        }
+
+        fn marginal_price(&self, st: &Self::State, dir: SwapDirection) -> Option<Price> {
+            if dir.from == self.0.t0 {
+                Some(st.0)
+            } else {
+                st.0.invert()
+            }
+        }
+    }
+
+    #[test]
+    fn flags_pools_whose_price_and_reserves_disagree() {
+        let e18 = U256::from(10u64).pow(U256::from(18u64));
+        let two = Price::from_ratio(U256::from(2u64), U256::from(1u64)).unwrap();
+        let pools: HashMap<_, _> = (1..=3)
+            .map(|id| (PoolId(id), Priced(Cp::new(id, 1, 2))))
+            .collect();
+        let mut graph = AMMGraph::new();
+        let mut world = World::default();
+        for id in 1..=3 {
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(1), TokenId(2));
+        }
+        world.set_pool_state(PoolId(1), (two, (e18, e18 * U256::from(2u64))));
+        // The reserves moved 1:1 but the price was not rewritten.
+        world.set_pool_state(PoolId(2), (two, (e18, e18)));
+
+        let report = world.audit(&Engine::new(&pools), &graph, DEFAULT_AUDIT_TOLERANCE_BPS);
+        assert_eq!((report.checked, report.skipped), (4, 2));
+        let flagged: Vec<_> = report.inconsistent.iter().map(|i| i.pool).collect();
+        assert_eq!(flagged, [PoolId(2), PoolId(2)]);
+        assert!(report.inconsistent[0].dev_bps >= 5_000);
+
+        let v2 = HashMap::from([(PoolId(1), UniV2Pool::new(PoolId(1), TokenId(1), TokenId(2)))]);
+        let mut world = World::default();
+        world.set_pool_state(PoolId(1), UniV2State::new(e18, e18 * U256::from(3u64)));
+        let report = world.audit(&Engine::new(&v2), &graph, DEFAULT_AUDIT_TOLERANCE_BPS);
+        assert!(report.is_clean(), "{:?}", report.inconsistent);
+        assert_eq!(report.checked, 2);
+    }
+}
@@ -0,0 +1,351 @@
+use crate::{
+    engine::{Engine, Hop},
+    graph::AMMGraph,
+    ids::TokenId,
+    pool::Pool,
+    registry::Registry,
+    timeline::Timeline,
+    world::World,
+};
+use alloy_primitives::{I256, U256};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+
+pub trait WorldSource<S> {
+    fn world_at(&mut self, block: u64) -> Option<World<S>>;
+}
+
+impl<S: Clone> WorldSource<S> for Timeline<S> {
+    fn world_at(&mut self, block: u64) -> Option<World<S>> {
+        (&*self).world_at(block)
+    }
+}
+
+/// A shared timeline, so parallel workers can each materialize their own
+/// blocks from one copy.
+impl<S: Clone> WorldSource<S> for &Timeline<S> {
+    fn world_at(&mut self, block: u64) -> Option<World<S>> {
+        self.at(block).map(|v| {
+            let mut w = v.materialize();
+            w.block.number = block;
+            w
+        })
+    }
+}
+
+impl<S, F: FnMut(u64) -> Option<World<S>>> WorldSource<S> for F {
+    fn world_at(&mut self, block: u64) -> Option<World<S>> {
+        self(block)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trade {
+    pub plan: Vec<Hop>,
+    pub amount_in: U256,
+    pub min_out: U256,
+}
+
+pub trait Strategy<S> {
+    fn on_block(
+        &mut self,
+        block: u64,
+        world: &World<S>,
+        graph: &AMMGraph,
+        reg: &Registry,
+    ) -> Vec<Trade>;
+}
+
+impl<S, F> Strategy<S> for F
+where
+    F: FnMut(u64, &World<S>, &AMMGraph, &Registry) -> Vec<Trade>,
+{
+    fn on_block(
+        &mut self,
+        block: u64,
+        world: &World<S>,
+        graph: &AMMGraph,
+        reg: &Registry,
+    ) -> Vec<Trade> {
+        self(block, world, graph, reg)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PathStats {
+    pub attempts: u64,
+    pub fills: u64,
+    pub total_in: U256,
+    pub total_out: U256,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BacktestReport {
+    pub blocks: u64,
+    pub missing_blocks: Vec<u64>,
+    pub trades: u64,
+    pub fills: u64,
+    pub pnl: HashMap<TokenId, I256>,
+    pub paths: HashMap<Vec<Hop>, PathStats>,
+}
+
+impl BacktestReport {
+    pub fn fill_rate(&self) -> f64 {
+        if self.trades == 0 {
+            return 0.0;
+        }
+        self.fills as f64 / self.trades as f64
+    }
+
+    pub fn pnl(&self, t: TokenId) -> I256 {
+        self.pnl.get(&t).copied().unwrap_or_default()
+    }
+
+    /// Folds in the report of a disjoint set of blocks.
+    pub fn merge(&mut self, other: BacktestReport) {
+        self.blocks += other.blocks;
+        self.missing_blocks.extend(other.missing_blocks);
+        self.missing_blocks.sort_unstable();
+        self.trades += other.trades;
+        self.fills += other.fills;
+        for (t, amt) in other.pnl {
+            let bal = self.pnl.entry(t).or_default();
+            *bal = bal.saturating_add(amt);
+        }
+        for (plan, o) in other.paths {
+            let stats = self.paths.entry(plan).or_default();
+            stats.attempts += o.attempts;
+            stats.fills += o.fills;
+            stats.total_in = stats.total_in.saturating_add(o.total_in);
+            stats.total_out = stats.total_out.saturating_add(o.total_out);
+        }
+    }
+
+    fn book(&mut self, t: TokenId, amt: U256, credit: bool) {
+        let amt = I256::try_from(amt).unwrap_or(I256::MAX);
+        let bal = self.pnl.entry(t).or_default();
+        *bal = if credit {
+            bal.saturating_add(amt)
+        } else {
+            bal.saturating_sub(amt)
+        };
+    }
+}
+
+pub struct Backtest<'a, P: Pool> {
+    pub engine: &'a Engine<'a, P>,
+    pub graph: &'a AMMGraph,
+    pub registry: &'a Registry,
+}
+
+impl<'a, P: Pool> Backtest<'a, P> {
+    pub fn new(engine: &'a Engine<'a, P>, graph: &'a AMMGraph, registry: &'a Registry) -> Self {
+        Self {
+            engine,
+            graph,
+            registry,
+        }
+    }
+
+    pub fn run<W, St>(
+        &self,
+        source: &mut W,
+        blocks: RangeInclusive<u64>,
+        strategy: &mut St,
+    ) -> BacktestReport
+    where
+        W: WorldSource<P::State>,
+        St: Strategy<P::State>,
+    {
+        let mut report = BacktestReport::default();
+        for block in blocks {
+            self.run_block(source, block, strategy, &mut report);
+        }
+        report
+    }
+
+    /// [`Backtest::run`] with the blocks spread over the available cores.
+    /// Each worker materializes its own worlds from a copy of `source` and
+    /// trades them with a copy of `strategy`, so blocks must be independent:
+    /// a strategy carrying state between blocks sees only its worker's. The
+    /// report is the same however the blocks were scheduled.
+    pub fn run_parallel<W, St>(
+        &self,
+        source: &W,
+        blocks: RangeInclusive<u64>,
+        strategy: &St,
+    ) -> BacktestReport
+    where
+        P: Sync,
+        P::State: Send + Sync,
+        W: WorldSource<P::State> + Clone + Send,
+        St: Strategy<P::State> + Clone + Send,
+    {
+        let (start, end) = blocks.into_inner();
+        if start > end {
+            return BacktestReport::default();
+        }
+        let workers = thread::available_parallelism()
+            .map_or(1, |n| n.get() as u64)
+            .min((end - start).saturating_add(1));
+        let next = AtomicU64::new(start);
+        let report = Mutex::new(BacktestReport::default());
+        thread::scope(|s| {
+            for _ in 0..workers {
+                let (mut source, mut strategy) = (source.clone(), strategy.clone());
+                let (next, report) = (&next, &report);
+                s.spawn(move || {
+                    let mut ours = BacktestReport::default();
+                    loop {
+                        let block = next.fetch_add(1, Ordering::Relaxed);
+                        // Past `end`, or wrapped around after `u64::MAX`.
+                        if block > end || block < start {
+                            break;
+                        }
+                        self.run_block(&mut source, block, &mut strategy, &mut ours);
+                    }
+                    report.lock().expect("backtest report poisoned").merge(ours);
+                });
+            }
+        });
+        report.into_inner().expect("backtest report poisoned")
+    }
+
+    fn run_block<W, St>(
+        &self,
+        source: &mut W,
+        block: u64,
+        strategy: &mut St,
+        report: &mut BacktestReport,
+    ) where
+        W: WorldSource<P::State>,
+        St: Strategy<P::State>,
+    {
+        let Some(mut world) = source.world_at(block) else {
+            report.missing_blocks.push(block);
+            return;
+        };
+        report.blocks += 1;
+
+        let trades = strategy.on_block(block, &world, self.graph, self.registry);
+        for trade in trades {
+            if trade.plan.is_empty() {
+                continue;
+            }
+            report.trades += 1;
+            let stats = report.paths.entry(trade.plan.clone()).or_default();
+            stats.attempts += 1;
+
+            let mut scratch = world.clone();
+            let path = self
+                .engine
+                .apply(&mut scratch, &trade.plan, trade.amount_in);
+            let last = path.steps.last().expect("non-empty plan");
+            if last.amt_out < trade.min_out {
+                continue;
+            }
+
+            stats.fills += 1;
+            stats.total_in = stats.total_in.saturating_add(trade.amount_in);
+            stats.total_out = stats.total_out.saturating_add(last.amt_out);
+            report.fills += 1;
+            report.book(path.steps[0].from, trade.amount_in, false);
+            report.book(last.to, last.amt_out, true);
+            world = scratch;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::WorldDiff;
+
+    #[test]
+    fn collects_pnl_fill_rate_and_path_stats() {
+        let mut pools = HashMap::new();
+        let mut graph = AMMGraph::new();
+        for (id, a, b) in [(1, 1, 2), (2, 1, 2)] {
+            pools.insert(PoolId(id), Cp::new(id, a, b));
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(a), TokenId(b));
+        }
+        let engine = Engine::new(&pools);
+        let reg = Registry::default();
+
+        let mut base = World::default();
+        base.pool_states
+            .insert(PoolId(1), reserves(1_000_000, 1_000_000));
+        base.pool_states
+            .insert(PoolId(2), reserves(1_000_000, 1_000_000));
+        let mut tl = Timeline::new();
+        tl.insert_snapshot(100, base);
+        let mut skew = WorldDiff::default();
+        skew.set_pool_state(PoolId(2), reserves(1_000_000, 1_100_000));
+        tl.insert_diff(102, skew);
+
+        let cycle = vec![hop(2, 1, 2), hop(1, 2, 1)];
+        let mut strategy = |_block: u64, _w: &World<(U256, U256)>, _g: &AMMGraph, _r: &Registry| {
+            vec![
+                Trade {
+                    plan: cycle.clone(),
+                    amount_in: U256::from(10_000u64),
+                    min_out: U256::from(10_001u64),
+                },
+                Trade {
+                    plan: vec![hop(1, 1, 2)],
+                    amount_in: U256::from(1_000u64),
+                    min_out: U256::ZERO,
+                },
+            ]
+        };
+
+        let report = Backtest::new(&engine, &graph, &reg).run(&mut tl, 99..=103, &mut strategy);
+        assert_eq!(report.blocks, 4);
+        assert_eq!(report.missing_blocks, vec![99]);
+        assert_eq!(report.trades, 8);
+        assert_eq!(report.fills, 6, "cycle only fills after the skew");
+        assert_eq!(report.fill_rate(), 0.75);
+        assert_eq!(report.paths[&cycle].fills, 2);
+        assert!(report.pnl(TokenId(1)) < I256::ZERO);
+        assert!(report.pnl(TokenId(2)) > I256::ZERO);
+    }
+
+    #[test]
+    fn parallel_run_reports_what_a_serial_one_does() {
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2)), (PoolId(2), Cp::new(2, 1, 2))]);
+        let engine = Engine::new(&pools);
+        let (graph, reg) = (AMMGraph::new(), Registry::default());
+
+        let mut base = World::default();
+        base.set_pool_state(PoolId(1), reserves(1_000_000, 1_000_000));
+        base.set_pool_state(PoolId(2), reserves(1_000_000, 1_000_000));
+        let mut tl = Timeline::new();
+        tl.insert_snapshot(10, base);
+        for block in (20..200).step_by(7) {
+            let mut skew = WorldDiff::default();
+            skew.set_pool_state(PoolId(2), reserves(1_000_000, 1_000_000 + 1_000 * block));
+            tl.insert_diff(block, skew);
+        }
+
+        let cycle = vec![hop(2, 1, 2), hop(1, 2, 1)];
+        let strategy = |block: u64, _w: &World<(U256, U256)>, _g: &AMMGraph, _r: &Registry| {
+            vec![Trade {
+                plan: cycle.clone(),
+                amount_in: U256::from(1_000 * (block % 5 + 1)),
+                min_out: U256::from(1_000 * (block % 5 + 1) + 1),
+            }]
+        };
+
+        let backtest = Backtest::new(&engine, &graph, &reg);
+        let serial = backtest.run(&mut &tl, 0..=199, &mut strategy.clone());
+        let parallel = backtest.run_parallel(&&tl, 0..=199, &strategy);
+        assert_eq!(parallel, serial);
+        assert_eq!(parallel.missing_blocks, (0..10).collect::<Vec<_>>());
+        assert!(parallel.fills > 0 && parallel.fills < parallel.trades);
+    }
+}
@@ -0,0 +1,421 @@
+use alloy_primitives::{
+    Address, U256,
+    utils::{format_units, parse_units},
+};
+use alloy_provider::{
+    Provider, ProviderBuilder,
+    network::{Ethereum, Network, TransactionBuilder},
+};
+use alloy_sol_types::{SolCall, sol};
+use clap::{Args, Parser, Subcommand};
+use std::error::Error;
+use std::path::{Path as FsPath, PathBuf};
+use wayfinder::{
+    BlockContext, ChainId, Engine, Registry, Scanner, TokenId, UniV2State, World,
+    arb::ScanConfig,
+    config::{ChainConfig, Config, ConfigError},
+    engine::{Hop, Path},
+    registry::{PoolKind, PoolMeta, TokenMeta},
+    univ2::pools_from_registry,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+sol! {
+    function allPairsLength() external view returns (uint256);
+    function allPairs(uint256 index) external view returns (address);
+    function token0() external view returns (address);
+    function token1() external view returns (address);
+    function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+    function symbol() external view returns (string);
+    function decimals() external view returns (uint8);
+}
+
+const UNIV2_FEE: u32 = 3000;
+
+#[derive(Parser)]
+#[command(
+    name = "wayfinder",
+    about = "Index AMM pools, quote swaps and search routes"
+)]
+struct Cli {
+    /// TOML deployment config; optional when flags cover the RPC URL.
+    #[arg(long, env = "WAYFINDER_CONFIG", default_value = "wayfinder.toml")]
+    config: PathBuf,
+    /// Chain section of the config to use; may be omitted if there is only one.
+    #[arg(long, env = "WAYFINDER_CHAIN")]
+    chain: Option<String>,
+    #[arg(long, env = "WAYFINDER_RPC_URL")]
+    rpc_url: Option<String>,
+    #[arg(long, env = "WAYFINDER_REGISTRY")]
+    registry: Option<PathBuf>,
+    #[command(subcommand)]
+    cmd: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Discover UniswapV2-style pairs from a factory into the registry file.
+    Index {
+        #[arg(long)]
+        factory: Address,
+        #[arg(long, default_value_t = 0)]
+        start: u64,
+        #[arg(long, default_value_t = 500)]
+        limit: u64,
+    },
+    /// Quote the best output for swapping `amount` of `from` into `to`.
+    Quote(SwapArgs),
+    /// List the best routes from `from` to `to`, ranked by output.
+    Route {
+        #[command(flatten)]
+        swap: SwapArgs,
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+    },
+    #[command(subcommand)]
+    Arb(ArbCommand),
+}
+
+#[derive(Subcommand)]
+enum ArbCommand {
+    /// Scan cycles through the base tokens for profitable arbitrage.
+    Scan {
+        /// Base token symbols or addresses; defaults to the configured base
+        /// tokens, or every indexed token.
+        #[arg(long = "base")]
+        bases: Vec<String>,
+        #[arg(long)]
+        max_hops: Option<usize>,
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+    },
+}
+
+#[derive(Args)]
+struct SwapArgs {
+    from: String,
+    to: String,
+    /// Input amount in whole units of `from`, e.g. `1.5`.
+    amount: String,
+    #[arg(long)]
+    max_hops: Option<usize>,
+}
+
+struct Ctx {
+    rpc_url: String,
+    registry: PathBuf,
+    chain: Option<ChainConfig>,
+    scan: ScanConfig,
+}
+
+impl Ctx {
+    fn resolve(cli: &Cli) -> Result<Self> {
+        let config = match Config::load(&cli.config) {
+            Ok(config) => config,
+            Err(ConfigError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                Config::default()
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let chain = if config.chains.is_empty() && cli.chain.is_none() {
+            None
+        } else {
+            Some(config.chain(cli.chain.as_deref())?.clone())
+        };
+        let scan = chain
+            .as_ref()
+            .map_or_else(ScanConfig::default, |c| config.scan_config(c));
+
+        let rpc_url = cli
+            .rpc_url
+            .clone()
+            .or_else(|| chain.as_ref()?.rpc_url.clone())
+            .ok_or("no RPC URL: pass --rpc-url, set WAYFINDER_RPC_URL or add rpc_url to config")?;
+        let registry = cli
+            .registry
+            .clone()
+            .or_else(|| chain.as_ref()?.registry.clone())
+            .unwrap_or_else(|| PathBuf::from("registry.json"));
+        Ok(Self {
+            rpc_url,
+            registry,
+            chain,
+            scan,
+        })
+    }
+
+    fn load_registry(&self) -> Result<Registry> {
+        let reg = Registry::load(&self.registry)
+            .map_err(|e| format!("loading registry {}: {e}", self.registry.display()))?;
+        Ok(match &self.chain {
+            Some(chain) => chain.filter_registry(&reg),
+            None => reg,
+        })
+    }
+
+    fn scan_config(&self, max_hops: Option<usize>) -> ScanConfig {
+        ScanConfig {
+            max_hops: max_hops.unwrap_or(self.scan.max_hops),
+            ..self.scan
+        }
+    }
+}
+
+async fn call<C: SolCall>(
+    provider: &impl Provider,
+    to: Address,
+    call: C,
+    block: u64,
+) -> Result<C::Return> {
+    let tx = <Ethereum as Network>::TransactionRequest::default()
+        .with_to(to)
+        .with_input(call.abi_encode());
+    let data = provider.call(tx).block(block.into()).await?;
+    Ok(C::abi_decode_returns(&data)?)
+}
+
+async fn token_meta(provider: &impl Provider, address: Address, block: u64) -> TokenMeta {
+    let symbol = call(provider, address, symbolCall {}, block)
+        .await
+        .unwrap_or_else(|_| address.to_string());
+    let decimals = call(provider, address, decimalsCall {}, block)
+        .await
+        .unwrap_or(18);
+    TokenMeta {
+        address,
+        symbol,
+        decimals,
+    }
+}
+
+async fn index(
+    provider: &impl Provider,
+    path: &FsPath,
+    factory: Address,
+    start: u64,
+    limit: u64,
+) -> Result<()> {
+    let mut reg = if path.exists() {
+        Registry::load(path)?
+    } else {
+        Registry::default()
+    };
+    let chain = ChainId(provider.get_chain_id().await?);
+    let block = provider.get_block_number().await?;
+
+    let len = call(provider, factory, allPairsLengthCall {}, block).await?;
+    let end = U256::from(start.saturating_add(limit)).min(len).to::<u64>();
+    let mut added = 0;
+    for i in start..end {
+        let pair = call(
+            provider,
+            factory,
+            allPairsCall {
+                index: U256::from(i),
+            },
+            block,
+        )
+        .await?;
+        if reg.pool_by_addr.contains_key(&pair) {
+            continue;
+        }
+        let mut ids = [TokenId(0); 2];
+        for (id, addr) in ids.iter_mut().zip([
+            call(provider, pair, token0Call {}, block).await?,
+            call(provider, pair, token1Call {}, block).await?,
+        ]) {
+            *id = match reg.token_by_addr.get(&addr) {
+                Some(&tid) => tid,
+                None => reg.insert_token_hashed(chain, token_meta(provider, addr, block).await),
+            };
+        }
+        reg.insert_pool_hashed(
+            chain,
+            PoolMeta {
+                address: pair,
+                kind: PoolKind::UniV2,
+                token0: ids[0],
+                token1: ids[1],
+                fee: UNIV2_FEE,
+            },
+        );
+        added += 1;
+    }
+
+    reg.save(path)?;
+    println!(
+        "indexed {added} new pairs ({start}..{end} of {len}) at block {block}; registry has {} pools, {} tokens",
+        reg.pool_meta.len(),
+        reg.token_meta.len()
+    );
+    Ok(())
+}
+
+async fn load_world(provider: &impl Provider, reg: &Registry) -> Result<World<UniV2State>> {
+    let number = provider.get_block_number().await?;
+    let mut world = World {
+        block: BlockContext {
+            number,
+            ..BlockContext::default()
+        },
+        ..World::default()
+    };
+    for (&pid, meta) in &reg.pool_meta {
+        if meta.kind != PoolKind::UniV2 {
+            continue;
+        }
+        let r = call(provider, meta.address, getReservesCall {}, number).await?;
+        world.set_pool_state(
+            pid,
+            UniV2State::new(U256::from(r.reserve0), U256::from(r.reserve1)),
+        );
+    }
+    Ok(world)
+}
+
+fn resolve_token(reg: &Registry, s: &str) -> Result<TokenId> {
+    reg.resolve_token(s)
+        .ok_or_else(|| format!("unknown or ambiguous token {s}").into())
+}
+
+fn symbol(reg: &Registry, tid: TokenId) -> String {
+    reg.token(tid)
+        .map(|m| m.symbol.clone())
+        .unwrap_or_else(|| tid.to_string())
+}
+
+fn fmt_amount(reg: &Registry, tid: TokenId, amt: U256) -> String {
+    let decimals = reg.token(tid).map_or(0, |m| m.decimals);
+    format_units(amt, decimals).unwrap_or_else(|_| amt.to_string())
+}
+
+fn describe(reg: &Registry, plan: &[Hop]) -> String {
+    let mut out = symbol(reg, plan[0].dir.from);
+    for h in plan {
+        let pool = reg
+            .pool(h.pool)
+            .map_or_else(|| h.pool.to_string(), |m| m.address.to_string());
+        out.push_str(&format!(" -[{pool}]-> {}", symbol(reg, h.dir.to)));
+    }
+    out
+}
+
+fn amount_out(path: &Path) -> U256 {
+    path.steps.last().map(|s| s.amt_out).unwrap_or_default()
+}
+
+async fn swap(
+    provider: &impl Provider,
+    ctx: &Ctx,
+    reg: &Registry,
+    args: &SwapArgs,
+    top: usize,
+) -> Result<()> {
+    let from = resolve_token(reg, &args.from)?;
+    let to = resolve_token(reg, &args.to)?;
+    let decimals = reg.token(from).map_or(18, |m| m.decimals);
+    let amt_in = parse_units(&args.amount, decimals)?.get_absolute();
+
+    let world = load_world(provider, reg).await?;
+    let (pools, graph) = pools_from_registry(reg);
+    let engine = Engine::new(&pools);
+    let scanner = Scanner::new(&engine, &graph).with_config(ctx.scan_config(args.max_hops));
+
+    let mut paths: Vec<(Vec<Hop>, Path)> = scanner
+        .routes(&world, from, to)
+        .into_iter()
+        .map(|plan| {
+            let path = engine.simulate_chained(&world, &plan, amt_in);
+            (plan, path)
+        })
+        .collect();
+    paths.sort_by_key(|(_, p)| std::cmp::Reverse(amount_out(p)));
+    if paths.is_empty() {
+        return Err(format!("no route from {} to {}", args.from, args.to).into());
+    }
+
+    println!("block {}", world.block.number);
+    for (plan, path) in paths.iter().take(top) {
+        println!(
+            "{} {}  {}",
+            fmt_amount(reg, to, amount_out(path)),
+            symbol(reg, to),
+            describe(reg, plan)
+        );
+    }
+    Ok(())
+}
+
+async fn arb_scan(
+    provider: &impl Provider,
+    ctx: &Ctx,
+    reg: &Registry,
+    bases: &[String],
+    max_hops: Option<usize>,
+    top: usize,
+) -> Result<()> {
+    let configured = ctx
+        .chain
+        .as_ref()
+        .map(|c| c.base_tokens(reg))
+        .unwrap_or_default();
+    let bases = if !bases.is_empty() {
+        bases
+            .iter()
+            .map(|b| resolve_token(reg, b))
+            .collect::<Result<_>>()?
+    } else if !configured.is_empty() {
+        configured
+    } else {
+        let mut all: Vec<TokenId> = reg.token_meta.keys().copied().collect();
+        all.sort();
+        all
+    };
+
+    let world = load_world(provider, reg).await?;
+    let (pools, graph) = pools_from_registry(reg);
+    let engine = Engine::new(&pools);
+    let scanner = Scanner::new(&engine, &graph).with_config(ctx.scan_config(max_hops));
+
+    let opps = scanner.scan(&world, &bases);
+    println!("block {}: {} opportunities", world.block.number, opps.len());
+    for opp in opps.iter().take(top) {
+        let base = opp.base();
+        println!(
+            "+{} {} on {} in  {}",
+            fmt_amount(reg, base, opp.net),
+            symbol(reg, base),
+            fmt_amount(reg, base, opp.optimal_in),
+            describe(reg, &opp.plan)
+        );
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let ctx = Ctx::resolve(&cli)?;
+    let provider = ProviderBuilder::new().connect(&ctx.rpc_url).await?;
+
+    match &cli.cmd {
+        Command::Index {
+            factory,
+            start,
+            limit,
+        } => index(&provider, &ctx.registry, *factory, *start, *limit).await,
+        Command::Quote(args) => swap(&provider, &ctx, &ctx.load_registry()?, args, 1).await,
+        Command::Route { swap: args, top } => {
+            swap(&provider, &ctx, &ctx.load_registry()?, args, *top).await
+        }
+        Command::Arb(ArbCommand::Scan {
+            bases,
+            max_hops,
+            top,
+        }) => {
+            let reg = ctx.load_registry()?;
+            arb_scan(&provider, &ctx, &reg, bases, *max_hops, *top).await
+        }
+    }
+}
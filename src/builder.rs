@@ -0,0 +1,241 @@
+//! Assembles a [`Router`] and checks that its parts agree on the pool set.
+
+use crate::{
+    arb::ScanConfig, error::BuildError, gas::GasTracker, graph::AMMGraph, ids::PoolId, pool::Pool,
+    registry::Registry, router::Router, world::World,
+};
+use std::collections::HashMap;
+
+/// Entry point for [`Wayfinder::builder`].
+pub struct Wayfinder;
+
+impl Wayfinder {
+    pub fn builder<P: Pool>() -> RouterBuilder<P> {
+        RouterBuilder::default()
+    }
+}
+
+pub struct RouterBuilder<P: Pool> {
+    registry: Option<Registry>,
+    graph: Option<AMMGraph>,
+    graph_from_registry: bool,
+    pools: Option<HashMap<PoolId, P>>,
+    world: Option<World<P::State>>,
+    gas: Option<GasTracker>,
+    config: Option<ScanConfig>,
+}
+
+impl<P: Pool> Default for RouterBuilder<P> {
+    fn default() -> Self {
+        Self {
+            registry: None,
+            graph: None,
+            graph_from_registry: false,
+            pools: None,
+            world: None,
+            gas: None,
+            config: None,
+        }
+    }
+}
+
+impl<P: Pool> RouterBuilder<P> {
+    pub fn registry(mut self, registry: Registry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    pub fn graph(mut self, graph: AMMGraph) -> Self {
+        self.graph = Some(graph);
+        self.graph_from_registry = false;
+        self
+    }
+
+    /// Builds the graph from every registry pool instead of taking one.
+    pub fn graph_from_registry(mut self) -> Self {
+        self.graph = None;
+        self.graph_from_registry = true;
+        self
+    }
+
+    pub fn pools(mut self, pools: HashMap<PoolId, P>) -> Self {
+        self.pools = Some(pools);
+        self
+    }
+
+    pub fn world(mut self, world: World<P::State>) -> Self {
+        self.world = Some(world);
+        self
+    }
+
+    pub fn gas_model(mut self, gas: GasTracker) -> Self {
+        self.gas = Some(gas);
+        self
+    }
+
+    pub fn config(mut self, config: ScanConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Every graph pool must have registry metadata, an implementation and
+    /// state in the world; the first one that does not, by id, is reported.
+    /// A graph built from the registry rejects pools pairing a token with
+    /// itself as [`BuildError::PoolMismatch`].
+    pub fn build(self) -> Result<Router<P>, BuildError> {
+        let registry = self.registry.ok_or(BuildError::Missing("registry"))?;
+        let graph = match self.graph {
+            Some(graph) => graph,
+            None if self.graph_from_registry => {
+                let degenerate = registry
+                    .pool_meta
+                    .iter()
+                    .filter(|(_, m)| m.token0 == m.token1)
+                    .map(|(&pid, _)| pid)
+                    .min();
+                if let Some(pid) = degenerate {
+                    return Err(BuildError::PoolMismatch(pid));
+                }
+                AMMGraph::from_registry(&registry)
+            }
+            None => return Err(BuildError::Missing("graph")),
+        };
+        let pools = self.pools.ok_or(BuildError::Missing("pools"))?;
+        let world = self.world.ok_or(BuildError::Missing("world"))?;
+
+        let mut graph_pools: Vec<PoolId> = graph.pool_idx.keys().copied().collect();
+        graph_pools.sort();
+        for pid in graph_pools {
+            if registry.pool(pid).is_none() {
+                return Err(BuildError::MissingMeta(pid));
+            }
+            if !pools.contains_key(&pid) {
+                return Err(BuildError::MissingImpl(pid));
+            }
+            if !world.pool_states.contains_key(&pid) {
+                return Err(BuildError::MissingState(pid));
+            }
+        }
+
+        let mut router = Router::new(registry, graph, pools, world);
+        if let Some(config) = self.config {
+            router.config = config;
+        }
+        if let Some(gas) = self.gas {
+            router = router.with_gas(gas);
+        }
+        Ok(router)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gas::BlockFees;
+    use crate::ids::TokenId;
+    use crate::registry::{PoolKind, PoolMeta};
+    use crate::test_utils::{Cp, reserves};
+    use alloy_primitives::{Address, U256};
+
+    fn parts() -> (Registry, HashMap<PoolId, Cp>, World<(U256, U256)>) {
+        let mut reg = Registry::default();
+        let mut pools = HashMap::new();
+        let mut world = World::default();
+        for (id, t0, t1) in [(1, 1, 2), (2, 2, 3)] {
+            reg.upsert_pool(
+                PoolId(id),
+                PoolMeta {
+                    address: Address::repeat_byte(id as u8),
+                    kind: PoolKind::UniV2,
+                    token0: TokenId(t0),
+                    token1: TokenId(t1),
+                    fee: 0,
+                },
+            );
+            pools.insert(PoolId(id), Cp::new(id, t0, t1));
+            world.set_pool_state(PoolId(id), reserves(1_000_000, 1_000_000));
+        }
+        (reg, pools, world)
+    }
+
+    #[test]
+    fn builds_a_wired_router() {
+        let (reg, pools, world) = parts();
+        let mut gas = GasTracker::new(4);
+        gas.observe(BlockFees {
+            number: 1,
+            basefee: 10,
+            gas_used: 15_000_000,
+            gas_limit: 30_000_000,
+            tips: vec![2],
+        });
+        let router = Wayfinder::builder()
+            .registry(reg)
+            .graph_from_registry()
+            .pools(pools)
+            .world(world)
+            .gas_model(gas)
+            .build()
+            .unwrap();
+        assert_eq!(router.config.gas_per_hop, U256::from(100_000u64 * 12));
+        assert!(
+            router
+                .quote(TokenId(1), TokenId(3), U256::from(1_000u64))
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn reports_wiring_mistakes() {
+        let (reg, mut pools, mut world) = parts();
+        let build = |reg: &Registry, pools: &HashMap<PoolId, Cp>, world: &World<_>| {
+            let pools = pools
+                .iter()
+                .map(|(&k, v)| (k, Cp::new(k.0, v.t0.0, v.t1.0)));
+            Wayfinder::builder()
+                .registry(reg.clone())
+                .graph_from_registry()
+                .pools(pools.collect())
+                .world(world.clone())
+                .build()
+                .err()
+        };
+        assert_eq!(build(&reg, &pools, &world), None);
+
+        world.pool_states.remove(&PoolId(2));
+        assert_eq!(
+            build(&reg, &pools, &world),
+            Some(BuildError::MissingState(PoolId(2)))
+        );
+        pools.remove(&PoolId(1));
+        assert_eq!(
+            build(&reg, &pools, &world),
+            Some(BuildError::MissingImpl(PoolId(1)))
+        );
+
+        let mut bad = reg.clone();
+        let mut meta = bad.pool(PoolId(1)).unwrap().clone();
+        meta.token1 = meta.token0;
+        bad.upsert_pool(PoolId(1), meta);
+        assert_eq!(
+            build(&bad, &pools, &world),
+            Some(BuildError::PoolMismatch(PoolId(1)))
+        );
+
+        let (reg, pools, world) = parts();
+        let mut graph = AMMGraph::from_registry(&reg);
+        graph.connect_bidirectional_pair(PoolId(9), TokenId(1), TokenId(9));
+        let err = Wayfinder::builder()
+            .registry(reg)
+            .graph(graph)
+            .pools(pools)
+            .world(world)
+            .build()
+            .err();
+        assert_eq!(err, Some(BuildError::MissingMeta(PoolId(9))));
+        assert_eq!(
+            Wayfinder::builder::<Cp>().build().err(),
+            Some(BuildError::Missing("registry"))
+        );
+    }
+}
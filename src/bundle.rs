@@ -0,0 +1,255 @@
+use alloy_primitives::{Address, B256, Bytes, U256};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BundleTx {
+    pub to: Address,
+    pub data: Bytes,
+    pub value: U256,
+    pub gas_limit: u64,
+    /// Allowed to revert without invalidating the bundle.
+    pub can_revert: bool,
+}
+
+impl BundleTx {
+    pub fn call(to: Address, data: Bytes, gas_limit: u64) -> Self {
+        Self {
+            to,
+            data,
+            value: U256::ZERO,
+            gas_limit,
+            can_revert: false,
+        }
+    }
+
+    pub fn with_value(mut self, value: U256) -> Self {
+        self.value = value;
+        self
+    }
+
+    pub fn allow_revert(mut self) -> Self {
+        self.can_revert = true;
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bundle {
+    pub txs: Vec<BundleTx>,
+    pub target_block: u64,
+    pub min_timestamp: Option<u64>,
+    pub max_timestamp: Option<u64>,
+}
+
+impl Bundle {
+    pub fn new(target_block: u64) -> Self {
+        Self {
+            txs: Vec::new(),
+            target_block,
+            min_timestamp: None,
+            max_timestamp: None,
+        }
+    }
+
+    /// One call to `to` per calldata blob, e.g. the output of
+    /// [`encode_executor`](crate::exec::encode_executor) for each path.
+    pub fn from_calldata<I>(target_block: u64, to: Address, gas_limit: u64, blobs: I) -> Self
+    where
+        I: IntoIterator<Item = Bytes>,
+    {
+        let mut bundle = Self::new(target_block);
+        bundle.txs.extend(
+            blobs
+                .into_iter()
+                .map(|data| BundleTx::call(to, data, gas_limit)),
+        );
+        bundle
+    }
+
+    pub fn push(mut self, tx: BundleTx) -> Self {
+        self.txs.push(tx);
+        self
+    }
+
+    pub fn valid_between(mut self, min_timestamp: u64, max_timestamp: u64) -> Self {
+        self.min_timestamp = Some(min_timestamp);
+        self.max_timestamp = Some(max_timestamp);
+        self
+    }
+
+    pub fn gas_limit(&self) -> u64 {
+        self.txs.iter().map(|t| t.gas_limit).sum()
+    }
+
+    /// Builds `eth_sendBundle` params. `sign` must return the raw signed
+    /// transaction and its hash; signing is left to the caller's wallet.
+    pub fn into_request<F>(self, mut sign: F) -> SendBundleRequest
+    where
+        F: FnMut(&BundleTx) -> (Bytes, B256),
+    {
+        let mut txs = Vec::with_capacity(self.txs.len());
+        let mut reverting_tx_hashes = Vec::new();
+        for tx in &self.txs {
+            let (raw, hash) = sign(tx);
+            if tx.can_revert {
+                reverting_tx_hashes.push(hash);
+            }
+            txs.push(raw);
+        }
+        SendBundleRequest {
+            txs,
+            block_number: format!("{:#x}", self.target_block),
+            min_timestamp: self.min_timestamp,
+            max_timestamp: self.max_timestamp,
+            reverting_tx_hashes,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub struct SendBundleRequest {
+    pub txs: Vec<Bytes>,
+    pub block_number: String,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub min_timestamp: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub max_timestamp: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub reverting_tx_hashes: Vec<B256>,
+}
+
+pub fn gas_cost(gas_used: u64, basefee: u64, priority_fee: u64) -> U256 {
+    U256::from(gas_used) * (U256::from(basefee) + U256::from(priority_fee))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BribePolicy {
+    /// Share of post-gas profit paid to the builder, in basis points.
+    pub share_bps: u32,
+    /// Minimum profit kept after gas and bribe.
+    pub min_profit: U256,
+}
+
+impl Default for BribePolicy {
+    fn default() -> Self {
+        Self {
+            share_bps: 9_000,
+            min_profit: U256::ZERO,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProfitSplit {
+    pub gross: U256,
+    pub gas: U256,
+    pub bribe: U256,
+    pub kept: U256,
+}
+
+impl BribePolicy {
+    /// Splits `gross` (in wei) into gas, builder bribe and kept profit, or
+    /// `None` if what is left falls short of `min_profit`.
+    pub fn split(&self, gross: U256, gas: U256) -> Option<ProfitSplit> {
+        let net = gross.checked_sub(gas)?;
+        let bribe = net * U256::from(self.share_bps.min(10_000)) / U256::from(10_000u64);
+        let kept = net - bribe;
+        if kept < self.min_profit {
+            return None;
+        }
+        Some(ProfitSplit {
+            gross,
+            gas,
+            bribe,
+            kept,
+        })
+    }
+
+    /// Largest priority fee per gas that still leaves `min_profit`, for
+    /// paying the bribe through gas price rather than a coinbase transfer.
+    pub fn max_priority_fee(&self, gross: U256, gas_used: u64, basefee: u64) -> Option<U256> {
+        if gas_used == 0 {
+            return None;
+        }
+        let base = gas_cost(gas_used, basefee, 0);
+        let budget = gross.checked_sub(base)?.checked_sub(self.min_profit)?;
+        Some(budget / U256::from(gas_used))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ether(n: u64) -> U256 {
+        U256::from(n) * U256::from(10u64).pow(U256::from(18u64))
+    }
+
+    #[test]
+    fn bundle_from_calldata_builds_flashbots_request() {
+        let router = Address::repeat_byte(7);
+        let bundle = Bundle::from_calldata(
+            19_000_001,
+            router,
+            250_000,
+            [Bytes::from_static(&[1, 2]), Bytes::from_static(&[3])],
+        )
+        .push(BundleTx::call(Address::repeat_byte(8), Bytes::new(), 21_000).allow_revert())
+        .valid_between(100, 112);
+        assert_eq!(bundle.gas_limit(), 521_000);
+
+        let req = bundle.into_request(|tx| (tx.data.clone(), B256::repeat_byte(tx.to[0])));
+        assert_eq!(req.block_number, "0x121eac1");
+        assert_eq!(req.txs.len(), 3);
+        assert_eq!(req.reverting_tx_hashes, vec![B256::repeat_byte(8)]);
+        assert_eq!(
+            (req.min_timestamp, req.max_timestamp),
+            (Some(100), Some(112))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn request_serializes_as_eth_send_bundle_params() {
+        let req = Bundle::new(1).into_request(|_| (Bytes::new(), B256::ZERO));
+        let json = serde_json::to_value(&req).unwrap();
+        assert_eq!(json, serde_json::json!({ "txs": [], "blockNumber": "0x1" }));
+    }
+
+    #[test]
+    fn bribe_split_respects_gas_and_min_profit() {
+        let policy = BribePolicy {
+            share_bps: 8_000,
+            min_profit: ether(1) / U256::from(100u64),
+        };
+        let gas = gas_cost(200_000, 20_000_000_000, 0);
+        let split = policy.split(ether(1), gas).unwrap();
+        assert_eq!(split.gas + split.bribe + split.kept, ether(1));
+        assert_eq!(
+            split.bribe,
+            (ether(1) - gas) * U256::from(4u64) / U256::from(5u64)
+        );
+
+        assert!(policy.split(gas, gas).is_none());
+        assert!(policy.split(gas / U256::from(2u64), gas).is_none());
+    }
+
+    #[test]
+    fn max_priority_fee_leaves_min_profit() {
+        let policy = BribePolicy {
+            share_bps: 0,
+            min_profit: U256::from(1_000_000u64),
+        };
+        let fee = policy
+            .max_priority_fee(U256::from(101_000_000u64), 100, 0)
+            .unwrap();
+        assert_eq!(fee, U256::from(1_000_000u64));
+        assert_eq!(policy.max_priority_fee(U256::ZERO, 100, 1), None);
+    }
+}
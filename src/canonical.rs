@@ -0,0 +1,159 @@
+//! One key per logical route, however it was enumerated: the same cycle
+//! found from each of its tokens, or the same path rebuilt from a
+//! simulation, compare equal.
+
+use crate::{
+    arb::ArbOpportunity,
+    engine::{Hop, Path},
+};
+use alloy_primitives::{B256, keccak256};
+use smallvec::SmallVec;
+use std::collections::HashSet;
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct RouteKey(pub SmallVec<[Hop; 4]>);
+
+impl RouteKey {
+    pub fn of(plan: &[Hop]) -> Self {
+        Self(canonical_plan(plan))
+    }
+
+    /// Keccak of the hops as big-endian `pool || from || to`: an 8-byte
+    /// pool id and two 4-byte token ids, 16 bytes per hop. Stable across
+    /// builds and platforms, so fit for storage.
+    pub fn id(&self) -> B256 {
+        let mut buf = Vec::with_capacity(self.0.len() * 16);
+        for h in &self.0 {
+            buf.extend_from_slice(&h.pool.0.to_be_bytes());
+            buf.extend_from_slice(&h.dir.from.0.to_be_bytes());
+            buf.extend_from_slice(&h.dir.to.0.to_be_bytes());
+        }
+        keccak256(buf)
+    }
+}
+
+/// `plan` with cycles rotated to start at their smallest token, ties going
+/// to the smallest rotation. Open routes are returned as they are.
+pub fn canonical_plan(plan: &[Hop]) -> SmallVec<[Hop; 4]> {
+    let is_cycle = plan
+        .first()
+        .zip(plan.last())
+        .is_some_and(|(first, last)| first.dir.from == last.dir.to);
+    if !is_cycle {
+        return plan.into();
+    }
+    let start = plan.iter().map(|h| h.dir.from).min().unwrap();
+    let rotation = |i: usize| plan[i..].iter().chain(&plan[..i]);
+    let best = (0..plan.len())
+        .filter(|&i| plan[i].dir.from == start)
+        .min_by(|&a, &b| rotation(a).cmp(rotation(b)))
+        .unwrap();
+    rotation(best).copied().collect()
+}
+
+impl Path {
+    pub fn plan(&self) -> SmallVec<[Hop; 4]> {
+        self.steps
+            .iter()
+            .map(|s| Hop::new(s.pool, s.direction()))
+            .collect()
+    }
+
+    pub fn canonical_key(&self) -> RouteKey {
+        RouteKey::of(&self.plan())
+    }
+
+    /// [`RouteKey::id`] of the route, whatever amounts it was simulated at.
+    pub fn id(&self) -> B256 {
+        self.canonical_key().id()
+    }
+}
+
+impl ArbOpportunity {
+    pub fn canonical_key(&self) -> RouteKey {
+        RouteKey::of(&self.plan)
+    }
+}
+
+/// Drops opportunities whose route already appeared earlier in `opps`, so
+/// on a net-ranked list the most profitable sizing of each cycle is kept.
+pub fn dedup_opportunities(opps: &mut Vec<ArbOpportunity>) {
+    let mut seen = HashSet::new();
+    opps.retain(|o| seen.insert(o.canonical_key()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::World;
+    use alloy_primitives::U256;
+    use std::collections::HashMap;
+
+    #[test]
+    fn rotations_of_a_cycle_share_a_key() {
+        let cycle = [hop(1, 2, 3), hop(2, 3, 1), hop(3, 1, 2)];
+        let rotated = [hop(3, 1, 2), hop(1, 2, 3), hop(2, 3, 1)];
+        assert_eq!(RouteKey::of(&cycle), RouteKey::of(&rotated));
+        assert_eq!(canonical_plan(&cycle).as_slice(), &rotated);
+        assert_ne!(
+            RouteKey::of(&cycle),
+            RouteKey::of(&[hop(3, 2, 1), hop(2, 1, 3), hop(1, 3, 2)]),
+            "the reverse cycle is a different route"
+        );
+        assert_eq!(canonical_plan(&cycle[..2]).as_slice(), &cycle[..2]);
+
+        // Token 1 appears twice; the smaller rotation from it wins.
+        let figure_eight = [hop(5, 1, 2), hop(6, 2, 1), hop(4, 1, 3), hop(7, 3, 1)];
+        assert_eq!(canonical_plan(&figure_eight)[0], hop(4, 1, 3));
+
+        let pools = HashMap::from([
+            (PoolId(1), Cp::new(1, 2, 3)),
+            (PoolId(2), Cp::new(2, 3, 1)),
+            (PoolId(3), Cp::new(3, 1, 2)),
+        ]);
+        let mut world = World::default();
+        for i in 1..=3 {
+            world.set_pool_state(PoolId(i), reserves(1_000_000, 1_000_000));
+        }
+        let engine = Engine::new(&pools);
+        let path = engine.simulate_chained(&world, &cycle, U256::from(100u64));
+        assert_eq!(path.canonical_key(), RouteKey::of(&rotated));
+        assert_eq!(path.id(), RouteKey::of(&rotated).id());
+        assert_ne!(path.id(), RouteKey::of(&cycle[..2]).id());
+        let larger = engine.simulate_chained(&world, &rotated, U256::from(200u64));
+        assert_eq!(larger.id(), path.id());
+        let paths = HashSet::from([path.clone(), larger, path]);
+        assert_eq!(paths.len(), 2, "equal only with equal amounts");
+
+        let opp = |plan: &[Hop], net: u64| ArbOpportunity {
+            plan: plan.to_vec(),
+            optimal_in: U256::ZERO,
+            gross: U256::from(net),
+            gas: U256::ZERO,
+            net: U256::from(net),
+        };
+        let mut opps = vec![opp(&cycle, 9), opp(&rotated, 8), opp(&cycle[..1], 1)];
+        dedup_opportunities(&mut opps);
+        assert_eq!(opps.len(), 2);
+        assert_eq!(opps[0].net, U256::from(9u64));
+    }
+
+    #[test]
+    fn route_ids_are_pinned() {
+        let key = RouteKey::of(&[hop(1, 2, 3), hop(0x0102_0304_0506, 3, 2)]);
+        let bytes = alloy_primitives::hex!(
+            "0000000000000001 00000002 00000003"
+            "0000010203040506 00000003 00000002"
+        );
+        assert_eq!(key.id(), keccak256(bytes));
+        assert_eq!(
+            key.id(),
+            alloy_primitives::b256!(
+                "aeeec7d1cec5d43fc14b0707bd732d3fa597e83cfbb322fb7c337ed5dffac427"
+            )
+        );
+    }
+}
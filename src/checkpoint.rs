@@ -0,0 +1,110 @@
+use crate::registry::Registry;
+use crate::world::World;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Checkpoint<S> {
+    pub block: u64,
+    pub world: World<S>,
+}
+
+impl<S: Serialize> World<S> {
+    pub fn save_checkpoint(&self, path: impl AsRef<Path>, block: u64) -> io::Result<()> {
+        #[derive(Serialize)]
+        struct CheckpointRef<'a, S> {
+            block: u64,
+            world: &'a World<S>,
+        }
+
+        let out = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(out, &CheckpointRef { block, world: self }).map_err(io::Error::other)
+    }
+}
+
+pub fn load_checkpoint<S: DeserializeOwned>(path: impl AsRef<Path>) -> io::Result<Checkpoint<S>> {
+    let rdr = BufReader::new(File::open(path)?);
+    let mut ckpt: Checkpoint<S> = serde_json::from_reader(rdr).map_err(io::Error::other)?;
+    ckpt.world.restamp();
+    Ok(ckpt)
+}
+
+impl Registry {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let out = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(out, self).map_err(io::Error::other)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let rdr = BufReader::new(File::open(path)?);
+        serde_json::from_reader(rdr).map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::{AccountId, PoolId, TokenId};
+    use alloy_primitives::U256;
+
+    #[test]
+    fn checkpoint_round_trips() {
+        let mut world = World::default();
+        world
+            .pool_states
+            .insert(PoolId(1), (U256::from(5u64), U256::MAX));
+        world
+            .pool_states
+            .insert(PoolId(9), (U256::ZERO, U256::from(7u64)));
+        let (owner, spender) = (AccountId(1), AccountId(2));
+        world.credit(owner, TokenId(3), U256::from(10u64));
+        world.approve(owner, spender, TokenId(3), U256::MAX);
+
+        let path = std::env::temp_dir().join(format!("wayfinder-ckpt-{}.json", std::process::id()));
+        world.save_checkpoint(&path, 19_000_000).unwrap();
+        let ckpt: Checkpoint<(U256, U256)> = load_checkpoint(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(ckpt.block, 19_000_000);
+        assert_eq!(ckpt.world.pool_states, world.pool_states);
+        assert_eq!(ckpt.world.holdings, world.holdings);
+        assert_eq!(ckpt.world.allowance(owner, spender, TokenId(3)), U256::MAX);
+    }
+
+    #[test]
+    fn registry_round_trips() {
+        use crate::ids::ChainId;
+        use crate::registry::{PoolKind, PoolMeta, TokenMeta};
+        use alloy_primitives::Address;
+
+        let mut reg = Registry::default();
+        let tid = reg.insert_token_hashed(
+            ChainId::MAINNET,
+            TokenMeta {
+                address: Address::repeat_byte(1),
+                symbol: "WETH".into(),
+                decimals: 18,
+            },
+        );
+        let pid = reg.insert_pool_hashed(
+            ChainId::MAINNET,
+            PoolMeta {
+                address: Address::repeat_byte(2),
+                kind: PoolKind::UniV2,
+                token0: tid,
+                token1: TokenId(7),
+                fee: 3000,
+            },
+        );
+
+        let path = std::env::temp_dir().join(format!("wayfinder-reg-{}.json", std::process::id()));
+        reg.save(&path).unwrap();
+        let back = Registry::load(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(back.token_by_addr[&Address::repeat_byte(1)], tid);
+        assert_eq!(back.pool(pid).unwrap().fee, 3000);
+    }
+}
@@ -0,0 +1,508 @@
+//! Compact binary snapshots of a [`World`]: varint amounts, delta-encoded
+//! ids and ticks, and optionally zstd on top (`zstd` feature). Far smaller
+//! than JSON checkpoints once V3 tick maps are involved.
+
+use crate::{
+    ids::{AccountId, PoolId, TokenId},
+    univ2::UniV2State,
+    world::{BlockContext, World},
+};
+use alloy_primitives::U256;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"WFC1";
+
+/// Binary encoding for snapshot contents.
+pub trait Compact: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+    fn decode(input: &mut &[u8]) -> io::Result<Self>;
+}
+
+fn truncated() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "truncated compact snapshot")
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what.to_string())
+}
+
+pub fn put_varint(out: &mut Vec<u8>, mut v: u128) {
+    while v >= 0x80 {
+        out.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+pub fn get_varint(input: &mut &[u8]) -> io::Result<u128> {
+    let mut v = 0u128;
+    for shift in (0..128).step_by(7) {
+        let (&b, rest) = input.split_first().ok_or_else(truncated)?;
+        *input = rest;
+        v |= u128::from(b & 0x7f) << shift;
+        if b < 0x80 {
+            return Ok(v);
+        }
+    }
+    Err(invalid("varint too long"))
+}
+
+fn zigzag(v: i128) -> u128 {
+    ((v << 1) ^ (v >> 127)) as u128
+}
+
+fn unzigzag(v: u128) -> i128 {
+    (v >> 1) as i128 ^ -((v & 1) as i128)
+}
+
+macro_rules! compact_int {
+    ($($t:ty),*) => {$(
+        impl Compact for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                put_varint(out, *self as u128);
+            }
+
+            fn decode(input: &mut &[u8]) -> io::Result<Self> {
+                get_varint(input)?
+                    .try_into()
+                    .map_err(|_| invalid(concat!(stringify!($t), " out of range")))
+            }
+        }
+    )*};
+}
+
+compact_int!(u32, u64, u128, usize);
+
+impl Compact for i32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        put_varint(out, zigzag((*self).into()));
+    }
+
+    fn decode(input: &mut &[u8]) -> io::Result<Self> {
+        unzigzag(get_varint(input)?)
+            .try_into()
+            .map_err(|_| invalid("i32 out of range"))
+    }
+}
+
+impl Compact for i128 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        put_varint(out, zigzag(*self));
+    }
+
+    fn decode(input: &mut &[u8]) -> io::Result<Self> {
+        get_varint(input).map(unzigzag)
+    }
+}
+
+/// Length byte, then the big-endian bytes without leading zeros.
+impl Compact for U256 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let bytes = self.to_be_bytes_trimmed_vec();
+        out.push(bytes.len() as u8);
+        out.extend_from_slice(&bytes);
+    }
+
+    fn decode(input: &mut &[u8]) -> io::Result<Self> {
+        let (&len, rest) = input.split_first().ok_or_else(truncated)?;
+        let len = len as usize;
+        if len > 32 {
+            return Err(invalid("U256 longer than 32 bytes"));
+        }
+        if rest.len() < len {
+            return Err(truncated());
+        }
+        let (bytes, rest) = rest.split_at(len);
+        *input = rest;
+        Ok(U256::from_be_slice(bytes))
+    }
+}
+
+impl<A: Compact, B: Compact> Compact for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> io::Result<Self> {
+        Ok((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+impl Compact for UniV2State {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.reserve0, self.reserve1).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> io::Result<Self> {
+        let (reserve0, reserve1) = Compact::decode(input)?;
+        Ok(UniV2State::new(reserve0, reserve1))
+    }
+}
+
+impl Compact for BlockContext {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.number.encode(out);
+        self.timestamp.encode(out);
+        self.basefee.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> io::Result<Self> {
+        Ok(BlockContext {
+            number: Compact::decode(input)?,
+            timestamp: Compact::decode(input)?,
+            basefee: Compact::decode(input)?,
+        })
+    }
+}
+
+/// Ticks are stored as gaps in units of the tick spacing, which for dense
+/// maps is a single byte each.
+#[cfg(feature = "rpc")]
+impl Compact for crate::v3storage::TickMap {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.tick_spacing.encode(out);
+        self.sqrt_price_x96.encode(out);
+        self.tick.encode(out);
+        self.liquidity.encode(out);
+        (i32::from(self.words.0), i32::from(self.words.1)).encode(out);
+        self.ticks.len().encode(out);
+        let spacing = i128::from(self.tick_spacing.max(1));
+        let mut prev = 0i128;
+        for (&tick, info) in &self.ticks {
+            let tick = i128::from(tick);
+            let gap = tick - prev;
+            // Off-grid ticks only come from corrupt reads; keep them exact.
+            if gap % spacing == 0 {
+                zigzag(gap / spacing * 2).encode(out);
+            } else {
+                zigzag(gap * 2 + 1).encode(out);
+            }
+            prev = tick;
+            info.liquidity_gross.encode(out);
+            info.liquidity_net.encode(out);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> io::Result<Self> {
+        use crate::v3storage::{TickInfo, TickMap};
+
+        let tick_spacing = i32::decode(input)?;
+        let sqrt_price_x96 = U256::decode(input)?;
+        let tick = i32::decode(input)?;
+        let liquidity = u128::decode(input)?;
+        let (lo, hi) = <(i32, i32)>::decode(input)?;
+        let words = (
+            lo.try_into()
+                .map_err(|_| invalid("bitmap word out of range"))?,
+            hi.try_into()
+                .map_err(|_| invalid("bitmap word out of range"))?,
+        );
+        let n = usize::decode(input)?;
+        let spacing = i128::from(tick_spacing.max(1));
+        let mut ticks = std::collections::BTreeMap::new();
+        let mut prev = 0i128;
+        for _ in 0..n {
+            let gap = unzigzag(u128::decode(input)?);
+            let step = if gap & 1 == 0 {
+                (gap / 2).checked_mul(spacing)
+            } else {
+                Some((gap - 1) / 2)
+            };
+            prev = step
+                .and_then(|step| prev.checked_add(step))
+                .ok_or_else(|| invalid("tick out of range"))?;
+            let at = i32::try_from(prev).map_err(|_| invalid("tick out of range"))?;
+            let info = TickInfo {
+                liquidity_gross: Compact::decode(input)?,
+                liquidity_net: Compact::decode(input)?,
+            };
+            ticks.insert(at, info);
+        }
+        Ok(TickMap {
+            tick_spacing,
+            sqrt_price_x96,
+            tick,
+            liquidity,
+            ticks,
+            words,
+        })
+    }
+}
+
+/// Ids are written sorted, each as the gap from the previous one.
+fn encode_sorted<K: Copy + Ord + std::hash::Hash, V>(
+    out: &mut Vec<u8>,
+    map: &HashMap<K, V>,
+    id: impl Fn(K) -> u64,
+    mut value: impl FnMut(&mut Vec<u8>, &V),
+) {
+    let mut keys: Vec<K> = map.keys().copied().collect();
+    keys.sort_unstable();
+    keys.len().encode(out);
+    let mut prev = 0;
+    for k in keys {
+        (id(k) - prev).encode(out);
+        prev = id(k);
+        value(out, &map[&k]);
+    }
+}
+
+fn decode_sorted<K: std::hash::Hash + Eq, V>(
+    input: &mut &[u8],
+    key: impl Fn(u64) -> io::Result<K>,
+    mut value: impl FnMut(&mut &[u8]) -> io::Result<V>,
+) -> io::Result<HashMap<K, V>> {
+    let n = usize::decode(input)?;
+    let mut map = HashMap::with_capacity(n.min(input.len()));
+    let mut prev = 0u64;
+    for _ in 0..n {
+        prev = prev
+            .checked_add(u64::decode(input)?)
+            .ok_or_else(|| invalid("id overflow"))?;
+        map.insert(key(prev)?, value(input)?);
+    }
+    Ok(map)
+}
+
+fn token(id: u64) -> io::Result<TokenId> {
+    u32::try_from(id)
+        .map(TokenId)
+        .map_err(|_| invalid("token id out of range"))
+}
+
+fn account(id: u64) -> io::Result<AccountId> {
+    u32::try_from(id)
+        .map(AccountId)
+        .map_err(|_| invalid("account id out of range"))
+}
+
+fn encode_amounts(out: &mut Vec<u8>, amounts: &HashMap<TokenId, U256>) {
+    encode_sorted(out, amounts, |t| t.0.into(), |out, v| v.encode(out));
+}
+
+fn decode_amounts(input: &mut &[u8]) -> io::Result<HashMap<TokenId, U256>> {
+    decode_sorted(input, token, U256::decode)
+}
+
+impl<S: Compact> Compact for World<S> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.block.encode(out);
+        self.version.encode(out);
+        encode_sorted(out, &self.pool_states, |p| p.0, |out, st| st.encode(out));
+        encode_sorted(out, &self.pool_versions, |p| p.0, |out, v| v.encode(out));
+        encode_sorted(out, &self.holdings, |a| a.0.into(), encode_amounts);
+        let mut allowances: Vec<_> = self.allowances.iter().collect();
+        allowances.sort_unstable_by_key(|(k, _)| **k);
+        allowances.len().encode(out);
+        for ((owner, spender), amounts) in allowances {
+            (owner.0, spender.0).encode(out);
+            encode_amounts(out, amounts);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> io::Result<Self> {
+        let block = BlockContext::decode(input)?;
+        let version = u64::decode(input)?;
+        let pool_states = decode_sorted(input, |id| Ok(PoolId(id)), S::decode)?;
+        let pool_versions = decode_sorted(input, |id| Ok(PoolId(id)), u64::decode)?;
+        let holdings = decode_sorted(input, account, decode_amounts)?;
+        let n = usize::decode(input)?;
+        let mut allowances = HashMap::with_capacity(n.min(input.len()));
+        for _ in 0..n {
+            let (owner, spender) = <(u32, u32)>::decode(input)?;
+            allowances.insert(
+                (AccountId(owner), AccountId(spender)),
+                decode_amounts(input)?,
+            );
+        }
+        let mut world = World {
+            block,
+            pool_states,
+            holdings,
+            allowances,
+            version,
+            pool_versions,
+        };
+        world.restamp();
+        Ok(world)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    /// zstd at the given level; 3 is the library default.
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+impl<S: Compact> World<S> {
+    /// Writes a compact snapshot taken at `block`.
+    pub fn write_compact<W: Write>(
+        &self,
+        mut out: W,
+        block: u64,
+        compression: Compression,
+    ) -> io::Result<()> {
+        let mut body = Vec::new();
+        block.encode(&mut body);
+        self.encode(&mut body);
+        out.write_all(MAGIC)?;
+        match compression {
+            Compression::None => {
+                out.write_all(&[0])?;
+                out.write_all(&body)
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(level) => {
+                out.write_all(&[1])?;
+                zstd::stream::copy_encode(&body[..], out, level)
+            }
+        }
+    }
+
+    /// Reads a snapshot from [`World::write_compact`], returning its block.
+    pub fn read_compact<R: Read>(mut rdr: R) -> io::Result<(u64, World<S>)> {
+        let mut header = [0u8; 5];
+        rdr.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid("not a compact snapshot"));
+        }
+        let mut body = Vec::new();
+        match header[4] {
+            0 => {
+                rdr.read_to_end(&mut body)?;
+            }
+            #[cfg(feature = "zstd")]
+            1 => zstd::stream::copy_decode(rdr, &mut body)?,
+            #[cfg(not(feature = "zstd"))]
+            1 => return Err(invalid("snapshot needs the zstd feature")),
+            _ => return Err(invalid("unknown snapshot compression")),
+        }
+        let mut input = &body[..];
+        let block = u64::decode(&mut input)?;
+        let world = World::decode(&mut input)?;
+        if !input.is_empty() {
+            return Err(invalid("trailing bytes after snapshot"));
+        }
+        Ok((block, world))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varints_and_amounts_round_trip() {
+        let mut buf = Vec::new();
+        for v in [0u128, 127, 128, u64::MAX.into(), u128::MAX] {
+            put_varint(&mut buf, v);
+        }
+        for v in [0i128, -1, 1, i128::MIN, i128::MAX] {
+            v.encode(&mut buf);
+        }
+        for v in [U256::ZERO, U256::from(255u64), U256::MAX] {
+            v.encode(&mut buf);
+        }
+        let mut input = &buf[..];
+        for v in [0u128, 127, 128, u64::MAX.into(), u128::MAX] {
+            assert_eq!(get_varint(&mut input).unwrap(), v);
+        }
+        for v in [0i128, -1, 1, i128::MIN, i128::MAX] {
+            assert_eq!(i128::decode(&mut input).unwrap(), v);
+        }
+        for v in [U256::ZERO, U256::from(255u64), U256::MAX] {
+            assert_eq!(U256::decode(&mut input).unwrap(), v);
+        }
+        assert!(input.is_empty());
+        assert_eq!(
+            U256::decode(&mut &[2u8, 1][..]).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn worlds_round_trip() {
+        let mut world: World<UniV2State> = World::default();
+        world.block.number = 19_000_000;
+        for id in [3u64, 1, 1 << 40] {
+            world.set_pool_state(PoolId(id), UniV2State::new(U256::from(id), U256::MAX));
+        }
+        world.credit(AccountId(7), TokenId(2), U256::from(5u64));
+        world.approve(AccountId(7), AccountId(8), TokenId(2), U256::MAX);
+
+        let mut buf = Vec::new();
+        world
+            .write_compact(&mut buf, 42, Compression::None)
+            .unwrap();
+        let (block, back) = World::<UniV2State>::read_compact(&buf[..]).unwrap();
+        assert_eq!(block, 42);
+        assert_eq!(back.block, world.block);
+        assert_eq!(back.pool_states, world.pool_states);
+        // Restamped, but in the same order.
+        let order = |w: &World<UniV2State>| {
+            let mut pools: Vec<_> = w.pool_versions.iter().map(|(&p, &v)| (v, p)).collect();
+            pools.sort();
+            pools.into_iter().map(|(_, p)| p).collect::<Vec<_>>()
+        };
+        assert_eq!(order(&back), order(&world));
+        assert!(back.pool_versions.values().all(|v| *v > world.version));
+        assert_eq!(back.holdings, world.holdings);
+        assert_eq!(back.allowances, world.allowances);
+        assert!(World::<UniV2State>::read_compact(&buf[..buf.len() - 1]).is_err());
+
+        #[cfg(feature = "zstd")]
+        {
+            let mut zbuf = Vec::new();
+            world
+                .write_compact(&mut zbuf, 42, Compression::Zstd(3))
+                .unwrap();
+            let (_, back) = World::<UniV2State>::read_compact(&zbuf[..]).unwrap();
+            assert_eq!(back.pool_states, world.pool_states);
+        }
+
+        #[cfg(feature = "rpc")]
+        {
+            use crate::v3storage::{TickInfo, TickMap};
+            let ticks = (-50i32..50)
+                .map(|i| {
+                    let info = TickInfo {
+                        liquidity_gross: 1_000 + u128::from(i.unsigned_abs()),
+                        liquidity_net: i128::from(i) * 1_000,
+                    };
+                    (i * 60, info)
+                })
+                .chain([(7, TickInfo::default())])
+                .collect();
+            let map = TickMap {
+                tick_spacing: 60,
+                sqrt_price_x96: U256::from(1u64) << 96,
+                tick: -3,
+                liquidity: 1 << 70,
+                ticks,
+                words: (-1, 0),
+            };
+            let mut buf = Vec::new();
+            map.encode(&mut buf);
+            assert!(buf.len() < 101 * 8, "{} bytes", buf.len());
+            assert_eq!(TickMap::decode(&mut &buf[..]).unwrap(), map);
+
+            // A corrupt gap too large to scale by the spacing.
+            let mut bad = Vec::new();
+            TickMap {
+                ticks: Default::default(),
+                ..map
+            }
+            .encode(&mut bad);
+            bad.pop();
+            1usize.encode(&mut bad);
+            (1u128 << 125).encode(&mut bad);
+            let err = TickMap::decode(&mut &bad[..]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Quoting one plan against two worlds side by side, e.g. log-derived
+//! against RPC-fetched state when validating sync, or a what-if edit
+//! against the live world.
+
+use crate::{
+    engine::{Engine, Hop, Path},
+    error::WayfinderError,
+    ids::PoolId,
+    pool::Pool,
+    revalidate::delta_bps,
+    world::StateView,
+};
+use alloy_primitives::U256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HopDiff {
+    pub index: usize,
+    pub pool: PoolId,
+    pub out_a: U256,
+    pub out_b: U256,
+    /// `out_b` relative to `out_a`, in basis points.
+    pub delta_bps: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct WorldComparison {
+    pub a: Path,
+    pub b: Path,
+    /// Hops whose output differs. Differences compound, so the first entry
+    /// is where the worlds start to disagree.
+    pub diverging: Vec<HopDiff>,
+}
+
+impl WorldComparison {
+    pub fn agrees(&self) -> bool {
+        self.diverging.is_empty()
+    }
+
+    pub fn first_divergence(&self) -> Option<&HopDiff> {
+        self.diverging.first()
+    }
+}
+
+impl<P: Pool> Engine<'_, P> {
+    /// Simulates `plan` with `amount` on both worlds. Either failing, as
+    /// with [`Engine::try_simulate`], is an error.
+    pub fn compare_worlds<A: StateView<P::State>, B: StateView<P::State>>(
+        &self,
+        world_a: &A,
+        world_b: &B,
+        plan: &[Hop],
+        amount: U256,
+    ) -> Result<WorldComparison, WayfinderError> {
+        let a = self.try_simulate(world_a, plan, amount)?;
+        let b = self.try_simulate(world_b, plan, amount)?;
+        let diverging = a
+            .steps
+            .iter()
+            .zip(&b.steps)
+            .enumerate()
+            .filter(|(_, (sa, sb))| sa.amt_out != sb.amt_out)
+            .map(|(index, (sa, sb))| HopDiff {
+                index,
+                pool: sa.pool,
+                out_a: sa.amt_out,
+                out_b: sb.amt_out,
+                delta_bps: delta_bps(sa.amt_out, sb.amt_out),
+            })
+            .collect();
+        Ok(WorldComparison { a, b, diverging })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::{World, WorldDiff};
+    use std::collections::HashMap;
+
+    #[test]
+    fn pinpoints_the_first_hop_where_worlds_disagree() {
+        let pools = HashMap::from([
+            (PoolId(1), Cp::new(1, 1, 2)),
+            (PoolId(2), Cp::new(2, 2, 3)),
+            (PoolId(3), Cp::new(3, 3, 4)),
+        ]);
+        let mut rpc = World::default();
+        for id in 1..=3 {
+            rpc.set_pool_state(PoolId(id), reserves(1_000_000, 1_000_000));
+        }
+        let mut logs = WorldDiff::default();
+        logs.set_pool_state(PoolId(2), reserves(1_000_000, 1_010_000));
+        let derived = rpc.with_overlay(logs);
+        let engine = Engine::new(&pools);
+        let plan = [hop(1, 1, 2), hop(2, 2, 3), hop(3, 3, 4)];
+        let amt = U256::from(10_000u64);
+
+        assert!(
+            engine
+                .compare_worlds(&rpc, &rpc, &plan, amt)
+                .unwrap()
+                .agrees()
+        );
+        let cmp = engine.compare_worlds(&rpc, &derived, &plan, amt).unwrap();
+        let first = cmp.first_divergence().unwrap();
+        assert_eq!((first.index, first.pool), (1, PoolId(2)));
+        assert!((90..=110).contains(&first.delta_bps), "{first:?}");
+        assert_eq!(cmp.diverging.len(), 2, "hop 3 inherits the difference");
+        assert_eq!(cmp.a.steps[0].amt_out, cmp.b.steps[0].amt_out);
+
+        let mut empty = World::default();
+        empty.set_pool_state(PoolId(1), reserves(1, 1));
+        assert!(engine.compare_worlds(&rpc, &empty, &plan, amt).is_err());
+    }
+}
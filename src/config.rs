@@ -0,0 +1,269 @@
+use crate::{
+    arb::ScanConfig,
+    ids::{ChainId, TokenId},
+    registry::Registry,
+};
+use alloy_primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("reading config: {0}")]
+    Io(std::io::Error),
+    #[error("parsing config: {0}")]
+    Parse(toml::de::Error),
+    #[error("no chain named {0} in config")]
+    UnknownChain(String),
+    #[error("config has several chains, pick one by name")]
+    AmbiguousChain,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub chains: BTreeMap<String, ChainConfig>,
+    pub router: RouterConfig,
+    pub gas: GasConfig,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    pub rpc_url: Option<String>,
+    pub registry: Option<PathBuf>,
+    #[serde(default)]
+    pub base_tokens: Vec<Address>,
+    #[serde(default)]
+    pub denylist: Denylist,
+    pub router: Option<RouterConfig>,
+    pub gas: Option<GasConfig>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Denylist {
+    pub tokens: Vec<Address>,
+    pub pools: Vec<Address>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RouterConfig {
+    pub max_hops: usize,
+    pub max_in: U256,
+    pub max_iters: usize,
+}
+
+impl Default for RouterConfig {
+    fn default() -> Self {
+        let scan = ScanConfig::default();
+        Self {
+            max_hops: scan.max_hops,
+            max_in: scan.max_in,
+            max_iters: scan.max_iters,
+        }
+    }
+}
+
+/// Gas cost per hop is `gas_per_hop * gas_price_wei`, so it is only a
+/// meaningful profit threshold for ETH-denominated base tokens.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GasConfig {
+    pub gas_per_hop: u64,
+    pub gas_price_wei: u64,
+}
+
+impl GasConfig {
+    pub fn cost_per_hop(&self) -> U256 {
+        U256::from(self.gas_per_hop) * U256::from(self.gas_price_wei)
+    }
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s).map_err(ConfigError::Parse)
+    }
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        std::fs::read_to_string(path)
+            .map_err(ConfigError::Io)?
+            .parse()
+    }
+
+    /// Looks up a chain by name; `None` picks the only configured chain.
+    pub fn chain(&self, name: Option<&str>) -> Result<&ChainConfig, ConfigError> {
+        match name {
+            Some(name) => self
+                .chains
+                .get(name)
+                .ok_or_else(|| ConfigError::UnknownChain(name.to_string())),
+            None if self.chains.len() == 1 => Ok(self.chains.values().next().unwrap()),
+            None if self.chains.is_empty() => Err(ConfigError::UnknownChain(String::new())),
+            None => Err(ConfigError::AmbiguousChain),
+        }
+    }
+
+    pub fn scan_config(&self, chain: &ChainConfig) -> ScanConfig {
+        let router = chain.router.unwrap_or(self.router);
+        let gas = chain.gas.unwrap_or(self.gas);
+        ScanConfig {
+            max_hops: router.max_hops,
+            max_in: router.max_in,
+            gas_per_hop: gas.cost_per_hop(),
+            max_iters: router.max_iters,
+            funding: Default::default(),
+        }
+    }
+}
+
+impl ChainConfig {
+    pub fn chain(&self) -> ChainId {
+        ChainId(self.chain_id)
+    }
+
+    /// Copy of `reg` without denylisted pools, denylisted tokens, or any pool
+    /// touching a denylisted token.
+    pub fn filter_registry(&self, reg: &Registry) -> Registry {
+        let bad_tokens: HashSet<TokenId> = self
+            .denylist
+            .tokens
+            .iter()
+            .filter_map(|a| reg.token_by_addr.get(a).copied())
+            .collect();
+        let bad_pools: HashSet<&Address> = self.denylist.pools.iter().collect();
+
+        let mut out = Registry::default();
+        for (&tid, meta) in &reg.token_meta {
+            if !bad_tokens.contains(&tid) {
+                out.upsert_token(tid, meta.clone());
+            }
+        }
+        for (&pid, meta) in &reg.pool_meta {
+            if bad_pools.contains(&meta.address)
+                || bad_tokens.contains(&meta.token0)
+                || bad_tokens.contains(&meta.token1)
+            {
+                continue;
+            }
+            out.upsert_pool(pid, meta.clone());
+        }
+        out
+    }
+
+    /// Base tokens resolved against `reg`; unknown addresses are skipped.
+    pub fn base_tokens(&self, reg: &Registry) -> Vec<TokenId> {
+        self.base_tokens
+            .iter()
+            .filter_map(|a| reg.token_by_addr.get(a).copied())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::PoolId;
+    use crate::registry::{PoolKind, PoolMeta, TokenMeta};
+    use alloy_primitives::address;
+
+    const EXAMPLE: &str = r#"
+        [router]
+        max_hops = 4
+
+        [gas]
+        gas_per_hop = 100000
+        gas_price_wei = 20000000000
+
+        [chains.mainnet]
+        chain_id = 1
+        rpc_url = "http://localhost:8545"
+        registry = "mainnet.json"
+        base_tokens = ["0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"]
+
+        [chains.mainnet.denylist]
+        tokens = ["0x00000000000000000000000000000000000000bb"]
+
+        [chains.base]
+        chain_id = 8453
+        router = { max_hops = 2, max_in = "1000000" }
+    "#;
+
+    fn registry() -> Registry {
+        let mut reg = Registry::default();
+        for (tid, addr) in [
+            (1, address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2")),
+            (2, address!("00000000000000000000000000000000000000aa")),
+            (3, address!("00000000000000000000000000000000000000bb")),
+        ] {
+            reg.upsert_token(
+                TokenId(tid),
+                TokenMeta {
+                    address: addr,
+                    symbol: format!("T{tid}"),
+                    decimals: 18,
+                },
+            );
+        }
+        for (pid, t0, t1) in [(1, 1, 2), (2, 1, 3)] {
+            reg.upsert_pool(
+                PoolId(pid),
+                PoolMeta {
+                    address: Address::repeat_byte(0x10 + pid as u8),
+                    kind: PoolKind::UniV2,
+                    token0: TokenId(t0),
+                    token1: TokenId(t1),
+                    fee: 3000,
+                },
+            );
+        }
+        reg
+    }
+
+    #[test]
+    fn parses_chains_with_router_and_gas_defaults() {
+        let cfg: Config = EXAMPLE.parse().unwrap();
+        let mainnet = cfg.chain(Some("mainnet")).unwrap();
+        assert_eq!(mainnet.chain(), ChainId::MAINNET);
+
+        let scan = cfg.scan_config(mainnet);
+        assert_eq!(scan.max_hops, 4);
+        assert_eq!(scan.max_in, ScanConfig::default().max_in);
+        assert_eq!(scan.gas_per_hop, U256::from(2_000_000_000_000_000u64));
+
+        let base = cfg.scan_config(cfg.chain(Some("base")).unwrap());
+        assert_eq!((base.max_hops, base.max_in), (2, U256::from(1_000_000u64)));
+
+        assert!(matches!(cfg.chain(None), Err(ConfigError::AmbiguousChain)));
+        assert!(matches!(
+            cfg.chain(Some("optimism")),
+            Err(ConfigError::UnknownChain(_))
+        ));
+    }
+
+    #[test]
+    fn denylist_drops_tokens_and_their_pools() {
+        let cfg: Config = EXAMPLE.parse().unwrap();
+        let mainnet = cfg.chain(Some("mainnet")).unwrap();
+        let reg = mainnet.filter_registry(&registry());
+
+        assert!(reg.token(TokenId(3)).is_none());
+        assert!(reg.pool(PoolId(1)).is_some());
+        assert!(reg.pool(PoolId(2)).is_none());
+        assert_eq!(mainnet.base_tokens(&reg), vec![TokenId(1)]);
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let err = "[router]\nmax_hopz = 2\n".parse::<Config>().unwrap_err();
+        assert!(matches!(err, ConfigError::Parse(_)));
+    }
+}
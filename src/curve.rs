@@ -0,0 +1,234 @@
+use crate::{
+    arb::Scanner,
+    engine::{Engine, Hop},
+    ids::TokenId,
+    pool::Pool,
+    world::StateView,
+};
+use alloy_primitives::U256;
+
+/// Sampled input → output curve, linearly interpolated between samples.
+/// Always starts at `(0, 0)`; inputs are strictly increasing.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QuoteCurve {
+    pub points: Vec<(U256, U256)>,
+}
+
+impl QuoteCurve {
+    pub fn max_in(&self) -> U256 {
+        self.points.last().map(|p| p.0).unwrap_or_default()
+    }
+
+    /// Interpolated output for `amt_in`, clamped to the last sample.
+    pub fn output(&self, amt_in: U256) -> U256 {
+        let i = self.points.partition_point(|p| p.0 < amt_in);
+        match (i.checked_sub(1), self.points.get(i)) {
+            (_, Some(&(x, y))) if x == amt_in => y,
+            (Some(lo), Some(&hi)) => lerp(self.points[lo], hi, amt_in),
+            _ => self.points.last().map(|p| p.1).unwrap_or_default(),
+        }
+    }
+
+    /// Output per unit input over the segment containing `amt_in`.
+    pub fn marginal_rate(&self, amt_in: U256) -> f64 {
+        let i = self
+            .points
+            .partition_point(|p| p.0 <= amt_in)
+            .clamp(1, self.points.len().max(1));
+        let (Some(&(x0, y0)), Some(&(x1, y1))) = (self.points.get(i - 1), self.points.get(i))
+        else {
+            return 0.0;
+        };
+        f64::from(y1.saturating_sub(y0)) / f64::from(x1 - x0)
+    }
+
+    /// Smallest interpolated input yielding at least `amt_out`, or `None` if
+    /// the curve never reaches it.
+    pub fn input_for(&self, amt_out: U256) -> Option<U256> {
+        let i = self.points.iter().position(|p| p.1 >= amt_out)?;
+        if i == 0 {
+            return Some(U256::ZERO);
+        }
+        let ((x0, y0), (x1, y1)) = (self.points[i - 1], self.points[i]);
+        let dy = y1 - y0;
+        Some(x0 + ((x1 - x0) * (amt_out - y0)).div_ceil(dy))
+    }
+}
+
+fn lerp((x0, y0): (U256, U256), (x1, y1): (U256, U256), x: U256) -> U256 {
+    if y1 >= y0 {
+        y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+    } else {
+        y0 - (y0 - y1) * (x - x0) / (x1 - x0)
+    }
+}
+
+/// `points` inputs spaced geometrically from `min_in` to `max_in`, deduped.
+pub fn log_spaced(min_in: U256, max_in: U256, points: usize) -> Vec<U256> {
+    let min_in = min_in.max(U256::from(1u64));
+    if points < 2 || max_in <= min_in {
+        return vec![max_in.max(min_in)];
+    }
+    let (lo, hi) = (f64::from(min_in), f64::from(max_in));
+    let step = (hi / lo).powf(1.0 / (points - 1) as f64);
+    let mut out: Vec<U256> = (0..points)
+        .map(|i| match i {
+            0 => min_in,
+            i if i == points - 1 => max_in,
+            i => U256::try_from(lo * step.powi(i as i32))
+                .unwrap_or(max_in)
+                .clamp(min_in, max_in),
+        })
+        .collect();
+    out.dedup();
+    out
+}
+
+impl<P: Pool> Engine<'_, P> {
+    /// Samples `plan` at `points` log-spaced inputs in `[min_in, max_in]`.
+    pub fn quote_curve<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plan: &[Hop],
+        min_in: U256,
+        max_in: U256,
+        points: usize,
+    ) -> QuoteCurve {
+        let mut curve = QuoteCurve {
+            points: vec![(U256::ZERO, U256::ZERO)],
+        };
+        for amt in log_spaced(min_in, max_in, points) {
+            let path = self.simulate_chained(world, plan, amt);
+            curve.points.push((
+                amt,
+                path.steps.last().map(|s| s.amt_out).unwrap_or_default(),
+            ));
+        }
+        curve
+    }
+}
+
+impl<P: Pool> Scanner<'_, P> {
+    /// Like [`Engine::quote_curve`] for a token pair: each sample takes the
+    /// best single route at that size, so the curve is the routes' envelope.
+    pub fn quote_curve<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        from: TokenId,
+        to: TokenId,
+        min_in: U256,
+        max_in: U256,
+        points: usize,
+    ) -> QuoteCurve {
+        let routes = self.routes(world, from, to);
+        let mut curve = QuoteCurve {
+            points: vec![(U256::ZERO, U256::ZERO)],
+        };
+        if routes.is_empty() {
+            return curve;
+        }
+        for amt in log_spaced(min_in, max_in, points) {
+            let best = routes
+                .iter()
+                .map(|plan| {
+                    let path = self.engine.simulate_chained(world, plan, amt);
+                    path.steps.last().map(|s| s.amt_out).unwrap_or_default()
+                })
+                .max()
+                .unwrap_or_default();
+            curve.points.push((amt, best));
+        }
+        curve
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::AMMGraph;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::World;
+    use std::collections::HashMap;
+
+    fn setup() -> (HashMap<PoolId, Cp>, AMMGraph, World<(U256, U256)>) {
+        let mut pools = HashMap::new();
+        let mut graph = AMMGraph::new();
+        let mut world = World::default();
+        for (id, r0, r1) in [(1, 1_000_000, 1_000_000), (2, 10_000, 20_000)] {
+            pools.insert(PoolId(id), Cp::new(id, 1, 2));
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(1), TokenId(2));
+            world.pool_states.insert(PoolId(id), reserves(r0, r1));
+        }
+        (pools, graph, world)
+    }
+
+    #[test]
+    fn log_spacing_hits_endpoints_and_grows_geometrically() {
+        let xs = log_spaced(U256::from(10u64), U256::from(100_000u64), 5);
+        let expected: Vec<U256> = [10u64, 100, 1_000, 10_000, 100_000]
+            .into_iter()
+            .map(U256::from)
+            .collect();
+        assert_eq!(xs, expected);
+    }
+
+    #[test]
+    fn curve_interpolates_plan_outputs() {
+        let (pools, _, world) = setup();
+        let engine = Engine::new(&pools);
+        let plan = [hop(1, 1, 2)];
+        let curve =
+            engine.quote_curve(&world, &plan, U256::from(100u64), U256::from(100_000u64), 8);
+        assert_eq!(curve.points.len(), 9);
+        assert_eq!(curve.max_in(), U256::from(100_000u64));
+
+        let exact = |amt: u64| {
+            engine
+                .simulate_chained(&world, &plan, U256::from(amt))
+                .steps[0]
+                .amt_out
+        };
+        assert_eq!(curve.output(U256::from(100_000u64)), exact(100_000));
+        // Chords of a concave curve sit below it.
+        let mid = curve.output(U256::from(50_000u64));
+        assert!(
+            mid <= exact(50_000) && mid * U256::from(100u64) >= exact(50_000) * U256::from(98u64)
+        );
+        assert!(
+            curve.marginal_rate(U256::from(50u64)) > curve.marginal_rate(U256::from(90_000u64))
+        );
+
+        let need = curve.input_for(mid).unwrap();
+        assert!(need.abs_diff(U256::from(50_000u64)) <= U256::from(1u64));
+        assert_eq!(curve.input_for(U256::from(10_000_000u64)), None);
+    }
+
+    #[test]
+    fn pair_curve_takes_best_route_per_size() {
+        let (pools, graph, world) = setup();
+        let engine = Engine::new(&pools);
+        let scanner = Scanner::new(&engine, &graph).with_config(crate::arb::ScanConfig {
+            max_hops: 1,
+            ..Default::default()
+        });
+        let curve = scanner.quote_curve(
+            &world,
+            TokenId(1),
+            TokenId(2),
+            U256::from(10u64),
+            U256::from(100_000u64),
+            5,
+        );
+        // The shallow pool pays more for small trades, the deep one for large.
+        let out = |pid: u64, amt: u64| {
+            engine
+                .simulate_chained(&world, &[hop(pid, 1, 2)], U256::from(amt))
+                .steps[0]
+                .amt_out
+        };
+        assert_eq!(curve.output(U256::from(10u64)), out(2, 10));
+        assert_eq!(curve.output(U256::from(100_000u64)), out(1, 100_000));
+    }
+}
@@ -0,0 +1,341 @@
+//! Typed decoders for the pool events log-driven sync consumes, mapped to
+//! protocol-neutral [`StateUpdate`]s.
+
+use crate::{
+    ids::PoolId,
+    registry::Registry,
+    univ2::UniV2State,
+    world::{World, WorldDiff},
+};
+use alloy_primitives::{Address, I256, Log, U256};
+use alloy_sol_types::{SolEvent, sol};
+
+sol! {
+    interface IUniswapV2Pair {
+        event Sync(uint112 reserve0, uint112 reserve1);
+        event Swap(address indexed sender, uint256 amount0In, uint256 amount1In, uint256 amount0Out, uint256 amount1Out, address indexed to);
+    }
+
+    interface IUniswapV3Pool {
+        event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick);
+        event Mint(address sender, address indexed owner, int24 indexed tickLower, int24 indexed tickUpper, uint128 amount, uint256 amount0, uint256 amount1);
+        event Burn(address indexed owner, int24 indexed tickLower, int24 indexed tickUpper, uint128 amount, uint256 amount0, uint256 amount1);
+    }
+
+    interface ICurvePool {
+        event TokenExchange(address indexed buyer, int128 sold_id, uint256 tokens_sold, int128 bought_id, uint256 tokens_bought);
+    }
+
+    interface IBalancerVault {
+        event Swap(bytes32 indexed poolId, address indexed tokenIn, address indexed tokenOut, uint256 amountIn, uint256 amountOut);
+    }
+}
+
+/// A pool-side token, by position for Curve and by address for Balancer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Coin {
+    Index(usize),
+    Token(Address),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateUpdate {
+    /// Absolute reserves after the transaction (V2 `Sync`).
+    Reserves { reserve0: U256, reserve1: U256 },
+    /// Net change of the pool's balances (V2 `Swap`). V2 pairs emit `Sync`
+    /// before `Swap`, so this is already reflected in the reserves.
+    Swap { amount0: I256, amount1: I256 },
+    /// V3 `Swap`: balance deltas and the price the pool ended on.
+    V3Swap {
+        amount0: I256,
+        amount1: I256,
+        sqrt_price_x96: U256,
+        liquidity: u128,
+        tick: i32,
+    },
+    /// V3 `Mint` (positive) or `Burn` (negative) over a tick range.
+    V3Liquidity {
+        tick_lower: i32,
+        tick_upper: i32,
+        delta: i128,
+    },
+    /// Curve `TokenExchange` or Balancer `Swap`.
+    Exchange {
+        sold: Coin,
+        amount_in: U256,
+        bought: Coin,
+        amount_out: U256,
+    },
+}
+
+/// A decoded log and the address of the pool it is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodedLog {
+    pub pool: Address,
+    pub update: StateUpdate,
+}
+
+/// Decodes `log` if it is one of the supported events; `None` otherwise.
+pub fn decode_log(log: &Log) -> Option<DecodedLog> {
+    let topic0 = *log.data.topics().first()?;
+    let data = &log.data;
+    let pool = log.address;
+    let update = match topic0 {
+        IUniswapV2Pair::Sync::SIGNATURE_HASH => {
+            let e = IUniswapV2Pair::Sync::decode_log_data(data).ok()?;
+            StateUpdate::Reserves {
+                reserve0: U256::from(e.reserve0),
+                reserve1: U256::from(e.reserve1),
+            }
+        }
+        IUniswapV2Pair::Swap::SIGNATURE_HASH => {
+            let e = IUniswapV2Pair::Swap::decode_log_data(data).ok()?;
+            StateUpdate::Swap {
+                amount0: net(e.amount0In, e.amount0Out)?,
+                amount1: net(e.amount1In, e.amount1Out)?,
+            }
+        }
+        IUniswapV3Pool::Swap::SIGNATURE_HASH => {
+            let e = IUniswapV3Pool::Swap::decode_log_data(data).ok()?;
+            StateUpdate::V3Swap {
+                amount0: e.amount0,
+                amount1: e.amount1,
+                sqrt_price_x96: U256::from(e.sqrtPriceX96),
+                liquidity: e.liquidity,
+                tick: e.tick.as_i32(),
+            }
+        }
+        IUniswapV3Pool::Mint::SIGNATURE_HASH => {
+            let e = IUniswapV3Pool::Mint::decode_log_data(data).ok()?;
+            StateUpdate::V3Liquidity {
+                tick_lower: e.tickLower.as_i32(),
+                tick_upper: e.tickUpper.as_i32(),
+                delta: i128::try_from(e.amount).ok()?,
+            }
+        }
+        IUniswapV3Pool::Burn::SIGNATURE_HASH => {
+            let e = IUniswapV3Pool::Burn::decode_log_data(data).ok()?;
+            StateUpdate::V3Liquidity {
+                tick_lower: e.tickLower.as_i32(),
+                tick_upper: e.tickUpper.as_i32(),
+                delta: -i128::try_from(e.amount).ok()?,
+            }
+        }
+        ICurvePool::TokenExchange::SIGNATURE_HASH => {
+            let e = ICurvePool::TokenExchange::decode_log_data(data).ok()?;
+            StateUpdate::Exchange {
+                sold: Coin::Index(usize::try_from(e.sold_id).ok()?),
+                amount_in: e.tokens_sold,
+                bought: Coin::Index(usize::try_from(e.bought_id).ok()?),
+                amount_out: e.tokens_bought,
+            }
+        }
+        IBalancerVault::Swap::SIGNATURE_HASH => {
+            let e = IBalancerVault::Swap::decode_log_data(data).ok()?;
+            // Balancer pool ids start with the pool's address.
+            return Some(DecodedLog {
+                pool: Address::from_slice(&e.poolId[..20]),
+                update: StateUpdate::Exchange {
+                    sold: Coin::Token(e.tokenIn),
+                    amount_in: e.amountIn,
+                    bought: Coin::Token(e.tokenOut),
+                    amount_out: e.amountOut,
+                },
+            });
+        }
+        _ => return None,
+    };
+    Some(DecodedLog { pool, update })
+}
+
+fn net(amt_in: U256, amt_out: U256) -> Option<I256> {
+    I256::try_from(amt_in)
+        .ok()?
+        .checked_sub(I256::try_from(amt_out).ok()?)
+}
+
+/// Decodes `logs` in order, keeping those from pools in `reg`.
+pub fn decode_logs<'a>(
+    reg: &Registry,
+    logs: impl IntoIterator<Item = &'a Log>,
+) -> Vec<(PoolId, StateUpdate)> {
+    logs.into_iter()
+        .filter_map(decode_log)
+        .filter_map(|d| Some((*reg.pool_by_addr.get(&d.pool)?, d.update)))
+        .collect()
+}
+
+impl StateUpdate {
+    /// Applies the update to a V2 pair's state; returns whether it changed
+    /// anything. Only `Reserves` does: a V2 `Swap` is covered by its `Sync`.
+    pub fn apply_univ2(&self, st: &mut UniV2State) -> bool {
+        match *self {
+            StateUpdate::Reserves { reserve0, reserve1 } => {
+                *st = UniV2State::new(reserve0, reserve1);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Folds `updates` over `world` into one diff, e.g. for a block passed to
+/// [`ReorgGuard::apply_block`](crate::reorg::ReorgGuard::apply_block).
+pub fn univ2_diff(
+    world: &World<UniV2State>,
+    updates: impl IntoIterator<Item = (PoolId, StateUpdate)>,
+) -> WorldDiff<UniV2State> {
+    let mut diff = WorldDiff::default();
+    for (pid, update) in updates {
+        let mut st = diff
+            .pool_states
+            .get(&pid)
+            .or_else(|| world.pool_states.get(&pid))
+            .copied()
+            .unwrap_or_default();
+        if update.apply_univ2(&mut st) {
+            diff.set_pool_state(pid, st);
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{PoolKind, PoolMeta};
+    use crate::{ids::TokenId, test_utils::reserves};
+    use alloy_primitives::{B256, aliases::I24, aliases::U112};
+
+    fn log(address: Address, e: &impl SolEvent) -> Log {
+        Log {
+            address,
+            data: e.encode_log_data(),
+        }
+    }
+
+    #[test]
+    fn decodes_every_supported_event() {
+        let pool = Address::repeat_byte(1);
+        let sender = Address::repeat_byte(9);
+        let v2_swap = IUniswapV2Pair::Swap {
+            sender,
+            amount0In: U256::from(100u64),
+            amount1In: U256::ZERO,
+            amount0Out: U256::ZERO,
+            amount1Out: U256::from(90u64),
+            to: sender,
+        };
+        assert_eq!(
+            decode_log(&log(pool, &v2_swap)).unwrap().update,
+            StateUpdate::Swap {
+                amount0: I256::try_from(100).unwrap(),
+                amount1: I256::try_from(-90).unwrap(),
+            }
+        );
+
+        let v3_swap = IUniswapV3Pool::Swap {
+            sender,
+            recipient: sender,
+            amount0: I256::try_from(-5).unwrap(),
+            amount1: I256::try_from(7).unwrap(),
+            sqrtPriceX96: alloy_primitives::aliases::U160::from(1u64 << 40),
+            liquidity: 1_000,
+            tick: I24::try_from(-887).unwrap(),
+        };
+        assert!(matches!(
+            decode_log(&log(pool, &v3_swap)).unwrap().update,
+            StateUpdate::V3Swap {
+                liquidity: 1_000,
+                tick: -887,
+                ..
+            }
+        ));
+
+        let burn = IUniswapV3Pool::Burn {
+            owner: sender,
+            tickLower: I24::try_from(-60).unwrap(),
+            tickUpper: I24::try_from(60).unwrap(),
+            amount: 500,
+            amount0: U256::ZERO,
+            amount1: U256::ZERO,
+        };
+        assert_eq!(
+            decode_log(&log(pool, &burn)).unwrap().update,
+            StateUpdate::V3Liquidity {
+                tick_lower: -60,
+                tick_upper: 60,
+                delta: -500,
+            }
+        );
+
+        let exchange = ICurvePool::TokenExchange {
+            buyer: sender,
+            sold_id: 0,
+            tokens_sold: U256::from(10u64),
+            bought_id: 2,
+            tokens_bought: U256::from(9u64),
+        };
+        assert!(matches!(
+            decode_log(&log(pool, &exchange)).unwrap().update,
+            StateUpdate::Exchange {
+                sold: Coin::Index(0),
+                bought: Coin::Index(2),
+                ..
+            }
+        ));
+
+        let mut pool_id = B256::ZERO;
+        pool_id[..20].copy_from_slice(pool.as_slice());
+        let vault_swap = IBalancerVault::Swap {
+            poolId: pool_id,
+            tokenIn: Address::repeat_byte(2),
+            tokenOut: Address::repeat_byte(3),
+            amountIn: U256::from(4u64),
+            amountOut: U256::from(3u64),
+        };
+        let vault = Address::repeat_byte(0xba);
+        assert_eq!(decode_log(&log(vault, &vault_swap)).unwrap().pool, pool);
+
+        let unknown = Log::new_unchecked(pool, vec![B256::repeat_byte(7)], Default::default());
+        assert_eq!(decode_log(&unknown), None);
+    }
+
+    #[test]
+    fn sync_logs_fold_into_a_world_diff() {
+        let mut reg = Registry::default();
+        for i in 1..=2u8 {
+            reg.upsert_pool(
+                PoolId(i as u64),
+                PoolMeta {
+                    address: Address::repeat_byte(i),
+                    kind: PoolKind::UniV2,
+                    token0: TokenId(1),
+                    token1: TokenId(2),
+                    fee: 3000,
+                },
+            );
+        }
+        let sync = |addr: u8, r: u64| {
+            let e = IUniswapV2Pair::Sync {
+                reserve0: U112::from(r),
+                reserve1: U112::from(r * 2),
+            };
+            log(Address::repeat_byte(addr), &e)
+        };
+        let logs = [sync(1, 10), sync(2, 5), sync(1, 11), sync(7, 1)];
+        let updates = decode_logs(&reg, &logs);
+        assert_eq!(updates.len(), 3);
+
+        let mut world = World::default();
+        world.set_pool_state(PoolId(2), UniV2State::new(U256::ONE, U256::ONE));
+        let diff = univ2_diff(&world, updates);
+        world.apply(diff);
+        let st = |pid| {
+            let s: UniV2State = world.pool_states[&PoolId(pid)];
+            (s.reserve0, s.reserve1)
+        };
+        assert_eq!(st(1), reserves(11, 22));
+        assert_eq!(st(2), reserves(5, 10));
+    }
+}
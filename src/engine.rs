@@ -1,82 +1,1009 @@
 use crate::{
-    Pool, World,
-    ids::{PoolId, TokenId},
+    Pool,
+    error::{EngineError, WayfinderError},
+    ids::{AccountId, PoolId, SwapDirection, TokenId},
+    memo::SwapMemo,
+    num::{AmountRepr, MathError},
+    telemetry,
+    transfer::TransferModels,
+    world::{BlockContext, StateView, World, WorldDiff},
 };
 use alloy_primitives::U256;
+use smallvec::SmallVec;
 use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::ops::ControlFlow;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct Step {
     pub pool: PoolId,
     pub from: TokenId,
     pub to: TokenId,
     pub amt_in: U256,
     pub amt_out: U256,
+    /// Why the hop yielded nothing, when it was this hop that failed.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub failure: Option<HopFailure>,
 }
 
-#[derive(Clone, Debug)]
+/// Why a hop produced no output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub enum HopFailure {
+    /// Nothing comes out however much goes in.
+    ZeroLiquidity,
+    /// More than the pool's math, or a hop constraint, allows.
+    ExceedsDepth,
+    /// The pool does not trade this direction.
+    Unsupported,
+    /// Too little to buy a single unit of output.
+    DustInput,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct Path {
-    pub steps: Vec<Step>,
+    /// Inline up to four hops; longer paths spill to the heap.
+    pub steps: PathSteps,
+}
+
+pub type PathSteps = SmallVec<[Step; 4]>;
+
+impl Path {
+    /// The hop that broke the path, if any did.
+    pub fn first_failure(&self) -> Option<(usize, HopFailure)> {
+        self.steps
+            .iter()
+            .enumerate()
+            .find_map(|(i, s)| Some((i, s.failure?)))
+    }
+}
+
+impl Step {
+    pub fn direction(&self) -> SwapDirection {
+        SwapDirection {
+            from: self.from,
+            to: self.to,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct Hop {
+    pub pool: PoolId,
+    pub dir: SwapDirection,
 }
 
-pub type Hop = (PoolId, TokenId, TokenId);
+impl Hop {
+    pub fn new(pool: PoolId, dir: SwapDirection) -> Self {
+        Self { pool, dir }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ApprovalPolicy {
+    #[default]
+    Check,
+    AssumeInfinite,
+}
+
+/// How pools are simulated: [`Pool::swap_approx`] to screen candidates
+/// cheaply, or [`Pool::swap`] for the finalists.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Accuracy {
+    Fast,
+    #[default]
+    Exact,
+}
+
+#[derive(Clone, Debug)]
+pub struct Execution {
+    pub path: Path,
+    pub unapproved_hops: Vec<usize>,
+    pub insufficient_balance: bool,
+    /// The hop that broke the path; nothing is committed past a failure.
+    pub failure: Option<(usize, HopFailure)>,
+}
+
+impl Execution {
+    pub fn committed(&self) -> bool {
+        self.unapproved_hops.is_empty() && !self.insufficient_balance && self.failure.is_none()
+    }
+}
+
+/// Checked after every hop with the step and the pool state it left behind;
+/// `Break` aborts the path with the given reason.
+pub trait HopConstraint<S>: Fn(&Step, &S) -> ControlFlow<&'static str> + Sync {}
+
+impl<S, F> HopConstraint<S> for F where F: Fn(&Step, &S) -> ControlFlow<&'static str> + Sync {}
 
 pub struct Engine<'a, P: Pool> {
     pub pools: &'a HashMap<PoolId, P>,
+    pub approvals: ApprovalPolicy,
+    pub memo: Option<&'a SwapMemo<P::State>>,
+    pub constraint: Option<&'a dyn HopConstraint<P::State>>,
+    pub transfers: Option<&'a TransferModels>,
+    pub accuracy: Accuracy,
 }
 
 impl<'a, P: Pool> Engine<'a, P> {
     pub fn new(pools: &'a HashMap<PoolId, P>) -> Self {
-        Self { pools }
+        Self {
+            pools,
+            approvals: ApprovalPolicy::default(),
+            memo: None,
+            constraint: None,
+            transfers: None,
+            accuracy: Accuracy::Exact,
+        }
+    }
+
+    pub fn with_approvals(mut self, approvals: ApprovalPolicy) -> Self {
+        self.approvals = approvals;
+        self
+    }
+
+    /// Reuses swap results for hops that start from a versioned world state.
+    pub fn with_memo(mut self, memo: &'a SwapMemo<P::State>) -> Self {
+        self.memo = Some(memo);
+        self
+    }
+
+    /// Aborts paths on a hop `constraint` rejects, e.g. a price outside an
+    /// oracle band: later hops yield zero, and the fallible methods return
+    /// [`EngineError::Constraint`].
+    pub fn with_constraint(mut self, constraint: &'a dyn HopConstraint<P::State>) -> Self {
+        self.constraint = Some(constraint);
+        self
+    }
+
+    /// Skims transfers of taxed tokens. The first step's `amt_in` stays what
+    /// the sender paid; every `amt_out` is what reaches the next pool or the
+    /// recipient.
+    pub fn with_transfers(mut self, transfers: &'a TransferModels) -> Self {
+        self.transfers = Some(transfers);
+        self
+    }
+
+    /// Fast results never enter the memo, so a shared memo stays exact.
+    pub fn with_accuracy(mut self, accuracy: Accuracy) -> Self {
+        self.accuracy = accuracy;
+        self
+    }
+
+    /// Screens `plans` at [`Accuracy::Fast`], then re-simulates the
+    /// `finalists` best exactly and returns them by exact output, best first.
+    pub fn screen<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plans: &[Vec<Hop>],
+        amt_in: U256,
+        finalists: usize,
+    ) -> Vec<Path> {
+        let fast = Engine {
+            accuracy: Accuracy::Fast,
+            ..*self
+        };
+        let exact = Engine {
+            accuracy: Accuracy::Exact,
+            ..*self
+        };
+        let out = |p: &Path| p.steps.last().map_or(U256::ZERO, |s| s.amt_out);
+        let mut screened: Vec<_> = plans
+            .iter()
+            .map(|plan| (out(&fast.simulate_chained(world, plan, amt_in)), plan))
+            .collect();
+        screened.sort_by_key(|&(o, _)| std::cmp::Reverse(o));
+        let mut finals: Vec<Path> = screened
+            .into_iter()
+            .take(finalists)
+            .map(|(_, plan)| exact.simulate_chained(world, plan, amt_in))
+            .collect();
+        finals.sort_by_key(|p| std::cmp::Reverse(out(p)));
+        finals
+    }
+
+    /// Simulates `plan` from `first_in`. Hops whose math fails yield zero.
+    ///
+    /// # Panics
+    ///
+    /// On a malformed plan or a pool missing from the engine or the world;
+    /// see [`Engine::try_simulate`] for the fallible form.
+    pub fn simulate_chained<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plan: &[Hop],
+        first_in: U256,
+    ) -> Path {
+        expect_run(self.run(world, plan, first_in)).0
+    }
+
+    /// Like [`Engine::simulate_chained`], but returns malformed plans, missing
+    /// pools and the first failed hop's math error or constraint violation
+    /// instead of panicking or treating the hop as a zero-output swap.
+    pub fn try_simulate<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plan: &[Hop],
+        first_in: U256,
+    ) -> Result<Path, WayfinderError> {
+        match self.run(world, plan, first_in)? {
+            (_, _, Some(e)) => Err(e),
+            (path, _, None) => Ok(path),
+        }
+    }
+
+    /// Output of [`Engine::simulate_chained`] for callers holding amounts in
+    /// another integer type.
+    pub fn simulate_as<A: AmountRepr, V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plan: &[Hop],
+        first_in: A,
+    ) -> A {
+        let path = self.simulate_chained(world, plan, first_in.into_amount());
+        A::from_amount(path.steps.last().map_or(U256::ZERO, |s| s.amt_out))
+    }
+
+    /// Like [`Engine::simulate_chained`], also returning the states the
+    /// path leaves its pools in, to overlay or inspect.
+    pub fn simulate_diff<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plan: &[Hop],
+        first_in: U256,
+    ) -> (Path, WorldDiff<P::State>) {
+        let (path, pool_states, _) = expect_run(self.run(world, plan, first_in));
+        let diff = WorldDiff {
+            block: None,
+            pool_states,
+        };
+        (path, diff)
+    }
+
+    pub fn apply(&self, world: &mut World<P::State>, plan: &[Hop], first_in: U256) -> Path {
+        let (path, scratch, _) = expect_run(self.run(&*world, plan, first_in));
+        for (pid, st) in scratch {
+            world.set_pool_state(pid, st);
+        }
+        path
     }
 
-    pub fn simulate_chained(&self, world: &World<P::State>, plan: &[Hop], first_in: U256) -> Path {
-        assert!(!plan.is_empty(), "path must have at least one hop");
+    /// Fallible [`Engine::apply`]; the world is only updated on success.
+    pub fn try_apply(
+        &self,
+        world: &mut World<P::State>,
+        plan: &[Hop],
+        first_in: U256,
+    ) -> Result<Path, WayfinderError> {
+        let (path, scratch, error) = self.run(&*world, plan, first_in)?;
+        if let Some(e) = error {
+            return Err(e);
+        }
+        for (pid, st) in scratch {
+            world.set_pool_state(pid, st);
+        }
+        Ok(path)
+    }
+
+    pub fn execute(
+        &self,
+        world: &mut World<P::State>,
+        owner: AccountId,
+        spender: AccountId,
+        plan: &[Hop],
+        first_in: U256,
+    ) -> Execution {
+        let (path, scratch, _) = expect_run(self.run(&*world, plan, first_in));
+
+        let mut unapproved_hops = Vec::new();
+        if self.approvals == ApprovalPolicy::Check {
+            let mut used: HashMap<TokenId, U256> = HashMap::new();
+            for (i, step) in path.steps.iter().enumerate() {
+                let need = used.entry(step.from).or_default();
+                *need = need.saturating_add(step.amt_in);
+                if world.allowance(owner, spender, step.from) < *need {
+                    unapproved_hops.push(i);
+                }
+            }
+        }
 
-        let start_token = plan[0].1;
-        let mut amt_in = first_in;
+        let start_token = plan[0].dir.from;
+        let insufficient_balance = world.holding(owner, start_token) < first_in;
+
+        let exec = Execution {
+            failure: path.first_failure(),
+            path,
+            unapproved_hops,
+            insufficient_balance,
+        };
+        if !exec.committed() {
+            return exec;
+        }
+
+        for step in &exec.path.steps {
+            if self.approvals == ApprovalPolicy::Check {
+                world.spend_allowance(owner, spender, step.from, step.amt_in);
+            }
+            world.debit(owner, step.from, step.amt_in);
+            world.credit(owner, step.to, step.amt_out);
+        }
+        for (pid, st) in scratch {
+            world.set_pool_state(pid, st);
+        }
 
+        exec
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", name = "simulate", skip_all, fields(hops = plan.len(), %first_in))
+    )]
+    fn run<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plan: &[Hop],
+        first_in: U256,
+    ) -> Result<Run<P::State>, EngineError> {
+        let start_token = plan.first().ok_or(EngineError::EmptyPlan)?.dir.from;
+        let mut at = Cursor::new(start_token, first_in);
         let mut scratch: HashMap<PoolId, P::State> = HashMap::new();
+        let mut steps = PathSteps::with_capacity(plan.len());
+        for &hop in plan {
+            steps.push(self.step(world, &mut scratch, hop, &mut at)?);
+        }
 
-        let mut last_token = start_token;
-        let mut steps = Vec::with_capacity(plan.len());
+        telemetry::path_simulated(steps.len());
+        Ok((Path { steps }, scratch, at.fault.map(Into::into)))
+    }
 
-        for &(pid, from, to) in plan {
-            assert_eq!(
-                from, last_token,
-                "path discontinuity: expected from {:?}",
-                last_token
-            );
+    /// Simulates one hop from `at` on `scratch`, loading the pool's state
+    /// from `world` on first use, and moves `at` past it.
+    pub(crate) fn step<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        scratch: &mut HashMap<PoolId, P::State>,
+        Hop { pool: pid, dir }: Hop,
+        at: &mut Cursor,
+    ) -> Result<Step, EngineError> {
+        let SwapDirection { from, to } = dir;
+        if !dir.is_valid() {
+            return Err(EngineError::SelfSwap(pid));
+        }
+        if from != at.token {
+            return Err(EngineError::Discontinuity {
+                pool: pid,
+                expected: at.token,
+                found: from,
+            });
+        }
+        let amt_in = at.amt;
+        let ctx = world.block();
 
-            let pool = self.pools.get(&pid).expect("missing pool impl");
-            debug_assert!(pool.supports(from, to), "unsupported direction");
+        #[cfg(feature = "tracing")]
+        let hop_span = tracing::trace_span!(
+            "hop",
+            pool = %pid,
+            %from,
+            %to,
+            %amt_in,
+            amt_out = tracing::field::Empty
+        )
+        .entered();
 
-            let st = scratch.entry(pid).or_insert_with(|| {
+        let pool = self.pools.get(&pid).ok_or(EngineError::MissingPool(pid))?;
+        let supported = pool.supports(dir);
+
+        // Later hops receive an output already skimmed on its way here.
+        let swap_in = match self.transfers {
+            Some(t) if at.hops == 0 => t.received(from, amt_in),
+            _ => amt_in,
+        };
+        // Only supported swaps that succeeded are memoized, so a hit never
+        // hides an error.
+        let memo_key = match (self.memo, scratch.contains_key(&pid)) {
+            (Some(memo), false)
+                if supported && !swap_in.is_zero() && self.accuracy == Accuracy::Exact =>
+            {
                 world
-                    .pool_states
-                    .get(&pid)
-                    .expect("missing pool state")
-                    .clone()
-            });
+                    .pool_version(pid)
+                    .map(|v| memo.key(pid, v, ctx, dir, swap_in))
+            }
+            _ => None,
+        };
+        let cached = memo_key.and_then(|k| self.memo?.get(&k));
+        let mut failure = None;
 
-            let amt_out = if amt_in.is_zero() {
+        let amt_out = if let Some((out, st)) = cached {
+            scratch.insert(pid, st);
+            out
+        } else {
+            let st = match scratch.entry(pid) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => e.insert(
+                    world
+                        .pool_state(pid)
+                        .ok_or(EngineError::MissingPoolState(pid))?
+                        .clone(),
+                ),
+            };
+            let out = if swap_in.is_zero() {
+                U256::ZERO
+            } else if !supported {
+                failure = Some(HopFailure::Unsupported);
+                at.fault
+                    .get_or_insert(Fault::Engine(EngineError::Unsupported { pool: pid, dir }));
                 U256::ZERO
             } else {
-                pool.swap(st, from, to, amt_in)
+                let swapped = match self.accuracy {
+                    Accuracy::Fast => pool.swap_approx(st, &ctx, dir, swap_in),
+                    Accuracy::Exact => pool.swap(st, &ctx, dir, swap_in),
+                };
+                match swapped {
+                    Ok(out) => out,
+                    Err(e) => {
+                        failure = Some(match e {
+                            MathError::DivisionByZero => HopFailure::ZeroLiquidity,
+                            MathError::Overflow | MathError::Underflow => HopFailure::ExceedsDepth,
+                        });
+                        at.fault.get_or_insert(Fault::Math(e));
+                        U256::ZERO
+                    }
+                }
             };
+            if let (Some(memo), Some(key), None) = (self.memo, memo_key, failure) {
+                memo.insert(key, out, st.clone());
+            }
+            out
+        };
+        if amt_out.is_zero() && !swap_in.is_zero() && failure.is_none() {
+            failure = Some(classify_zero_out(pool, &scratch[&pid], &ctx, dir, swap_in));
+        }
+        let amt_out = match self.transfers {
+            Some(t) => t.received(to, amt_out),
+            None => amt_out,
+        };
+        #[cfg(feature = "tracing")]
+        hop_span.record("amt_out", tracing::field::display(amt_out));
 
-            steps.push(Step {
-                pool: pid,
-                from,
-                to,
-                amt_in,
-                amt_out,
-            });
+        let mut step = Step {
+            pool: pid,
+            from,
+            to,
+            amt_in,
+            amt_out,
+            failure,
+        };
+        let violation = match self.constraint {
+            Some(check) if at.fault.is_none() => check(&step, &scratch[&pid]).break_value(),
+            _ => None,
+        };
+        if violation.is_some() {
+            step.failure = Some(HopFailure::ExceedsDepth);
+        }
 
-            last_token = to;
-            amt_in = amt_out;
+        at.token = to;
+        at.amt = amt_out;
+        at.hops += 1;
+        if let Some(reason) = violation {
+            at.fault = Some(Fault::Engine(EngineError::Constraint { pool: pid, reason }));
+            at.amt = U256::ZERO;
         }
+        Ok(step)
+    }
+}
+
+/// A simulated path, the scratch pool states it left behind, and the first
+/// math error or constraint violation hit along the way.
+type Run<S> = (Path, HashMap<PoolId, S>, Option<WayfinderError>);
+
+/// The first math error or constraint violation on a path. Copyable, unlike
+/// [`WayfinderError`], so a plan trie can hand it down shared prefixes.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Fault {
+    Math(MathError),
+    Engine(EngineError),
+}
+
+impl From<Fault> for WayfinderError {
+    fn from(fault: Fault) -> Self {
+        match fault {
+            Fault::Math(e) => e.into(),
+            Fault::Engine(e) => e.into(),
+        }
+    }
+}
+
+/// Where a path stands between hops.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Cursor {
+    /// The token the next hop must take in.
+    pub token: TokenId,
+    pub amt: U256,
+    /// Hops taken so far.
+    pub hops: usize,
+    pub fault: Option<Fault>,
+}
+
+impl Cursor {
+    pub fn new(token: TokenId, amt: U256) -> Self {
+        Self {
+            token,
+            amt,
+            hops: 0,
+            fault: None,
+        }
+    }
+}
+
+fn expect_run<S>(run: Result<Run<S>, EngineError>) -> Run<S> {
+    run.unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// A zero-output swap of `amt_in` is dust if far larger inputs do produce
+/// output, and a sign of an empty pool otherwise.
+pub(crate) fn classify_zero_out<P: Pool>(
+    pool: &P,
+    st: &P::State,
+    ctx: &BlockContext,
+    dir: SwapDirection,
+    amt_in: U256,
+) -> HopFailure {
+    let live = [16, 32, 64].into_iter().any(|shift| {
+        amt_in
+            .checked_shl(shift)
+            .is_some_and(|probe| !swap_or_zero(pool, &mut st.clone(), ctx, dir, probe).is_zero())
+    });
+    if live {
+        HopFailure::DustInput
+    } else {
+        HopFailure::ZeroLiquidity
+    }
+}
+
+/// Swaps on `st`, treating a math failure as zero output so the route never
+/// wins a ranking. Pools leave state untouched when a swap fails.
+pub(crate) fn swap_or_zero<P: Pool>(
+    pool: &P,
+    st: &mut P::State,
+    ctx: &BlockContext,
+    dir: SwapDirection,
+    amt_in: U256,
+) -> U256 {
+    pool.swap(st, ctx, dir, amt_in).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::num::MathResult;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::BlockContext;
+
+    fn setup() -> (HashMap<PoolId, Cp>, World<(U256, U256)>) {
+        let a = TokenId(1);
+        let mut pools = HashMap::new();
+        pools.insert(PoolId(1), Cp::new(1, 1, 2));
+        let mut world = World::default();
+        world.pool_states.insert(PoolId(1), reserves(1_000, 1_000));
+        world.set_holding(AccountId(1), a, U256::from(100u64));
+        (pools, world)
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn path_round_trips_through_json() {
+        let (pools, world) = setup();
+        let path =
+            Engine::new(&pools).simulate_chained(&world, &[hop(1, 1, 2)], U256::from(100u64));
+
+        let json = serde_json::to_string(&path).unwrap();
+        let back: Path = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.steps[0].amt_out, path.steps[0].amt_out);
+        assert_eq!(back.steps[0].direction(), path.steps[0].direction());
+    }
+
+    #[test]
+    fn engine_and_world_are_shared_across_threads() {
+        let (pools, world) = setup();
+        let memo = SwapMemo::default();
+        let engine = Engine::new(&pools).with_memo(&memo);
+        let plan = [hop(1, 1, 2)];
+        let expected = engine.simulate_chained(&world, &plan, U256::from(10u64));
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    let path = engine.simulate_chained(&world, &plan, U256::from(10u64));
+                    assert_eq!(path.steps[0].amt_out, expected.steps[0].amt_out);
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn malformed_plans_and_missing_pools_are_errors() {
+        let (pools, world) = setup();
+        let engine = Engine::new(&pools);
+        let amt = U256::from(10u64);
+        let err = |plan: &[Hop]| match engine.try_simulate(&world, plan, amt) {
+            Err(WayfinderError::Engine(e)) => e,
+            other => panic!("expected engine error, got {other:?}"),
+        };
+        assert_eq!(err(&[]), EngineError::EmptyPlan);
+        assert_eq!(err(&[hop(2, 1, 2)]), EngineError::MissingPool(PoolId(2)));
+        assert_eq!(
+            err(&[hop(1, 1, 2), hop(1, 1, 2)]),
+            EngineError::Discontinuity {
+                pool: PoolId(1),
+                expected: TokenId(2),
+                found: TokenId(1),
+            }
+        );
+
+        let mut empty = world.clone();
+        empty.pool_states.clear();
+        assert!(matches!(
+            engine.try_simulate(&empty, &[hop(1, 1, 2)], amt),
+            Err(WayfinderError::Engine(EngineError::MissingPoolState(
+                PoolId(1)
+            )))
+        ));
+    }
+
+    #[test]
+    fn overflowing_hop_zeroes_the_route_and_try_simulate_reports_it() {
+        let (pools, mut world) = setup();
+        world
+            .pool_states
+            .insert(PoolId(1), (U256::MAX - U256::from(1u64), U256::MAX));
+        let engine = Engine::new(&pools);
+        let plan = [hop(1, 1, 2)];
+        let amt = U256::from(100u64);
+
+        let path = engine.simulate_chained(&world, &plan, amt);
+        assert_eq!(path.steps[0].amt_out, U256::ZERO);
+        assert!(matches!(
+            engine.try_simulate(&world, &plan, amt),
+            Err(WayfinderError::Math(MathError::Overflow))
+        ));
+        // A memoized engine reports the failure every time.
+        world.touch(PoolId(1));
+        let memo = SwapMemo::default();
+        let memoized = Engine::new(&pools).with_memo(&memo);
+        for _ in 0..2 {
+            assert!(matches!(
+                memoized.try_simulate(&world, &plan, amt),
+                Err(WayfinderError::Math(MathError::Overflow))
+            ));
+        }
+        assert!(engine.try_apply(&mut world, &plan, amt).is_err());
+        engine.apply(&mut world, &plan, amt);
+        assert_eq!(
+            world.pool_states[&PoolId(1)],
+            (U256::MAX - U256::from(1u64), U256::MAX)
+        );
+    }
+
+    #[test]
+    fn hop_constraints_abort_the_path() {
+        let (mut pools, mut world) = setup();
+        pools.insert(PoolId(2), Cp::new(2, 2, 3));
+        world.set_pool_state(PoolId(2), reserves(1_000, 1_000));
+        // Reject hops that leave pool 1 with more than 1 050 of token 1.
+        let band = |step: &Step, st: &(U256, U256)| {
+            if step.pool == PoolId(1) && st.0 > U256::from(1_050u64) {
+                ControlFlow::Break("price out of band")
+            } else {
+                ControlFlow::Continue(())
+            }
+        };
+        let engine = Engine::new(&pools).with_constraint(&band);
+        let plan = [hop(1, 1, 2), hop(2, 2, 3)];
+
+        assert!(
+            engine
+                .try_simulate(&world, &plan, U256::from(50u64))
+                .is_ok()
+        );
+        let path = engine.simulate_chained(&world, &plan, U256::from(100u64));
+        assert!(path.steps[0].amt_out > U256::ZERO);
+        assert_eq!(path.steps[1].amt_out, U256::ZERO);
+        assert!(matches!(
+            engine.try_simulate(&world, &plan, U256::from(100u64)),
+            Err(WayfinderError::Engine(EngineError::Constraint {
+                pool: PoolId(1),
+                reason: "price out of band"
+            }))
+        ));
+        assert!(
+            engine
+                .try_apply(&mut world, &plan, U256::from(100u64))
+                .is_err()
+        );
+        assert_eq!(world.pool_states[&PoolId(1)], reserves(1_000, 1_000));
+    }
+
+    #[test]
+    fn zero_output_hops_are_classified() {
+        let (mut pools, mut world) = setup();
+        pools.insert(PoolId(2), Cp::new(2, 2, 3));
+        world.pool_states.insert(PoolId(2), reserves(1_000, 0));
+        let engine = Engine::new(&pools);
+        let failure = |plan: &[Hop], amt: u64| {
+            engine
+                .simulate_chained(&world, plan, U256::from(amt))
+                .first_failure()
+        };
+
+        assert_eq!(failure(&[hop(1, 1, 2)], 100), None);
+        assert_eq!(
+            failure(&[hop(1, 1, 2)], 1),
+            Some((0, HopFailure::DustInput))
+        );
+        assert_eq!(
+            failure(&[hop(1, 1, 2), hop(2, 2, 3)], 100),
+            Some((1, HopFailure::ZeroLiquidity))
+        );
+        assert_eq!(
+            failure(&[hop(1, 1, 3)], 100),
+            Some((0, HopFailure::Unsupported))
+        );
+        world.touch(PoolId(1));
+        let memo = SwapMemo::default();
+        let memoized = Engine::new(&pools).with_memo(&memo);
+        for _ in 0..2 {
+            assert!(matches!(
+                memoized.try_simulate(&world, &[hop(1, 1, 3)], U256::from(100u64)),
+                Err(WayfinderError::Engine(EngineError::Unsupported { .. }))
+            ));
+        }
+
+        let cap = |s: &Step, _: &(U256, U256)| {
+            if s.amt_out > U256::from(50u64) {
+                ControlFlow::Break("too large")
+            } else {
+                ControlFlow::Continue(())
+            }
+        };
+        let capped = Engine::new(&pools).with_constraint(&cap);
+        let path = capped.simulate_chained(&world, &[hop(1, 1, 2)], U256::from(100u64));
+        assert_eq!(path.first_failure(), Some((0, HopFailure::ExceedsDepth)));
+    }
+
+    #[test]
+    fn execute_flags_missing_approval_and_leaves_world_untouched() {
+        let (pools, mut world) = setup();
+        let before = world.clone();
+        let plan = [hop(1, 1, 2)];
+
+        let exec = Engine::new(&pools).execute(
+            &mut world,
+            AccountId(1),
+            AccountId(99),
+            &plan,
+            U256::from(100u64),
+        );
+        assert_eq!(exec.unapproved_hops, vec![0]);
+        assert!(!exec.committed());
+        assert!(before.diff(&world).is_empty());
+    }
+
+    #[test]
+    fn execute_leaves_world_untouched_when_a_hop_fails() {
+        let (pools, mut world) = setup();
+        let (me, router) = (AccountId(1), AccountId(99));
+        world.approve(me, router, TokenId(1), U256::from(150u64));
+        world
+            .pool_states
+            .insert(PoolId(1), (U256::MAX - U256::from(1u64), U256::MAX));
+        let before = world.clone();
+
+        let exec = Engine::new(&pools).execute(
+            &mut world,
+            me,
+            router,
+            &[hop(1, 1, 2)],
+            U256::from(100u64),
+        );
+        assert_eq!(exec.failure, Some((0, HopFailure::ExceedsDepth)));
+        assert!(!exec.committed());
+        assert!(before.diff(&world).is_empty());
+        assert_eq!(world.holding(me, TokenId(1)), U256::from(100u64));
+        assert_eq!(world.allowance(me, router, TokenId(1)), U256::from(150u64));
+    }
+
+    #[test]
+    fn execute_commits_state_holdings_and_allowance() {
+        let (pools, mut world) = setup();
+        let (me, router) = (AccountId(1), AccountId(99));
+        world.approve(me, router, TokenId(1), U256::from(150u64));
+        let plan = [hop(1, 1, 2)];
+
+        let exec = Engine::new(&pools).execute(&mut world, me, router, &plan, U256::from(100u64));
+        assert!(exec.committed());
+        assert_eq!(exec.path.steps[0].amt_out, U256::from(90u64));
+        assert_eq!(world.holding(me, TokenId(1)), U256::ZERO);
+        assert_eq!(world.holding(me, TokenId(2)), U256::from(90u64));
+        assert_eq!(world.allowance(me, router, TokenId(1)), U256::from(50u64));
+        assert_eq!(
+            world.pool_states[&PoolId(1)],
+            (U256::from(1_100u64), U256::from(910u64))
+        );
+    }
+
+    #[test]
+    fn assume_infinite_approvals_skips_check() {
+        let (pools, mut world) = setup();
+        let plan = [hop(1, 1, 2)];
+
+        let exec = Engine::new(&pools)
+            .with_approvals(ApprovalPolicy::AssumeInfinite)
+            .execute(
+                &mut world,
+                AccountId(1),
+                AccountId(99),
+                &plan,
+                U256::from(100u64),
+            );
+        assert!(exec.committed());
+        assert_eq!(world.holding(AccountId(1), TokenId(2)), U256::from(90u64));
+    }
+
+    struct Decaying;
+
+    impl Pool for Decaying {
+        type State = ();
+
+        fn id(&self) -> PoolId {
+            PoolId(7)
+        }
+
+        fn supports(&self, _dir: SwapDirection) -> bool {
+            true
+        }
+
+        fn swap(
+            &self,
+            _st: &mut Self::State,
+            ctx: &BlockContext,
+            _dir: SwapDirection,
+            amt_in: U256,
+        ) -> MathResult<U256> {
+            Ok(amt_in >> (ctx.timestamp / 100) as usize)
+        }
+    }
+
+    #[test]
+    fn swap_sees_block_context_of_view() {
+        let mut pools = HashMap::new();
+        pools.insert(PoolId(7), Decaying);
+        let mut world = World::default();
+        world.pool_states.insert(PoolId(7), ());
+        world.block.timestamp = 100;
+
+        let engine = Engine::new(&pools);
+        let plan = [hop(7, 1, 2)];
+        let now = engine.simulate_chained(&world, &plan, U256::from(64u64));
+        assert_eq!(now.steps[0].amt_out, U256::from(32u64));
+
+        let next = WorldDiff {
+            block: Some(BlockContext {
+                number: 1,
+                timestamp: 300,
+                basefee: 0,
+            }),
+            ..Default::default()
+        };
+        let pending =
+            engine.simulate_chained(&world.with_overlay(next.clone()), &plan, U256::from(64u64));
+        assert_eq!(pending.steps[0].amt_out, U256::from(8u64));
+
+        // A new block leaves pool versions alone, so the memo must key on it.
+        let memo = SwapMemo::default();
+        let memoized = Engine::new(&pools).with_memo(&memo);
+        world.set_pool_state(PoolId(7), ());
+        let amt = U256::from(64u64);
+        let now = memoized.simulate_chained(&world, &plan, amt);
+        assert_eq!(now.steps[0].amt_out, U256::from(32u64));
+        let pending = memoized.simulate_chained(&world.with_overlay(next.clone()), &plan, amt);
+        assert_eq!(pending.steps[0].amt_out, U256::from(8u64));
+        world.apply(next);
+        let later = memoized.simulate_chained(&world, &plan, amt);
+        assert_eq!(later.steps[0].amt_out, U256::from(8u64));
+    }
+
+    /// Pays `rate_bps` of the input, but only half of it above `cap`; the
+    /// approximation ignores the cap.
+    struct Stepped {
+        id: u64,
+        rate_bps: u64,
+        cap: u64,
+    }
+
+    impl Pool for Stepped {
+        type State = ();
+
+        fn id(&self) -> PoolId {
+            PoolId(self.id)
+        }
+
+        fn supports(&self, _dir: SwapDirection) -> bool {
+            true
+        }
+
+        fn swap(
+            &self,
+            st: &mut (),
+            ctx: &BlockContext,
+            dir: SwapDirection,
+            amt_in: U256,
+        ) -> MathResult<U256> {
+            if amt_in > U256::from(self.cap) {
+                return Ok(amt_in / U256::from(2u64));
+            }
+            self.swap_approx(st, ctx, dir, amt_in)
+        }
+
+        fn swap_approx(
+            &self,
+            _st: &mut (),
+            _ctx: &BlockContext,
+            _dir: SwapDirection,
+            amt_in: U256,
+        ) -> MathResult<U256> {
+            Ok(amt_in * U256::from(self.rate_bps) / U256::from(10_000u64))
+        }
+    }
+
+    #[test]
+    fn screen_ranks_fast_then_reprices_finalists_exactly() {
+        let mut pools = HashMap::new();
+        let mut world = World::default();
+        for (id, rate_bps, cap) in [(1, 9_000, 500), (2, 8_500, u64::MAX), (3, 5_000, u64::MAX)] {
+            pools.insert(PoolId(id), Stepped { id, rate_bps, cap });
+            world.pool_states.insert(PoolId(id), ());
+        }
+        let plans: Vec<_> = (1..=3).map(|id| vec![hop(id, 1, 2)]).collect();
+        let amt = U256::from(1_000u64);
+        let engine = Engine::new(&pools);
+
+        let fast = Engine::new(&pools).with_accuracy(Accuracy::Fast);
+        assert_eq!(
+            fast.simulate_chained(&world, &plans[0], amt).steps[0].amt_out,
+            U256::from(900u64)
+        );
 
-        Path { steps }
+        let best = engine.screen(&world, &plans, amt, 2);
+        let got: Vec<_> = best
+            .iter()
+            .map(|p| (p.steps[0].pool, p.steps[0].amt_out))
+            .collect();
+        assert_eq!(
+            got,
+            [
+                (PoolId(2), U256::from(850u64)),
+                (PoolId(1), U256::from(500u64))
+            ]
+        );
+        // A Fast engine still reprices its finalists exactly.
+        assert_eq!(fast.screen(&world, &plans, amt, 2), best);
     }
 }
@@ -0,0 +1,95 @@
+use crate::{
+    ids::{PoolId, SwapDirection, TokenId},
+    num::MathError,
+};
+use alloy_primitives::U256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum GraphError {
+    #[error("token {0} is not in the graph")]
+    UnknownToken(TokenId),
+    #[error("pool {0} is not in the graph")]
+    UnknownPool(PoolId),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum RegistryError {
+    #[error("no registry entry for token {0}")]
+    UnknownToken(TokenId),
+    #[error("no registry entry for pool {0}")]
+    UnknownPool(PoolId),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum EngineError {
+    #[error("path must have at least one hop")]
+    EmptyPlan,
+    #[error("self-swap hop in pool {0}")]
+    SelfSwap(PoolId),
+    #[error("path discontinuity at pool {pool}: expected from {expected}, got {found}")]
+    Discontinuity {
+        pool: PoolId,
+        expected: TokenId,
+        found: TokenId,
+    },
+    #[error("no pool implementation for {0}")]
+    MissingPool(PoolId),
+    #[error("no state for pool {0}")]
+    MissingPoolState(PoolId),
+    #[error("flash loan of {loan} cannot fund a plan from {from} to {to}")]
+    LoanToken {
+        loan: TokenId,
+        from: TokenId,
+        to: TokenId,
+    },
+    #[error("pool {pool} does not swap {dir}")]
+    Unsupported { pool: PoolId, dir: SwapDirection },
+    #[error("hop through pool {pool} violated a constraint: {reason}")]
+    Constraint { pool: PoolId, reason: &'static str },
+    #[error("plan returns {out} but the flash loan needs {owed}")]
+    Unrepayable { owed: U256, out: U256 },
+}
+
+/// A routing stack whose parts do not refer to the same pools.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum BuildError {
+    #[error("no {0} given")]
+    Missing(&'static str),
+    #[error("pool {0} is in the graph but not the registry")]
+    MissingMeta(PoolId),
+    #[error("pool {0} is in the graph but has no implementation")]
+    MissingImpl(PoolId),
+    #[error("pool {0} is in the graph but has no state")]
+    MissingState(PoolId),
+    #[error("pool {0} is already routable")]
+    DuplicatePool(PoolId),
+    #[error("implementation of pool {0} does not match its metadata")]
+    PoolMismatch(PoolId),
+}
+
+/// Crate-wide error for fallible entry points, wrapping the module errors.
+#[derive(Debug, thiserror::Error)]
+pub enum WayfinderError {
+    #[error(transparent)]
+    Graph(#[from] GraphError),
+    #[error(transparent)]
+    Registry(#[from] RegistryError),
+    #[error(transparent)]
+    Engine(#[from] EngineError),
+    #[error(transparent)]
+    Math(#[from] MathError),
+    #[error(transparent)]
+    Build(#[from] BuildError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("rpc: {0}")]
+    Rpc(String),
+    #[error("store: {0}")]
+    Store(String),
+    #[error("no state for pool {pool} at block {block}")]
+    Unavailable { pool: PoolId, block: u64 },
+    #[error("no checkpoint at or before block {0}")]
+    NoCheckpoint(u64),
+}
+
+pub type Result<T, E = WayfinderError> = std::result::Result<T, E>;
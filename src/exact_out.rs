@@ -0,0 +1,158 @@
+//! Buy-side quotes: the least input that buys a given output, subject to a
+//! limit on what the taker will pay.
+
+use crate::{
+    arb::Scanner,
+    engine::{Engine, Hop, Path},
+    ids::TokenId,
+    pool::Pool,
+    router::Router,
+    world::StateView,
+};
+use alloy_primitives::U256;
+
+impl<P: Pool> Engine<'_, P> {
+    /// Simulates `plan` from the smallest input, up to `max_in`, that yields
+    /// at least `amt_out`; `None` if even `max_in` falls short. Outputs are
+    /// assumed to grow with the input, as they do on every AMM curve.
+    ///
+    /// # Panics
+    ///
+    /// Like [`Engine::simulate_chained`], on a malformed plan.
+    pub fn simulate_exact_out<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plan: &[Hop],
+        amt_out: U256,
+        max_in: U256,
+    ) -> Option<Path> {
+        if amt_out.is_zero() || max_in.is_zero() {
+            return None;
+        }
+        let out = |amt: U256| {
+            self.simulate_chained(world, plan, amt)
+                .steps
+                .last()
+                .map_or(U256::ZERO, |s| s.amt_out)
+        };
+
+        // Double up to a bracket, then bisect it: `lo` falls short, `hi` buys.
+        let (mut lo, mut hi) = (U256::ZERO, U256::from(1u64));
+        while out(hi) < amt_out {
+            if hi >= max_in {
+                return None;
+            }
+            lo = hi;
+            hi = hi.saturating_mul(U256::from(2u64)).min(max_in);
+        }
+        while hi - lo > U256::from(1u64) {
+            let mid = lo + (hi - lo) / U256::from(2u64);
+            if out(mid) >= amt_out {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+        Some(self.simulate_chained(world, plan, hi))
+    }
+}
+
+impl<P: Pool> Scanner<'_, P> {
+    /// The route buying `amt_out` of `to` for the least `from`, paying at
+    /// most `max_in`. Equal inputs prefer the larger output.
+    pub fn best_route_exact_out<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        from: TokenId,
+        to: TokenId,
+        amt_out: U256,
+        max_in: U256,
+    ) -> Option<Path> {
+        self.routes(world, from, to)
+            .iter()
+            .filter_map(|plan| self.engine.simulate_exact_out(world, plan, amt_out, max_in))
+            .min_by(|a, b| {
+                let key = |p: &Path| (p.steps[0].amt_in, p.steps[p.steps.len() - 1].amt_out);
+                let (a, b) = (key(a), key(b));
+                a.0.cmp(&b.0).then(b.1.cmp(&a.1))
+            })
+    }
+}
+
+impl<P: Pool> Router<P> {
+    /// Least input of `from` that buys `amount_out` of `to`, if some route
+    /// does so for at most `max_in`.
+    pub fn quote_exact_out(
+        &self,
+        from: TokenId,
+        to: TokenId,
+        amount_out: U256,
+        max_in: U256,
+    ) -> Option<U256> {
+        self.route_exact_out(from, to, amount_out, max_in)
+            .map(|p| p.steps[0].amt_in)
+    }
+
+    /// Route behind [`Router::quote_exact_out`].
+    pub fn route_exact_out(
+        &self,
+        from: TokenId,
+        to: TokenId,
+        amount_out: U256,
+        max_in: U256,
+    ) -> Option<Path> {
+        let engine = self.engine();
+        Scanner::new(&engine, self.graph())
+            .with_config(self.config)
+            .best_route_exact_out(self.world(), from, to, amount_out, max_in)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::AMMGraph;
+    use crate::ids::PoolId;
+    use crate::registry::Registry;
+    use crate::test_utils::{Cp, reserves};
+    use crate::world::World;
+    use std::collections::HashMap;
+
+    #[test]
+    fn buys_exact_output_for_the_least_input_within_the_limit() {
+        let mut graph = AMMGraph::new();
+        let mut pools = HashMap::new();
+        let mut world = World::default();
+        for (id, t0, t1, r) in [(1, 1, 2, 1_000_000), (2, 2, 3, 1_000_000), (3, 1, 3, 1_000)] {
+            pools.insert(PoolId(id), Cp::new(id, t0, t1));
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(t0), TokenId(t1));
+            world.set_pool_state(PoolId(id), reserves(r, r));
+        }
+        let router = Router::new(Registry::default(), graph, pools, world);
+        let (want, cap) = (U256::from(500u64), U256::from(1_000_000u64));
+
+        let path = router
+            .route_exact_out(TokenId(1), TokenId(3), want, cap)
+            .unwrap();
+        assert_eq!(path.steps.len(), 2, "the shallow direct pool costs more");
+        let paid = path.steps[0].amt_in;
+        assert!(path.steps[1].amt_out >= want);
+        let engine = router.engine();
+        let less = engine.simulate_chained(router.world(), &path.plan(), paid - U256::from(1u64));
+        assert!(less.steps[1].amt_out < want);
+        assert_eq!(
+            router.quote_exact_out(TokenId(1), TokenId(3), want, cap),
+            Some(paid)
+        );
+
+        assert_eq!(
+            router.quote_exact_out(TokenId(1), TokenId(3), want, paid - U256::from(1u64)),
+            None
+        );
+        assert_eq!(
+            router.quote_exact_out(TokenId(1), TokenId(3), U256::from(2_000_000u64), U256::MAX),
+            None,
+            "more than the pools hold"
+        );
+    }
+}
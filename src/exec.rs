@@ -0,0 +1,798 @@
+use crate::{
+    engine::{Hop, Path},
+    ids::{PoolId, SwapDirection, TokenId},
+    registry::{PoolKind, Registry},
+};
+use alloy_primitives::{Address, B256, Bytes, U256, address};
+use alloy_sol_types::{SolCall, SolValue, sol};
+use smallvec::smallvec;
+
+sol! {
+    struct ExactInputParams {
+        bytes path;
+        address recipient;
+        uint256 amountIn;
+        uint256 amountOutMinimum;
+    }
+
+    function exactInput(ExactInputParams params) external payable returns (uint256 amountOut);
+
+    struct ExactInputSingleParams {
+        address tokenIn;
+        address tokenOut;
+        uint24 fee;
+        address recipient;
+        uint256 amountIn;
+        uint256 amountOutMinimum;
+        uint160 sqrtPriceLimitX96;
+    }
+
+    struct ExactOutputParams {
+        bytes path;
+        address recipient;
+        uint256 amountOut;
+        uint256 amountInMaximum;
+    }
+
+    function exactInputSingle(ExactInputSingleParams params) external payable returns (uint256 amountOut);
+
+    function exactOutput(ExactOutputParams params) external payable returns (uint256 amountIn);
+
+    function swapExactTokensForTokens(
+        uint256 amountIn,
+        uint256 amountOutMin,
+        address[] path,
+        address to,
+        uint256 deadline
+    ) external returns (uint256[] amounts);
+
+    function swapTokensForExactTokens(
+        uint256 amountOut,
+        uint256 amountInMax,
+        address[] path,
+        address to,
+        uint256 deadline
+    ) external returns (uint256[] amounts);
+
+    function execute(bytes commands, bytes[] inputs, uint256 deadline) external payable;
+
+    struct ExecutorHop {
+        address pool;
+        uint8 kind;
+        address tokenIn;
+        address tokenOut;
+    }
+
+    function executePath(
+        ExecutorHop[] hops,
+        uint256 amountIn,
+        uint256 amountOutMin,
+        address recipient
+    ) external returns (uint256 amountOut);
+}
+
+pub const V3_SWAP_EXACT_IN: u8 = 0x00;
+pub const V3_SWAP_EXACT_OUT: u8 = 0x01;
+pub const V2_SWAP_EXACT_IN: u8 = 0x08;
+pub const V2_SWAP_EXACT_OUT: u8 = 0x09;
+const UR_COMMAND_MASK: u8 = 0x3f;
+pub const UR_ADDRESS_THIS: Address = address!("0000000000000000000000000000000000000002");
+pub const UR_CONTRACT_BALANCE: U256 = U256::from_limbs([0, 0, 0, 1 << 63]);
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ExecError {
+    #[error("path has no steps")]
+    EmptyPath,
+    #[error("no registry entry for token {0}")]
+    MissingToken(TokenId),
+    #[error("no registry entry for pool {0}")]
+    MissingPool(PoolId),
+    #[error("pool {0} of kind {1:?} unsupported here")]
+    UnsupportedKind(PoolId, PoolKind),
+    #[error("unknown selector 0x{}", alloy_primitives::hex::encode(.0))]
+    UnknownSelector([u8; 4]),
+    #[error("no registry entry for token at {0}")]
+    UnknownTokenAddress(Address),
+    #[error("no {0:?} pool for {1}/{2} in registry")]
+    UnknownPool(PoolKind, Address, Address),
+    #[error("malformed calldata: {0}")]
+    Malformed(String),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ExecParams {
+    pub recipient: Address,
+    pub amount_out_min: U256,
+    pub deadline: U256,
+}
+
+fn token_addr(reg: &Registry, t: TokenId) -> Result<Address, ExecError> {
+    reg.token(t)
+        .map(|m| m.address)
+        .ok_or(ExecError::MissingToken(t))
+}
+
+fn pool_kind(reg: &Registry, p: PoolId) -> Result<PoolKind, ExecError> {
+    reg.pool(p).map(|m| m.kind).ok_or(ExecError::MissingPool(p))
+}
+
+pub fn v3_path(path: &Path, reg: &Registry) -> Result<Bytes, ExecError> {
+    let first = path.steps.first().ok_or(ExecError::EmptyPath)?;
+    let mut out = Vec::with_capacity(20 + path.steps.len() * 23);
+    out.extend_from_slice(token_addr(reg, first.from)?.as_slice());
+    for step in &path.steps {
+        let meta = reg
+            .pool(step.pool)
+            .ok_or(ExecError::MissingPool(step.pool))?;
+        if meta.kind != PoolKind::UniV3 {
+            return Err(ExecError::UnsupportedKind(step.pool, meta.kind));
+        }
+        out.extend_from_slice(&meta.fee.to_be_bytes()[1..]);
+        out.extend_from_slice(token_addr(reg, step.to)?.as_slice());
+    }
+    Ok(out.into())
+}
+
+pub fn encode_exact_input(
+    path: &Path,
+    reg: &Registry,
+    params: ExecParams,
+) -> Result<Bytes, ExecError> {
+    let call = exactInputCall {
+        params: ExactInputParams {
+            path: v3_path(path, reg)?,
+            recipient: params.recipient,
+            amountIn: path.steps[0].amt_in,
+            amountOutMinimum: params.amount_out_min,
+        },
+    };
+    Ok(call.abi_encode().into())
+}
+
+pub fn encode_universal_router(
+    path: &Path,
+    reg: &Registry,
+    params: ExecParams,
+) -> Result<Bytes, ExecError> {
+    if path.steps.is_empty() {
+        return Err(ExecError::EmptyPath);
+    }
+
+    let mut segments: Vec<(PoolKind, Path)> = Vec::new();
+    for step in &path.steps {
+        let kind = pool_kind(reg, step.pool)?;
+        match segments.last_mut() {
+            Some((k, seg)) if *k == kind => seg.steps.push(step.clone()),
+            _ => segments.push((
+                kind,
+                Path {
+                    steps: smallvec![step.clone()],
+                },
+            )),
+        }
+    }
+
+    let last = segments.len() - 1;
+    let mut commands = Vec::with_capacity(segments.len());
+    let mut inputs = Vec::with_capacity(segments.len());
+    for (i, (kind, seg)) in segments.iter().enumerate() {
+        let recipient = if i == last {
+            params.recipient
+        } else {
+            UR_ADDRESS_THIS
+        };
+        let (amount_in, payer_is_user) = if i == 0 {
+            (seg.steps[0].amt_in, true)
+        } else {
+            (UR_CONTRACT_BALANCE, false)
+        };
+        let min_out = if i == last {
+            params.amount_out_min
+        } else {
+            U256::ZERO
+        };
+
+        match kind {
+            PoolKind::UniV3 => {
+                commands.push(V3_SWAP_EXACT_IN);
+                inputs.push(Bytes::from(
+                    (
+                        recipient,
+                        amount_in,
+                        min_out,
+                        v3_path(seg, reg)?,
+                        payer_is_user,
+                    )
+                        .abi_encode_params(),
+                ));
+            }
+            PoolKind::UniV2 => {
+                let mut tokens = vec![token_addr(reg, seg.steps[0].from)?];
+                for step in &seg.steps {
+                    tokens.push(token_addr(reg, step.to)?);
+                }
+                commands.push(V2_SWAP_EXACT_IN);
+                inputs.push(Bytes::from(
+                    (recipient, amount_in, min_out, tokens, payer_is_user).abi_encode_params(),
+                ));
+            }
+        }
+    }
+
+    let call = executeCall {
+        commands: commands.into(),
+        inputs,
+        deadline: params.deadline,
+    };
+    Ok(call.abi_encode().into())
+}
+
+pub fn encode_executor(
+    path: &Path,
+    reg: &Registry,
+    params: ExecParams,
+) -> Result<Bytes, ExecError> {
+    let first = path.steps.first().ok_or(ExecError::EmptyPath)?;
+    let hops = path
+        .steps
+        .iter()
+        .map(|step| {
+            let meta = reg
+                .pool(step.pool)
+                .ok_or(ExecError::MissingPool(step.pool))?;
+            Ok(ExecutorHop {
+                pool: meta.address,
+                kind: meta.kind as u8,
+                tokenIn: token_addr(reg, step.from)?,
+                tokenOut: token_addr(reg, step.to)?,
+            })
+        })
+        .collect::<Result<Vec<_>, ExecError>>()?;
+
+    let call = executePathCall {
+        hops,
+        amountIn: first.amt_in,
+        amountOutMin: params.amount_out_min,
+        recipient: params.recipient,
+    };
+    Ok(call.abi_encode().into())
+}
+
+/// `UniswapV2Pair` slots a swap reads: `token0`, `token1`, the packed
+/// reserves and the reentrancy lock.
+pub const V2_SWAP_SLOTS: [u64; 4] = [6, 7, 8, 12];
+/// `UniswapV3Pool` slots every swap reads: `slot0` and `liquidity`. The fee
+/// growth slot depends on the direction and ticks on the distance moved.
+pub const V3_SWAP_SLOTS: [u64; 2] = [0, 4];
+const V3_FEE_GROWTH_GLOBAL0_SLOT: u64 = 1;
+const V3_FEE_GROWTH_GLOBAL1_SLOT: u64 = 2;
+
+/// EIP-2930 intrinsic gas per listed address and per listed storage key.
+pub const ACCESS_LIST_ADDRESS_GAS: u64 = 2_400;
+pub const ACCESS_LIST_KEY_GAS: u64 = 1_900;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccessListItem {
+    pub address: Address,
+    pub storage_keys: Vec<B256>,
+}
+
+/// An EIP-2930 access list, addresses in the order the path first touches
+/// them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccessList(pub Vec<AccessListItem>);
+
+impl AccessList {
+    fn add(&mut self, address: Address, keys: impl IntoIterator<Item = B256>) {
+        let at = match self.0.iter().position(|i| i.address == address) {
+            Some(at) => at,
+            None => {
+                self.0.push(AccessListItem {
+                    address,
+                    storage_keys: Vec::new(),
+                });
+                self.0.len() - 1
+            }
+        };
+        let item = &mut self.0[at];
+        for key in keys {
+            if !item.storage_keys.contains(&key) {
+                item.storage_keys.push(key);
+            }
+        }
+    }
+
+    /// Intrinsic gas the list itself costs; worth it when it prewarms more
+    /// than that in cold-access surcharges.
+    pub fn cost(&self) -> u64 {
+        self.0
+            .iter()
+            .map(|i| ACCESS_LIST_ADDRESS_GAS + ACCESS_LIST_KEY_GAS * i.storage_keys.len() as u64)
+            .sum()
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl From<AccessList> for alloy_rpc_types_eth::AccessList {
+    fn from(list: AccessList) -> Self {
+        Self(
+            list.0
+                .into_iter()
+                .map(|i| alloy_rpc_types_eth::AccessListItem {
+                    address: i.address,
+                    storage_keys: i.storage_keys,
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Access list for executing `path`: every pool with the slots its swap is
+/// known to read, and every token contract. Token balance slots depend on
+/// each token's layout and are left to the node.
+pub fn access_list(path: &Path, reg: &Registry) -> Result<AccessList, ExecError> {
+    if path.steps.is_empty() {
+        return Err(ExecError::EmptyPath);
+    }
+    let slot = |s: u64| B256::from(U256::from(s));
+    let mut list = AccessList::default();
+    for step in &path.steps {
+        let meta = reg
+            .pool(step.pool)
+            .ok_or(ExecError::MissingPool(step.pool))?;
+        list.add(token_addr(reg, step.from)?, []);
+        let keys: Vec<B256> = match meta.kind {
+            PoolKind::UniV2 => V2_SWAP_SLOTS.iter().map(|&s| slot(s)).collect(),
+            PoolKind::UniV3 => {
+                let growth = if step.from == meta.token0 {
+                    V3_FEE_GROWTH_GLOBAL0_SLOT
+                } else {
+                    V3_FEE_GROWTH_GLOBAL1_SLOT
+                };
+                V3_SWAP_SLOTS
+                    .iter()
+                    .chain([&growth])
+                    .map(|&s| slot(s))
+                    .collect()
+            }
+        };
+        list.add(meta.address, keys);
+        list.add(token_addr(reg, step.to)?, []);
+    }
+    Ok(list)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwapAmounts {
+    ExactIn { amount_in: U256, min_out: U256 },
+    ExactOut { amount_out: U256, max_in: U256 },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedSwap {
+    pub plan: Vec<Hop>,
+    pub amounts: SwapAmounts,
+    pub recipient: Address,
+}
+
+impl DecodedSwap {
+    pub fn simulation_input(&self) -> U256 {
+        match self.amounts {
+            SwapAmounts::ExactIn { amount_in, .. } => amount_in,
+            SwapAmounts::ExactOut { max_in, .. } => max_in,
+        }
+    }
+}
+
+fn malformed(e: alloy_sol_types::Error) -> ExecError {
+    ExecError::Malformed(e.to_string())
+}
+
+fn token_id(reg: &Registry, a: Address) -> Result<TokenId, ExecError> {
+    reg.token_by_addr
+        .get(&a)
+        .copied()
+        .ok_or(ExecError::UnknownTokenAddress(a))
+}
+
+fn hop_for(
+    reg: &Registry,
+    kind: PoolKind,
+    from: Address,
+    to: Address,
+    fee: Option<u32>,
+) -> Result<Hop, ExecError> {
+    let (f, t) = (token_id(reg, from)?, token_id(reg, to)?);
+    let pid = reg
+        .find_pool(kind, f, t, fee)
+        .ok_or(ExecError::UnknownPool(kind, from, to))?;
+    let dir = SwapDirection::new(f, t).ok_or(ExecError::Malformed("self-swap hop".into()))?;
+    Ok(Hop::new(pid, dir))
+}
+
+fn v2_plan(reg: &Registry, tokens: &[Address]) -> Result<Vec<Hop>, ExecError> {
+    if tokens.len() < 2 {
+        return Err(ExecError::Malformed("v2 path needs two tokens".into()));
+    }
+    tokens
+        .windows(2)
+        .map(|w| hop_for(reg, PoolKind::UniV2, w[0], w[1], None))
+        .collect()
+}
+
+fn v3_plan(reg: &Registry, path: &[u8], reversed: bool) -> Result<Vec<Hop>, ExecError> {
+    if path.len() < 43 || !(path.len() - 20).is_multiple_of(23) {
+        return Err(ExecError::Malformed("bad v3 path length".into()));
+    }
+    let mut plan = Vec::with_capacity((path.len() - 20) / 23);
+    let mut at = Address::from_slice(&path[..20]);
+    for chunk in path[20..].chunks(23) {
+        let fee = u32::from_be_bytes([0, chunk[0], chunk[1], chunk[2]]);
+        let next = Address::from_slice(&chunk[3..]);
+        let (from, to) = if reversed { (next, at) } else { (at, next) };
+        plan.push(hop_for(reg, PoolKind::UniV3, from, to, Some(fee))?);
+        at = next;
+    }
+    if reversed {
+        plan.reverse();
+    }
+    Ok(plan)
+}
+
+pub fn decode_swaps(data: &[u8], reg: &Registry) -> Result<Vec<DecodedSwap>, ExecError> {
+    let sel: [u8; 4] = data
+        .get(..4)
+        .ok_or(ExecError::Malformed(
+            "calldata shorter than selector".into(),
+        ))?
+        .try_into()
+        .unwrap();
+
+    let swap = match sel {
+        swapExactTokensForTokensCall::SELECTOR => {
+            let c = swapExactTokensForTokensCall::abi_decode(data).map_err(malformed)?;
+            DecodedSwap {
+                plan: v2_plan(reg, &c.path)?,
+                amounts: SwapAmounts::ExactIn {
+                    amount_in: c.amountIn,
+                    min_out: c.amountOutMin,
+                },
+                recipient: c.to,
+            }
+        }
+        swapTokensForExactTokensCall::SELECTOR => {
+            let c = swapTokensForExactTokensCall::abi_decode(data).map_err(malformed)?;
+            DecodedSwap {
+                plan: v2_plan(reg, &c.path)?,
+                amounts: SwapAmounts::ExactOut {
+                    amount_out: c.amountOut,
+                    max_in: c.amountInMax,
+                },
+                recipient: c.to,
+            }
+        }
+        exactInputCall::SELECTOR => {
+            let p = exactInputCall::abi_decode(data).map_err(malformed)?.params;
+            DecodedSwap {
+                plan: v3_plan(reg, &p.path, false)?,
+                amounts: SwapAmounts::ExactIn {
+                    amount_in: p.amountIn,
+                    min_out: p.amountOutMinimum,
+                },
+                recipient: p.recipient,
+            }
+        }
+        exactInputSingleCall::SELECTOR => {
+            let p = exactInputSingleCall::abi_decode(data)
+                .map_err(malformed)?
+                .params;
+            let fee = Some(p.fee.to::<u32>());
+            DecodedSwap {
+                plan: vec![hop_for(reg, PoolKind::UniV3, p.tokenIn, p.tokenOut, fee)?],
+                amounts: SwapAmounts::ExactIn {
+                    amount_in: p.amountIn,
+                    min_out: p.amountOutMinimum,
+                },
+                recipient: p.recipient,
+            }
+        }
+        exactOutputCall::SELECTOR => {
+            let p = exactOutputCall::abi_decode(data).map_err(malformed)?.params;
+            DecodedSwap {
+                plan: v3_plan(reg, &p.path, true)?,
+                amounts: SwapAmounts::ExactOut {
+                    amount_out: p.amountOut,
+                    max_in: p.amountInMaximum,
+                },
+                recipient: p.recipient,
+            }
+        }
+        executeCall::SELECTOR => {
+            let c = executeCall::abi_decode(data).map_err(malformed)?;
+            return decode_universal_router(&c.commands, &c.inputs, reg);
+        }
+        _ => return Err(ExecError::UnknownSelector(sel)),
+    };
+    Ok(vec![swap])
+}
+
+fn decode_universal_router(
+    commands: &[u8],
+    inputs: &[Bytes],
+    reg: &Registry,
+) -> Result<Vec<DecodedSwap>, ExecError> {
+    type V3Input = (Address, U256, U256, Bytes, bool);
+    type V2Input = (Address, U256, U256, Vec<Address>, bool);
+
+    let mut swaps: Vec<DecodedSwap> = Vec::new();
+    for (&cmd, input) in commands.iter().zip(inputs) {
+        let (recipient, a, b, plan, exact_in) = match cmd & UR_COMMAND_MASK {
+            V3_SWAP_EXACT_IN | V3_SWAP_EXACT_OUT => {
+                let (r, a, b, path, _) = V3Input::abi_decode_params(input).map_err(malformed)?;
+                let exact_in = cmd & UR_COMMAND_MASK == V3_SWAP_EXACT_IN;
+                (r, a, b, v3_plan(reg, &path, !exact_in)?, exact_in)
+            }
+            V2_SWAP_EXACT_IN | V2_SWAP_EXACT_OUT => {
+                let (r, a, b, path, _) = V2Input::abi_decode_params(input).map_err(malformed)?;
+                (
+                    r,
+                    a,
+                    b,
+                    v2_plan(reg, &path)?,
+                    cmd & UR_COMMAND_MASK == V2_SWAP_EXACT_IN,
+                )
+            }
+            _ => continue,
+        };
+
+        let chained = exact_in
+            && a == UR_CONTRACT_BALANCE
+            && swaps.last().is_some_and(|prev| {
+                prev.recipient == UR_ADDRESS_THIS
+                    && prev.plan.last().map(|h| h.dir.to) == Some(plan[0].dir.from)
+            });
+        if chained {
+            let prev = swaps.last_mut().unwrap();
+            prev.plan.extend(plan);
+            prev.recipient = recipient;
+            if let SwapAmounts::ExactIn { min_out, .. } = &mut prev.amounts {
+                *min_out = b;
+            }
+            continue;
+        }
+
+        let amounts = if exact_in {
+            SwapAmounts::ExactIn {
+                amount_in: a,
+                min_out: b,
+            }
+        } else {
+            SwapAmounts::ExactOut {
+                amount_out: a,
+                max_in: b,
+            }
+        };
+        swaps.push(DecodedSwap {
+            plan,
+            amounts,
+            recipient,
+        });
+    }
+    Ok(swaps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        engine::Step,
+        registry::{PoolMeta, TokenMeta},
+    };
+
+    fn addr(b: u8) -> Address {
+        Address::repeat_byte(b)
+    }
+
+    fn registry() -> Registry {
+        let mut reg = Registry::default();
+        for t in 1..=3u8 {
+            reg.upsert_token(
+                TokenId(t as u32),
+                TokenMeta {
+                    address: addr(t),
+                    symbol: format!("T{t}"),
+                    decimals: 18,
+                },
+            );
+        }
+        for (p, kind, t0, t1, fee) in [
+            (10u64, PoolKind::UniV3, 1, 2, 500),
+            (11, PoolKind::UniV3, 2, 3, 3000),
+            (20, PoolKind::UniV2, 2, 3, 3000),
+        ] {
+            reg.upsert_pool(
+                PoolId(p),
+                PoolMeta {
+                    address: addr(p as u8),
+                    kind,
+                    token0: TokenId(t0),
+                    token1: TokenId(t1),
+                    fee,
+                },
+            );
+        }
+        reg
+    }
+
+    fn path(hops: &[(u64, u32, u32)]) -> Path {
+        Path {
+            steps: hops
+                .iter()
+                .map(|&(p, from, to)| Step {
+                    pool: PoolId(p),
+                    from: TokenId(from),
+                    to: TokenId(to),
+                    amt_in: U256::from(1_000u64),
+                    amt_out: U256::from(900u64),
+                    failure: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn params() -> ExecParams {
+        ExecParams {
+            recipient: addr(0xaa),
+            amount_out_min: U256::from(800u64),
+            deadline: U256::from(1_700_000_000u64),
+        }
+    }
+
+    #[test]
+    fn v3_path_packs_tokens_and_fees() {
+        let reg = registry();
+        let bytes = v3_path(&path(&[(10, 1, 2), (11, 2, 3)]), &reg).unwrap();
+        assert_eq!(bytes.len(), 20 + 23 * 2);
+        assert_eq!(&bytes[..20], addr(1).as_slice());
+        assert_eq!(&bytes[20..23], &[0x00, 0x01, 0xf4]);
+        assert_eq!(&bytes[23..43], addr(2).as_slice());
+        assert_eq!(&bytes[43..46], &[0x00, 0x0b, 0xb8]);
+        assert_eq!(&bytes[46..], addr(3).as_slice());
+
+        assert_eq!(
+            v3_path(&path(&[(20, 2, 3)]), &reg),
+            Err(ExecError::UnsupportedKind(PoolId(20), PoolKind::UniV2))
+        );
+        assert_eq!(
+            v3_path(&path(&[(99, 2, 3)]), &reg),
+            Err(ExecError::MissingPool(PoolId(99)))
+        );
+    }
+
+    #[test]
+    fn universal_router_splits_mixed_venues_into_commands() {
+        let reg = registry();
+        let data =
+            encode_universal_router(&path(&[(10, 1, 2), (20, 2, 3)]), &reg, params()).unwrap();
+        assert_eq!(&data[..4], executeCall::SELECTOR.as_slice());
+
+        let call = executeCall::abi_decode(&data).unwrap();
+        assert_eq!(
+            call.commands.as_ref(),
+            &[V3_SWAP_EXACT_IN, V2_SWAP_EXACT_IN]
+        );
+        assert_eq!(call.inputs.len(), 2);
+        assert_eq!(call.deadline, params().deadline);
+
+        type V2In = (Address, U256, U256, Vec<Address>, bool);
+        let (recipient, amount_in, min_out, tokens, payer_is_user) =
+            V2In::abi_decode_params(&call.inputs[1]).unwrap();
+        assert_eq!(recipient, params().recipient);
+        assert_eq!(amount_in, UR_CONTRACT_BALANCE);
+        assert_eq!(min_out, params().amount_out_min);
+        assert_eq!(tokens, vec![addr(2), addr(3)]);
+        assert!(!payer_is_user);
+    }
+
+    #[test]
+    fn decodes_encoded_routes_back_into_plans() {
+        let reg = registry();
+        let p = path(&[(10, 1, 2), (11, 2, 3)]);
+        let expected: Vec<Hop> = p
+            .steps
+            .iter()
+            .map(|s| Hop::new(s.pool, s.direction()))
+            .collect();
+
+        let v3 = decode_swaps(&encode_exact_input(&p, &reg, params()).unwrap(), &reg).unwrap();
+        assert_eq!(v3.len(), 1);
+        assert_eq!(v3[0].plan, expected);
+        assert_eq!(
+            v3[0].amounts,
+            SwapAmounts::ExactIn {
+                amount_in: U256::from(1_000u64),
+                min_out: params().amount_out_min
+            }
+        );
+
+        let mixed = path(&[(10, 1, 2), (20, 2, 3)]);
+        let ur = encode_universal_router(&mixed, &reg, params()).unwrap();
+        let decoded = decode_swaps(&ur, &reg).unwrap();
+        assert_eq!(decoded.len(), 1, "chained commands merge into one swap");
+        assert_eq!(decoded[0].plan.len(), 2);
+        assert_eq!(decoded[0].plan[1].pool, PoolId(20));
+        assert_eq!(decoded[0].recipient, params().recipient);
+        assert_eq!(decoded[0].simulation_input(), U256::from(1_000u64));
+    }
+
+    #[test]
+    fn decodes_v2_and_reversed_v3_exact_out() {
+        let reg = registry();
+        let v2 = swapTokensForExactTokensCall {
+            amountOut: U256::from(5u64),
+            amountInMax: U256::from(7u64),
+            path: vec![addr(3), addr(2)],
+            to: addr(0xaa),
+            deadline: U256::ZERO,
+        }
+        .abi_encode();
+        let d = decode_swaps(&v2, &reg).unwrap();
+        assert_eq!(d[0].plan[0].pool, PoolId(20));
+        assert_eq!(d[0].plan[0].dir.from, TokenId(3));
+        assert_eq!(d[0].simulation_input(), U256::from(7u64));
+
+        let out_path = v3_path(&path(&[(11, 3, 2), (10, 2, 1)]), &reg).unwrap();
+        let v3 = exactOutputCall {
+            params: ExactOutputParams {
+                path: out_path,
+                recipient: addr(0xaa),
+                amountOut: U256::from(1u64),
+                amountInMaximum: U256::from(2u64),
+            },
+        }
+        .abi_encode();
+        let d = decode_swaps(&v3, &reg).unwrap();
+        assert_eq!(
+            d[0].plan
+                .iter()
+                .map(|h| (h.pool, h.dir.from))
+                .collect::<Vec<_>>(),
+            vec![(PoolId(10), TokenId(1)), (PoolId(11), TokenId(2))]
+        );
+
+        assert_eq!(
+            decode_swaps(&[0xde, 0xad, 0xbe, 0xef], &reg),
+            Err(ExecError::UnknownSelector([0xde, 0xad, 0xbe, 0xef]))
+        );
+    }
+
+    #[test]
+    fn executor_call_lists_every_hop() {
+        let reg = registry();
+        let data = encode_executor(&path(&[(10, 1, 2), (20, 2, 3)]), &reg, params()).unwrap();
+        let call = executePathCall::abi_decode(&data).unwrap();
+        assert_eq!(call.hops.len(), 2);
+        assert_eq!(call.hops[1].pool, addr(20));
+        assert_eq!(call.hops[1].kind, PoolKind::UniV2 as u8);
+        assert_eq!(call.amountIn, U256::from(1_000u64));
+    }
+
+    #[test]
+    fn access_list_warms_pools_slots_and_tokens_once() {
+        let reg = registry();
+        let list = access_list(&path(&[(10, 1, 2), (20, 2, 3)]), &reg).unwrap();
+        let addrs: Vec<_> = list.0.iter().map(|i| i.address).collect();
+        assert_eq!(addrs, [addr(1), addr(10), addr(2), addr(20), addr(3)]);
+
+        let slot = |s: u64| B256::from(U256::from(s));
+        assert_eq!(list.0[1].storage_keys, [slot(0), slot(4), slot(1)]);
+        assert_eq!(list.0[3].storage_keys, V2_SWAP_SLOTS.map(slot));
+        assert!(list.0[0].storage_keys.is_empty());
+        assert_eq!(list.cost(), 5 * 2_400 + 7 * 1_900);
+
+        assert_eq!(
+            access_list(&path(&[(99, 1, 2)]), &reg),
+            Err(ExecError::MissingPool(PoolId(99)))
+        );
+    }
+}
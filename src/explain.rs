@@ -0,0 +1,174 @@
+//! Human-readable routes for logs, alerts and UIs:
+//! `swap 1.5 WETH → 2,431 USDC via UniV3 0.05% (0x88e6…5640)`.
+
+use crate::{
+    engine::{HopFailure, Path},
+    export::format_units,
+    ids::{PoolId, TokenId},
+    registry::{PoolKind, Registry},
+};
+use alloy_primitives::{Address, U256};
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExplainedHop {
+    pub pool: PoolId,
+    /// `None` for pools the registry does not know.
+    pub kind: Option<PoolKind>,
+    /// In hundredths of a basis point, as in [`PoolMeta`](crate::registry::PoolMeta).
+    pub fee: Option<u32>,
+    pub address: Option<Address>,
+    pub token_out: String,
+    /// Decimal amount with grouped thousands; raw for unknown tokens.
+    pub amount_out: String,
+    pub failure: Option<HopFailure>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExplainedPath {
+    pub token_in: String,
+    pub amount_in: String,
+    pub hops: Vec<ExplainedHop>,
+}
+
+impl Path {
+    pub fn explained(&self, reg: &Registry) -> ExplainedPath {
+        let first = self.steps.first();
+        ExplainedPath {
+            token_in: first.map(|s| symbol(reg, s.from)).unwrap_or_default(),
+            amount_in: first
+                .map(|s| amount(reg, s.from, s.amt_in))
+                .unwrap_or_default(),
+            hops: self
+                .steps
+                .iter()
+                .map(|s| {
+                    let meta = reg.pool(s.pool);
+                    ExplainedHop {
+                        pool: s.pool,
+                        kind: meta.map(|m| m.kind),
+                        fee: meta.map(|m| m.fee),
+                        address: meta.map(|m| m.address),
+                        token_out: symbol(reg, s.to),
+                        amount_out: amount(reg, s.to, s.amt_out),
+                        failure: s.failure,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// One line naming every hop's output, venue, fee tier and pool address.
+    pub fn explain(&self, reg: &Registry) -> String {
+        self.explained(reg).to_string()
+    }
+}
+
+impl fmt::Display for ExplainedPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.hops.is_empty() {
+            return f.write_str("empty path");
+        }
+        write!(f, "swap {} {}", self.amount_in, self.token_in)?;
+        for h in &self.hops {
+            write!(f, " → {} {}", h.amount_out, h.token_out)?;
+            match (h.kind, h.fee, h.address) {
+                (Some(kind), Some(fee), Some(addr)) => write!(
+                    f,
+                    " via {kind:?} {}% ({})",
+                    format_units(U256::from(fee), 4),
+                    short_address(addr)
+                )?,
+                _ => write!(f, " via pool {}", h.pool)?,
+            }
+            if let Some(failure) = h.failure {
+                write!(f, " [{failure:?}]")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn symbol(reg: &Registry, t: TokenId) -> String {
+    reg.token(t)
+        .map_or_else(|| t.to_string(), |m| m.symbol.clone())
+}
+
+fn amount(reg: &Registry, t: TokenId, amount: U256) -> String {
+    let Some(meta) = reg.token(t) else {
+        return amount.to_string();
+    };
+    let s = format_units(amount, meta.decimals);
+    let (int, frac) = s.split_at(s.find('.').unwrap_or(s.len()));
+    let mut grouped = String::with_capacity(s.len() + int.len() / 3);
+    for (i, c) in int.chars().enumerate() {
+        if i > 0 && (int.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped + frac
+}
+
+fn short_address(a: Address) -> String {
+    let hex = a.to_string();
+    format!("{}…{}", &hex[..6], &hex[hex.len() - 4..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Step;
+    use crate::registry::{PoolMeta, TokenMeta};
+    use smallvec::smallvec;
+
+    #[test]
+    fn renders_symbols_decimals_and_venues() {
+        let mut reg = Registry::default();
+        for (t, symbol, decimals) in [(1, "WETH", 18), (2, "USDC", 6)] {
+            reg.upsert_token(
+                TokenId(t),
+                TokenMeta {
+                    address: Address::repeat_byte(t as u8),
+                    symbol: symbol.into(),
+                    decimals,
+                },
+            );
+        }
+        reg.upsert_pool(
+            PoolId(7),
+            PoolMeta {
+                address: Address::repeat_byte(0xab),
+                kind: PoolKind::UniV3,
+                token0: TokenId(1),
+                token1: TokenId(2),
+                fee: 500,
+            },
+        );
+        let step = |pool, from, to, amt_in: u128, amt_out: u128| Step {
+            pool: PoolId(pool),
+            from: TokenId(from),
+            to: TokenId(to),
+            amt_in: U256::from(amt_in),
+            amt_out: U256::from(amt_out),
+            failure: None,
+        };
+        let mut path = Path {
+            steps: smallvec![
+                step(7, 1, 2, 1_500_000_000_000_000_000, 2_431_250_000),
+                step(8, 2, 3, 2_431_250_000, 0),
+            ],
+        };
+        path.steps[1].failure = Some(HopFailure::ZeroLiquidity);
+
+        assert_eq!(
+            path.explain(&reg),
+            "swap 1.5 WETH → 2,431.25 USDC via UniV3 0.05% (0xABaB…ABaB) \
+             → 0 3 via pool 8 [ZeroLiquidity]"
+        );
+        let explained = path.explained(&reg);
+        assert_eq!(explained.hops[0].fee, Some(500));
+        assert_eq!(explained.hops[1].kind, None);
+        assert_eq!(Path { steps: smallvec![] }.explain(&reg), "empty path");
+    }
+}
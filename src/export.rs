@@ -0,0 +1,376 @@
+//! CSV (and, with the `parquet` feature, Parquet) dumps of simulated paths
+//! and opportunity scans, with registry symbols and decimal amounts, for
+//! loading into pandas or duckdb.
+
+use crate::{arb::ArbOpportunity, engine::Path, ids::TokenId, registry::Registry};
+use alloy_primitives::U256;
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+/// `amount` as a decimal string with `decimals` fractional digits, without
+/// trailing zeros.
+pub fn format_units(amount: U256, decimals: u8) -> String {
+    let digits = amount.to_string();
+    let decimals = decimals as usize;
+    if decimals == 0 {
+        return digits;
+    }
+    let padded = format!("{digits:0>width$}", width = decimals + 1);
+    let (int, frac) = padded.split_at(padded.len() - decimals);
+    let frac = frac.trim_end_matches('0');
+    if frac.is_empty() {
+        int.to_string()
+    } else {
+        format!("{int}.{frac}")
+    }
+}
+
+pub(crate) fn field(s: &str) -> Cow<'_, str> {
+    if s.contains([',', '"', '\n']) {
+        Cow::Owned(format!("\"{}\"", s.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+struct Tokens<'a>(&'a Registry);
+
+impl Tokens<'_> {
+    fn symbol(&self, t: TokenId) -> String {
+        self.0
+            .token(t)
+            .map_or_else(|| t.to_string(), |m| m.symbol.clone())
+    }
+
+    /// Raw amount for tokens the registry does not know.
+    fn amount(&self, t: TokenId, amount: U256) -> String {
+        match self.0.token(t) {
+            Some(m) => format_units(amount, m.decimals),
+            None => amount.to_string(),
+        }
+    }
+}
+
+/// One row per step: `path,hop,pool,pool_address,token_in,amount_in,token_out,amount_out`.
+pub fn write_paths_csv<'a, W: Write>(
+    mut out: W,
+    reg: &Registry,
+    paths: impl IntoIterator<Item = &'a Path>,
+) -> io::Result<()> {
+    let tokens = Tokens(reg);
+    writeln!(
+        out,
+        "path,hop,pool,pool_address,token_in,amount_in,token_out,amount_out"
+    )?;
+    for (i, path) in paths.into_iter().enumerate() {
+        for (j, s) in path.steps.iter().enumerate() {
+            writeln!(
+                out,
+                "{i},{j},{},{},{},{},{},{}",
+                s.pool,
+                reg.pool(s.pool)
+                    .map(|m| m.address.to_string())
+                    .unwrap_or_default(),
+                field(&tokens.symbol(s.from)),
+                tokens.amount(s.from, s.amt_in),
+                field(&tokens.symbol(s.to)),
+                tokens.amount(s.to, s.amt_out),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// One row per opportunity, amounts in the base token:
+/// `rank,base,hops,route,optimal_in,gross,gas,net`.
+pub fn write_opportunities_csv<W: Write>(
+    mut out: W,
+    reg: &Registry,
+    opps: &[ArbOpportunity],
+) -> io::Result<()> {
+    let tokens = Tokens(reg);
+    writeln!(out, "rank,base,hops,route,optimal_in,gross,gas,net")?;
+    for (rank, o) in opps.iter().enumerate() {
+        let base = o.base();
+        let route = std::iter::once(base)
+            .chain(o.plan.iter().map(|h| h.dir.to))
+            .map(|t| tokens.symbol(t))
+            .collect::<Vec<_>>()
+            .join(">");
+        writeln!(
+            out,
+            "{rank},{},{},{},{},{},{},{}",
+            field(&tokens.symbol(base)),
+            o.plan.len(),
+            field(&route),
+            tokens.amount(base, o.optimal_in),
+            tokens.amount(base, o.gross),
+            tokens.amount(base, o.gas),
+            tokens.amount(base, o.net),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "parquet")]
+mod parquet_out {
+    use super::*;
+    use parquet::{
+        data_type::{ByteArray, ByteArrayType, Int64Type},
+        file::{properties::WriterProperties, writer::SerializedFileWriter},
+        schema::parser::parse_message_type,
+    };
+    use std::sync::Arc;
+
+    pub(super) enum Column {
+        Int(Vec<i64>),
+        Text(Vec<String>),
+    }
+
+    /// Writes `columns` as one row group; `schema` lists them in order.
+    pub(super) fn write<W: Write + Send>(
+        out: W,
+        schema: &str,
+        columns: Vec<Column>,
+    ) -> io::Result<()> {
+        let schema = Arc::new(parse_message_type(schema)?);
+        let props = Arc::new(WriterProperties::builder().build());
+        let mut writer = SerializedFileWriter::new(out, schema, props)?;
+        let mut group = writer.next_row_group()?;
+        for column in columns {
+            let mut col = group.next_column()?.expect("a schema field per column");
+            match column {
+                Column::Int(v) => {
+                    col.typed::<Int64Type>().write_batch(&v, None, None)?;
+                }
+                Column::Text(v) => {
+                    let v: Vec<ByteArray> = v.into_iter().map(|s| s.into_bytes().into()).collect();
+                    col.typed::<ByteArrayType>().write_batch(&v, None, None)?;
+                }
+            }
+            col.close()?;
+        }
+        group.close()?;
+        writer.close()?;
+        Ok(())
+    }
+}
+
+/// [`write_paths_csv`]'s columns as a Parquet file, amounts as decimal
+/// strings so wide integers survive.
+#[cfg(feature = "parquet")]
+pub fn write_paths_parquet<'a, W: Write + Send>(
+    out: W,
+    reg: &Registry,
+    paths: impl IntoIterator<Item = &'a Path>,
+) -> io::Result<()> {
+    use parquet_out::Column::{Int, Text};
+    let tokens = Tokens(reg);
+    let (mut path, mut hop, mut pool) = (vec![], vec![], vec![]);
+    let (mut address, mut token_in, mut amount_in) = (vec![], vec![], vec![]);
+    let (mut token_out, mut amount_out) = (vec![], vec![]);
+    for (i, p) in paths.into_iter().enumerate() {
+        for (j, s) in p.steps.iter().enumerate() {
+            path.push(i as i64);
+            hop.push(j as i64);
+            pool.push(s.pool.0 as i64);
+            address.push(
+                reg.pool(s.pool)
+                    .map(|m| m.address.to_string())
+                    .unwrap_or_default(),
+            );
+            token_in.push(tokens.symbol(s.from));
+            amount_in.push(tokens.amount(s.from, s.amt_in));
+            token_out.push(tokens.symbol(s.to));
+            amount_out.push(tokens.amount(s.to, s.amt_out));
+        }
+    }
+    parquet_out::write(
+        out,
+        "message path_steps {
+            required int64 path;
+            required int64 hop;
+            required int64 pool;
+            required binary pool_address (UTF8);
+            required binary token_in (UTF8);
+            required binary amount_in (UTF8);
+            required binary token_out (UTF8);
+            required binary amount_out (UTF8);
+        }",
+        vec![
+            Int(path),
+            Int(hop),
+            Int(pool),
+            Text(address),
+            Text(token_in),
+            Text(amount_in),
+            Text(token_out),
+            Text(amount_out),
+        ],
+    )
+}
+
+/// [`write_opportunities_csv`]'s columns as a Parquet file.
+#[cfg(feature = "parquet")]
+pub fn write_opportunities_parquet<W: Write + Send>(
+    out: W,
+    reg: &Registry,
+    opps: &[ArbOpportunity],
+) -> io::Result<()> {
+    use parquet_out::Column::{Int, Text};
+    let tokens = Tokens(reg);
+    let mut rank = vec![];
+    let (mut base, mut hops, mut route) = (vec![], vec![], vec![]);
+    let (mut optimal_in, mut gross, mut gas, mut net) = (vec![], vec![], vec![], vec![]);
+    for (i, o) in opps.iter().enumerate() {
+        let b = o.base();
+        rank.push(i as i64);
+        base.push(tokens.symbol(b));
+        hops.push(o.plan.len() as i64);
+        route.push(
+            std::iter::once(b)
+                .chain(o.plan.iter().map(|h| h.dir.to))
+                .map(|t| tokens.symbol(t))
+                .collect::<Vec<_>>()
+                .join(">"),
+        );
+        optimal_in.push(tokens.amount(b, o.optimal_in));
+        gross.push(tokens.amount(b, o.gross));
+        gas.push(tokens.amount(b, o.gas));
+        net.push(tokens.amount(b, o.net));
+    }
+    parquet_out::write(
+        out,
+        "message opportunities {
+            required int64 rank;
+            required binary base (UTF8);
+            required int64 hops;
+            required binary route (UTF8);
+            required binary optimal_in (UTF8);
+            required binary gross (UTF8);
+            required binary gas (UTF8);
+            required binary net (UTF8);
+        }",
+        vec![
+            Int(rank),
+            Text(base),
+            Int(hops),
+            Text(route),
+            Text(optimal_in),
+            Text(gross),
+            Text(gas),
+            Text(net),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::ids::PoolId;
+    use crate::registry::TokenMeta;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::World;
+    use alloy_primitives::Address;
+    use std::collections::HashMap;
+
+    #[test]
+    fn formats_units() {
+        assert_eq!(format_units(U256::from(1_500_000u64), 6), "1.5");
+        assert_eq!(format_units(U256::from(42u64), 6), "0.000042");
+        assert_eq!(format_units(U256::from(7_000u64), 3), "7");
+        assert_eq!(format_units(U256::from(7u64), 0), "7");
+        assert_eq!(format_units(U256::ZERO, 18), "0");
+    }
+
+    #[test]
+    fn writes_paths_and_opportunities() {
+        let mut reg = Registry::default();
+        for (t, symbol, decimals) in [(1, "WETH", 3), (2, "a,b", 0)] {
+            reg.upsert_token(
+                TokenId(t),
+                TokenMeta {
+                    address: Address::repeat_byte(t as u8),
+                    symbol: symbol.into(),
+                    decimals,
+                },
+            );
+        }
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2)), (PoolId(2), Cp::new(2, 2, 3))]);
+        let mut world = World::default();
+        world.set_pool_state(PoolId(1), reserves(1_000_000, 1_000_000));
+        world.set_pool_state(PoolId(2), reserves(1_000_000, 1_000_000));
+        let engine = Engine::new(&pools);
+        let plan = [hop(1, 1, 2), hop(2, 2, 3)];
+        let path = engine.simulate_chained(&world, &plan, U256::from(1_500u64));
+
+        let mut csv = Vec::new();
+        write_paths_csv(&mut csv, &reg, [&path]).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let rows: Vec<_> = csv.lines().collect();
+        assert_eq!(rows.len(), 3);
+        let mid = path.steps[0].amt_out;
+        assert_eq!(rows[1], format!("0,0,1,,WETH,1.5,\"a,b\",{mid}"));
+        assert!(rows[2].ends_with(&format!(",3,{}", path.steps[1].amt_out)));
+
+        let opp = ArbOpportunity {
+            plan: plan.to_vec(),
+            optimal_in: U256::from(1_500u64),
+            gross: U256::from(250u64),
+            gas: U256::from(50u64),
+            net: U256::from(200u64),
+        };
+        let mut csv = Vec::new();
+        write_opportunities_csv(&mut csv, &reg, &[opp]).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv.lines().nth(1).unwrap(),
+            "0,WETH,2,\"WETH>a,b>3\",1.5,0.25,0.05,0.2"
+        );
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn writes_parquet_matching_the_csv_columns() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use parquet::record::RowAccessor;
+
+        let mut reg = Registry::default();
+        reg.upsert_token(
+            TokenId(1),
+            TokenMeta {
+                address: Address::repeat_byte(1),
+                symbol: "WETH".into(),
+                decimals: 3,
+            },
+        );
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2))]);
+        let mut world = World::default();
+        world.set_pool_state(PoolId(1), reserves(1_000_000, 1_000_000));
+        let path =
+            Engine::new(&pools).simulate_chained(&world, &[hop(1, 1, 2)], U256::from(1_500u64));
+
+        let file =
+            std::env::temp_dir().join(format!("wayfinder-paths-{}.parquet", std::process::id()));
+        write_paths_parquet(std::fs::File::create(&file).unwrap(), &reg, [&path]).unwrap();
+        let reader = SerializedFileReader::new(std::fs::File::open(&file).unwrap()).unwrap();
+        let rows: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        std::fs::remove_file(&file).ok();
+
+        assert_eq!(rows.len(), 1);
+        let row = &rows[0];
+        assert_eq!(row.get_long(2).unwrap(), 1);
+        assert_eq!(row.get_string(4).unwrap(), "WETH");
+        assert_eq!(row.get_string(5).unwrap(), "1.5");
+        assert_eq!(row.get_string(6).unwrap(), "2");
+        assert_eq!(
+            *row.get_string(7).unwrap(),
+            path.steps[0].amt_out.to_string()
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! Where a plan's input comes from. Flash-loaned input has to be paid back,
+//! with the lender's fee, out of the plan's own output.
+
+use crate::{
+    engine::{Engine, Execution, Hop, Path},
+    error::{EngineError, WayfinderError},
+    ids::{AccountId, TokenId},
+    pool::Pool,
+    world::{StateView, World},
+};
+use alloy_primitives::{Address, U256};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Funding {
+    /// Paid from the owner's balance.
+    #[default]
+    Inventory,
+    /// Borrowed from `provider` (Aave, Balancer vault, a V3 pool, ...) and
+    /// repaid in the same transaction with `fee_bps` on top.
+    FlashLoan {
+        provider: Address,
+        token: TokenId,
+        fee_bps: u32,
+    },
+}
+
+impl Funding {
+    /// Lender fee on `principal`, rounded up as lenders do.
+    pub fn fee(&self, principal: U256) -> U256 {
+        match *self {
+            Funding::Inventory => U256::ZERO,
+            Funding::FlashLoan { fee_bps, .. } => {
+                let bps = U256::from(10_000u64);
+                principal
+                    .saturating_mul(U256::from(fee_bps))
+                    .saturating_add(bps - U256::from(1u64))
+                    / bps
+            }
+        }
+    }
+
+    /// What the plan's output must cover: principal plus fee for a flash
+    /// loan, nothing for inventory.
+    pub fn owed(&self, principal: U256) -> U256 {
+        match self {
+            Funding::Inventory => U256::ZERO,
+            Funding::FlashLoan { .. } => principal.saturating_add(self.fee(principal)),
+        }
+    }
+
+    /// A flash loan can only fund a plan that starts and ends in its token.
+    pub fn check_plan(&self, plan: &[Hop]) -> Result<(), EngineError> {
+        let Funding::FlashLoan { token, .. } = *self else {
+            return Ok(());
+        };
+        let (first, last) = match (plan.first(), plan.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Err(EngineError::EmptyPlan),
+        };
+        if first.dir.from != token || last.dir.to != token {
+            return Err(EngineError::LoanToken {
+                loan: token,
+                from: first.dir.from,
+                to: last.dir.to,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FundedPath {
+    pub path: Path,
+    pub owed: U256,
+}
+
+impl FundedPath {
+    pub fn amount_out(&self) -> U256 {
+        self.path
+            .steps
+            .last()
+            .map(|s| s.amt_out)
+            .unwrap_or_default()
+    }
+
+    /// Output left after repaying the loan.
+    pub fn surplus(&self) -> U256 {
+        self.amount_out().saturating_sub(self.owed)
+    }
+}
+
+impl<P: Pool> Engine<'_, P> {
+    /// [`Engine::try_simulate`] under `funding`; a plan that cannot repay
+    /// its flash loan is an error.
+    pub fn try_simulate_funded<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plan: &[Hop],
+        first_in: U256,
+        funding: &Funding,
+    ) -> Result<FundedPath, WayfinderError> {
+        funding.check_plan(plan)?;
+        let funded = FundedPath {
+            path: self.try_simulate(world, plan, first_in)?,
+            owed: funding.owed(first_in),
+        };
+        if funded.amount_out() < funded.owed {
+            return Err(EngineError::Unrepayable {
+                owed: funded.owed,
+                out: funded.amount_out(),
+            }
+            .into());
+        }
+        Ok(funded)
+    }
+
+    /// [`Engine::execute`] under `funding`. A flash loan credits `owner`
+    /// with the principal before the plan runs and debits principal and fee
+    /// after, so only the surplus stays; nothing changes if the plan
+    /// cannot repay.
+    pub fn execute_funded(
+        &self,
+        world: &mut World<P::State>,
+        owner: AccountId,
+        spender: AccountId,
+        plan: &[Hop],
+        first_in: U256,
+        funding: &Funding,
+    ) -> Result<Execution, WayfinderError> {
+        let funded = self.try_simulate_funded(&*world, plan, first_in, funding)?;
+        let Funding::FlashLoan { token, .. } = *funding else {
+            return Ok(self.execute(world, owner, spender, plan, first_in));
+        };
+        world.credit(owner, token, first_in);
+        let exec = self.execute(world, owner, spender, plan, first_in);
+        if exec.committed() {
+            world.debit(owner, token, funded.owed);
+        } else {
+            world.debit(owner, token, first_in);
+        }
+        Ok(exec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::ApprovalPolicy;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, hop, reserves};
+    use std::collections::HashMap;
+
+    #[test]
+    fn flash_loans_repay_principal_and_fee() {
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2)), (PoolId(2), Cp::new(2, 1, 2))]);
+        let engine = Engine::new(&pools).with_approvals(ApprovalPolicy::AssumeInfinite);
+        let mut world = World::default();
+        world.set_pool_state(PoolId(1), reserves(1_000_000, 2_000_000));
+        world.set_pool_state(PoolId(2), reserves(1_000_000, 1_000_000));
+        let cycle = [hop(1, 1, 2), hop(2, 2, 1)];
+        let loan = |fee_bps| Funding::FlashLoan {
+            provider: Address::ZERO,
+            token: TokenId(1),
+            fee_bps,
+        };
+        assert_eq!(loan(5).fee(U256::from(10_001u64)), U256::from(6u64));
+
+        let amt = U256::from(10_000u64);
+        let funded = engine
+            .try_simulate_funded(&world, &cycle, amt, &loan(9))
+            .unwrap();
+        assert_eq!(funded.owed, U256::from(10_009u64));
+        assert!(funded.surplus() > U256::ZERO);
+
+        assert!(matches!(
+            engine.try_simulate_funded(&world, &cycle, amt, &loan(10_000)),
+            Err(WayfinderError::Engine(EngineError::Unrepayable { .. }))
+        ));
+        assert!(matches!(
+            engine.try_simulate_funded(&world, &cycle[..1], amt, &loan(9)),
+            Err(WayfinderError::Engine(EngineError::LoanToken { .. }))
+        ));
+
+        // No inventory needed: the owner ends up holding just the surplus.
+        let owner = AccountId(1);
+        let exec = engine
+            .execute_funded(&mut world, owner, owner, &cycle, amt, &loan(9))
+            .unwrap();
+        assert!(exec.committed());
+        assert_eq!(world.holding(owner, TokenId(1)), funded.surplus());
+    }
+}
@@ -0,0 +1,390 @@
+//! Basefee and priority fee tracking over recent blocks, used to price the
+//! gas of a candidate before it is accepted, and per-chain models of what a
+//! plan costs to execute.
+
+use crate::{
+    arb::ScanConfig,
+    bundle::gas_cost,
+    engine::Hop,
+    ids::PoolId,
+    registry::{PoolKind, Registry},
+};
+use alloy_primitives::U256;
+use std::collections::{HashMap, VecDeque};
+
+/// EIP-1559 parameters.
+pub const ELASTICITY_MULTIPLIER: u64 = 2;
+pub const BASEFEE_CHANGE_DENOMINATOR: u64 = 8;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockFees {
+    pub number: u64,
+    pub basefee: u64,
+    pub gas_used: u64,
+    pub gas_limit: u64,
+    /// Effective priority fees paid in the block, sorted ascending.
+    pub tips: Vec<u64>,
+}
+
+/// Basefee of the block after one with `basefee` that used `gas_used` of
+/// `gas_limit`.
+pub fn next_basefee(basefee: u64, gas_used: u64, gas_limit: u64) -> u64 {
+    let target = gas_limit / ELASTICITY_MULTIPLIER;
+    if target == 0 || gas_used == target {
+        return basefee;
+    }
+    let change = |diff: u64| {
+        (u128::from(basefee) * u128::from(diff)
+            / u128::from(target)
+            / u128::from(BASEFEE_CHANGE_DENOMINATOR)) as u64
+    };
+    if gas_used > target {
+        basefee.saturating_add(change(gas_used - target).max(1))
+    } else {
+        basefee.saturating_sub(change(target - gas_used))
+    }
+}
+
+/// Fee conditions over the last `window` blocks.
+#[derive(Clone, Debug)]
+pub struct GasTracker {
+    pub window: usize,
+    blocks: VecDeque<BlockFees>,
+}
+
+impl GasTracker {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            blocks: VecDeque::new(),
+        }
+    }
+
+    /// Records `fees`; blocks older than the latest one seen are ignored.
+    pub fn observe(&mut self, mut fees: BlockFees) {
+        if self.latest().is_some_and(|b| b.number >= fees.number) {
+            return;
+        }
+        fees.tips.sort_unstable();
+        self.blocks.push_back(fees);
+        if self.blocks.len() > self.window {
+            self.blocks.pop_front();
+        }
+    }
+
+    pub fn latest(&self) -> Option<&BlockFees> {
+        self.blocks.back()
+    }
+
+    /// Predicted basefee of the block after the latest one.
+    pub fn next_basefee(&self) -> Option<u64> {
+        self.latest()
+            .map(|b| next_basefee(b.basefee, b.gas_used, b.gas_limit))
+    }
+
+    /// Median across the window of each block's `pct`-th percentile tip.
+    pub fn priority_fee(&self, pct: f64) -> Option<u64> {
+        let mut per_block: Vec<u64> = self
+            .blocks
+            .iter()
+            .filter(|b| !b.tips.is_empty())
+            .map(|b| b.tips[percentile_index(b.tips.len(), pct)])
+            .collect();
+        if per_block.is_empty() {
+            return None;
+        }
+        per_block.sort_unstable();
+        Some(per_block[per_block.len() / 2])
+    }
+
+    /// Cost of `gas_used` in the next block at the `pct`-th percentile tip.
+    pub fn cost(&self, gas_used: u64, pct: f64) -> Option<U256> {
+        let basefee = self.next_basefee()?;
+        Some(gas_cost(
+            gas_used,
+            basefee,
+            self.priority_fee(pct).unwrap_or(0),
+        ))
+    }
+}
+
+fn percentile_index(len: usize, pct: f64) -> usize {
+    let rank = (pct.clamp(0.0, 100.0) / 100.0 * len as f64).ceil() as usize;
+    rank.saturating_sub(1).min(len - 1)
+}
+
+impl ScanConfig {
+    /// Reprices `gas_per_hop` from `tracker` for hops using `gas_per_hop`
+    /// gas. Amounts are in wei, so this only fits scans based in WETH.
+    pub fn update_gas(&mut self, tracker: &GasTracker, gas_per_hop: u64, pct: f64) -> bool {
+        match tracker.cost(gas_per_hop, pct) {
+            Some(cost) => {
+                self.gas_per_hop = cost;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Gas paid per calldata byte on L1, counting every byte as non-zero.
+pub const CALLDATA_GAS_PER_BYTE: u64 = 16;
+
+/// Prices executing a plan on one chain, in wei.
+pub trait GasModel: Sync {
+    fn cost(&self, plan: &[Hop]) -> U256;
+}
+
+/// Execution gas and calldata of one swap, or of the transaction around them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapCost {
+    pub gas: u64,
+    pub calldata_bytes: u64,
+}
+
+/// What each hop of a plan costs by the kind of pool it swaps through.
+#[derive(Clone, Debug)]
+pub struct GasSchedule {
+    pub tx: SwapCost,
+    pub univ2: SwapCost,
+    pub univ3: SwapCost,
+    /// Pools without a known kind are charged this.
+    pub unknown: SwapCost,
+    pub kinds: HashMap<PoolId, PoolKind>,
+}
+
+impl Default for GasSchedule {
+    fn default() -> Self {
+        let cost = |gas, calldata_bytes| SwapCost {
+            gas,
+            calldata_bytes,
+        };
+        Self {
+            tx: cost(21_000 + 30_000, 180),
+            univ2: cost(60_000, 64),
+            univ3: cost(110_000, 96),
+            unknown: cost(110_000, 96),
+            kinds: HashMap::new(),
+        }
+    }
+}
+
+impl GasSchedule {
+    /// The default schedule with pool kinds taken from `reg`.
+    pub fn from_registry(reg: &Registry) -> Self {
+        Self {
+            kinds: reg.pool_meta.iter().map(|(&p, m)| (p, m.kind)).collect(),
+            ..Self::default()
+        }
+    }
+
+    pub fn hop(&self, pool: PoolId) -> SwapCost {
+        match self.kinds.get(&pool) {
+            Some(PoolKind::UniV2) => self.univ2,
+            Some(PoolKind::UniV3) => self.univ3,
+            None => self.unknown,
+        }
+    }
+
+    /// Total execution gas and calldata bytes of `plan`.
+    pub fn usage(&self, plan: &[Hop]) -> SwapCost {
+        plan.iter()
+            .map(|h| self.hop(h.pool))
+            .fold(self.tx, |a, b| SwapCost {
+                gas: a.gas + b.gas,
+                calldata_bytes: a.calldata_bytes + b.calldata_bytes,
+            })
+    }
+}
+
+/// Mainnet and other L1s: calldata is paid for as gas at the same price.
+#[derive(Clone, Debug)]
+pub struct L1Gas {
+    pub schedule: GasSchedule,
+    pub gas_price: u64,
+}
+
+impl GasModel for L1Gas {
+    fn cost(&self, plan: &[Hop]) -> U256 {
+        let u = self.schedule.usage(plan);
+        U256::from(u.gas + u.calldata_bytes * CALLDATA_GAS_PER_BYTE) * U256::from(self.gas_price)
+    }
+}
+
+/// OP-stack chains after Ecotone: L2 execution plus an L1 data fee priced
+/// from the L1 basefee and blob basefee with the chain's scalars, as the
+/// `GasPriceOracle` predeploy reports them.
+#[derive(Clone, Debug)]
+pub struct OpStackGas {
+    pub schedule: GasSchedule,
+    pub l2_gas_price: u64,
+    pub l1_basefee: u64,
+    pub blob_basefee: u64,
+    pub basefee_scalar: u64,
+    pub blob_basefee_scalar: u64,
+}
+
+impl GasModel for OpStackGas {
+    fn cost(&self, plan: &[Hop]) -> U256 {
+        let u = self.schedule.usage(plan);
+        let weighted =
+            U256::from(16u64) * U256::from(self.basefee_scalar) * U256::from(self.l1_basefee)
+                + U256::from(self.blob_basefee_scalar) * U256::from(self.blob_basefee);
+        let l1_fee = U256::from(u.calldata_bytes * CALLDATA_GAS_PER_BYTE) * weighted
+            / U256::from(16_000_000u64);
+        U256::from(u.gas) * U256::from(self.l2_gas_price) + l1_fee
+    }
+}
+
+/// Arbitrum: L2 execution plus the poster fee for the transaction's share
+/// of the L1 batch, at `l1_price_per_unit` per calldata gas unit as
+/// `ArbGasInfo.getL1BaseFeeEstimate` reports it.
+#[derive(Clone, Debug)]
+pub struct ArbitrumGas {
+    pub schedule: GasSchedule,
+    pub l2_gas_price: u64,
+    pub l1_price_per_unit: u64,
+}
+
+impl GasModel for ArbitrumGas {
+    fn cost(&self, plan: &[Hop]) -> U256 {
+        let u = self.schedule.usage(plan);
+        U256::from(u.gas) * U256::from(self.l2_gas_price)
+            + U256::from(u.calldata_bytes * CALLDATA_GAS_PER_BYTE)
+                * U256::from(self.l1_price_per_unit)
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl GasTracker {
+    /// Reward percentiles requested from `eth_feeHistory`; each block's
+    /// tips are approximated by these points.
+    pub const FEE_HISTORY_PERCENTILES: [f64; 5] = [10.0, 25.0, 50.0, 75.0, 90.0];
+
+    /// Pulls the last `window` blocks with `eth_feeHistory`.
+    pub async fn refresh<P: alloy_provider::Provider>(
+        &mut self,
+        provider: &P,
+    ) -> Result<(), crate::error::WayfinderError> {
+        let hist = provider
+            .get_fee_history(
+                self.window as u64,
+                alloy_rpc_types_eth::BlockNumberOrTag::Latest,
+                &Self::FEE_HISTORY_PERCENTILES,
+            )
+            .await
+            .map_err(|e| crate::error::WayfinderError::Rpc(e.to_string()))?;
+        // Only the ratio is reported, so gas is expressed against a nominal limit.
+        const LIMIT: u64 = 30_000_000;
+        for (i, &ratio) in hist.gas_used_ratio.iter().enumerate() {
+            let Some(&basefee) = hist.base_fee_per_gas.get(i) else {
+                break;
+            };
+            let tips = hist
+                .reward
+                .as_ref()
+                .and_then(|r| r.get(i))
+                .map(|r| r.iter().map(|&t| t as u64).collect())
+                .unwrap_or_default();
+            self.observe(BlockFees {
+                number: hist.oldest_block + i as u64,
+                basefee: basefee as u64,
+                gas_used: (ratio * LIMIT as f64) as u64,
+                gas_limit: LIMIT,
+                tips,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(number: u64, basefee: u64, used_pct: u64, tips: &[u64]) -> BlockFees {
+        BlockFees {
+            number,
+            basefee,
+            gas_used: 300_000 * used_pct,
+            gas_limit: 30_000_000,
+            tips: tips.to_vec(),
+        }
+    }
+
+    #[test]
+    fn basefee_follows_eip1559() {
+        assert_eq!(next_basefee(1_000, 30_000_000, 30_000_000), 1_125);
+        assert_eq!(next_basefee(1_000, 0, 30_000_000), 875);
+        assert_eq!(next_basefee(1_000, 15_000_000, 30_000_000), 1_000);
+        // Any excess raises the fee by at least 1.
+        assert_eq!(next_basefee(7, 15_000_001, 30_000_000), 8);
+    }
+
+    #[test]
+    fn tracker_prices_scan_gas_from_recent_blocks() {
+        let mut tracker = GasTracker::new(3);
+        let mut config = ScanConfig::default();
+        assert!(!config.update_gas(&tracker, 100_000, 50.0));
+
+        tracker.observe(block(1, 10, 50, &[5, 1, 3]));
+        tracker.observe(block(2, 10, 50, &[2, 4, 6]));
+        tracker.observe(block(3, 10, 50, &[9, 7, 8]));
+        tracker.observe(block(4, 100, 100, &[1, 2, 3]));
+        tracker.observe(block(2, 1, 0, &[]));
+        assert_eq!(tracker.latest().unwrap().number, 4);
+
+        // Per-block medians 4, 8, 2.
+        assert_eq!(tracker.priority_fee(50.0), Some(4));
+        assert_eq!(tracker.priority_fee(100.0), Some(6));
+        assert_eq!(tracker.next_basefee(), Some(112));
+
+        assert!(config.update_gas(&tracker, 100_000, 50.0));
+        assert_eq!(config.gas_per_hop, U256::from(100_000u64 * 116));
+    }
+
+    #[test]
+    fn l2_models_charge_for_calldata_posted_to_l1() {
+        use crate::ids::{SwapDirection, TokenId};
+
+        let mut schedule = GasSchedule::default();
+        schedule.kinds.insert(PoolId(1), PoolKind::UniV2);
+        schedule.kinds.insert(PoolId(2), PoolKind::UniV3);
+        let dir = SwapDirection::new(TokenId(1), TokenId(2)).unwrap();
+        let plan = [Hop::new(PoolId(1), dir), Hop::new(PoolId(2), dir)];
+        let u = schedule.usage(&plan);
+        assert_eq!((u.gas, u.calldata_bytes), (221_000, 340));
+
+        let gwei = 1_000_000_000;
+        let mainnet = L1Gas {
+            schedule: schedule.clone(),
+            gas_price: 20 * gwei,
+        };
+        assert_eq!(
+            mainnet.cost(&plan),
+            U256::from((221_000 + 340 * 16) * 20 * gwei)
+        );
+
+        // Base-like: 0.01 gwei L2 gas, 20 gwei L1 with no blob discount.
+        let op = OpStackGas {
+            schedule: schedule.clone(),
+            l2_gas_price: gwei / 100,
+            l1_basefee: 20 * gwei,
+            blob_basefee: 1,
+            basefee_scalar: 1_000_000,
+            blob_basefee_scalar: 0,
+        };
+        let execution = U256::from(221_000 * gwei / 100);
+        let l1_fee = op.cost(&plan) - execution;
+        assert_eq!(l1_fee, U256::from(340 * 16 * 20 * gwei));
+        assert!(l1_fee > execution * U256::from(10u64));
+
+        let arb = ArbitrumGas {
+            schedule,
+            l2_gas_price: gwei / 100,
+            l1_price_per_unit: 20 * gwei,
+        };
+        assert_eq!(arb.cost(&plan), op.cost(&plan));
+        assert!(arb.cost(&plan[..1]) < arb.cost(&plan));
+    }
+}
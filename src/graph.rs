@@ -1,4 +1,6 @@
-use crate::ids::{PoolId, TokenId};
+use crate::error::GraphError;
+use crate::ids::{PoolId, SwapDirection, TokenId};
+use crate::registry::Registry;
 use petgraph::Direction;
 use petgraph::prelude::*;
 use petgraph::stable_graph::StableDiGraph;
@@ -10,12 +12,19 @@ pub enum NodeKind {
     Pool(PoolId),
 }
 
+#[derive(Clone, Debug)]
 pub struct AMMGraph {
     pub g: StableDiGraph<NodeKind, ()>,
     pub token_idx: HashMap<TokenId, NodeIndex>,
     pub pool_idx: HashMap<PoolId, NodeIndex>,
 }
 
+impl Default for AMMGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AMMGraph {
     pub fn new() -> Self {
         Self {
@@ -25,6 +34,17 @@ impl AMMGraph {
         }
     }
 
+    /// Every registry pool, connected both ways between its two tokens.
+    pub fn from_registry(reg: &Registry) -> Self {
+        let mut graph = Self::new();
+        let mut pools: Vec<_> = reg.pool_meta.iter().collect();
+        pools.sort_by_key(|(pid, _)| **pid);
+        for (&pid, meta) in pools {
+            graph.connect_bidirectional_pair(pid, meta.token0, meta.token1);
+        }
+        graph
+    }
+
     pub fn add_token(&mut self, id: TokenId) -> NodeIndex {
         *self
             .token_idx
@@ -51,18 +71,62 @@ impl AMMGraph {
         self.g.add_edge(pix, tix, ());
     }
 
-    pub fn pools_accepting(&self, t: TokenId) -> impl Iterator<Item = NodeIndex> + '_ {
-        let tix = self.token_idx[&t];
-        self.g
+    pub fn token_node(&self, t: TokenId) -> Result<NodeIndex, GraphError> {
+        self.token_idx
+            .get(&t)
+            .copied()
+            .ok_or(GraphError::UnknownToken(t))
+    }
+
+    pub fn pool_node(&self, p: PoolId) -> Result<NodeIndex, GraphError> {
+        self.pool_idx
+            .get(&p)
+            .copied()
+            .ok_or(GraphError::UnknownPool(p))
+    }
+
+    pub fn pools_accepting(
+        &self,
+        t: TokenId,
+    ) -> Result<impl Iterator<Item = NodeIndex> + '_, GraphError> {
+        let tix = self.token_node(t)?;
+        Ok(self
+            .g
             .neighbors_directed(tix, Direction::Outgoing)
-            .filter(|&n| matches!(self.g[n], NodeKind::Pool(_)))
+            .filter(|&n| matches!(self.g[n], NodeKind::Pool(_))))
     }
 
-    pub fn tokens_emitted_by(&self, p: PoolId) -> impl Iterator<Item = NodeIndex> + '_ {
-        let pix = self.pool_idx[&p];
-        self.g
+    pub fn tokens_emitted_by(
+        &self,
+        p: PoolId,
+    ) -> Result<impl Iterator<Item = NodeIndex> + '_, GraphError> {
+        let pix = self.pool_node(p)?;
+        Ok(self
+            .g
             .neighbors_directed(pix, Direction::Outgoing)
-            .filter(|&n| matches!(self.g[n], NodeKind::Token(_)))
+            .filter(|&n| matches!(self.g[n], NodeKind::Token(_))))
+    }
+
+    pub fn pools_emitting(
+        &self,
+        t: TokenId,
+    ) -> Result<impl Iterator<Item = NodeIndex> + '_, GraphError> {
+        let tix = self.token_node(t)?;
+        Ok(self
+            .g
+            .neighbors_directed(tix, Direction::Incoming)
+            .filter(|&n| matches!(self.g[n], NodeKind::Pool(_))))
+    }
+
+    pub fn tokens_accepted_by(
+        &self,
+        p: PoolId,
+    ) -> Result<impl Iterator<Item = NodeIndex> + '_, GraphError> {
+        let pix = self.pool_node(p)?;
+        Ok(self
+            .g
+            .neighbors_directed(pix, Direction::Incoming)
+            .filter(|&n| matches!(self.g[n], NodeKind::Token(_))))
     }
 
     fn add_edge_unique(&mut self, from: NodeIndex, to: NodeIndex) {
@@ -71,16 +135,30 @@ impl AMMGraph {
         }
     }
 
-    pub fn connect_bidirectional_pair(&mut self, p: PoolId, a: TokenId, b: TokenId) {
-        let aix = self.add_token(a);
-        let bix = self.add_token(b);
+    pub fn connect_direction(&mut self, p: PoolId, dir: SwapDirection) {
+        let fix = self.add_token(dir.from);
+        let tix = self.add_token(dir.to);
         let pix = self.add_pool(p);
 
-        self.add_edge_unique(aix, pix);
-        self.add_edge_unique(pix, bix);
+        self.add_edge_unique(fix, pix);
+        self.add_edge_unique(pix, tix);
+    }
+
+    pub fn connect_bidirectional_pair(&mut self, p: PoolId, a: TokenId, b: TokenId) {
+        let dir = SwapDirection::new(a, b).expect("pair tokens must differ");
+        self.connect_direction(p, dir);
+        self.connect_direction(p, dir.reverse());
+    }
 
-        self.add_edge_unique(bix, pix);
-        self.add_edge_unique(pix, aix);
+    pub fn supports_direction(&self, p: PoolId, dir: SwapDirection) -> bool {
+        let (Some(&pix), Some(&fix), Some(&tix)) = (
+            self.pool_idx.get(&p),
+            self.token_idx.get(&dir.from),
+            self.token_idx.get(&dir.to),
+        ) else {
+            return false;
+        };
+        self.g.find_edge(fix, pix).is_some() && self.g.find_edge(pix, tix).is_some()
     }
 }
 
@@ -119,11 +197,11 @@ mod tests {
 
         g.connect_token_to_pool(t, p);
 
-        let pools: Vec<_> = g.pools_accepting(t).collect();
+        let pools: Vec<_> = g.pools_accepting(t).unwrap().collect();
         assert_eq!(pools.len(), 1);
         assert!(matches!(g.g[pools[0]], NodeKind::Pool(PoolId(10))));
 
-        let tokens: Vec<_> = g.tokens_emitted_by(p).collect();
+        let tokens: Vec<_> = g.tokens_emitted_by(p).unwrap().collect();
         assert!(tokens.is_empty());
 
         assert_eq!(g.g.edge_count(), 1);
@@ -135,6 +213,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn unknown_nodes_are_errors_not_panics() {
+        let mut g = AMMGraph::new();
+        g.connect_bidirectional_pair(PoolId(1), TokenId(1), TokenId(2));
+        assert_eq!(
+            g.pools_accepting(TokenId(9)).err(),
+            Some(GraphError::UnknownToken(TokenId(9)))
+        );
+        assert_eq!(
+            g.tokens_emitted_by(PoolId(9)).err(),
+            Some(GraphError::UnknownPool(PoolId(9)))
+        );
+    }
+
     #[test]
     fn connect_pool_to_token_and_query() {
         let mut g = AMMGraph::new();
@@ -143,11 +235,11 @@ mod tests {
 
         g.connect_pool_to_token(p, t);
 
-        let tokens: Vec<_> = g.tokens_emitted_by(p).collect();
+        let tokens: Vec<_> = g.tokens_emitted_by(p).unwrap().collect();
         assert_eq!(tokens.len(), 1);
         assert!(matches!(g.g[tokens[0]], NodeKind::Token(TokenId(2))));
 
-        let pools: Vec<_> = g.pools_accepting(t).collect();
+        let pools: Vec<_> = g.pools_accepting(t).unwrap().collect();
         assert!(pools.is_empty());
 
         assert_eq!(g.g.edge_count(), 1);
@@ -175,4 +267,16 @@ mod tests {
         g.connect_bidirectional_pair(p, a, b);
         assert_eq!(g.g.edge_count(), 4, "edges should be unique");
     }
+
+    #[test]
+    fn connect_direction_is_one_way() {
+        let mut g = AMMGraph::new();
+        let dir = SwapDirection::new(TokenId(1), TokenId(2)).unwrap();
+        let p = PoolId(5);
+
+        g.connect_direction(p, dir);
+        assert!(g.supports_direction(p, dir));
+        assert!(!g.supports_direction(p, dir.reverse()));
+        assert_eq!(g.g.edge_count(), 2);
+    }
 }
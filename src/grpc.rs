@@ -0,0 +1,336 @@
+use crate::{
+    arb::{ArbOpportunity, ScanConfig, Scanner},
+    engine::{Engine, Path},
+    graph::AMMGraph,
+    ids::{PoolId, TokenId},
+    pool::Pool,
+    world::{World, WorldDiff},
+};
+use alloy_primitives::U256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+pub mod pb {
+    tonic::include_proto!("wayfinder.v1");
+}
+
+use pb::opportunities_server::{Opportunities, OpportunitiesServer};
+
+const STREAM_BUFFER: usize = 64;
+
+struct Inner<P: Pool> {
+    pools: HashMap<PoolId, P>,
+    graph: AMMGraph,
+    config: ScanConfig,
+    world: watch::Sender<Arc<World<P::State>>>,
+}
+
+pub struct OpportunityFeed<P: Pool> {
+    inner: Arc<Inner<P>>,
+}
+
+impl<P: Pool> Clone for OpportunityFeed<P> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<P> OpportunityFeed<P>
+where
+    P: Pool + Send + Sync + 'static,
+    P::State: Send + Sync + 'static,
+{
+    pub fn new(pools: HashMap<PoolId, P>, graph: AMMGraph, world: World<P::State>) -> Self {
+        Self::with_config(pools, graph, world, ScanConfig::default())
+    }
+
+    pub fn with_config(
+        pools: HashMap<PoolId, P>,
+        graph: AMMGraph,
+        world: World<P::State>,
+        config: ScanConfig,
+    ) -> Self {
+        let (world, _) = watch::channel(Arc::new(world));
+        Self {
+            inner: Arc::new(Inner {
+                pools,
+                graph,
+                config,
+                world,
+            }),
+        }
+    }
+
+    pub fn publish(&self, world: World<P::State>) {
+        self.inner.world.send_replace(Arc::new(world));
+    }
+
+    pub fn update(&self, diff: WorldDiff<P::State>) {
+        self.inner
+            .world
+            .send_modify(|w| Arc::make_mut(w).apply(diff));
+    }
+
+    pub fn into_service(self) -> OpportunitiesServer<Self> {
+        OpportunitiesServer::new(self)
+    }
+
+    /// Clients may lower `max_hops` below the feed's config, never raise it.
+    fn config(&self, max_hops: u32) -> ScanConfig {
+        let limit = self.inner.config.max_hops;
+        ScanConfig {
+            max_hops: if max_hops == 0 {
+                limit
+            } else {
+                (max_hops as usize).min(limit)
+            },
+            ..self.inner.config
+        }
+    }
+
+    /// Runs `emit` on the blocking pool for every world published, so scans
+    /// never hold up the runtime's workers.
+    fn stream<T, F>(&self, emit: F) -> ReceiverStream<Result<T, Status>>
+    where
+        T: Send + 'static,
+        F: Fn(&Inner<P>, &World<P::State>) -> Vec<T> + Send + Sync + 'static,
+    {
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        let inner = Arc::clone(&self.inner);
+        let emit = Arc::new(emit);
+        let mut updates = inner.world.subscribe();
+        tokio::spawn(async move {
+            loop {
+                let world = Arc::clone(&updates.borrow_and_update());
+                let (inner, emit) = (Arc::clone(&inner), Arc::clone(&emit));
+                let items = match tokio::task::spawn_blocking(move || emit(&inner, &world)).await {
+                    Ok(items) => items,
+                    Err(e) => {
+                        let _ = tx.send(Err(Status::internal(e.to_string()))).await;
+                        return;
+                    }
+                };
+                for item in items {
+                    if tx.send(Ok(item)).await.is_err() {
+                        return;
+                    }
+                }
+                if updates.changed().await.is_err() {
+                    return;
+                }
+            }
+        });
+        ReceiverStream::new(rx)
+    }
+}
+
+fn parse_amount(s: &str) -> Result<U256, Status> {
+    s.parse()
+        .map_err(|_| Status::invalid_argument(format!("invalid amount {s}")))
+}
+
+pub fn arb_message(block: u64, opp: &ArbOpportunity) -> pb::ArbOpportunity {
+    pb::ArbOpportunity {
+        block,
+        base: opp.base().0,
+        plan: opp
+            .plan
+            .iter()
+            .map(|h| pb::Hop {
+                pool: h.pool.0,
+                token_in: h.dir.from.0,
+                token_out: h.dir.to.0,
+            })
+            .collect(),
+        optimal_in: opp.optimal_in.to_string(),
+        gross: opp.gross.to_string(),
+        gas: opp.gas.to_string(),
+        net: opp.net.to_string(),
+    }
+}
+
+pub fn quote_message(block: u64, path: &Path) -> pb::RouteQuote {
+    let (first, last) = (&path.steps[0], &path.steps[path.steps.len() - 1]);
+    pb::RouteQuote {
+        block,
+        token_in: first.from.0,
+        token_out: last.to.0,
+        amount_in: first.amt_in.to_string(),
+        amount_out: last.amt_out.to_string(),
+        steps: path
+            .steps
+            .iter()
+            .map(|s| pb::Step {
+                pool: s.pool.0,
+                token_in: s.from.0,
+                token_out: s.to.0,
+                amount_in: s.amt_in.to_string(),
+                amount_out: s.amt_out.to_string(),
+            })
+            .collect(),
+    }
+}
+
+#[tonic::async_trait]
+impl<P> Opportunities for OpportunityFeed<P>
+where
+    P: Pool + Send + Sync + 'static,
+    P::State: Send + Sync + 'static,
+{
+    type StreamOpportunitiesStream = ReceiverStream<Result<pb::ArbOpportunity, Status>>;
+    type StreamQuotesStream = ReceiverStream<Result<pb::RouteQuote, Status>>;
+
+    async fn stream_opportunities(
+        &self,
+        req: Request<pb::OpportunityRequest>,
+    ) -> Result<Response<Self::StreamOpportunitiesStream>, Status> {
+        let req = req.into_inner();
+        let config = self.config(req.max_hops);
+        let mut bases: Vec<TokenId> = req.bases.into_iter().map(TokenId).collect();
+        if bases.is_empty() {
+            bases = self.inner.graph.token_idx.keys().copied().collect();
+            bases.sort();
+        }
+
+        Ok(Response::new(self.stream(move |inner, world| {
+            let engine = Engine::new(&inner.pools);
+            Scanner::new(&engine, &inner.graph)
+                .with_config(config)
+                .scan(world, &bases)
+                .iter()
+                .map(|opp| arb_message(world.block.number, opp))
+                .collect()
+        })))
+    }
+
+    async fn stream_quotes(
+        &self,
+        req: Request<pb::QuoteRequest>,
+    ) -> Result<Response<Self::StreamQuotesStream>, Status> {
+        let req = req.into_inner();
+        let config = self.config(req.max_hops);
+        let amt_in = parse_amount(&req.amount_in)?;
+        let (from, to) = (TokenId(req.token_in), TokenId(req.token_out));
+        if from == to {
+            return Err(Status::invalid_argument("token_in equals token_out"));
+        }
+
+        Ok(Response::new(self.stream(move |inner, world| {
+            let engine = Engine::new(&inner.pools);
+            Scanner::new(&engine, &inner.graph)
+                .with_config(config)
+                .best_route(world, from, to, amt_in)
+                .map(|path| quote_message(world.block.number, &path))
+                .into_iter()
+                .collect()
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::univ2::{UniV2Pool, UniV2State};
+    use crate::world::BlockContext;
+    use tokio_stream::StreamExt;
+
+    fn feed() -> OpportunityFeed<UniV2Pool> {
+        let mut pools = HashMap::new();
+        let mut graph = AMMGraph::new();
+        let mut world = World::default();
+        for (id, t0, t1, r0, r1) in [
+            (1, 1, 2, 1_000_000u64, 2_000_000u64),
+            (2, 1, 2, 1_000_000, 2_400_000),
+        ] {
+            let pid = PoolId(id);
+            pools.insert(pid, UniV2Pool::new(pid, TokenId(t0), TokenId(t1)));
+            graph.connect_bidirectional_pair(pid, TokenId(t0), TokenId(t1));
+            world
+                .pool_states
+                .insert(pid, UniV2State::new(U256::from(r0), U256::from(r1)));
+        }
+        OpportunityFeed::new(pools, graph, world)
+    }
+
+    #[tokio::test]
+    async fn quotes_stream_on_every_world_update() {
+        let feed = feed();
+        let req = pb::QuoteRequest {
+            token_in: 1,
+            token_out: 2,
+            amount_in: "1000".into(),
+            max_hops: 1,
+        };
+        let mut stream = feed
+            .stream_quotes(Request::new(req))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(first.steps[0].pool, 2);
+
+        let mut diff = WorldDiff {
+            block: Some(BlockContext {
+                number: 2,
+                ..BlockContext::default()
+            }),
+            ..WorldDiff::default()
+        };
+        diff.set_pool_state(
+            PoolId(1),
+            UniV2State::new(U256::from(1_000_000u64), U256::from(3_000_000u64)),
+        );
+        feed.update(diff);
+
+        let second = stream.next().await.unwrap().unwrap();
+        assert_eq!(second.block, 2);
+        assert_eq!(second.steps[0].pool, 1);
+    }
+
+    #[tokio::test]
+    async fn opportunities_stream_profitable_cycles() {
+        let feed = feed();
+        let mut stream = feed
+            .stream_opportunities(Request::new(pb::OpportunityRequest {
+                bases: vec![1],
+                max_hops: 2,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let opp = stream.next().await.unwrap().unwrap();
+        assert_eq!(opp.base, 1);
+        assert_eq!(opp.plan.len(), 2);
+        assert!(opp.net.parse::<U256>().unwrap() > U256::ZERO);
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_amounts() {
+        let err = feed()
+            .stream_quotes(Request::new(pb::QuoteRequest {
+                token_in: 1,
+                token_out: 2,
+                amount_in: "lots".into(),
+                max_hops: 0,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn requested_max_hops_is_capped_by_the_feed() {
+        let feed = feed();
+        let limit = ScanConfig::default().max_hops;
+        assert_eq!(feed.config(0).max_hops, limit);
+        assert_eq!(feed.config(1).max_hops, 1);
+        assert_eq!(feed.config(u32::MAX).max_hops, limit);
+    }
+}
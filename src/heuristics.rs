@@ -0,0 +1,258 @@
+//! Aggregator-style candidate generation: direct pools, routes through a few
+//! connector tokens, and the best-paying intermediates, as a cheap shortlist
+//! ahead of (or instead of) exhaustive search.
+
+use crate::{
+    engine::{Engine, Hop, Path},
+    graph::{AMMGraph, NodeKind},
+    ids::{SwapDirection, TokenId},
+    pool::Pool,
+    registry::Registry,
+    world::StateView,
+};
+use alloy_primitives::{Address, U256, address};
+use std::cmp::Reverse;
+
+/// WETH, USDC and WBTC on Ethereum mainnet.
+pub const MAINNET_CONNECTORS: [Address; 3] = [
+    address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+    address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+    address!("2260fac5e5542a773aa44fbc8dfb5bda69c23bb1"),
+];
+
+#[derive(Clone, Debug)]
+pub struct HeuristicConfig {
+    pub connectors: Vec<TokenId>,
+    /// How many non-connector intermediates to keep, ranked by output.
+    pub top_intermediates: usize,
+}
+
+impl Default for HeuristicConfig {
+    fn default() -> Self {
+        Self {
+            connectors: Vec::new(),
+            top_intermediates: 3,
+        }
+    }
+}
+
+impl HeuristicConfig {
+    /// Connectors resolved against `reg`; unknown addresses are skipped.
+    pub fn with_connector_addresses(mut self, reg: &Registry, addrs: &[Address]) -> Self {
+        self.connectors = addrs
+            .iter()
+            .filter_map(|a| reg.token_by_addr.get(a).copied())
+            .collect();
+        self
+    }
+}
+
+pub struct Candidates<'a, P: Pool> {
+    pub engine: &'a Engine<'a, P>,
+    pub graph: &'a AMMGraph,
+    pub config: HeuristicConfig,
+}
+
+impl<'a, P: Pool> Candidates<'a, P> {
+    pub fn new(engine: &'a Engine<'a, P>, graph: &'a AMMGraph) -> Self {
+        Self {
+            engine,
+            graph,
+            config: HeuristicConfig::default(),
+        }
+    }
+
+    pub fn with_config(mut self, config: HeuristicConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Every direct pool, then two-hop routes via each connector, then
+    /// two-hop routes via the `top_intermediates` best other tokens. Legs of
+    /// multi-hop routes use the pool paying most for `amt_in`.
+    pub fn generate<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        from: TokenId,
+        to: TokenId,
+        amt_in: U256,
+    ) -> Vec<Vec<Hop>> {
+        let mut out: Vec<Vec<Hop>> = self
+            .direct(world, from, to)
+            .into_iter()
+            .map(|h| vec![h])
+            .collect();
+        if from == to {
+            return out;
+        }
+
+        for &c in &self.config.connectors {
+            if c == from || c == to {
+                continue;
+            }
+            if let Some((plan, _)) = self.via(world, from, c, to, amt_in) {
+                out.push(plan);
+            }
+        }
+
+        let mut ranked: Vec<(Vec<Hop>, U256)> = self
+            .neighbours(from)
+            .filter(|t| *t != to && !self.config.connectors.contains(t))
+            .filter_map(|mid| self.via(world, from, mid, to, amt_in))
+            .collect();
+        ranked.sort_by_key(|(_, out)| Reverse(*out));
+        out.extend(
+            ranked
+                .into_iter()
+                .take(self.config.top_intermediates)
+                .map(|(plan, _)| plan),
+        );
+        out
+    }
+
+    pub fn best_route<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        from: TokenId,
+        to: TokenId,
+        amt_in: U256,
+    ) -> Option<Path> {
+        self.generate(world, from, to, amt_in)
+            .iter()
+            .map(|plan| self.engine.simulate_chained(world, plan, amt_in))
+            .max_by_key(|path| path.steps.last().map(|s| s.amt_out).unwrap_or_default())
+    }
+
+    fn direct<V: StateView<P::State>>(&self, world: &V, from: TokenId, to: TokenId) -> Vec<Hop> {
+        let Some(dir) = SwapDirection::new(from, to) else {
+            return Vec::new();
+        };
+        let Ok(pools) = self.graph.pools_accepting(from) else {
+            return Vec::new();
+        };
+        pools
+            .filter_map(|pix| match self.graph.g[pix] {
+                NodeKind::Pool(pid) => Some(pid),
+                _ => None,
+            })
+            .filter(|pid| {
+                self.graph.supports_direction(*pid, dir)
+                    && world.pool_state(*pid).is_some()
+                    && self.engine.pools.get(pid).is_some_and(|p| p.supports(dir))
+            })
+            .map(|pid| Hop::new(pid, dir))
+            .collect()
+    }
+
+    fn neighbours(&self, from: TokenId) -> impl Iterator<Item = TokenId> + '_ {
+        let mut seen = Vec::new();
+        self.graph
+            .pools_accepting(from)
+            .into_iter()
+            .flatten()
+            .filter_map(|pix| match self.graph.g[pix] {
+                NodeKind::Pool(pid) => Some(pid),
+                _ => None,
+            })
+            .flat_map(|pid| self.graph.tokens_emitted_by(pid).into_iter().flatten())
+            .filter_map(|tix| match self.graph.g[tix] {
+                NodeKind::Token(t) => Some(t),
+                _ => None,
+            })
+            .filter(move |t| {
+                let fresh = *t != from && !seen.contains(t);
+                if fresh {
+                    seen.push(*t);
+                }
+                fresh
+            })
+    }
+
+    fn best_leg<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        from: TokenId,
+        to: TokenId,
+        amt_in: U256,
+    ) -> Option<(Hop, U256)> {
+        self.direct(world, from, to)
+            .into_iter()
+            .map(|h| {
+                let path = self.engine.simulate_chained(world, &[h], amt_in);
+                (h, path.steps[0].amt_out)
+            })
+            .max_by_key(|(_, out)| *out)
+    }
+
+    fn via<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        from: TokenId,
+        mid: TokenId,
+        to: TokenId,
+        amt_in: U256,
+    ) -> Option<(Vec<Hop>, U256)> {
+        let (first, mid_amt) = self.best_leg(world, from, mid, amt_in)?;
+        let (second, _) = self.best_leg(world, mid, to, mid_amt)?;
+        if first.pool == second.pool {
+            return None;
+        }
+        let plan = vec![first, second];
+        let out = self
+            .engine
+            .simulate_chained(world, &plan, amt_in)
+            .steps
+            .last()
+            .map(|s| s.amt_out)
+            .unwrap_or_default();
+        Some((plan, out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, reserves};
+    use crate::world::World;
+    use std::collections::HashMap;
+
+    #[test]
+    fn generates_direct_connector_and_top_intermediate_routes() {
+        // 1 -> 2 directly, via connector 9, and via intermediates 3 (deep) and 4 (thin).
+        let mut pools = HashMap::new();
+        let mut graph = AMMGraph::new();
+        let mut world = World::default();
+        for (id, t0, t1, r) in [
+            (1, 1, 2, 1_000),
+            (2, 1, 9, 100_000),
+            (3, 9, 2, 100_000),
+            (4, 1, 3, 1_000_000),
+            (5, 3, 2, 1_000_000),
+            (6, 1, 4, 5_000),
+            (7, 4, 2, 5_000),
+        ] {
+            pools.insert(PoolId(id), Cp::new(id, t0, t1));
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(t0), TokenId(t1));
+            world.pool_states.insert(PoolId(id), reserves(r, r));
+        }
+        let engine = Engine::new(&pools);
+        let cands = Candidates::new(&engine, &graph).with_config(HeuristicConfig {
+            connectors: vec![TokenId(9)],
+            top_intermediates: 1,
+        });
+
+        let amt = U256::from(500u64);
+        let plans = cands.generate(&world, TokenId(1), TokenId(2), amt);
+        let pools_of = |plan: &Vec<Hop>| plan.iter().map(|h| h.pool.0).collect::<Vec<_>>();
+        assert_eq!(
+            plans.iter().map(pools_of).collect::<Vec<_>>(),
+            vec![vec![1], vec![2, 3], vec![4, 5]]
+        );
+
+        let best = cands
+            .best_route(&world, TokenId(1), TokenId(2), amt)
+            .unwrap();
+        assert_eq!(best.steps[0].pool, PoolId(4));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{
+    engine::{Engine, Hop},
+    ids::PoolId,
+    pool::Pool,
+    prices::spot_rate,
+    world::StateView,
+};
+use alloy_primitives::U256;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PricePoint {
+    pub block: u64,
+    pub timestamp: u64,
+    pub price: f64,
+}
+
+#[derive(Clone, Debug)]
+struct Tracked {
+    hop: Hop,
+    probe_in: U256,
+    points: VecDeque<PricePoint>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PriceHistory {
+    capacity: usize,
+    pools: HashMap<PoolId, Tracked>,
+}
+
+impl PriceHistory {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "history capacity must be positive");
+        Self {
+            capacity,
+            pools: HashMap::new(),
+        }
+    }
+
+    pub fn track(&mut self, hop: Hop, probe_in: U256) {
+        self.pools.entry(hop.pool).or_insert_with(|| Tracked {
+            hop,
+            probe_in,
+            points: VecDeque::new(),
+        });
+    }
+
+    pub fn untrack(&mut self, pid: PoolId) {
+        self.pools.remove(&pid);
+    }
+
+    pub fn tracked(&self) -> impl Iterator<Item = PoolId> + '_ {
+        self.pools.keys().copied()
+    }
+
+    pub fn record(&mut self, pid: PoolId, point: PricePoint) {
+        let Some(t) = self.pools.get_mut(&pid) else {
+            return;
+        };
+        if t.points
+            .back()
+            .is_some_and(|last| last.block >= point.block)
+        {
+            t.points.retain(|p| p.block < point.block);
+        }
+        if t.points.len() == self.capacity {
+            t.points.pop_front();
+        }
+        t.points.push_back(point);
+    }
+
+    pub fn observe<P: Pool, V: StateView<P::State>>(&mut self, engine: &Engine<'_, P>, world: &V) {
+        let ctx = world.block();
+        let samples: Vec<_> = self
+            .pools
+            .iter()
+            .filter(|(pid, _)| world.pool_state(**pid).is_some())
+            .filter_map(|(&pid, t)| Some((pid, spot_rate(engine, world, t.hop, t.probe_in)?)))
+            .collect();
+        for (pid, price) in samples {
+            self.record(
+                pid,
+                PricePoint {
+                    block: ctx.number,
+                    timestamp: ctx.timestamp,
+                    price,
+                },
+            );
+        }
+    }
+
+    pub fn points(&self, pid: PoolId) -> impl Iterator<Item = &PricePoint> + '_ {
+        self.pools
+            .get(&pid)
+            .into_iter()
+            .flat_map(|t| t.points.iter())
+    }
+
+    pub fn latest(&self, pid: PoolId) -> Option<PricePoint> {
+        self.pools.get(&pid)?.points.back().copied()
+    }
+
+    pub fn twap(&self, pid: PoolId, from_block: u64, to_block: u64) -> Option<f64> {
+        if to_block <= from_block {
+            return None;
+        }
+        let points = &self.pools.get(&pid)?.points;
+        let mut weighted = 0.0;
+        let mut total = 0u64;
+        for (i, p) in points.iter().enumerate() {
+            let start = p.block.max(from_block);
+            let end = points
+                .get(i + 1)
+                .map_or(to_block, |n| n.block)
+                .min(to_block);
+            if end <= start {
+                continue;
+            }
+            weighted += p.price * (end - start) as f64;
+            total += end - start;
+        }
+        (total > 0).then(|| weighted / total as f64)
+    }
+
+    pub fn volatility(&self, pid: PoolId, last_n: usize) -> Option<f64> {
+        let points = &self.pools.get(&pid)?.points;
+        let skip = points.len().saturating_sub(last_n + 1);
+        let returns: Vec<f64> = points
+            .iter()
+            .skip(skip)
+            .zip(points.iter().skip(skip + 1))
+            .filter(|(a, b)| a.price > 0.0 && b.price > 0.0)
+            .map(|(a, b)| (b.price / a.price).ln())
+            .collect();
+        if returns.len() < 2 {
+            return None;
+        }
+        let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+        let var =
+            returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+        Some(var.sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::{SwapDirection, TokenId};
+
+    fn hop() -> Hop {
+        Hop::new(
+            PoolId(1),
+            SwapDirection::new(TokenId(1), TokenId(2)).unwrap(),
+        )
+    }
+
+    fn point(block: u64, price: f64) -> PricePoint {
+        PricePoint {
+            block,
+            timestamp: block * 12,
+            price,
+        }
+    }
+
+    #[test]
+    fn twap_weights_by_block_duration() {
+        let mut h = PriceHistory::new(8);
+        h.track(hop(), U256::from(1u64));
+        h.record(PoolId(1), point(10, 1.0));
+        h.record(PoolId(1), point(13, 2.0));
+        h.record(PoolId(1), point(14, 4.0));
+
+        assert_eq!(h.twap(PoolId(1), 10, 14), Some((3.0 + 2.0) / 4.0));
+        assert_eq!(h.twap(PoolId(1), 12, 16), Some((1.0 + 2.0 + 8.0) / 4.0));
+        assert_eq!(h.twap(PoolId(1), 0, 10), None);
+        assert_eq!(h.twap(PoolId(2), 10, 14), None);
+    }
+
+    #[test]
+    fn ring_buffer_and_reorged_points() {
+        let mut h = PriceHistory::new(3);
+        h.track(hop(), U256::from(1u64));
+        for b in 0..5 {
+            h.record(PoolId(1), point(b, 1.0 + b as f64));
+        }
+        assert_eq!(h.points(PoolId(1)).count(), 3);
+        assert_eq!(h.points(PoolId(1)).next().unwrap().block, 2);
+
+        h.record(PoolId(1), point(3, 9.0));
+        assert_eq!(h.latest(PoolId(1)), Some(point(3, 9.0)));
+        assert_eq!(h.points(PoolId(1)).count(), 2);
+    }
+
+    #[test]
+    fn volatility_is_zero_for_constant_growth() {
+        let mut h = PriceHistory::new(16);
+        h.track(hop(), U256::from(1u64));
+        for b in 0..6 {
+            h.record(PoolId(1), point(b, 1.1f64.powi(b as i32)));
+        }
+        assert!(h.volatility(PoolId(1), 10).unwrap() < 1e-12);
+
+        h.record(PoolId(1), point(6, 0.5));
+        assert!(h.volatility(PoolId(1), 3).unwrap() > 0.1);
+    }
+}
@@ -1,5 +1,305 @@
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-pub struct TokenId(pub u16);
+use alloy_primitives::{Address, keccak256};
+use std::collections::HashSet;
+use std::fmt;
+use std::num::{NonZeroU32, NonZeroU64, ParseIntError};
+use std::str::FromStr;
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-pub struct PoolId(pub u32);
+macro_rules! id_type {
+    ($name:ident, $nz:ident, $raw:ty, $nzraw:ty) => {
+        #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(
+            feature = "rkyv",
+            derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+            rkyv(derive(PartialEq, Eq, Hash))
+        )]
+        pub struct $name(pub $raw);
+
+        #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub struct $nz(pub $nzraw);
+
+        impl $nz {
+            pub fn new(id: $name) -> Option<Self> {
+                <$nzraw>::new(id.0).map(Self)
+            }
+
+            pub fn get(self) -> $name {
+                $name(self.0.get())
+            }
+        }
+
+        impl From<$nz> for $name {
+            fn from(id: $nz) -> Self {
+                id.get()
+            }
+        }
+
+        impl TryFrom<$name> for $nz {
+            type Error = $name;
+
+            fn try_from(id: $name) -> Result<Self, Self::Error> {
+                Self::new(id).ok_or(id)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl fmt::Display for $nz {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseIntError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                s.parse().map(Self)
+            }
+        }
+
+        impl FromStr for $nz {
+            type Err = ParseIntError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                s.parse().map(Self)
+            }
+        }
+    };
+}
+
+id_type!(TokenId, NonZeroTokenId, u32, NonZeroU32);
+id_type!(PoolId, NonZeroPoolId, u64, NonZeroU64);
+id_type!(AccountId, NonZeroAccountId, u32, NonZeroU32);
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    rkyv(derive(PartialEq, Eq, Hash))
+)]
+pub struct ChainId(pub u64);
+
+impl ChainId {
+    pub const MAINNET: ChainId = ChainId(1);
+}
+
+impl fmt::Display for ChainId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for ChainId {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseGlobalIdError {
+    #[error("expected `<chain>:<id>`")]
+    MissingChain,
+    #[error(transparent)]
+    Int(ParseIntError),
+}
+
+macro_rules! global_id_type {
+    ($name:ident, $field:ident, $local:ident) => {
+        #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(
+            feature = "rkyv",
+            derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+            rkyv(derive(PartialEq, Eq, Hash))
+        )]
+        pub struct $name {
+            pub chain: ChainId,
+            pub $field: $local,
+        }
+
+        impl $name {
+            pub fn new(chain: ChainId, $field: $local) -> Self {
+                Self { chain, $field }
+            }
+
+            pub fn local_on(self, chain: ChainId) -> Option<$local> {
+                (self.chain == chain).then_some(self.$field)
+            }
+        }
+
+        impl $local {
+            pub fn on(self, chain: ChainId) -> $name {
+                $name::new(chain, self)
+            }
+        }
+
+        impl From<$name> for (ChainId, $local) {
+            fn from(id: $name) -> Self {
+                (id.chain, id.$field)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}:{}", self.chain, self.$field)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseGlobalIdError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let (chain, id) = s.split_once(':').ok_or(ParseGlobalIdError::MissingChain)?;
+                Ok(Self {
+                    chain: chain.parse().map_err(ParseGlobalIdError::Int)?,
+                    $field: id.parse().map_err(ParseGlobalIdError::Int)?,
+                })
+            }
+        }
+    };
+}
+
+global_id_type!(GlobalTokenId, token, TokenId);
+global_id_type!(GlobalPoolId, pool, PoolId);
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    rkyv(derive(PartialEq, Eq, Hash))
+)]
+pub struct SwapDirection {
+    pub from: TokenId,
+    pub to: TokenId,
+}
+
+impl SwapDirection {
+    pub fn new(from: TokenId, to: TokenId) -> Option<Self> {
+        (from != to).then_some(Self { from, to })
+    }
+
+    pub fn reverse(self) -> Self {
+        Self {
+            from: self.to,
+            to: self.from,
+        }
+    }
+
+    pub fn is_valid(self) -> bool {
+        self.from != self.to
+    }
+}
+
+impl fmt::Display for SwapDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}->{}", self.from, self.to)
+    }
+}
+
+/// A curated set of tokens, e.g. the majors and stables routes may pass
+/// through.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TokenSet(pub HashSet<TokenId>);
+
+impl TokenSet {
+    pub fn contains(&self, t: TokenId) -> bool {
+        self.0.contains(&t)
+    }
+}
+
+impl FromIterator<TokenId> for TokenSet {
+    fn from_iter<I: IntoIterator<Item = TokenId>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+fn address_hash(chain: ChainId, address: Address, nonce: u32) -> [u8; 32] {
+    let mut buf = [0u8; 32];
+    buf[..8].copy_from_slice(&chain.0.to_be_bytes());
+    buf[8..28].copy_from_slice(address.as_slice());
+    buf[28..].copy_from_slice(&nonce.to_be_bytes());
+    keccak256(buf).0
+}
+
+/// Derives a token id from `keccak256(chain || address || nonce)`. Callers
+/// start at nonce 0 and bump it only when the id is already taken by a
+/// different address, so collided ids depend on insertion order.
+pub fn stable_token_id(chain: ChainId, address: Address, nonce: u32) -> TokenId {
+    let h = address_hash(chain, address, nonce);
+    TokenId(u32::from_be_bytes(h[..4].try_into().unwrap()))
+}
+
+/// Pool counterpart of [`stable_token_id`], using the same collision strategy.
+pub fn stable_pool_id(chain: ChainId, address: Address, nonce: u32) -> PoolId {
+    let h = address_hash(chain, address, nonce);
+    PoolId(u64::from_be_bytes(h[..8].try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_round_trip_through_strings() {
+        let t = TokenId(70_000);
+        assert_eq!(t.to_string(), "70000");
+        assert_eq!("70000".parse::<TokenId>().unwrap(), t);
+
+        let p = PoolId(u64::MAX);
+        assert_eq!(p.to_string().parse::<PoolId>().unwrap(), p);
+
+        assert!("-1".parse::<TokenId>().is_err());
+        assert!("0".parse::<NonZeroPoolId>().is_err());
+        assert_eq!("5".parse::<NonZeroPoolId>().unwrap().get(), PoolId(5));
+    }
+
+    #[test]
+    fn swap_direction_rejects_self_swaps() {
+        assert!(SwapDirection::new(TokenId(1), TokenId(1)).is_none());
+        let d = SwapDirection::new(TokenId(1), TokenId(2)).unwrap();
+        assert_eq!(d.reverse().from, TokenId(2));
+        assert_eq!(d.reverse().reverse(), d);
+        assert_eq!(d.to_string(), "1->2");
+    }
+
+    #[test]
+    fn global_ids_are_chain_scoped() {
+        let base = ChainId(8453);
+        let g = TokenId(42).on(base);
+        assert_eq!(g.to_string(), "8453:42");
+        assert_eq!("8453:42".parse::<GlobalTokenId>().unwrap(), g);
+        assert_eq!(g.local_on(base), Some(TokenId(42)));
+        assert_eq!(g.local_on(ChainId::MAINNET), None);
+        assert_ne!(g, TokenId(42).on(ChainId::MAINNET));
+
+        assert_eq!(
+            "42".parse::<GlobalPoolId>(),
+            Err(ParseGlobalIdError::MissingChain)
+        );
+        assert!(matches!(
+            "1:x".parse::<GlobalPoolId>(),
+            Err(ParseGlobalIdError::Int(_))
+        ));
+    }
+
+    #[test]
+    fn nonzero_ids_keep_option_niche() {
+        use std::mem::size_of;
+        assert_eq!(size_of::<Option<NonZeroTokenId>>(), size_of::<TokenId>());
+        assert_eq!(size_of::<Option<NonZeroPoolId>>(), size_of::<PoolId>());
+
+        assert_eq!(NonZeroTokenId::try_from(TokenId(0)), Err(TokenId(0)));
+        let nz = NonZeroTokenId::try_from(TokenId(3)).unwrap();
+        assert_eq!(TokenId::from(nz), TokenId(3));
+    }
+}
@@ -0,0 +1,252 @@
+//! Realized profit and loss of executed routes, per token and in the
+//! oracle's numeraire.
+
+use crate::{
+    engine::{Execution, Path},
+    export::field,
+    ids::TokenId,
+    prices::PriceOracle,
+    registry::Registry,
+};
+use alloy_primitives::{B256, I256, U256};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+#[derive(Clone, Debug)]
+pub struct LedgerEntry {
+    pub block: u64,
+    /// Hash of the confirmed transaction; `None` for local executions.
+    pub tx: Option<B256>,
+    pub path: Path,
+    /// Gas paid, in the native token.
+    pub gas_paid: U256,
+    /// Paid to the builder on top of gas, in the native token.
+    pub bribe: U256,
+}
+
+#[derive(Clone, Debug)]
+pub struct Ledger {
+    /// Token gas and bribes are booked against, e.g. WETH.
+    pub native: TokenId,
+    pub entries: Vec<LedgerEntry>,
+    balances: HashMap<TokenId, I256>,
+}
+
+impl Ledger {
+    pub fn new(native: TokenId) -> Self {
+        Self {
+            native,
+            entries: Vec::new(),
+            balances: HashMap::new(),
+        }
+    }
+
+    /// Books `entry`: its input is debited, its output credited, and fees
+    /// debited from the native token. Intermediate tokens net to zero.
+    pub fn record(&mut self, entry: LedgerEntry) {
+        if let (Some(first), Some(last)) = (entry.path.steps.first(), entry.path.steps.last()) {
+            self.book(first.from, first.amt_in, false);
+            self.book(last.to, last.amt_out, true);
+        }
+        self.book(
+            self.native,
+            entry.gas_paid.saturating_add(entry.bribe),
+            false,
+        );
+        self.entries.push(entry);
+    }
+
+    /// Books a committed [`Engine::execute`](crate::engine::Engine::execute);
+    /// returns `false` and books nothing if it did not commit.
+    pub fn record_execution(&mut self, block: u64, exec: &Execution, gas_paid: U256) -> bool {
+        if !exec.committed() {
+            return false;
+        }
+        self.record(LedgerEntry {
+            block,
+            tx: None,
+            path: exec.path.clone(),
+            gas_paid,
+            bribe: U256::ZERO,
+        });
+        true
+    }
+
+    /// Books a mined transaction from its receipt's gas figures.
+    pub fn record_receipt(
+        &mut self,
+        block: u64,
+        tx: B256,
+        path: Path,
+        gas_used: u64,
+        effective_gas_price: u128,
+    ) {
+        self.record(LedgerEntry {
+            block,
+            tx: Some(tx),
+            path,
+            gas_paid: U256::from(gas_used) * U256::from(effective_gas_price),
+            bribe: U256::ZERO,
+        });
+    }
+
+    fn book(&mut self, t: TokenId, amt: U256, credit: bool) {
+        let amt = I256::try_from(amt).unwrap_or(I256::MAX);
+        let bal = self.balances.entry(t).or_default();
+        *bal = if credit {
+            bal.saturating_add(amt)
+        } else {
+            bal.saturating_sub(amt)
+        };
+    }
+
+    pub fn pnl(&self, t: TokenId) -> I256 {
+        self.balances.get(&t).copied().unwrap_or_default()
+    }
+
+    /// Net token balances, sorted by token.
+    pub fn balances(&self) -> BTreeMap<TokenId, I256> {
+        self.balances.iter().map(|(&t, &b)| (t, b)).collect()
+    }
+
+    /// Total gas and bribes paid.
+    pub fn fees(&self) -> U256 {
+        self.entries.iter().fold(U256::ZERO, |acc, e| {
+            acc.saturating_add(e.gas_paid).saturating_add(e.bribe)
+        })
+    }
+
+    /// Realized PnL in `oracle`'s numeraire at `block`, or `None` if a
+    /// token with a non-zero balance has no fresh price.
+    pub fn value(&self, oracle: &PriceOracle, reg: &Registry, block: u64) -> Option<f64> {
+        self.balances
+            .iter()
+            .filter(|(_, b)| !b.is_zero())
+            .map(|(&t, b)| {
+                let v = oracle.value(reg, t, b.unsigned_abs(), block)?;
+                Some(if b.is_negative() { -v } else { v })
+            })
+            .sum()
+    }
+
+    /// One row per entry: `block,tx,token_in,amount_in,token_out,amount_out,hops,gas_paid,bribe`.
+    /// Tokens are written as registry symbols where known.
+    pub fn write_csv<W: Write>(&self, mut out: W, reg: &Registry) -> io::Result<()> {
+        writeln!(
+            out,
+            "block,tx,token_in,amount_in,token_out,amount_out,hops,gas_paid,bribe"
+        )?;
+        let symbol = |t: TokenId| {
+            reg.token(t)
+                .map_or_else(|| t.to_string(), |m| field(&m.symbol).into_owned())
+        };
+        for e in &self.entries {
+            let (Some(first), Some(last)) = (e.path.steps.first(), e.path.steps.last()) else {
+                continue;
+            };
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{}",
+                e.block,
+                e.tx.map(|h| h.to_string()).unwrap_or_default(),
+                symbol(first.from),
+                first.amt_in,
+                symbol(last.to),
+                last.amt_out,
+                e.path.steps.len(),
+                e.gas_paid,
+                e.bribe,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{ApprovalPolicy, Engine};
+    use crate::graph::AMMGraph;
+    use crate::ids::{AccountId, PoolId};
+    use crate::prices::PriceConfig;
+    use crate::registry::TokenMeta;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::World;
+    use alloy_primitives::Address;
+
+    #[test]
+    fn books_executions_and_receipts_and_exports_csv() {
+        let (weth, usdc) = (TokenId(1), TokenId(2));
+        let mut reg = Registry::default();
+        for (t, symbol) in [(weth, "WETH"), (usdc, "USDC")] {
+            reg.upsert_token(
+                t,
+                TokenMeta {
+                    address: Address::repeat_byte(t.0 as u8),
+                    symbol: symbol.into(),
+                    decimals: 0,
+                },
+            );
+        }
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2))]);
+        let mut graph = AMMGraph::new();
+        graph.connect_bidirectional_pair(PoolId(1), weth, usdc);
+        let mut world = World::default();
+        world.set_pool_state(PoolId(1), reserves(1_000_000, 2_000_000_000));
+        let mut engine = Engine::new(&pools);
+        engine.approvals = ApprovalPolicy::AssumeInfinite;
+
+        let mut ledger = Ledger::new(weth);
+        let owner = AccountId(1);
+        world.credit(owner, weth, U256::from(1_000u64));
+        let exec = engine.execute(
+            &mut world,
+            owner,
+            owner,
+            &[hop(1, 1, 2)],
+            U256::from(1_000u64),
+        );
+        assert!(ledger.record_execution(1, &exec, U256::from(3u64)));
+        let bought = exec.path.steps[0].amt_out;
+
+        let back = engine.simulate_chained(&world, &[hop(1, 2, 1)], bought);
+        ledger.record_receipt(2, B256::repeat_byte(0xab), back.clone(), 21_000, 1);
+
+        let weth_pnl = I256::try_from(back.steps[0].amt_out).unwrap()
+            - I256::try_from(1_000u64 + 3 + 21_000).unwrap();
+        assert_eq!(ledger.pnl(weth), weth_pnl);
+        assert!(ledger.pnl(usdc).is_zero());
+        assert_eq!(ledger.fees(), U256::from(21_003u64));
+
+        let mut oracle = PriceOracle::new(usdc, PriceConfig::default());
+        oracle.refresh(&engine, &graph, &reg, &world);
+        let value = ledger.value(&oracle, &reg, 0).unwrap();
+        let weth_price = oracle.price(weth, 0).unwrap();
+        assert!((value - weth_pnl.as_i64() as f64 * weth_price).abs() < 1e-6);
+
+        let mut csv = Vec::new();
+        ledger.write_csv(&mut csv, &reg).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let rows: Vec<_> = csv.lines().collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1], format!("1,,WETH,1000,USDC,{bought},1,3,0"));
+        assert!(rows[2].starts_with(&format!("2,{},USDC,", B256::repeat_byte(0xab))));
+
+        // Symbols are free text from token contracts.
+        reg.upsert_token(
+            weth,
+            TokenMeta {
+                address: Address::repeat_byte(1),
+                symbol: "W\"ETH, v2".into(),
+                decimals: 0,
+            },
+        );
+        let mut csv = Vec::new();
+        ledger.write_csv(&mut csv, &reg).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv.lines().nth(1).unwrap(),
+            format!("1,,\"W\"\"ETH, v2\",1000,USDC,{bought},1,3,0")
+        );
+    }
+}
@@ -1,13 +1,170 @@
+pub mod activity;
+#[cfg(feature = "anvil")]
+pub mod anvil;
+pub mod anytime;
+pub mod arb;
+#[cfg(feature = "rpc")]
+pub mod archive;
+pub mod audit;
+pub mod backtest;
+pub mod builder;
+pub mod bundle;
+pub mod canonical;
+#[cfg(feature = "serde")]
+pub mod checkpoint;
+pub mod compact;
+pub mod compare;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod curve;
+pub mod decode;
 pub mod engine;
+pub mod error;
+pub mod exact_out;
+pub mod exec;
+pub mod explain;
+pub mod export;
+pub mod funding;
+pub mod gas;
 pub mod graph;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod heuristics;
+pub mod history;
 pub mod ids;
+pub mod ledger;
+pub mod lp;
+pub mod memo;
+pub mod memory;
+pub mod migration;
+pub mod num;
+pub mod opportunities;
+#[cfg(feature = "aggregators")]
+pub mod parity;
+pub mod partial;
+pub mod pipeline;
 pub mod pool;
+pub mod pool_analytics;
+pub mod prefetch;
+pub mod prices;
+pub mod provider;
+pub mod ranking;
 pub mod registry;
+pub mod reorg;
+pub mod revalidate;
+pub mod rfq;
+pub mod rng;
+pub mod router;
+pub mod search;
+pub mod sequence;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod shared;
+pub mod sizing;
+pub mod solver;
+pub mod split;
+pub mod stability;
+pub mod stream;
+#[cfg(feature = "rpc")]
+pub mod sync;
+#[cfg(feature = "bench")]
+pub mod synth;
+pub mod telemetry;
+#[cfg(test)]
+mod test_utils;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+pub mod timeline;
+pub mod transfer;
+pub mod trie;
+pub mod univ2;
+#[cfg(feature = "rpc")]
+pub mod v3storage;
+pub mod validation;
+pub mod warmup;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "serde")]
+pub mod wire;
 pub mod world;
 
-pub use engine::{Engine, Path, Step};
+pub use activity::{ActivityTracker, PoolActivity};
+pub use arb::{ArbOpportunity, ScanConfig, Scanner};
+pub use audit::{AuditReport, Inconsistency};
+pub use builder::{RouterBuilder, Wayfinder};
+pub use canonical::RouteKey;
+#[cfg(feature = "serde")]
+pub use checkpoint::{Checkpoint, load_checkpoint};
+pub use compare::{HopDiff, WorldComparison};
+pub use curve::QuoteCurve;
+pub use decode::StateUpdate;
+pub use engine::{
+    Accuracy, ApprovalPolicy, Engine, Execution, Hop, HopConstraint, HopFailure, Path, Step,
+};
+pub use error::{BuildError, EngineError, GraphError, RegistryError, WayfinderError};
+pub use explain::{ExplainedHop, ExplainedPath};
+pub use funding::Funding;
+pub use gas::GasModel;
 pub use graph::{AMMGraph, NodeKind};
-pub use ids::{PoolId, TokenId};
-pub use pool::Pool;
+pub use ids::{
+    AccountId, ChainId, GlobalPoolId, GlobalTokenId, NonZeroAccountId, NonZeroPoolId,
+    NonZeroTokenId, PoolId, SwapDirection, TokenId, TokenSet, stable_pool_id, stable_token_id,
+};
+pub use lp::{LpImpact, LpPool, LpPosition};
+pub use migration::RegistryChange;
+pub use num::{AmountRepr, MathError, Price};
+#[cfg(feature = "sqlite")]
+pub use opportunities::SqliteStore;
+pub use opportunities::{MemoryStore, OpportunityRecord, OpportunityStore, Outcome};
+pub use partial::RouteResult;
+pub use pool::{DepthReport, Pool};
+pub use pool_analytics::{PoolAnalytics, PoolEstimate};
+pub use prefetch::Prefetcher;
+pub use provider::StateProvider;
+pub use ranking::{RankingPolicy, WeightedScore};
 pub use registry::{PoolKind, PoolMeta, Registry, TokenMeta};
-pub use world::World;
+pub use revalidate::Revalidation;
+pub use rfq::{FirmQuote, Quoter};
+pub use router::Router;
+pub use search::PathSearch;
+pub use shared::{SharedWorld, WorldWriter};
+pub use sizing::SizeWindow;
+pub use solver::{Order, Solution, SolveError, Solver, Split};
+pub use split::{SplitRoute, SplitSimulation};
+pub use stream::PathResult;
+pub use timeline::{Timeline, WorldView};
+pub use transfer::{TransferModel, TransferModels};
+pub use univ2::{UniV2Pool, UniV2State};
+pub use world::{
+    BlockContext, HoldingDelta, StateView, World, WorldDelta, WorldDiff, WorldOverlay, WorldStats,
+};
+
+// Market data and the engine are meant to be shared between threads, e.g. one
+// world behind an `Arc` serving many tokio tasks. Keep it that way.
+const _: () = {
+    const fn send_sync<T: Send + Sync>() {}
+    #[allow(dead_code)]
+    const fn generic<'a, S: Send + Sync, P: Pool + Sync + 'a>()
+    where
+        P::State: Send,
+    {
+        send_sync::<World<S>>();
+        send_sync::<Engine<'a, P>>();
+    }
+    send_sync::<AMMGraph>();
+    send_sync::<Registry>();
+    send_sync::<World<UniV2State>>();
+    send_sync::<WorldOverlay<'static, UniV2State>>();
+    send_sync::<Timeline<UniV2State>>();
+    send_sync::<Engine<'static, UniV2Pool>>();
+    send_sync::<Scanner<'static, UniV2Pool>>();
+    send_sync::<Solver<'static, UniV2Pool>>();
+    send_sync::<Router<UniV2Pool>>();
+    send_sync::<SharedWorld<UniV2State>>();
+    send_sync::<WorldWriter<UniV2State>>();
+    send_sync::<memo::SwapMemo<UniV2State>>();
+    send_sync::<trie::PlanTrie>();
+    send_sync::<Path>();
+    send_sync::<WayfinderError>();
+    send_sync::<&'static dyn Quoter>();
+};
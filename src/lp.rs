@@ -0,0 +1,170 @@
+//! What a simulated path does to liquidity providers: the fees it pays into
+//! their positions and how far the rebalanced position falls behind simply
+//! holding the tokens it started with.
+
+use crate::{
+    engine::{Engine, Hop},
+    ids::{PoolId, SwapDirection, TokenId},
+    num,
+    pool::Pool,
+    univ2::{UniV2Pool, UniV2State},
+    world::StateView,
+};
+use alloy_primitives::U256;
+
+/// A holding of `shares` out of `total_shares` of a pool's liquidity, e.g.
+/// LP tokens against the pair's total supply.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LpPosition {
+    pub pool: PoolId,
+    pub shares: U256,
+    pub total_shares: U256,
+}
+
+/// Pools whose liquidity can be valued as a share of their state.
+pub trait LpPool: Pool {
+    /// What `pos` redeems for in state `st`, token0 first.
+    fn redeemable(&self, st: &Self::State, pos: &LpPosition) -> Option<[(TokenId, U256); 2]>;
+
+    /// The part of `amt_in` swapped in `dir` that accrues to LPs.
+    fn lp_fee(&self, dir: SwapDirection, amt_in: U256) -> U256;
+}
+
+impl LpPool for UniV2Pool {
+    fn redeemable(&self, st: &UniV2State, pos: &LpPosition) -> Option<[(TokenId, U256); 2]> {
+        let share = |r| num::mul_div(r, pos.shares, pos.total_shares).ok();
+        Some([
+            (self.token0, share(st.reserve0)?),
+            (self.token1, share(st.reserve1)?),
+        ])
+    }
+
+    fn lp_fee(&self, _dir: SwapDirection, amt_in: U256) -> U256 {
+        num::mul_div(amt_in, U256::from(self.fee_bps), U256::from(10_000u64)).unwrap_or_default()
+    }
+}
+
+/// Values are in raw units of the position's token1, at the pool's price
+/// after the path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LpImpact {
+    pub position: LpPosition,
+    pub before: [(TokenId, U256); 2],
+    pub after: [(TokenId, U256); 2],
+    /// The position's share of the fees the path paid, token0 first.
+    pub fees: [U256; 2],
+    pub value: f64,
+    /// Value of still holding `before`.
+    pub hold_value: f64,
+    pub fee_value: f64,
+    /// `value - fee_value - hold_value`: the impermanent loss, never
+    /// positive on a constant-product pool.
+    pub divergence: f64,
+}
+
+impl<P: LpPool> Engine<'_, P> {
+    /// How swapping `first_in` along `plan` changes each of `positions`.
+    /// Positions in pools without an implementation or state, or whose
+    /// token0 side is left empty, are left out.
+    pub fn lp_impact<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plan: &[Hop],
+        first_in: U256,
+        positions: &[LpPosition],
+    ) -> Vec<LpImpact> {
+        let (path, diff) = self.simulate_diff(world, plan, first_in);
+        positions
+            .iter()
+            .filter_map(|pos| {
+                let pool = self.pools.get(&pos.pool)?;
+                let st = world.pool_state(pos.pool)?;
+                let before = pool.redeemable(st, pos)?;
+                let after = pool.redeemable(diff.pool_states.get(&pos.pool).unwrap_or(st), pos)?;
+
+                let mut fees = [U256::ZERO; 2];
+                for step in path.steps.iter().filter(|s| s.pool == pos.pool) {
+                    let paid = pool.lp_fee(step.direction(), step.amt_in);
+                    let ours = num::mul_div(paid, pos.shares, pos.total_shares).unwrap_or_default();
+                    let i = usize::from(step.from == before[1].0);
+                    fees[i] = fees[i].saturating_add(ours);
+                }
+
+                if after[0].1.is_zero() {
+                    return None;
+                }
+                let price = f64::from(after[1].1) / f64::from(after[0].1);
+                let worth = |a0: U256, a1: U256| f64::from(a0) * price + f64::from(a1);
+                let value = worth(after[0].1, after[1].1);
+                let hold_value = worth(before[0].1, before[1].1);
+                let fee_value = worth(fees[0], fees[1]);
+                Some(LpImpact {
+                    position: *pos,
+                    before,
+                    after,
+                    fees,
+                    value,
+                    hold_value,
+                    fee_value,
+                    divergence: value - fee_value - hold_value,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::World;
+    use std::collections::HashMap;
+
+    #[test]
+    fn swaps_pay_fees_to_positions_and_leave_them_diverged() {
+        let pools = HashMap::from([
+            (PoolId(1), UniV2Pool::new(PoolId(1), TokenId(1), TokenId(2))),
+            (PoolId(2), UniV2Pool::new(PoolId(2), TokenId(2), TokenId(3))),
+            (PoolId(3), UniV2Pool::new(PoolId(3), TokenId(3), TokenId(1))),
+        ]);
+        let e18 = U256::from(10u64).pow(U256::from(18u64));
+        let mut world = World::default();
+        world.set_pool_state(PoolId(1), UniV2State::new(e18, e18));
+        world.set_pool_state(PoolId(2), UniV2State::new(e18, e18));
+        // Drained of token0, so it has no price to value the position at.
+        world.set_pool_state(PoolId(3), UniV2State::new(U256::ZERO, e18));
+        let tenth = |pool| LpPosition {
+            pool: PoolId(pool),
+            shares: U256::from(1u64),
+            total_shares: U256::from(10u64),
+        };
+        let plan = [Hop::new(
+            PoolId(1),
+            SwapDirection::new(TokenId(1), TokenId(2)).unwrap(),
+        )];
+
+        let engine = Engine::new(&pools);
+        let amt = e18 / U256::from(100u64);
+        let impact = engine.lp_impact(
+            &world,
+            &plan,
+            amt,
+            &[tenth(1), tenth(2), tenth(3), tenth(9)],
+        );
+        assert_eq!(impact.len(), 2);
+
+        let hit = &impact[0];
+        assert_eq!(hit.before[0], (TokenId(1), e18 / U256::from(10u64)));
+        assert_eq!(hit.after[0].1, (e18 + amt) / U256::from(10u64));
+        // 0.3% of the input, a tenth of it ours.
+        assert_eq!(
+            hit.fees,
+            [amt * U256::from(3u64) / U256::from(10_000u64), U256::ZERO]
+        );
+        assert!(hit.fee_value > 0.0);
+        assert!(hit.divergence < 0.0);
+
+        let idle = &impact[1];
+        assert_eq!(idle.before, idle.after);
+        assert_eq!((idle.fee_value, idle.divergence), (0.0, 0.0));
+    }
+}
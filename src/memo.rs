@@ -0,0 +1,214 @@
+use crate::ids::{PoolId, SwapDirection};
+use crate::telemetry;
+use crate::world::BlockContext;
+use alloy_primitives::U256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MemoKey {
+    pub pool: PoolId,
+    pub version: u64,
+    /// Pools may price off the block, which moves without bumping versions.
+    pub block: BlockContext,
+    pub dir: SwapDirection,
+    pub bucket: U256,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+/// Cache of single-hop swap results for pool states the world can version
+/// (see [`StateView::pool_version`](crate::world::StateView::pool_version)).
+///
+/// With `precision_bits` set, inputs are truncated to that many significant
+/// bits before keying, so nearby amounts share an entry and a hit returns the
+/// output of whichever amount populated it. Leave it `None` for exact results.
+pub struct SwapMemo<S> {
+    entries: Mutex<HashMap<MemoKey, (U256, S)>>,
+    pub precision_bits: Option<u32>,
+    pub capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<S> Default for SwapMemo<S> {
+    fn default() -> Self {
+        Self::new(1 << 16)
+    }
+}
+
+impl<S> SwapMemo<S> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            precision_bits: None,
+            capacity,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn with_precision_bits(mut self, bits: u32) -> Self {
+        self.precision_bits = Some(bits.max(1));
+        self
+    }
+
+    pub fn key(
+        &self,
+        pool: PoolId,
+        version: u64,
+        block: BlockContext,
+        dir: SwapDirection,
+        amt_in: U256,
+    ) -> MemoKey {
+        let bucket = match self.precision_bits {
+            Some(bits) => {
+                let drop = amt_in.bit_len().saturating_sub(bits as usize);
+                (amt_in >> drop) << drop
+            }
+            None => amt_in,
+        };
+        MemoKey {
+            pool,
+            version,
+            block,
+            dir,
+            bucket,
+        }
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub(crate) fn with_entries<R>(
+        &self,
+        f: impl FnOnce(&mut HashMap<MemoKey, (U256, S)>) -> R,
+    ) -> R {
+        f(&mut self.entries.lock().unwrap())
+    }
+
+    pub fn stats(&self) -> MemoStats {
+        MemoStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().len(),
+        }
+    }
+}
+
+impl<S: Clone> SwapMemo<S> {
+    pub fn get(&self, key: &MemoKey) -> Option<(U256, S)> {
+        let hit = self.entries.lock().unwrap().get(key).cloned();
+        telemetry::memo_lookup(hit.is_some());
+        let counter = if hit.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        hit
+    }
+
+    /// Stores a result, dropping everything first once `capacity` is hit;
+    /// stale versions are never looked up again, so wholesale eviction is
+    /// as good as anything finer.
+    pub fn insert(&self, key: MemoKey, amt_out: U256, st: S) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.capacity {
+            entries.clear();
+        }
+        entries.insert(key, (amt_out, st));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::ids::TokenId;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::{World, WorldDiff};
+
+    fn setup() -> (HashMap<PoolId, Cp>, World<(U256, U256)>) {
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2)), (PoolId(2), Cp::new(2, 2, 3))]);
+        let mut world = World::default();
+        world.set_pool_state(PoolId(1), reserves(1_000_000, 1_000_000));
+        world.set_pool_state(PoolId(2), reserves(1_000_000, 1_000_000));
+        (pools, world)
+    }
+
+    #[test]
+    fn shared_prefixes_hit_and_state_changes_invalidate() {
+        let (pools, mut world) = setup();
+        let memo = SwapMemo::default();
+        let plain = Engine::new(&pools);
+        let engine = Engine::new(&pools).with_memo(&memo);
+        let plan = [hop(1, 1, 2), hop(2, 2, 3)];
+        let amt = U256::from(1_000u64);
+
+        let first = engine.simulate_chained(&world, &plan, amt);
+        let again = engine.simulate_chained(&world, &plan, amt);
+        assert_eq!(first.steps[1].amt_out, again.steps[1].amt_out);
+        assert_eq!(
+            first.steps[1].amt_out,
+            plain.simulate_chained(&world, &plan, amt).steps[1].amt_out
+        );
+        assert_eq!(memo.stats().hits, 2);
+
+        let mut diff = WorldDiff::default();
+        diff.set_pool_state(PoolId(1), reserves(2_000_000, 1_000_000));
+        world.apply(diff);
+        let moved = engine.simulate_chained(&world, &plan, amt);
+        assert!(moved.steps[0].amt_out < first.steps[0].amt_out);
+        assert_eq!(
+            moved.steps[1].amt_out,
+            plain.simulate_chained(&world, &plan, amt).steps[1].amt_out
+        );
+        assert_eq!(memo.stats().hits, 2);
+
+        // Unversioned overlay states are simulated, not served from cache.
+        let mut pending = WorldDiff::default();
+        pending.set_pool_state(PoolId(2), reserves(1, 1));
+        let overlay = world.with_overlay(pending);
+        let shadowed = engine.simulate_chained(&overlay, &plan, amt);
+        assert_eq!(shadowed.steps[1].amt_out, U256::ZERO);
+
+        // Worlds that diverge from a common clone never share a version.
+        let (_, mut a) = setup();
+        let mut b = a.clone();
+        a.set_pool_state(PoolId(1), reserves(10_000, 90_000));
+        b.set_pool_state(PoolId(1), reserves(50_000, 10_000));
+        for w in [&a, &b, &a] {
+            assert_eq!(
+                engine.simulate_chained(w, &plan, amt).steps[0].amt_out,
+                plain.simulate_chained(w, &plan, amt).steps[0].amt_out
+            );
+        }
+    }
+
+    #[test]
+    fn precision_bits_bucket_nearby_amounts() {
+        let memo: SwapMemo<()> = SwapMemo::default().with_precision_bits(8);
+        let dir = SwapDirection::new(TokenId(1), TokenId(2)).unwrap();
+        let k = |amt: u64| memo.key(PoolId(1), 1, BlockContext::default(), dir, U256::from(amt));
+        assert_eq!(k(1_000_000), k(1_000_100));
+        assert_ne!(k(1_000_000), k(1_100_000));
+        assert_ne!(
+            k(1_000_000),
+            memo.key(
+                PoolId(1),
+                2,
+                BlockContext::default(),
+                dir,
+                U256::from(1_000_000u64)
+            )
+        );
+    }
+}
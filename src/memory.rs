@@ -0,0 +1,264 @@
+//! Approximate memory accounting per subsystem, and caps that evict cold
+//! pool states before a long-running router outgrows its host.
+//!
+//! Figures are estimates from container capacities and element sizes; they
+//! track growth well but do not include allocator overhead.
+
+use crate::{
+    graph::{AMMGraph, NodeKind},
+    ids::PoolId,
+    memo::SwapMemo,
+    pool::Pool,
+    registry::Registry,
+    router::Router,
+    univ2::UniV2State,
+    world::World,
+};
+use alloy_primitives::U256;
+use std::collections::{HashMap, HashSet};
+use std::mem::size_of;
+
+/// Heap bytes owned by a value beyond its inline size.
+pub trait HeapSize {
+    fn heap_bytes(&self) -> usize {
+        0
+    }
+}
+
+impl HeapSize for U256 {}
+impl HeapSize for UniV2State {}
+impl<A: HeapSize, B: HeapSize> HeapSize for (A, B) {
+    fn heap_bytes(&self) -> usize {
+        self.0.heap_bytes() + self.1.heap_bytes()
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl HeapSize for crate::v3storage::TickMap {
+    fn heap_bytes(&self) -> usize {
+        // BTreeMap nodes hold up to 11 entries plus edges and lengths.
+        let entry = size_of::<(i32, crate::v3storage::TickInfo)>();
+        self.ticks.len() * (entry + entry / 2)
+    }
+}
+
+fn map_bytes<K, V>(map: &HashMap<K, V>) -> usize {
+    // One control byte per bucket on top of the slot.
+    map.capacity() * (size_of::<(K, V)>() + 1)
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    pub parts: Vec<(&'static str, usize)>,
+}
+
+impl MemoryReport {
+    pub fn total(&self) -> usize {
+        self.parts.iter().map(|(_, b)| b).sum()
+    }
+
+    pub fn get(&self, part: &str) -> Option<usize> {
+        self.parts.iter().find(|(p, _)| *p == part).map(|(_, b)| *b)
+    }
+
+    fn with(mut self, part: &'static str, bytes: usize) -> Self {
+        self.parts.push((part, bytes));
+        self
+    }
+
+    pub fn merge(mut self, other: MemoryReport) -> Self {
+        self.parts.extend(other.parts);
+        self
+    }
+}
+
+/// Byte caps; `None` leaves a subsystem unbounded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryBudget {
+    pub pool_states: Option<usize>,
+    pub memo: Option<usize>,
+}
+
+impl MemoryBudget {
+    /// Evicts cold pool states and clears an oversized memo; returns the
+    /// evicted pools.
+    pub fn enforce<S: HeapSize>(
+        &self,
+        world: &mut World<S>,
+        memo: Option<&SwapMemo<S>>,
+    ) -> Vec<PoolId> {
+        if let (Some(cap), Some(memo)) = (self.memo, memo) {
+            memo.enforce_cap(cap);
+        }
+        match self.pool_states {
+            Some(cap) => world.evict_cold(cap),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl<S: HeapSize> World<S> {
+    pub fn pool_state_bytes(&self) -> usize {
+        map_bytes(&self.pool_states)
+            + self
+                .pool_states
+                .values()
+                .map(HeapSize::heap_bytes)
+                .sum::<usize>()
+            + map_bytes(&self.pool_versions)
+    }
+
+    pub fn memory_report(&self) -> MemoryReport {
+        let holdings =
+            map_bytes(&self.holdings) + self.holdings.values().map(map_bytes).sum::<usize>();
+        let allowances =
+            map_bytes(&self.allowances) + self.allowances.values().map(map_bytes).sum::<usize>();
+        MemoryReport::default()
+            .with("world.pool_states", self.pool_state_bytes())
+            .with("world.holdings", holdings)
+            .with("world.allowances", allowances)
+    }
+
+    /// Drops the least recently updated pool states, in proportion to how
+    /// far pool state memory exceeds `max_bytes`; pools never versioned go
+    /// first. Returns the evicted pools, e.g. for a provider to refetch on
+    /// demand.
+    pub fn evict_cold(&mut self, max_bytes: usize) -> Vec<PoolId> {
+        let used = self.pool_state_bytes();
+        if used <= max_bytes {
+            return Vec::new();
+        }
+        let mut by_age: Vec<(u64, PoolId)> = self
+            .pool_states
+            .keys()
+            .map(|&pid| (self.pool_versions.get(&pid).copied().unwrap_or(0), pid))
+            .collect();
+        by_age.sort_unstable();
+        let keep = (by_age.len() as u128 * max_bytes as u128 / used as u128) as usize;
+        let evicting: HashSet<PoolId> = by_age[..by_age.len() - keep]
+            .iter()
+            .map(|&(_, pid)| pid)
+            .collect();
+        let evicted = self.prune(|pid, _| evicting.contains(&pid));
+        self.pool_versions.shrink_to_fit();
+        evicted
+    }
+}
+
+impl AMMGraph {
+    pub fn memory_report(&self) -> MemoryReport {
+        let nodes = self.g.capacity().0 * (size_of::<NodeKind>() + 2 * size_of::<u32>() * 2);
+        let edges = self.g.capacity().1 * 4 * size_of::<u32>();
+        let index = map_bytes(&self.token_idx) + map_bytes(&self.pool_idx);
+        MemoryReport::default().with("graph", nodes + edges + index)
+    }
+}
+
+impl Registry {
+    pub fn memory_report(&self) -> MemoryReport {
+        let symbols: usize = self.token_meta.values().map(|m| m.symbol.capacity()).sum();
+        let bytes = map_bytes(&self.token_meta)
+            + symbols
+            + map_bytes(&self.pool_meta)
+            + map_bytes(&self.token_by_addr)
+            + map_bytes(&self.pool_by_addr);
+        MemoryReport::default().with("registry", bytes)
+    }
+}
+
+impl<S: HeapSize> SwapMemo<S> {
+    pub fn memory_report(&self) -> MemoryReport {
+        let entry = size_of::<(crate::memo::MemoKey, (U256, S))>() + 1;
+        let bytes = self.with_entries(|entries| {
+            entries.capacity() * entry
+                + entries
+                    .values()
+                    .map(|(_, st)| st.heap_bytes())
+                    .sum::<usize>()
+        });
+        MemoryReport::default().with("memo", bytes)
+    }
+
+    /// Empties the memo, releasing its table, if it holds more than
+    /// `max_bytes`.
+    pub fn enforce_cap(&self, max_bytes: usize) -> bool {
+        let over = self.memory_report().total() > max_bytes;
+        if over {
+            self.with_entries(|entries| *entries = HashMap::new());
+        }
+        over
+    }
+}
+
+impl<P: Pool> Router<P>
+where
+    P::State: HeapSize,
+{
+    pub fn memory_report(&self) -> MemoryReport {
+        self.world()
+            .memory_report()
+            .merge(self.graph().memory_report())
+            .merge(self.registry().memory_report())
+            .with("pools", map_bytes(self.pools()))
+    }
+
+    /// Applies `budget` to the router's world; returns evicted pools.
+    pub fn enforce_budget(&mut self, budget: &MemoryBudget) -> Vec<PoolId> {
+        budget.enforce(self.world_mut(), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::ids::TokenId;
+    use crate::test_utils::{Cp, hop, reserves};
+
+    #[test]
+    fn reports_and_evicts_cold_pools_under_a_cap() {
+        let mut pools = HashMap::new();
+        let mut graph = AMMGraph::new();
+        let mut world = World::default();
+        for id in 1..=64 {
+            pools.insert(PoolId(id), Cp::new(id, 1, 2));
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(1), TokenId(2));
+            world.set_pool_state(PoolId(id), reserves(1_000, 1_000));
+        }
+        // Pool 1 is the most recently updated.
+        world.set_pool_state(PoolId(1), reserves(2_000, 2_000));
+
+        let memo = SwapMemo::new(1 << 10);
+        let engine = Engine::new(&pools).with_memo(&memo);
+        engine.simulate_chained(&world, &[hop(1, 1, 2)], U256::from(10u64));
+        let report = world
+            .memory_report()
+            .merge(graph.memory_report())
+            .merge(memo.memory_report());
+        assert!(
+            report.get("world.pool_states").unwrap() > 64 * size_of::<(PoolId, (U256, U256))>()
+        );
+        assert!(report.get("graph").unwrap() > 0);
+        assert!(report.get("memo").unwrap() > 0);
+        assert_eq!(
+            report.total(),
+            report.parts.iter().map(|p| p.1).sum::<usize>()
+        );
+
+        let before = world.pool_state_bytes();
+        let budget = MemoryBudget {
+            pool_states: Some(before / 4),
+            memo: Some(0),
+        };
+        let evicted = budget.enforce(&mut world, Some(&memo));
+        assert!(
+            !evicted.is_empty() && evicted.len() < 64,
+            "{}",
+            evicted.len()
+        );
+        assert!(!evicted.contains(&PoolId(1)), "hot pool kept");
+        assert!(world.pool_state_bytes() < before / 2);
+        assert_eq!(memo.stats().entries, 0);
+        assert!(budget.enforce(&mut world, None).is_empty());
+    }
+}
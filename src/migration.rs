@@ -0,0 +1,146 @@
+//! Registry changes as an ordered list of upserts and removals, so an
+//! indexer can bring replica registries in other processes up to date by
+//! shipping what changed rather than a whole snapshot.
+
+use crate::{
+    ids::{PoolId, TokenId},
+    registry::{PoolMeta, Registry, TokenMeta},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RegistryChange {
+    UpsertToken(TokenId, TokenMeta),
+    UpsertPool(PoolId, PoolMeta),
+    RemovePool(PoolId),
+    RemoveToken(TokenId),
+}
+
+impl Registry {
+    /// The changes that turn this registry into `target`: token upserts,
+    /// pool upserts, pool removals, then token removals, each in id order,
+    /// so a replica applying them in order never holds a pool whose tokens
+    /// it lacks.
+    pub fn migration_to(&self, target: &Registry) -> Vec<RegistryChange> {
+        let mut tokens: Vec<_> = target
+            .token_meta
+            .iter()
+            .filter(|&(tid, meta)| self.token(*tid) != Some(meta))
+            .map(|(&tid, meta)| (tid, meta.clone()))
+            .collect();
+        tokens.sort_by_key(|(tid, _)| *tid);
+        let mut pools: Vec<_> = target
+            .pool_meta
+            .iter()
+            .filter(|&(pid, meta)| self.pool(*pid) != Some(meta))
+            .map(|(&pid, meta)| (pid, meta.clone()))
+            .collect();
+        pools.sort_by_key(|(pid, _)| *pid);
+        let mut gone_pools: Vec<_> = self
+            .pool_meta
+            .keys()
+            .filter(|pid| !target.pool_meta.contains_key(pid))
+            .copied()
+            .collect();
+        gone_pools.sort();
+        let mut gone_tokens: Vec<_> = self
+            .token_meta
+            .keys()
+            .filter(|tid| !target.token_meta.contains_key(tid))
+            .copied()
+            .collect();
+        gone_tokens.sort();
+
+        let tokens = tokens
+            .into_iter()
+            .map(|(tid, meta)| RegistryChange::UpsertToken(tid, meta));
+        let pools = pools
+            .into_iter()
+            .map(|(pid, meta)| RegistryChange::UpsertPool(pid, meta));
+        tokens
+            .chain(pools)
+            .chain(gone_pools.into_iter().map(RegistryChange::RemovePool))
+            .chain(gone_tokens.into_iter().map(RegistryChange::RemoveToken))
+            .collect()
+    }
+
+    pub fn apply_migration(&mut self, changes: &[RegistryChange]) {
+        for change in changes {
+            match change {
+                RegistryChange::UpsertToken(tid, meta) => self.upsert_token(*tid, meta.clone()),
+                RegistryChange::UpsertPool(pid, meta) => self.upsert_pool(*pid, meta.clone()),
+                RegistryChange::RemovePool(pid) => {
+                    self.remove_pool(*pid);
+                }
+                RegistryChange::RemoveToken(tid) => {
+                    self.remove_token(*tid);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::PoolKind;
+    use alloy_primitives::Address;
+
+    fn token(b: u8) -> TokenMeta {
+        TokenMeta {
+            address: Address::repeat_byte(b),
+            symbol: format!("T{b}"),
+            decimals: 18,
+        }
+    }
+
+    fn pool(b: u8, t0: u32, t1: u32) -> PoolMeta {
+        PoolMeta {
+            address: Address::repeat_byte(0x80 | b),
+            kind: PoolKind::UniV2,
+            token0: TokenId(t0),
+            token1: TokenId(t1),
+            fee: 3_000,
+        }
+    }
+
+    #[test]
+    fn migration_brings_a_replica_in_line_in_safe_order() {
+        let mut old = Registry::default();
+        for t in 1..=3 {
+            old.upsert_token(TokenId(t), token(t as u8));
+        }
+        old.upsert_pool(PoolId(1), pool(1, 1, 2));
+        old.upsert_pool(PoolId(2), pool(2, 2, 3));
+
+        let mut new = old.clone();
+        new.remove_pool(PoolId(2));
+        new.remove_token(TokenId(3));
+        new.upsert_token(TokenId(4), token(4));
+        new.upsert_pool(PoolId(3), pool(3, 1, 4));
+        // Same id, moved to a new address.
+        new.upsert_token(TokenId(1), token(9));
+
+        let changes = old.migration_to(&new);
+        assert_eq!(
+            changes,
+            [
+                RegistryChange::UpsertToken(TokenId(1), token(9)),
+                RegistryChange::UpsertToken(TokenId(4), token(4)),
+                RegistryChange::UpsertPool(PoolId(3), pool(3, 1, 4)),
+                RegistryChange::RemovePool(PoolId(2)),
+                RegistryChange::RemoveToken(TokenId(3)),
+            ]
+        );
+
+        let mut replica = old.clone();
+        replica.apply_migration(&changes);
+        assert!(replica.migration_to(&new).is_empty());
+        assert_eq!(replica.token_by_addr, new.token_by_addr);
+        assert_eq!(replica.pool_by_addr, new.pool_by_addr);
+        assert_eq!(
+            replica.resolve_token(&Address::repeat_byte(1).to_string()),
+            None
+        );
+    }
+}
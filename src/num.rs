@@ -0,0 +1,316 @@
+//! Shared pool math: checked operations returning [`MathError`] instead of
+//! wrapping or panicking, a fixed-point [`Price`], and helpers that stay in
+//! native `u128` while operands allow and fall back to `U256` otherwise.
+//! Amounts are `U256` throughout; callers on other 256-bit integer types
+//! convert at the edges through [`AmountRepr`].
+
+use alloy_primitives::{U256, U512};
+
+/// A 256-bit unsigned integer type that converts losslessly to and from
+/// `U256`, so callers can pass and receive their own type at the engine's
+/// edges, e.g. [`Engine::simulate_as`](crate::engine::Engine::simulate_as).
+/// Pools, the engine and the router still compute in `U256`; this only
+/// saves the conversion at the call site.
+/// Implemented for ethers' `primitive_types::U256` with the
+/// `primitive-types` feature and for `ethnum::U256` with `ethnum`.
+pub trait AmountRepr: Copy {
+    fn into_amount(self) -> U256;
+    fn from_amount(a: U256) -> Self;
+}
+
+impl AmountRepr for U256 {
+    fn into_amount(self) -> U256 {
+        self
+    }
+
+    fn from_amount(a: U256) -> Self {
+        a
+    }
+}
+
+#[cfg(feature = "primitive-types")]
+impl AmountRepr for primitive_types::U256 {
+    fn into_amount(self) -> U256 {
+        U256::from_limbs(self.0)
+    }
+
+    fn from_amount(a: U256) -> Self {
+        Self(a.into_limbs())
+    }
+}
+
+#[cfg(feature = "ethnum")]
+impl AmountRepr for ethnum::U256 {
+    fn into_amount(self) -> U256 {
+        let (hi, lo) = self.into_words();
+        U256::from_limbs([lo as u64, (lo >> 64) as u64, hi as u64, (hi >> 64) as u64])
+    }
+
+    fn from_amount(a: U256) -> Self {
+        let [l0, l1, l2, l3] = a.into_limbs();
+        Self::from_words(
+            ((l3 as u128) << 64) | l2 as u128,
+            ((l1 as u128) << 64) | l0 as u128,
+        )
+    }
+}
+
+#[inline]
+pub fn to_u128(x: U256) -> Option<u128> {
+    let [lo, hi, 0, 0] = *x.as_limbs() else {
+        return None;
+    };
+    Some(((hi as u128) << 64) | lo as u128)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum MathError {
+    #[error("arithmetic overflow")]
+    Overflow,
+    #[error("arithmetic underflow")]
+    Underflow,
+    #[error("division by zero")]
+    DivisionByZero,
+}
+
+pub type MathResult<T> = Result<T, MathError>;
+
+#[inline]
+pub fn add(a: U256, b: U256) -> MathResult<U256> {
+    a.checked_add(b).ok_or(MathError::Overflow)
+}
+
+#[inline]
+pub fn sub(a: U256, b: U256) -> MathResult<U256> {
+    a.checked_sub(b).ok_or(MathError::Underflow)
+}
+
+#[inline]
+pub fn mul(a: U256, b: U256) -> MathResult<U256> {
+    a.checked_mul(b).ok_or(MathError::Overflow)
+}
+
+#[inline]
+pub fn div(a: U256, b: U256) -> MathResult<U256> {
+    a.checked_div(b).ok_or(MathError::DivisionByZero)
+}
+
+/// `a * b / d`, flooring, with a full-width intermediate product so only
+/// the quotient has to fit.
+#[inline]
+pub fn mul_div(a: U256, b: U256, d: U256) -> MathResult<U256> {
+    if d.is_zero() {
+        return Err(MathError::DivisionByZero);
+    }
+    if let (Some(a), Some(b), Some(d)) = (to_u128(a), to_u128(b), to_u128(d))
+        && let Some(p) = a.checked_mul(b)
+    {
+        return Ok(U256::from(p / d));
+    }
+    narrow(U512::from(a) * U512::from(b) / U512::from(d)).ok_or(MathError::Overflow)
+}
+
+/// Constant-product output `in_with_fee * r_out / (r_in * 10_000 + in_with_fee)`
+/// with `in_with_fee = amt_in * fee_factor`, or zero for an empty pool.
+#[inline]
+pub fn cp_amount_out(r_in: U256, r_out: U256, amt_in: U256, fee_factor: u32) -> MathResult<U256> {
+    if let Some(out) = cp_amount_out_u128(r_in, r_out, amt_in, fee_factor) {
+        return Ok(U256::from(out));
+    }
+    let in_with_fee = mul(amt_in, U256::from(fee_factor))?;
+    let den = add(mul(r_in, U256::from(10_000u64))?, in_with_fee)?;
+    if den.is_zero() {
+        return Ok(U256::ZERO);
+    }
+    mul_div(in_with_fee, r_out, den)
+}
+
+#[inline]
+fn cp_amount_out_u128(r_in: U256, r_out: U256, amt_in: U256, fee_factor: u32) -> Option<u128> {
+    let (r_in, r_out, amt_in) = (to_u128(r_in)?, to_u128(r_out)?, to_u128(amt_in)?);
+    let in_with_fee = amt_in.checked_mul(fee_factor as u128)?;
+    let den = r_in.checked_mul(10_000)?.checked_add(in_with_fee)?;
+    if den == 0 {
+        return Some(0);
+    }
+    Some(in_with_fee.checked_mul(r_out)? / den)
+}
+
+/// Unsigned fixed-point price with 96 fractional bits: `Price(x)` is the
+/// number `x / 2^96` of output units per input unit, in raw token units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Price(pub U256);
+
+impl Price {
+    pub const FRACTION_BITS: usize = 96;
+    pub const ONE: Price = Price(U256::from_limbs([0, 1 << 32, 0, 0]));
+
+    /// `num / den`, or `None` if `den` is zero or the result overflows.
+    pub fn from_ratio(num: U256, den: U256) -> Option<Self> {
+        if den.is_zero() {
+            return None;
+        }
+        let q = (U512::from(num) << Self::FRACTION_BITS) / U512::from(den);
+        narrow(q).map(Self)
+    }
+
+    /// Price of token0 in token1 for reserves `(r0, r1)`.
+    pub fn from_reserves(r0: U256, r1: U256) -> Option<Self> {
+        Self::from_ratio(r1, r0)
+    }
+
+    /// Price of token0 in token1 from a Uniswap V3 `sqrtPriceX96`.
+    pub fn from_sqrt_price_x96(sqrt_price_x96: U256) -> Option<Self> {
+        let sq = U512::from(sqrt_price_x96) * U512::from(sqrt_price_x96);
+        narrow(sq >> Self::FRACTION_BITS).map(Self)
+    }
+
+    /// Output for `amt_in` at this price, rounded down.
+    pub fn quote(self, amt_in: U256) -> Option<U256> {
+        narrow((U512::from(amt_in) * U512::from(self.0)) >> Self::FRACTION_BITS)
+    }
+
+    /// Input needed for `amt_out` at this price, rounded up.
+    pub fn quote_inverse(self, amt_out: U256) -> Option<U256> {
+        if self.0.is_zero() {
+            return None;
+        }
+        let num = U512::from(amt_out) << Self::FRACTION_BITS;
+        narrow(num.div_ceil(U512::from(self.0)))
+    }
+
+    pub fn invert(self) -> Option<Self> {
+        Self::from_ratio(Self::ONE.0, self.0)
+    }
+
+    /// Price of composing this hop with `next`, rounded down.
+    pub fn then(self, next: Price) -> Option<Self> {
+        narrow((U512::from(self.0) * U512::from(next.0)) >> Self::FRACTION_BITS).map(Self)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        f64::from(self.0) / 2f64.powi(Self::FRACTION_BITS as i32)
+    }
+}
+
+fn narrow(x: U512) -> Option<U256> {
+    let limbs = x.as_limbs();
+    limbs[4..]
+        .iter()
+        .all(|l| *l == 0)
+        .then(|| U256::from_limbs([limbs[0], limbs[1], limbs[2], limbs[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SplitMix64;
+
+    fn wide(rng: &mut SplitMix64) -> U256 {
+        // Mix magnitudes so both the fast path and the fallback are hit.
+        let bits = rng.below(200) as usize;
+        let x = U256::from_limbs([
+            rng.next_u64(),
+            rng.next_u64(),
+            rng.next_u64(),
+            rng.next_u64(),
+        ]);
+        x >> (256 - bits.max(1))
+    }
+
+    #[test]
+    fn fast_paths_match_checked_u256() {
+        let mut rng = SplitMix64::new(1);
+        for _ in 0..20_000 {
+            let (a, b, d) = (wide(&mut rng), wide(&mut rng), wide(&mut rng));
+            let expected = match (a.checked_mul(b), d.is_zero()) {
+                (_, true) => Err(MathError::DivisionByZero),
+                (Some(p), false) => Ok(p / d),
+                (None, false) => mul_div(a, b, d),
+            };
+            assert_eq!(mul_div(a, b, d), expected, "{a} * {b} / {d}");
+
+            let (r_in, r_out, amt) = (wide(&mut rng), wide(&mut rng), wide(&mut rng));
+            let fee = 10_000 - rng.below(100) as u32;
+            let expected = (|| {
+                let in_with_fee = amt.checked_mul(U256::from(fee))?;
+                let den = r_in
+                    .checked_mul(U256::from(10_000u64))?
+                    .checked_add(in_with_fee)?;
+                if den.is_zero() {
+                    return Some(U256::ZERO);
+                }
+                let wide = U512::from(in_with_fee) * U512::from(r_out) / U512::from(den);
+                narrow(wide)
+            })();
+            assert_eq!(cp_amount_out(r_in, r_out, amt, fee).ok(), expected);
+        }
+    }
+
+    #[test]
+    fn checked_ops_report_instead_of_wrapping() {
+        assert_eq!(add(U256::MAX, U256::ONE), Err(MathError::Overflow));
+        assert_eq!(sub(U256::ZERO, U256::ONE), Err(MathError::Underflow));
+        assert_eq!(mul(U256::MAX, U256::from(2u64)), Err(MathError::Overflow));
+        assert_eq!(div(U256::ONE, U256::ZERO), Err(MathError::DivisionByZero));
+        // The product overflows U256 but the quotient fits.
+        assert_eq!(
+            mul_div(U256::MAX, U256::from(2u64), U256::from(4u64)),
+            Ok(U256::MAX >> 1)
+        );
+        assert_eq!(
+            cp_amount_out(U256::MAX, U256::ONE, U256::ONE, 9_970),
+            Err(MathError::Overflow)
+        );
+    }
+
+    #[test]
+    fn price_conversions_and_quotes() {
+        let e18 = U256::from(10u64).pow(U256::from(18u64));
+        let p = Price::from_reserves(e18, e18 * U256::from(2_000u64)).unwrap();
+        assert_eq!(p.to_f64(), 2_000.0);
+        assert_eq!(p.quote(e18).unwrap(), e18 * U256::from(2_000u64));
+        assert_eq!(p.quote_inverse(e18 * U256::from(2_000u64)).unwrap(), e18);
+        let round_trip = p.invert().unwrap().then(p).unwrap();
+        assert!(Price::ONE.0 - round_trip.0 < U256::from(1u64 << 20));
+
+        // sqrtPriceX96 for price 4 is 2 * 2^96.
+        let sqrt = U256::from(2u64) << 96;
+        assert_eq!(
+            Price::from_sqrt_price_x96(sqrt).unwrap(),
+            Price::from_ratio(U256::from(4u64), U256::from(1u64)).unwrap()
+        );
+
+        assert_eq!(Price::from_ratio(U256::ONE, U256::ZERO), None);
+        assert_eq!(Price::from_ratio(U256::MAX, U256::ONE), None);
+        assert_eq!(Price::ONE.quote(U256::MAX), Some(U256::MAX));
+        assert_eq!(Price(U256::MAX).quote(U256::MAX), None);
+        assert_eq!(Price::default().quote_inverse(U256::ONE), None);
+    }
+
+    #[test]
+    fn u128_conversion_is_exact_at_the_boundary() {
+        assert_eq!(to_u128(U256::from(u128::MAX)), Some(u128::MAX));
+        assert_eq!(to_u128(U256::from(u128::MAX) + U256::from(1u64)), None);
+        assert_eq!(to_u128(U256::ZERO), Some(0));
+    }
+
+    #[test]
+    fn foreign_amounts_round_trip_losslessly() {
+        let x = U256::from_limbs([1, 2, 3, u64::MAX]);
+        assert_eq!(U256::from_amount(x).into_amount(), x);
+        #[cfg(feature = "primitive-types")]
+        {
+            let p = primitive_types::U256::from_amount(x);
+            assert_eq!(p >> 192, primitive_types::U256::from(u64::MAX));
+            assert_eq!(p.into_amount(), x);
+        }
+        #[cfg(feature = "ethnum")]
+        {
+            let e = ethnum::U256::from_amount(x);
+            assert_eq!(e >> 192u32, ethnum::U256::from(u64::MAX));
+            assert_eq!(e.into_amount(), x);
+        }
+    }
+}
@@ -0,0 +1,381 @@
+//! Every opportunity a scan turned up, with the block it was seen in, its
+//! sizes and what became of it, kept for strategy research. [`MemoryStore`]
+//! holds them in a `Vec`; with the `sqlite` feature [`SqliteStore`] keeps
+//! them in a table that outlives the process.
+
+use crate::{arb::ArbOpportunity, canonical::RouteKey, engine::Hop, error::Result, ids::TokenId};
+use alloy_primitives::U256;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::RangeInclusive;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    /// Found by a scan and not acted on.
+    Seen,
+    Submitted,
+    Landed,
+    /// Submitted but reverted, outbid or dropped.
+    Failed,
+}
+
+impl Outcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Seen => "seen",
+            Outcome::Submitted => "submitted",
+            Outcome::Landed => "landed",
+            Outcome::Failed => "failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        [
+            Outcome::Seen,
+            Outcome::Submitted,
+            Outcome::Landed,
+            Outcome::Failed,
+        ]
+        .into_iter()
+        .find(|o| o.as_str() == s)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpportunityRecord {
+    pub block: u64,
+    pub plan: Vec<Hop>,
+    pub optimal_in: U256,
+    pub gross: U256,
+    pub gas: U256,
+    pub net: U256,
+    pub outcome: Outcome,
+}
+
+impl OpportunityRecord {
+    pub fn new(block: u64, opp: &ArbOpportunity, outcome: Outcome) -> Self {
+        Self {
+            block,
+            plan: opp.plan.clone(),
+            optimal_in: opp.optimal_in,
+            gross: opp.gross,
+            gas: opp.gas,
+            net: opp.net,
+            outcome,
+        }
+    }
+
+    /// The base token and the token first bought with it.
+    pub fn pair(&self) -> Option<(TokenId, TokenId)> {
+        self.plan.first().map(|h| (h.dir.from, h.dir.to))
+    }
+}
+
+pub trait OpportunityStore {
+    fn record(&mut self, rec: &OpportunityRecord) -> Result<()>;
+
+    /// Records seen in `blocks`, oldest first, in the order recorded.
+    fn range(&self, blocks: RangeInclusive<u64>) -> Result<Vec<OpportunityRecord>>;
+
+    fn top_pairs(&self, blocks: RangeInclusive<u64>, n: usize) -> Result<Vec<PairCount>> {
+        Ok(top_pairs(&self.range(blocks)?, n))
+    }
+
+    fn profit_decay(&self, blocks: RangeInclusive<u64>, max_age: u64) -> Result<Vec<(u64, f64)>> {
+        Ok(profit_decay(&self.range(blocks)?, max_age))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PairCount {
+    pub pair: (TokenId, TokenId),
+    pub count: usize,
+    pub landed: usize,
+}
+
+/// The `n` pairs seen most often, most first.
+pub fn top_pairs(records: &[OpportunityRecord], n: usize) -> Vec<PairCount> {
+    let mut counts: HashMap<(TokenId, TokenId), PairCount> = HashMap::new();
+    for rec in records {
+        let Some(pair) = rec.pair() else { continue };
+        let c = counts.entry(pair).or_insert(PairCount {
+            pair,
+            count: 0,
+            landed: 0,
+        });
+        c.count += 1;
+        c.landed += usize::from(rec.outcome == Outcome::Landed);
+    }
+    let mut out: Vec<_> = counts.into_values().collect();
+    out.sort_by(|a, b| b.count.cmp(&a.count).then(a.pair.cmp(&b.pair)));
+    out.truncate(n);
+    out
+}
+
+/// Mean net profit of a route `age` blocks after it was first seen, as a
+/// fraction of what it was then, for each age up to `max_age` that has
+/// sightings. Routes are matched by [`RouteKey`], so a cycle found from
+/// different tokens counts once.
+pub fn profit_decay(records: &[OpportunityRecord], max_age: u64) -> Vec<(u64, f64)> {
+    let mut first: HashMap<RouteKey, (u64, f64)> = HashMap::new();
+    let mut sorted: Vec<_> = records.iter().collect();
+    sorted.sort_by_key(|r| r.block);
+    let mut by_age: BTreeMap<u64, (f64, usize)> = BTreeMap::new();
+    for rec in sorted {
+        let net = f64::from(rec.net);
+        let &mut (seen, base) = first
+            .entry(RouteKey::of(&rec.plan))
+            .or_insert((rec.block, net));
+        let age = rec.block - seen;
+        if base <= 0.0 || age > max_age {
+            continue;
+        }
+        let (sum, n) = by_age.entry(age).or_default();
+        *sum += net / base;
+        *n += 1;
+    }
+    by_age
+        .into_iter()
+        .map(|(age, (sum, n))| (age, sum / n as f64))
+        .collect()
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    pub records: Vec<OpportunityRecord>,
+}
+
+impl OpportunityStore for MemoryStore {
+    fn record(&mut self, rec: &OpportunityRecord) -> Result<()> {
+        self.records.push(rec.clone());
+        Ok(())
+    }
+
+    fn range(&self, blocks: RangeInclusive<u64>) -> Result<Vec<OpportunityRecord>> {
+        let mut out: Vec<_> = self
+            .records
+            .iter()
+            .filter(|r| blocks.contains(&r.block))
+            .cloned()
+            .collect();
+        out.sort_by_key(|r| r.block);
+        Ok(out)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::SqliteStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::*;
+    use crate::{
+        error::WayfinderError,
+        ids::{PoolId, SwapDirection},
+    };
+    use rusqlite::{Connection, params};
+
+    fn store_err(e: impl std::fmt::Display) -> WayfinderError {
+        WayfinderError::Store(e.to_string())
+    }
+
+    /// One row per record. Amounts are decimal text, and `plan` is
+    /// `pool:from:to` per hop, comma separated; `route` is the hex
+    /// [`RouteKey::id`] and `base`, `bought` the record's pair, for queries
+    /// written directly against the table.
+    pub struct SqliteStore {
+        conn: Connection,
+    }
+
+    impl SqliteStore {
+        pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self> {
+            Self::init(Connection::open(path).map_err(store_err)?)
+        }
+
+        pub fn in_memory() -> Result<Self> {
+            Self::init(Connection::open_in_memory().map_err(store_err)?)
+        }
+
+        fn init(conn: Connection) -> Result<Self> {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS opportunities (
+                    id INTEGER PRIMARY KEY,
+                    block INTEGER NOT NULL,
+                    route TEXT NOT NULL,
+                    base INTEGER,
+                    bought INTEGER,
+                    plan TEXT NOT NULL,
+                    optimal_in TEXT NOT NULL,
+                    gross TEXT NOT NULL,
+                    gas TEXT NOT NULL,
+                    net TEXT NOT NULL,
+                    outcome TEXT NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS opportunities_block ON opportunities (block);
+                CREATE INDEX IF NOT EXISTS opportunities_route ON opportunities (route);",
+            )
+            .map_err(store_err)?;
+            Ok(Self { conn })
+        }
+    }
+
+    fn encode_plan(plan: &[Hop]) -> String {
+        plan.iter()
+            .map(|h| format!("{}:{}:{}", h.pool, h.dir.from, h.dir.to))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn decode_plan(s: &str) -> Option<Vec<Hop>> {
+        if s.is_empty() {
+            return Some(Vec::new());
+        }
+        s.split(',')
+            .map(|hop| {
+                let mut parts = hop.split(':');
+                let pool: PoolId = parts.next()?.parse().ok()?;
+                let from: TokenId = parts.next()?.parse().ok()?;
+                let to: TokenId = parts.next()?.parse().ok()?;
+                Some(Hop::new(pool, SwapDirection::new(from, to)?))
+            })
+            .collect()
+    }
+
+    impl OpportunityStore for SqliteStore {
+        fn record(&mut self, rec: &OpportunityRecord) -> Result<()> {
+            let pair = rec.pair();
+            self.conn
+                .execute(
+                    "INSERT INTO opportunities
+                        (block, route, base, bought, plan, optimal_in, gross, gas, net, outcome)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    params![
+                        rec.block as i64,
+                        RouteKey::of(&rec.plan).id().to_string(),
+                        pair.map(|p| p.0.0),
+                        pair.map(|p| p.1.0),
+                        encode_plan(&rec.plan),
+                        rec.optimal_in.to_string(),
+                        rec.gross.to_string(),
+                        rec.gas.to_string(),
+                        rec.net.to_string(),
+                        rec.outcome.as_str(),
+                    ],
+                )
+                .map_err(store_err)?;
+            Ok(())
+        }
+
+        fn range(&self, blocks: RangeInclusive<u64>) -> Result<Vec<OpportunityRecord>> {
+            let mut stmt = self
+                .conn
+                .prepare_cached(
+                    "SELECT block, plan, optimal_in, gross, gas, net, outcome
+                     FROM opportunities WHERE block BETWEEN ?1 AND ?2 ORDER BY block, id",
+                )
+                .map_err(store_err)?;
+            let clamp = |b: u64| b.min(i64::MAX as u64) as i64;
+            let rows = stmt
+                .query_map(
+                    params![clamp(*blocks.start()), clamp(*blocks.end())],
+                    |row| {
+                        Ok((
+                            row.get::<_, i64>(0)?,
+                            row.get::<_, String>(1)?,
+                            [
+                                row.get::<_, String>(2)?,
+                                row.get::<_, String>(3)?,
+                                row.get::<_, String>(4)?,
+                                row.get::<_, String>(5)?,
+                            ],
+                            row.get::<_, String>(6)?,
+                        ))
+                    },
+                )
+                .map_err(store_err)?;
+            let mut out = Vec::new();
+            for row in rows {
+                let (block, plan, amounts, outcome) = row.map_err(store_err)?;
+                let corrupt = || store_err(format!("malformed row at block {block}"));
+                let amounts: Vec<U256> = amounts
+                    .iter()
+                    .map(|a| a.parse().map_err(|_| corrupt()))
+                    .collect::<Result<_>>()?;
+                out.push(OpportunityRecord {
+                    block: block as u64,
+                    plan: decode_plan(&plan).ok_or_else(corrupt)?,
+                    optimal_in: amounts[0],
+                    gross: amounts[1],
+                    gas: amounts[2],
+                    net: amounts[3],
+                    outcome: Outcome::parse(&outcome).ok_or_else(corrupt)?,
+                });
+            }
+            Ok(out)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::hop;
+
+    fn rec(block: u64, plan: Vec<Hop>, net: u64, outcome: Outcome) -> OpportunityRecord {
+        OpportunityRecord {
+            block,
+            plan,
+            optimal_in: U256::from(1_000u64),
+            gross: U256::from(net + 10),
+            gas: U256::from(10u64),
+            net: U256::from(net),
+            outcome,
+        }
+    }
+
+    fn fill(store: &mut impl OpportunityStore) {
+        let cycle = vec![hop(1, 1, 2), hop(2, 2, 1)];
+        let rotated = vec![hop(2, 2, 1), hop(1, 1, 2)];
+        let other = vec![hop(3, 1, 3), hop(4, 3, 1)];
+        for r in [
+            rec(10, cycle.clone(), 100, Outcome::Seen),
+            rec(11, rotated, 50, Outcome::Landed),
+            rec(12, cycle, 25, Outcome::Failed),
+            rec(11, other.clone(), 40, Outcome::Seen),
+            rec(12, other, 40, Outcome::Seen),
+        ] {
+            store.record(&r).unwrap();
+        }
+    }
+
+    fn check(store: &impl OpportunityStore) {
+        let all = store.range(0..=u64::MAX).unwrap();
+        assert_eq!(all.len(), 5);
+        assert_eq!(all[1].plan, [hop(2, 2, 1), hop(1, 1, 2)]);
+        assert_eq!(all[1].outcome, Outcome::Landed);
+        assert_eq!(store.range(11..=11).unwrap().len(), 2);
+
+        let top = store.top_pairs(0..=u64::MAX, 2).unwrap();
+        assert_eq!(top[0].pair, (TokenId(1), TokenId(2)));
+        assert_eq!((top[0].count, top[0].landed), (2, 0));
+        assert_eq!((top[1].pair, top[1].count), ((TokenId(1), TokenId(3)), 2));
+
+        // The cycle halves each block; the other route holds its value.
+        let decay = store.profit_decay(0..=u64::MAX, 1).unwrap();
+        assert_eq!(decay, [(0, 1.0), (1, 0.75)]);
+    }
+
+    #[test]
+    fn memory_store_answers_frequency_and_decay_queries() {
+        let mut store = MemoryStore::default();
+        fill(&mut store);
+        check(&store);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_store_round_trips_records() {
+        let mut store = SqliteStore::in_memory().unwrap();
+        fill(&mut store);
+        check(&store);
+    }
+}
@@ -0,0 +1,264 @@
+//! Quote parity against public aggregators (1inch, 0x, Paraswap): the same
+//! pair and size is quoted by each and compared with our best route, to
+//! track where pool coverage or heuristics fall short.
+
+use crate::{
+    arb::Scanner,
+    error::WayfinderError,
+    ids::{ChainId, TokenId},
+    pool::Pool,
+    registry::{Registry, TokenMeta},
+    world::StateView,
+};
+use alloy_primitives::U256;
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Aggregator {
+    OneInch,
+    ZeroX,
+    Paraswap,
+}
+
+/// A sell-side quote request with registry-resolved tokens.
+#[derive(Clone, Copy, Debug)]
+pub struct QuoteRequest<'a> {
+    pub chain: ChainId,
+    pub sell: &'a TokenMeta,
+    pub buy: &'a TokenMeta,
+    pub amount: U256,
+}
+
+impl Aggregator {
+    pub const ALL: [Aggregator; 3] = [Aggregator::OneInch, Aggregator::ZeroX, Aggregator::Paraswap];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Aggregator::OneInch => "1inch",
+            Aggregator::ZeroX => "0x",
+            Aggregator::Paraswap => "paraswap",
+        }
+    }
+
+    /// Endpoint and query string for `req`.
+    pub fn url(self, req: &QuoteRequest<'_>) -> String {
+        let (chain, sell, buy, amount) = (req.chain, req.sell.address, req.buy.address, req.amount);
+        match self {
+            Aggregator::OneInch => format!(
+                "https://api.1inch.dev/swap/v6.0/{chain}/quote?src={sell}&dst={buy}&amount={amount}"
+            ),
+            Aggregator::ZeroX => format!(
+                "https://api.0x.org/swap/permit2/price?chainId={chain}&sellToken={sell}&buyToken={buy}&sellAmount={amount}"
+            ),
+            Aggregator::Paraswap => format!(
+                "https://api.paraswap.io/prices?network={chain}&srcToken={sell}&destToken={buy}&amount={amount}&srcDecimals={}&destDecimals={}&side=SELL",
+                req.sell.decimals, req.buy.decimals
+            ),
+        }
+    }
+
+    /// Headers carrying `key`, if the API takes one.
+    pub fn auth_headers(self, key: &str) -> Vec<(&'static str, String)> {
+        match self {
+            Aggregator::OneInch => vec![("Authorization", format!("Bearer {key}"))],
+            Aggregator::ZeroX => vec![("0x-api-key", key.into()), ("0x-version", "v2".into())],
+            Aggregator::Paraswap => Vec::new(),
+        }
+    }
+
+    /// Output amount from a quote response body.
+    pub fn parse(self, body: &Value) -> Option<U256> {
+        let amount = match self {
+            Aggregator::OneInch => &body["dstAmount"],
+            Aggregator::ZeroX => &body["buyAmount"],
+            Aggregator::Paraswap => &body["priceRoute"]["destAmount"],
+        };
+        amount.as_str()?.parse().ok()
+    }
+}
+
+pub struct AggregatorClient {
+    http: reqwest::Client,
+    pub api_keys: HashMap<Aggregator, String>,
+}
+
+impl Default for AggregatorClient {
+    fn default() -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            api_keys: HashMap::new(),
+        }
+    }
+}
+
+impl AggregatorClient {
+    pub fn with_api_key(mut self, agg: Aggregator, key: impl Into<String>) -> Self {
+        self.api_keys.insert(agg, key.into());
+        self
+    }
+
+    pub async fn quote(
+        &self,
+        agg: Aggregator,
+        req: &QuoteRequest<'_>,
+    ) -> Result<U256, WayfinderError> {
+        let err = |e: &dyn std::fmt::Display| WayfinderError::Rpc(format!("{}: {e}", agg.name()));
+        let mut http = self.http.get(agg.url(req));
+        if let Some(key) = self.api_keys.get(&agg) {
+            for (name, value) in agg.auth_headers(key) {
+                http = http.header(name, value);
+            }
+        }
+        let body: Value = http
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| err(&e))?
+            .json()
+            .await
+            .map_err(|e| err(&e))?;
+        agg.parse(&body)
+            .ok_or_else(|| err(&"no output amount in response"))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ParityReport {
+    pub sell: TokenId,
+    pub buy: TokenId,
+    pub amount_in: U256,
+    /// Our best route's output; `None` if we found no route.
+    pub ours: Option<U256>,
+    pub theirs: Vec<(Aggregator, Result<U256, String>)>,
+}
+
+impl ParityReport {
+    pub fn best_external(&self) -> Option<(Aggregator, U256)> {
+        self.theirs
+            .iter()
+            .filter_map(|(a, r)| r.as_ref().ok().map(|&out| (*a, out)))
+            .max_by_key(|&(_, out)| out)
+    }
+
+    /// How far our output falls short of the best aggregator, in basis
+    /// points of theirs; zero when we match or beat it, 10 000 with no route.
+    pub fn shortfall_bps(&self) -> Option<u64> {
+        let (_, theirs) = self.best_external()?;
+        if theirs.is_zero() {
+            return Some(0);
+        }
+        let ours = self.ours.unwrap_or_default();
+        let short = theirs.saturating_sub(ours) * U256::from(10_000u64) / theirs;
+        Some(short.saturating_to())
+    }
+}
+
+impl<P: Pool> Scanner<'_, P> {
+    /// Quotes each `(sell, buy, amount)` with [`Scanner::best_route`] and
+    /// with every aggregator in `aggs`. Aggregator failures are recorded in
+    /// the report rather than aborting the run.
+    pub async fn parity<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        reg: &Registry,
+        client: &AggregatorClient,
+        chain: ChainId,
+        aggs: &[Aggregator],
+        trades: &[(TokenId, TokenId, U256)],
+    ) -> Result<Vec<ParityReport>, WayfinderError> {
+        let mut reports = Vec::with_capacity(trades.len());
+        for &(sell, buy, amount_in) in trades {
+            let req = QuoteRequest {
+                chain,
+                sell: reg.require_token(sell)?,
+                buy: reg.require_token(buy)?,
+                amount: amount_in,
+            };
+            let ours = self
+                .best_route(world, sell, buy, amount_in)
+                .and_then(|p| p.steps.last().map(|s| s.amt_out));
+            let mut theirs = Vec::with_capacity(aggs.len());
+            for &agg in aggs {
+                let res = client.quote(agg, &req).await.map_err(|e| e.to_string());
+                theirs.push((agg, res));
+            }
+            reports.push(ParityReport {
+                sell,
+                buy,
+                amount_in,
+                ours,
+                theirs,
+            });
+        }
+        Ok(reports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::Address;
+    use serde_json::json;
+
+    #[test]
+    fn builds_requests_and_parses_responses() {
+        let token = |b, decimals| TokenMeta {
+            address: Address::repeat_byte(b),
+            symbol: String::new(),
+            decimals,
+        };
+        let (sell, buy) = (token(1, 18), token(2, 6));
+        let req = QuoteRequest {
+            chain: ChainId::MAINNET,
+            sell: &sell,
+            buy: &buy,
+            amount: U256::from(1_000u64),
+        };
+        let url = Aggregator::Paraswap.url(&req);
+        assert!(url.contains("network=1&"), "{url}");
+        assert!(url.contains("srcDecimals=18&destDecimals=6"), "{url}");
+        assert!(Aggregator::OneInch.url(&req).contains("/v6.0/1/quote?"));
+
+        let out = U256::from(123u64);
+        assert_eq!(
+            Aggregator::OneInch.parse(&json!({ "dstAmount": "123" })),
+            Some(out)
+        );
+        assert_eq!(
+            Aggregator::ZeroX.parse(&json!({ "buyAmount": "123" })),
+            Some(out)
+        );
+        assert_eq!(
+            Aggregator::Paraswap.parse(&json!({ "priceRoute": { "destAmount": "123" } })),
+            Some(out)
+        );
+        assert_eq!(Aggregator::ZeroX.parse(&json!({ "error": "x" })), None);
+    }
+
+    #[test]
+    fn shortfall_is_against_the_best_aggregator() {
+        let mut report = ParityReport {
+            sell: TokenId(1),
+            buy: TokenId(2),
+            amount_in: U256::from(1u64),
+            ours: Some(U256::from(9_900u64)),
+            theirs: vec![
+                (Aggregator::OneInch, Ok(U256::from(9_950u64))),
+                (Aggregator::ZeroX, Ok(U256::from(10_000u64))),
+                (Aggregator::Paraswap, Err("timeout".into())),
+            ],
+        };
+        assert_eq!(
+            report.best_external(),
+            Some((Aggregator::ZeroX, U256::from(10_000u64)))
+        );
+        assert_eq!(report.shortfall_bps(), Some(100));
+        report.ours = Some(U256::from(20_000u64));
+        assert_eq!(report.shortfall_bps(), Some(0));
+        report.ours = None;
+        assert_eq!(report.shortfall_bps(), Some(10_000));
+        report.theirs.truncate(0);
+        assert_eq!(report.shortfall_bps(), None);
+    }
+}
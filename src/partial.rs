@@ -0,0 +1,207 @@
+//! Orders too large for any single route within an impact limit: fill what
+//! the best route absorbs, hand the rest to the next route, and report what
+//! is left over.
+
+use crate::{
+    arb::Scanner,
+    engine::{Hop, Path},
+    ids::TokenId,
+    pool::{DEPTH_PRECISION, Pool},
+    router::Router,
+    solver::route_scratch,
+    world::{StateView, World},
+};
+use alloy_primitives::{U256, U512};
+
+#[derive(Clone, Debug, Default)]
+pub struct RouteResult {
+    /// Each fill's path and input, in the order they were placed; later
+    /// fills were simulated after the earlier ones.
+    pub fills: Vec<(Path, U256)>,
+    pub unfilled: U256,
+}
+
+impl RouteResult {
+    pub fn filled(&self) -> U256 {
+        self.fills.iter().map(|(_, amt)| *amt).sum()
+    }
+
+    pub fn amount_out(&self) -> U256 {
+        self.fills
+            .iter()
+            .filter_map(|(path, _)| path.steps.last())
+            .map(|s| s.amt_out)
+            .sum()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.unfilled.is_zero()
+    }
+}
+
+impl<P: Pool> Scanner<'_, P> {
+    /// Sells up to `amt_in` of `from` over at most `max_fills` routes,
+    /// giving each the most it takes with an average price no more than
+    /// `impact_bps` worse than the route's marginal price. The route paying most at its fill goes first.
+    pub fn route_partial<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        from: TokenId,
+        to: TokenId,
+        amt_in: U256,
+        impact_bps: u32,
+        max_fills: usize,
+    ) -> RouteResult {
+        let routes = self.routes(world, from, to);
+        let mut scratch = route_scratch(world, &routes);
+        // Impact is measured against prices before any fill, so a route
+        // drained by one fill can't be refilled at its worse new price.
+        let marginals: Vec<_> = routes
+            .iter()
+            .map(|plan| self.marginal(&scratch, plan, amt_in))
+            .collect();
+        let mut left = amt_in;
+        let mut fills = Vec::new();
+        while !left.is_zero() && fills.len() < max_fills {
+            let best = routes
+                .iter()
+                .zip(&marginals)
+                .filter_map(|(plan, &marginal)| {
+                    let fill = self.fillable(&scratch, plan, left, marginal?, impact_bps);
+                    let out = self.out(&scratch, plan, fill);
+                    (!out.is_zero()).then_some((out, fill, plan))
+                })
+                .max_by_key(|&(out, fill, _)| (out, fill));
+            let Some((_, fill, plan)) = best else {
+                break;
+            };
+            let path = self.engine.apply(&mut scratch, plan, fill);
+            fills.push((path, fill));
+            left -= fill;
+        }
+        RouteResult {
+            fills,
+            unfilled: left,
+        }
+    }
+
+    fn out(&self, world: &World<P::State>, plan: &[Hop], amt: U256) -> U256 {
+        let path = self.engine.simulate_chained(world, plan, amt);
+        path.steps.last().map_or(U256::ZERO, |s| s.amt_out)
+    }
+
+    /// The route's price as a `(probe, probe_out)` pair, probed like
+    /// [`bisect_depth`](crate::pool::bisect_depth) does a single pool.
+    fn marginal(&self, world: &World<P::State>, plan: &[Hop], cap: U256) -> Option<(U256, U256)> {
+        let mut probe = U256::from(1u64);
+        let mut probe_out = self.out(world, plan, probe);
+        while probe_out < U256::from(DEPTH_PRECISION) && probe < cap {
+            let doubled = self.out(world, plan, probe << 1);
+            if !probe_out.is_zero() && doubled + U256::from(1u64) < probe_out << 1 {
+                break;
+            }
+            probe <<= 1;
+            probe_out = doubled;
+        }
+        (!probe_out.is_zero()).then_some((probe, probe_out))
+    }
+
+    /// Largest input up to `left` still within `impact_bps` of `marginal`.
+    fn fillable(
+        &self,
+        world: &World<P::State>,
+        plan: &[Hop],
+        left: U256,
+        (probe, probe_out): (U256, U256),
+        impact_bps: u32,
+    ) -> U256 {
+        // out(amt) / amt >= (1 - impact) * probe_out / probe, cross-multiplied.
+        let keep = U512::from(10_000 - impact_bps.min(10_000));
+        let within = |amt: U256| {
+            U512::from(self.out(world, plan, amt)) * U512::from(probe) * U512::from(10_000u64)
+                >= U512::from(probe_out) * U512::from(amt) * keep
+        };
+        if within(left) {
+            return left;
+        }
+        let (mut lo, mut hi) = (U256::ZERO, left);
+        while hi - lo > U256::from(1u64) {
+            let mid = lo + ((hi - lo) >> 1);
+            if within(mid) {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+}
+
+impl<P: Pool> Router<P> {
+    /// [`Scanner::route_partial`] with the router's config.
+    pub fn route_partial(
+        &self,
+        from: TokenId,
+        to: TokenId,
+        amt_in: U256,
+        impact_bps: u32,
+        max_fills: usize,
+    ) -> RouteResult {
+        let engine = self.engine();
+        Scanner::new(&engine, self.graph())
+            .with_config(self.config)
+            .route_partial(self.world(), from, to, amt_in, impact_bps, max_fills)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::graph::AMMGraph;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, reserves};
+    use std::collections::HashMap;
+
+    #[test]
+    fn overflow_goes_to_a_fallback_route_then_stays_unfilled() {
+        // A deep direct pool and a shallow two-hop route.
+        let pools = HashMap::from([
+            (PoolId(1), Cp::new(1, 1, 2)),
+            (PoolId(2), Cp::new(2, 1, 3)),
+            (PoolId(3), Cp::new(3, 3, 2)),
+        ]);
+        let mut graph = AMMGraph::new();
+        let mut world = World::default();
+        for (id, a, b, r) in [(1, 1, 2, 1_000_000), (2, 1, 3, 200_000), (3, 3, 2, 200_000)] {
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(a), TokenId(b));
+            world.set_pool_state(PoolId(id), reserves(r, r));
+        }
+        let engine = Engine::new(&pools);
+        let scanner = Scanner::new(&engine, &graph);
+        let amt = U256::from(100_000u64);
+
+        let small = scanner.route_partial(
+            &world,
+            TokenId(1),
+            TokenId(2),
+            amt / U256::from(10u64),
+            500,
+            2,
+        );
+        assert!(small.is_complete());
+        assert_eq!(small.fills.len(), 1);
+
+        let result = scanner.route_partial(&world, TokenId(1), TokenId(2), amt, 500, 2);
+        assert_eq!(result.fills.len(), 2);
+        assert_eq!(result.fills[0].0.steps.len(), 1, "deep pool first");
+        assert!(!result.is_complete());
+        assert_eq!(result.filled() + result.unfilled, amt);
+        let primary = result.fills[0].1;
+        assert!(primary > U256::from(40_000u64) && primary < U256::from(60_000u64));
+
+        let only = scanner.route_partial(&world, TokenId(1), TokenId(2), amt, 500, 1);
+        assert_eq!(only.unfilled, amt - primary);
+        assert!(result.amount_out() > only.amount_out());
+    }
+}
@@ -0,0 +1,274 @@
+//! The per-block loop: apply the world update, let each strategy re-scan
+//! what the update touched, rank everything and send the ranking on.
+
+use crate::{
+    arb::Scanner,
+    canonical::RouteKey,
+    engine::Hop,
+    ids::{PoolId, TokenId},
+    pool::Pool,
+    router::Router,
+    world::WorldDiff,
+};
+use alloy_primitives::U256;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{Receiver, SendError, Sender};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    pub strategy: &'static str,
+    pub plan: Vec<Hop>,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    /// What candidates are ranked by, highest first.
+    pub score: U256,
+}
+
+#[derive(Clone, Debug)]
+pub struct Ranking {
+    pub block: u64,
+    pub candidates: Vec<Candidate>,
+}
+
+pub trait Strategy<P: Pool>: Send {
+    fn name(&self) -> &'static str;
+
+    /// Every candidate worth acting on in `router`'s current world.
+    /// `changed` holds the pools updated since the previous call, or is
+    /// `None` when everything must be treated as new.
+    fn scan(&mut self, router: &Router<P>, changed: Option<&HashSet<PoolId>>) -> Vec<Candidate>;
+}
+
+/// Cyclic arbitrage from `bases`, scored by net profit. Cycles whose pools
+/// did not change keep their previous sizing.
+pub struct ArbStrategy {
+    pub bases: Vec<TokenId>,
+    cache: HashMap<RouteKey, Option<Candidate>>,
+}
+
+impl ArbStrategy {
+    pub fn new(bases: Vec<TokenId>) -> Self {
+        Self {
+            bases,
+            cache: HashMap::new(),
+        }
+    }
+}
+
+impl<P: Pool> Strategy<P> for ArbStrategy {
+    fn name(&self) -> &'static str {
+        "arb"
+    }
+
+    fn scan(&mut self, router: &Router<P>, changed: Option<&HashSet<PoolId>>) -> Vec<Candidate> {
+        let engine = router.engine();
+        let scanner = Scanner::new(&engine, router.graph()).with_config(router.config);
+        let mut cache = HashMap::new();
+        for &base in &self.bases {
+            for plan in scanner.cycles(router.world(), base) {
+                let key = RouteKey::of(&plan);
+                if cache.contains_key(&key) {
+                    continue;
+                }
+                let stale = changed.is_none_or(|c| plan.iter().any(|h| c.contains(&h.pool)));
+                let cached = if stale { None } else { self.cache.remove(&key) };
+                let cand = cached.unwrap_or_else(|| {
+                    scanner.evaluate(router.world(), plan).map(|o| Candidate {
+                        strategy: "arb",
+                        amount_out: o.optimal_in + o.gross,
+                        amount_in: o.optimal_in,
+                        score: o.net,
+                        plan: o.plan,
+                    })
+                });
+                cache.insert(key, cand);
+            }
+        }
+        self.cache = cache;
+        self.cache.values().flatten().cloned().collect()
+    }
+}
+
+/// Moves `amount` of inventory from `from` to `to` along the best route,
+/// while that yields at least `min_out`; scored by the surplus over it.
+pub struct RebalanceStrategy {
+    pub from: TokenId,
+    pub to: TokenId,
+    pub amount: U256,
+    pub min_out: U256,
+}
+
+impl<P: Pool> Strategy<P> for RebalanceStrategy {
+    fn name(&self) -> &'static str {
+        "rebalance"
+    }
+
+    fn scan(&mut self, router: &Router<P>, _: Option<&HashSet<PoolId>>) -> Vec<Candidate> {
+        let Some(path) = router.route(self.from, self.to, self.amount) else {
+            return Vec::new();
+        };
+        let out = path.steps.last().map_or(U256::ZERO, |s| s.amt_out);
+        if out < self.min_out {
+            return Vec::new();
+        }
+        vec![Candidate {
+            strategy: "rebalance",
+            plan: path.plan().into_vec(),
+            amount_in: self.amount,
+            amount_out: out,
+            score: out - self.min_out,
+        }]
+    }
+}
+
+pub struct Pipeline<P: Pool> {
+    router: Router<P>,
+    strategies: Vec<Box<dyn Strategy<P>>>,
+    /// Candidates sent per block; all of them if zero.
+    pub top_n: usize,
+    out: Sender<Ranking>,
+    scanned: bool,
+}
+
+impl<P: Pool> Pipeline<P> {
+    pub fn new(router: Router<P>, out: Sender<Ranking>) -> Self {
+        Self {
+            router,
+            strategies: Vec::new(),
+            top_n: 0,
+            out,
+            scanned: false,
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: impl Strategy<P> + 'static) -> Self {
+        self.strategies.push(Box::new(strategy));
+        self
+    }
+
+    pub fn with_top_n(mut self, n: usize) -> Self {
+        self.top_n = n;
+        self
+    }
+
+    pub fn router(&self) -> &Router<P> {
+        &self.router
+    }
+
+    /// Applies `diff`, re-scans and sends the ranking. Fails only once the
+    /// receiving side is gone.
+    pub fn on_update(&mut self, diff: WorldDiff<P::State>) -> Result<(), SendError<Ranking>> {
+        let changed: HashSet<PoolId> = diff.pool_states.keys().copied().collect();
+        self.router.apply(diff);
+        let changed = self.scanned.then_some(&changed);
+        self.scanned = true;
+
+        let mut candidates: Vec<Candidate> = self
+            .strategies
+            .iter_mut()
+            .flat_map(|s| s.scan(&self.router, changed))
+            .collect();
+        candidates.sort_by(|a, b| {
+            Reverse(a.score)
+                .cmp(&Reverse(b.score))
+                .then_with(|| a.plan.cmp(&b.plan))
+        });
+        if self.top_n > 0 {
+            candidates.truncate(self.top_n);
+        }
+        self.out.send(Ranking {
+            block: self.router.world().block.number,
+            candidates,
+        })
+    }
+
+    /// Runs until `updates` closes or the ranking receiver is dropped, then
+    /// hands the router back.
+    pub fn run(mut self, updates: Receiver<WorldDiff<P::State>>) -> Router<P> {
+        for diff in updates {
+            if self.on_update(diff).is_err() {
+                break;
+            }
+        }
+        self.router
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::AMMGraph;
+    use crate::registry::Registry;
+    use crate::test_utils::{Cp, reserves};
+    use crate::world::{BlockContext, World};
+    use std::sync::mpsc;
+
+    fn router() -> Router<Cp> {
+        let mut graph = AMMGraph::new();
+        let mut pools = HashMap::new();
+        let mut world = World::default();
+        for (id, t0, t1) in [(1, 1, 2), (2, 1, 2), (3, 2, 3)] {
+            pools.insert(PoolId(id), Cp::new(id, t0, t1));
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(t0), TokenId(t1));
+            world.set_pool_state(PoolId(id), reserves(1_000_000, 1_000_000));
+        }
+        Router::new(Registry::default(), graph, pools, world)
+    }
+
+    #[test]
+    fn ranks_strategies_on_each_update() {
+        let (tx, rx) = mpsc::channel();
+        let (updates_tx, updates) = mpsc::channel();
+        let pipeline = Pipeline::new(router(), tx)
+            .with_strategy(ArbStrategy::new(vec![TokenId(1)]))
+            .with_strategy(RebalanceStrategy {
+                from: TokenId(1),
+                to: TokenId(3),
+                amount: U256::from(1_000u64),
+                min_out: U256::from(900u64),
+            });
+
+        let block = |n, pools: &[(u64, u64, u64)]| {
+            let mut diff = WorldDiff {
+                block: Some(BlockContext {
+                    number: n,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            for &(id, r0, r1) in pools {
+                diff.set_pool_state(PoolId(id), reserves(r0, r1));
+            }
+            diff
+        };
+        updates_tx.send(block(1, &[])).unwrap();
+        updates_tx
+            .send(block(2, &[(2, 1_000_000, 1_100_000)]))
+            .unwrap();
+        updates_tx
+            .send(block(3, &[(3, 1_000_000, 500_000)]))
+            .unwrap();
+        drop(updates_tx);
+        pipeline.run(updates);
+
+        let rankings: Vec<Ranking> = rx.iter().collect();
+        assert_eq!(rankings.len(), 3);
+        assert_eq!(rankings[0].block, 1);
+        assert!(
+            rankings[0]
+                .candidates
+                .iter()
+                .all(|c| c.strategy == "rebalance")
+        );
+
+        let second = &rankings[1].candidates;
+        assert_eq!(second[0].strategy, "arb", "{second:?}");
+        assert!(second.windows(2).all(|w| w[0].score >= w[1].score));
+
+        // The arb survives from cache; the rebalance no longer clears its floor.
+        let third = &rankings[2].candidates;
+        assert_eq!(third.len(), 1);
+        assert_eq!(third[0], second[0]);
+    }
+}
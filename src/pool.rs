@@ -1,9 +1,233 @@
-use crate::ids::{PoolId, TokenId};
-use alloy_primitives::U256;
+use crate::{
+    engine::Engine,
+    ids::{PoolId, SwapDirection},
+    num::{MathResult, Price},
+    world::{BlockContext, StateView},
+};
+use alloy_primitives::{U256, U512};
 
 pub trait Pool {
     type State: Clone;
     fn id(&self) -> PoolId;
-    fn supports(&self, from: TokenId, to: TokenId) -> bool;
-    fn swap(&self, st: &mut Self::State, from: TokenId, to: TokenId, amt_in: U256) -> U256;
+    fn supports(&self, dir: SwapDirection) -> bool;
+    /// Swaps `amt_in` through the pool, updating `st`. On error `st` must be
+    /// left as it was.
+    fn swap(
+        &self,
+        st: &mut Self::State,
+        ctx: &BlockContext,
+        dir: SwapDirection,
+        amt_in: U256,
+    ) -> MathResult<U256>;
+
+    /// A cheaper estimate of [`Pool::swap`] for screening many candidates,
+    /// e.g. a concentrated-liquidity swap at the current tick's liquidity
+    /// without crossing ticks. Defaults to the exact swap. On error `st`
+    /// must be left as it was.
+    fn swap_approx(
+        &self,
+        st: &mut Self::State,
+        ctx: &BlockContext,
+        dir: SwapDirection,
+        amt_in: U256,
+    ) -> MathResult<U256> {
+        self.swap(st, ctx, dir, amt_in)
+    }
+
+    /// Price an infinitesimal swap in `dir` gets, fees included, as read
+    /// from the state itself rather than by swapping; `None` if the pool
+    /// does not report one.
+    fn marginal_price(&self, _st: &Self::State, _dir: SwapDirection) -> Option<Price> {
+        None
+    }
+
+    /// Largest input whose average execution price is at most `impact_bps`
+    /// worse than the marginal price, fees included in both.
+    fn depth(
+        &self,
+        st: &Self::State,
+        ctx: &BlockContext,
+        dir: SwapDirection,
+        impact_bps: u32,
+    ) -> U256 {
+        bisect_depth(self, st, ctx, dir, impact_bps)
+    }
+}
+
+pub(crate) const DEPTH_PRECISION: u64 = 1_000_000;
+const DEPTH_MAX_BITS: usize = 192;
+
+/// Generic [`Pool::depth`]: estimates the marginal price from the smallest
+/// power-of-two input that resolves it, then bisects on simulated swaps.
+pub fn bisect_depth<P: Pool + ?Sized>(
+    pool: &P,
+    st: &P::State,
+    ctx: &BlockContext,
+    dir: SwapDirection,
+    impact_bps: u32,
+) -> U256 {
+    if impact_bps == 0 {
+        return U256::ZERO;
+    }
+    if impact_bps >= 10_000 {
+        return U256::MAX;
+    }
+    // Failing swaps count as no output, which bounds the search from above.
+    let out = |amt: U256| {
+        pool.swap(&mut st.clone(), ctx, dir, amt)
+            .unwrap_or_default()
+    };
+
+    let mut probe = U256::from(1u64);
+    let mut probe_out = out(probe);
+    while probe_out < U256::from(DEPTH_PRECISION) {
+        if probe.bit_len() >= DEPTH_MAX_BITS {
+            return U256::ZERO;
+        }
+        let doubled = out(probe << 1);
+        if !probe_out.is_zero() && doubled + U256::from(1u64) < probe_out << 1 {
+            break;
+        }
+        probe <<= 1;
+        probe_out = doubled;
+    }
+
+    // out(amt) / amt >= (1 - impact) * probe_out / probe, cross-multiplied.
+    let keep = U512::from(10_000 - impact_bps);
+    let within = |amt: U256| {
+        U512::from(out(amt)) * U512::from(probe) * U512::from(10_000u64)
+            >= U512::from(probe_out) * U512::from(amt) * keep
+    };
+
+    let (mut lo, mut hi) = (probe, probe << 1);
+    while within(hi) {
+        if hi.bit_len() >= DEPTH_MAX_BITS {
+            return hi;
+        }
+        lo = hi;
+        hi <<= 1;
+    }
+    while hi - lo > U256::from(1u64) {
+        let mid = lo + ((hi - lo) >> 1);
+        if within(mid) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DepthReport {
+    pub dir: Option<SwapDirection>,
+    pub impact_bps: u32,
+    /// Per-pool depth, deepest first.
+    pub pools: Vec<(PoolId, U256)>,
+    pub total: U256,
+}
+
+impl DepthReport {
+    /// Every pool in `engine` quoting `dir` with state in `world`.
+    pub fn collect<P: Pool, V: StateView<P::State>>(
+        engine: &Engine<'_, P>,
+        world: &V,
+        dir: SwapDirection,
+        impact_bps: u32,
+    ) -> Self {
+        let ctx = world.block();
+        let mut pools: Vec<(PoolId, U256)> = engine
+            .pools
+            .iter()
+            .filter(|(_, p)| p.supports(dir))
+            .filter_map(|(&pid, p)| {
+                Some((pid, p.depth(world.pool_state(pid)?, &ctx, dir, impact_bps)))
+            })
+            .filter(|(_, d)| !d.is_zero())
+            .collect();
+        pools.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let total = pools
+            .iter()
+            .fold(U256::ZERO, |acc, (_, d)| acc.saturating_add(*d));
+        Self {
+            dir: Some(dir),
+            impact_bps,
+            pools,
+            total,
+        }
+    }
+
+    /// Splits `amt_in` across pools in proportion to depth; rounding dust
+    /// goes to the deepest pool.
+    pub fn allocate(&self, amt_in: U256) -> Vec<(PoolId, U256)> {
+        if self.total.is_zero() {
+            return Vec::new();
+        }
+        let mut out: Vec<(PoolId, U256)> = self
+            .pools
+            .iter()
+            .map(|&(pid, d)| {
+                let share = U512::from(amt_in) * U512::from(d) / U512::from(self.total);
+                (pid, U256::from(share))
+            })
+            .collect();
+        let placed = out.iter().fold(U256::ZERO, |acc, (_, a)| acc + *a);
+        out[0].1 += amt_in - placed;
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::TokenId;
+    use crate::univ2::{UniV2Pool, UniV2State};
+    use std::collections::HashMap;
+
+    #[test]
+    fn bisection_matches_constant_product_closed_form() {
+        // x = r_in * i / ((1 - i) * (1 - f)) for a constant-product pool.
+        let pool = UniV2Pool::new(PoolId(1), TokenId(1), TokenId(2));
+        let r = U256::from(10u64).pow(U256::from(21u64));
+        let st = UniV2State::new(r, r * U256::from(3u64));
+        let dir = SwapDirection::new(TokenId(1), TokenId(2)).unwrap();
+
+        let depth = pool.depth(&st, &BlockContext::default(), dir, 100);
+        let expected = r * U256::from(100u64 * 10_000) / U256::from(9_900u64 * 9_970);
+        let err = depth.abs_diff(expected) * U256::from(10_000u64) / expected;
+        assert!(err <= U256::from(1u64), "{depth} vs {expected}");
+    }
+
+    #[test]
+    fn report_ranks_pools_and_allocates_by_depth() {
+        let pools = HashMap::from([
+            (PoolId(1), UniV2Pool::new(PoolId(1), TokenId(1), TokenId(2))),
+            (PoolId(2), UniV2Pool::new(PoolId(2), TokenId(1), TokenId(2))),
+            (PoolId(3), UniV2Pool::new(PoolId(3), TokenId(2), TokenId(3))),
+        ]);
+        let mut world = crate::world::World::default();
+        let e18 = U256::from(10u64).pow(U256::from(18u64));
+        world
+            .pool_states
+            .insert(PoolId(1), UniV2State::new(e18, e18));
+        world
+            .pool_states
+            .insert(PoolId(2), UniV2State::new(e18 * U256::from(3u64), e18));
+        world
+            .pool_states
+            .insert(PoolId(3), UniV2State::new(e18, e18));
+
+        let engine = Engine::new(&pools);
+        let dir = SwapDirection::new(TokenId(1), TokenId(2)).unwrap();
+        let report = DepthReport::collect(&engine, &world, dir, 50);
+        assert_eq!(
+            report.pools.iter().map(|(p, _)| *p).collect::<Vec<_>>(),
+            vec![PoolId(2), PoolId(1)]
+        );
+
+        let amt = U256::from(1_000_001u64);
+        let alloc = report.allocate(amt);
+        assert_eq!(alloc.iter().fold(U256::ZERO, |a, (_, x)| a + *x), amt);
+        assert!(alloc[0].1 > alloc[1].1 * U256::from(2u64));
+    }
 }
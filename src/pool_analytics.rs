@@ -0,0 +1,212 @@
+//! Per-pool estimates for liquidity providers: the fee yield recent volume
+//! implies, annualized, and the divergence loss the recorded price path would
+//! have cost a full-range position. Volume comes from an
+//! [`ActivityTracker`] and prices from a [`PriceHistory`], both fed by sync.
+
+use crate::{
+    activity::ActivityTracker,
+    engine::Engine,
+    history::PriceHistory,
+    ids::PoolId,
+    lp::{LpPool, LpPosition},
+    registry::Registry,
+    world::StateView,
+};
+use alloy_primitives::U256;
+
+pub const SECONDS_PER_YEAR: f64 = 365.0 * 24.0 * 3600.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PoolEstimate {
+    pub pool: PoolId,
+    /// Reserves valued in raw units of token1 at the current price.
+    pub tvl: f64,
+    /// Fees on the tracker's window of volume over `tvl`, scaled to a year;
+    /// `None` without two timed price points to date the window by.
+    pub fee_apr: Option<f64>,
+    /// Loss against holding, as a fraction, of a position opened at the
+    /// oldest recorded price: `1 - 2√r / (1 + r)` for a price ratio `r`.
+    pub divergence_loss: Option<f64>,
+    /// Seconds between the oldest and newest recorded prices.
+    pub history_secs: u64,
+}
+
+/// Estimates over one registry's pools; see [`Registry::pool_analytics`].
+#[derive(Clone, Copy, Debug)]
+pub struct PoolAnalytics<'a> {
+    pub registry: &'a Registry,
+    pub activity: &'a ActivityTracker,
+    pub history: &'a PriceHistory,
+}
+
+impl Registry {
+    pub fn pool_analytics<'a>(
+        &'a self,
+        activity: &'a ActivityTracker,
+        history: &'a PriceHistory,
+    ) -> PoolAnalytics<'a> {
+        PoolAnalytics {
+            registry: self,
+            activity,
+            history,
+        }
+    }
+}
+
+impl PoolAnalytics<'_> {
+    /// `None` for pools missing from the registry, the engine or the world,
+    /// or with nothing on one side.
+    pub fn estimate<P: LpPool, V: StateView<P::State>>(
+        &self,
+        engine: &Engine<'_, P>,
+        world: &V,
+        pid: PoolId,
+    ) -> Option<PoolEstimate> {
+        let meta = self.registry.pool(pid)?;
+        let whole = LpPosition {
+            pool: pid,
+            shares: U256::from(1u64),
+            total_shares: U256::from(1u64),
+        };
+        let [(_, r0), (_, r1)] = engine
+            .pools
+            .get(&pid)?
+            .redeemable(world.pool_state(pid)?, &whole)?;
+        let (r0, r1) = (f64::from(r0), f64::from(r1));
+        if r0 <= 0.0 || r1 <= 0.0 {
+            return None;
+        }
+        let price = r1 / r0;
+        let tvl = r0 * price + r1;
+
+        let points: Vec<_> = self.history.points(pid).copied().collect();
+        let (first, last) = (points.first(), points.last());
+        let history_secs = match (first, last) {
+            (Some(a), Some(b)) => b.timestamp.saturating_sub(a.timestamp),
+            _ => 0,
+        };
+        let block_secs = match (first, last) {
+            (Some(a), Some(b)) if b.block > a.block && b.timestamp > a.timestamp => {
+                Some((b.timestamp - a.timestamp) as f64 / (b.block - a.block) as f64)
+            }
+            _ => None,
+        };
+
+        let [v0, v1] = self.activity.volume(pid, world.block().number);
+        // Fees are in hundredths of a basis point.
+        let fees = (f64::from(v0) * price + f64::from(v1)) * f64::from(meta.fee) / 1e6;
+        let window_secs = block_secs.map(|s| s * self.activity.window as f64);
+        let fee_apr = window_secs.map(|w| fees / tvl * SECONDS_PER_YEAR / w);
+
+        let divergence_loss = match (first, last) {
+            (Some(a), Some(b)) if a.price > 0.0 && points.len() > 1 => {
+                let r = b.price / a.price;
+                Some(1.0 - 2.0 * r.sqrt() / (1.0 + r))
+            }
+            _ => None,
+        };
+
+        Some(PoolEstimate {
+            pool: pid,
+            tvl,
+            fee_apr,
+            divergence_loss,
+            history_secs,
+        })
+    }
+
+    /// Estimates for every registered pool that has one, best fee APR first.
+    pub fn estimates<P: LpPool, V: StateView<P::State>>(
+        &self,
+        engine: &Engine<'_, P>,
+        world: &V,
+    ) -> Vec<PoolEstimate> {
+        let mut out: Vec<_> = self
+            .registry
+            .pool_meta
+            .keys()
+            .filter_map(|&pid| self.estimate(engine, world, pid))
+            .collect();
+        out.sort_by(|a, b| {
+            b.fee_apr
+                .unwrap_or(f64::NEG_INFINITY)
+                .total_cmp(&a.fee_apr.unwrap_or(f64::NEG_INFINITY))
+                .then(a.pool.cmp(&b.pool))
+        });
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Hop;
+    use crate::history::PricePoint;
+    use crate::ids::{SwapDirection, TokenId};
+    use crate::registry::{PoolKind, PoolMeta};
+    use crate::univ2::{UniV2Pool, UniV2State};
+    use crate::world::World;
+    use alloy_primitives::Address;
+    use std::collections::HashMap;
+
+    #[test]
+    fn annualizes_window_fees_and_prices_divergence() {
+        let (a, b) = (TokenId(1), TokenId(2));
+        let mut reg = Registry::default();
+        let mut pools = HashMap::new();
+        let mut world = World::default();
+        let e18 = U256::from(10u64).pow(U256::from(18u64));
+        for id in [1, 2] {
+            reg.upsert_pool(
+                PoolId(id),
+                PoolMeta {
+                    address: Address::repeat_byte(id as u8),
+                    kind: PoolKind::UniV2,
+                    token0: a,
+                    token1: b,
+                    fee: 3_000,
+                },
+            );
+            pools.insert(PoolId(id), UniV2Pool::new(PoolId(id), a, b));
+            world.set_pool_state(PoolId(id), UniV2State::new(e18, e18));
+        }
+        world.block.number = 100;
+
+        // A tenth of the pool's depth in each direction over 100 blocks.
+        let mut activity = ActivityTracker::new(100);
+        activity.record_swap(PoolId(1), 100, [e18 / U256::from(10u64), U256::ZERO]);
+        activity.record_swap(PoolId(1), 100, [U256::ZERO, e18 / U256::from(10u64)]);
+
+        let mut history = PriceHistory::new(8);
+        history.track(
+            Hop::new(PoolId(1), SwapDirection::new(a, b).unwrap()),
+            U256::from(1u64),
+        );
+        for (block, price) in [(0, 1.0), (100, 4.0)] {
+            history.record(
+                PoolId(1),
+                PricePoint {
+                    block,
+                    timestamp: block * 12,
+                    price,
+                },
+            );
+        }
+
+        let engine = Engine::new(&pools);
+        let analytics = reg.pool_analytics(&activity, &history);
+        let est = analytics.estimate(&engine, &world, PoolId(1)).unwrap();
+        assert_eq!(est.tvl, 2e18);
+        assert_eq!(est.history_secs, 1_200);
+        // 0.3% of 0.2e18 is 6e14 on 2e18, over 1,200 seconds.
+        let apr = est.fee_apr.unwrap();
+        assert!((apr - 3e-4 * SECONDS_PER_YEAR / 1_200.0).abs() < 1e-9 * apr);
+        // A 4x move costs a full-range position 1 - 2·2/5 = 20%.
+        assert!((est.divergence_loss.unwrap() - 0.2).abs() < 1e-12);
+
+        let all = analytics.estimates(&engine, &world);
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].pool, PoolId(1));
+        assert_eq!((all[1].fee_apr, all[1].divergence_loss), (None, None));
+    }
+}
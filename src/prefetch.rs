@@ -0,0 +1,186 @@
+//! Refresh order driven by route popularity. Pools that keep turning up in
+//! winning routes are the ones whose stale state costs the most, so each
+//! block they are refreshed first, within a fixed budget of reads.
+
+use crate::{
+    engine::{Hop, Path},
+    error::WayfinderError,
+    ids::PoolId,
+    provider::StateProvider,
+    world::World,
+};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap, VecDeque};
+
+/// Blocks of winning routes [`Prefetcher::new`] remembers by default.
+pub const DEFAULT_PREFETCH_WINDOW: u64 = 100;
+
+/// Pools [`Prefetcher::new`] refreshes per block by default.
+pub const DEFAULT_PREFETCH_BUDGET: usize = 64;
+
+#[derive(Clone, Debug)]
+pub struct Prefetcher {
+    /// Blocks a win counts toward a pool's popularity.
+    pub window: u64,
+    /// Pools read per [`World::prefetch`].
+    pub budget: usize,
+    /// Blocks each pool was on a winning route, oldest first.
+    wins: HashMap<PoolId, VecDeque<u64>>,
+}
+
+impl Default for Prefetcher {
+    fn default() -> Self {
+        Self::new(DEFAULT_PREFETCH_WINDOW, DEFAULT_PREFETCH_BUDGET)
+    }
+}
+
+impl Prefetcher {
+    pub fn new(window: u64, budget: usize) -> Self {
+        Self {
+            window: window.max(1),
+            budget,
+            wins: HashMap::new(),
+        }
+    }
+
+    /// Counts `plan` as having won at `block`, once per pool it uses.
+    pub fn record_route(&mut self, block: u64, plan: &[Hop]) {
+        let pools: BTreeSet<_> = plan.iter().map(|h| h.pool).collect();
+        let oldest = block.saturating_sub(self.window - 1);
+        for pid in pools {
+            let wins = self.wins.entry(pid).or_default();
+            if wins.back().is_none_or(|&b| b <= block) {
+                wins.push_back(block);
+            }
+            while wins.front().is_some_and(|&b| b < oldest) {
+                wins.pop_front();
+            }
+        }
+    }
+
+    pub fn record_path(&mut self, block: u64, path: &Path) {
+        let plan: Vec<_> = path
+            .steps
+            .iter()
+            .map(|s| Hop::new(s.pool, s.direction()))
+            .collect();
+        self.record_route(block, &plan);
+    }
+
+    /// Winning routes through `pid` within the window ending at `block`.
+    pub fn popularity(&self, pid: PoolId, block: u64) -> usize {
+        let oldest = block.saturating_sub(self.window - 1);
+        self.wins.get(&pid).map_or(0, |w| {
+            w.iter().filter(|&&b| (oldest..=block).contains(&b)).count()
+        })
+    }
+
+    /// `pools` most popular first, ties going to the most recent winner and
+    /// then the lower id; pools without wins keep to the back.
+    pub fn order(&self, pools: impl IntoIterator<Item = PoolId>, block: u64) -> Vec<PoolId> {
+        let mut queue: BinaryHeap<_> = pools
+            .into_iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|pid| {
+                let last = self.wins.get(&pid).and_then(|w| w.back()).copied();
+                (self.popularity(pid, block), last, Reverse(pid))
+            })
+            .collect();
+        std::iter::from_fn(|| queue.pop().map(|(_, _, Reverse(pid))| pid)).collect()
+    }
+
+    /// Pools with a win in the window ending at `block`.
+    pub fn popular(&self, block: u64) -> Vec<PoolId> {
+        let pools = self
+            .wins
+            .keys()
+            .copied()
+            .filter(|&pid| self.popularity(pid, block) > 0);
+        self.order(pools, block)
+    }
+}
+
+impl<S> World<S> {
+    /// Re-reads, at the world's block, the `budget` pools the prefetcher
+    /// ranks highest among those the world holds or recently routed
+    /// through, most popular first. Returns the pools read.
+    pub async fn prefetch<P: StateProvider<S>>(
+        &mut self,
+        provider: &P,
+        prefetcher: &Prefetcher,
+    ) -> Result<Vec<PoolId>, WayfinderError> {
+        let block = self.block.number;
+        let candidates = self
+            .pool_states
+            .keys()
+            .copied()
+            .chain(prefetcher.popular(block));
+        let mut order = prefetcher.order(candidates, block);
+        order.truncate(prefetcher.budget);
+        for &pid in &order {
+            let st = provider.pool_state(pid, block).await?;
+            self.set_pool_state(pid, st);
+        }
+        Ok(order)
+    }
+}
+
+#[cfg(feature = "rpc")]
+impl<P: alloy_provider::Provider> crate::sync::WorldSync<P> {
+    /// [`WorldSync::refresh`](crate::sync::WorldSync::refresh) of the
+    /// prefetcher's `budget` highest ranked pools among `pools`.
+    pub async fn refresh_popular(
+        &mut self,
+        world: &mut World<crate::univ2::UniV2State>,
+        prefetcher: &Prefetcher,
+        pools: &[PoolId],
+        block: u64,
+    ) -> Result<crate::sync::RefreshReport, WayfinderError> {
+        let mut order = prefetcher.order(pools.iter().copied(), block);
+        order.truncate(prefetcher.budget);
+        self.refresh(world, &order, block).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{hop, reserves};
+
+    #[tokio::test]
+    async fn refreshes_the_most_routed_pools_first_within_budget() {
+        let mut prefetcher = Prefetcher::new(10, 2);
+        prefetcher.record_route(1, &[hop(1, 1, 2), hop(2, 2, 3)]);
+        prefetcher.record_route(5, &[hop(2, 2, 3), hop(3, 3, 1), hop(2, 3, 2)]);
+        prefetcher.record_route(6, &[hop(3, 3, 1)]);
+        assert_eq!(prefetcher.popularity(PoolId(2), 6), 2);
+        // Pool 3 ties pool 2 but won more recently; pool 4 never won.
+        let all = [PoolId(4), PoolId(1), PoolId(2), PoolId(3)];
+        assert_eq!(
+            prefetcher.order(all, 6),
+            [PoolId(3), PoolId(2), PoolId(1), PoolId(4)]
+        );
+        // Pool 1's only win has left the window.
+        assert_eq!(
+            prefetcher.order(all, 12),
+            [PoolId(3), PoolId(2), PoolId(1), PoolId(4)]
+        );
+        assert_eq!(prefetcher.popular(12), [PoolId(3), PoolId(2)]);
+
+        let mut source = World::default();
+        source.block.number = 12;
+        for id in 1..=4 {
+            source.set_pool_state(PoolId(id), reserves(2_000, 2_000));
+        }
+        let mut world = World::default();
+        world.block.number = 12;
+        world.set_pool_state(PoolId(1), reserves(1_000, 1_000));
+        world.set_pool_state(PoolId(4), reserves(1_000, 1_000));
+
+        let read = world.prefetch(&source, &prefetcher).await.unwrap();
+        assert_eq!(read, [PoolId(3), PoolId(2)]);
+        assert_eq!(world.pool_states[&PoolId(3)], reserves(2_000, 2_000));
+        assert_eq!(world.pool_states[&PoolId(1)], reserves(1_000, 1_000));
+    }
+}
@@ -0,0 +1,393 @@
+use crate::{
+    engine::{Engine, Hop},
+    graph::{AMMGraph, NodeKind},
+    ids::{SwapDirection, TokenId},
+    num::Price,
+    pool::Pool,
+    registry::Registry,
+    telemetry,
+    world::StateView,
+};
+use alloy_primitives::U256;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+#[derive(Clone, Copy, Debug)]
+pub struct PriceConfig {
+    pub depth: f64,
+    pub max_impact_bps: u64,
+    pub hop_penalty_bps: u64,
+    pub max_hops: usize,
+    pub max_age_blocks: u64,
+}
+
+impl Default for PriceConfig {
+    fn default() -> Self {
+        Self {
+            depth: 1_000.0,
+            max_impact_bps: 500,
+            hop_penalty_bps: 10,
+            max_hops: 3,
+            max_age_blocks: 5,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PriceEntry {
+    pub price: f64,
+    pub block: u64,
+    pub cost_bps: u64,
+    pub path: Vec<Hop>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PriceOracle {
+    pub numeraire: TokenId,
+    pub config: PriceConfig,
+    prices: HashMap<TokenId, PriceEntry>,
+}
+
+/// Whole-token prices among `tokens`, each derived through the numeraire
+/// along its most liquid path. `values` is row-major: row `i`, column `j`
+/// holds the price of `tokens[i]` in `tokens[j]`, `None` where either side
+/// has no price.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PriceMatrix {
+    pub numeraire: TokenId,
+    pub block: u64,
+    pub tokens: Vec<TokenId>,
+    pub values: Vec<Option<f64>>,
+}
+
+impl PriceMatrix {
+    /// Price of one `base` in `quote`.
+    pub fn get(&self, base: TokenId, quote: TokenId) -> Option<f64> {
+        let i = self.tokens.iter().position(|&t| t == base)?;
+        let j = self.tokens.iter().position(|&t| t == quote)?;
+        self.values[i * self.tokens.len() + j]
+    }
+
+    /// [`PriceMatrix::get`] as a fixed-point [`Price`] of whole tokens.
+    pub fn fixed(&self, base: TokenId, quote: TokenId) -> Option<Price> {
+        let v = self.get(base, quote)? * 2f64.powi(Price::FRACTION_BITS as i32);
+        U256::try_from(v).ok().map(Price)
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Option<f64>]> {
+        self.values.chunks(self.tokens.len().max(1))
+    }
+}
+
+/// Refreshes an oracle for `numeraire` with default settings and tabulates
+/// `tokens` against each other.
+pub fn matrix<P: Pool, V: StateView<P::State>>(
+    engine: &Engine<'_, P>,
+    graph: &AMMGraph,
+    reg: &Registry,
+    world: &V,
+    tokens: &[TokenId],
+    numeraire: TokenId,
+) -> PriceMatrix {
+    let mut oracle = PriceOracle::new(numeraire, PriceConfig::default());
+    oracle.refresh(engine, graph, reg, world);
+    oracle.matrix(tokens, world.block().number)
+}
+
+fn pow10(exp: i32) -> f64 {
+    10f64.powi(exp)
+}
+
+pub fn spot_rate<P: Pool, V: StateView<P::State>>(
+    engine: &Engine<'_, P>,
+    world: &V,
+    hop: Hop,
+    probe_in: U256,
+) -> Option<f64> {
+    if probe_in.is_zero() {
+        return None;
+    }
+    let path = engine.simulate_chained(world, &[hop], probe_in);
+    let rate = f64::from(path.steps[0].amt_out) / f64::from(probe_in);
+    (rate > 0.0).then_some(rate)
+}
+
+impl PriceOracle {
+    pub fn new(numeraire: TokenId, config: PriceConfig) -> Self {
+        Self {
+            numeraire,
+            config,
+            prices: HashMap::new(),
+        }
+    }
+
+    pub fn entry(&self, t: TokenId) -> Option<&PriceEntry> {
+        self.prices.get(&t)
+    }
+
+    pub fn is_stale(&self, t: TokenId, block: u64) -> bool {
+        self.prices
+            .get(&t)
+            .is_none_or(|e| block.saturating_sub(e.block) > self.config.max_age_blocks)
+    }
+
+    pub fn price(&self, t: TokenId, block: u64) -> Option<f64> {
+        let stale = self.is_stale(t, block);
+        telemetry::price_lookup(!stale);
+        if stale {
+            return None;
+        }
+        self.prices.get(&t).map(|e| e.price)
+    }
+
+    pub fn value(&self, reg: &Registry, t: TokenId, amount: U256, block: u64) -> Option<f64> {
+        let decimals = reg.token(t)?.decimals as i32;
+        Some(f64::from(amount) / pow10(decimals) * self.price(t, block)?)
+    }
+
+    /// Cross prices among `tokens` from the prices fresh at `block`.
+    pub fn matrix(&self, tokens: &[TokenId], block: u64) -> PriceMatrix {
+        let prices: Vec<_> = tokens.iter().map(|&t| self.price(t, block)).collect();
+        let values = prices
+            .iter()
+            .flat_map(|a| {
+                prices.iter().map(move |b| match (a, b) {
+                    (Some(a), Some(b)) if *b > 0.0 => Some(a / b),
+                    _ => None,
+                })
+            })
+            .collect();
+        PriceMatrix {
+            numeraire: self.numeraire,
+            block,
+            tokens: tokens.to_vec(),
+            values,
+        }
+    }
+
+    pub fn stale_tokens(&self, block: u64) -> Vec<TokenId> {
+        let mut out: Vec<_> = self
+            .prices
+            .keys()
+            .copied()
+            .filter(|&t| self.is_stale(t, block))
+            .collect();
+        out.sort();
+        out
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", name = "price_refresh", skip_all, fields(numeraire = %self.numeraire))
+    )]
+    pub fn refresh<P: Pool, V: StateView<P::State>>(
+        &mut self,
+        engine: &Engine<'_, P>,
+        graph: &AMMGraph,
+        reg: &Registry,
+        world: &V,
+    ) -> usize {
+        let block = world.block().number;
+        if reg.token(self.numeraire).is_none() || !graph.token_idx.contains_key(&self.numeraire) {
+            return 0;
+        }
+
+        let mut best: HashMap<TokenId, PriceEntry> = HashMap::new();
+        best.insert(
+            self.numeraire,
+            PriceEntry {
+                price: 1.0,
+                block,
+                cost_bps: 0,
+                path: Vec::new(),
+            },
+        );
+
+        let mut heap = BinaryHeap::new();
+        heap.push(Reverse((0u64, self.numeraire)));
+
+        while let Some(Reverse((cost, at))) = heap.pop() {
+            let at_entry = best[&at].clone();
+            if cost > at_entry.cost_bps || at_entry.path.len() >= self.config.max_hops {
+                continue;
+            }
+            let Some(at_dec) = reg.token(at).map(|m| m.decimals as i32) else {
+                continue;
+            };
+
+            for pix in graph.pools_emitting(at).into_iter().flatten() {
+                let NodeKind::Pool(pid) = graph.g[pix] else {
+                    continue;
+                };
+                if !engine.pools.contains_key(&pid) || world.pool_state(pid).is_none() {
+                    continue;
+                }
+                for tix in graph.tokens_accepted_by(pid).into_iter().flatten() {
+                    let NodeKind::Token(prev) = graph.g[tix] else {
+                        continue;
+                    };
+                    let Some(dir) = SwapDirection::new(prev, at) else {
+                        continue;
+                    };
+                    let Some(prev_dec) = reg.token(prev).map(|m| m.decimals as i32) else {
+                        continue;
+                    };
+                    if !engine.pools[&pid].supports(dir) {
+                        continue;
+                    }
+
+                    let hop = Hop::new(pid, dir);
+                    let Some((price, impact)) =
+                        self.probe(engine, world, hop, prev_dec, at_dec, at_entry.price)
+                    else {
+                        continue;
+                    };
+                    if impact > self.config.max_impact_bps {
+                        continue;
+                    }
+
+                    let next_cost = cost + impact + self.config.hop_penalty_bps;
+                    if best.get(&prev).is_some_and(|e| e.cost_bps <= next_cost) {
+                        continue;
+                    }
+                    let mut path = Vec::with_capacity(at_entry.path.len() + 1);
+                    path.push(hop);
+                    path.extend_from_slice(&at_entry.path);
+                    best.insert(
+                        prev,
+                        PriceEntry {
+                            price,
+                            block,
+                            cost_bps: next_cost,
+                            path,
+                        },
+                    );
+                    heap.push(Reverse((next_cost, prev)));
+                }
+            }
+        }
+
+        let updated = best.len();
+        self.prices.extend(best);
+        updated
+    }
+
+    fn probe<P: Pool, V: StateView<P::State>>(
+        &self,
+        engine: &Engine<'_, P>,
+        world: &V,
+        hop: Hop,
+        from_dec: i32,
+        to_dec: i32,
+        to_price: f64,
+    ) -> Option<(f64, u64)> {
+        let small_in = (U256::from(10u64).pow(U256::from(from_dec as u64)) / U256::from(1_000u64))
+            .max(U256::from(1u64));
+        let small_rate = spot_rate(engine, world, hop, small_in)?;
+        let price = small_rate * pow10(from_dec - to_dec) * to_price;
+
+        let depth_in = U256::try_from(self.config.depth / price * pow10(from_dec)).ok()?;
+        if depth_in <= small_in {
+            return Some((price, 0));
+        }
+        let depth_rate = spot_rate(engine, world, hop, depth_in).unwrap_or_default();
+        let impact = ((1.0 - depth_rate / small_rate).max(0.0) * 10_000.0) as u64;
+        Some((price, impact))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::PoolId;
+    use crate::registry::TokenMeta;
+    use crate::test_utils::Cp;
+    use crate::world::World;
+    use alloy_primitives::Address;
+
+    const WETH: TokenId = TokenId(1);
+    const USDC: TokenId = TokenId(2);
+    const DAI: TokenId = TokenId(3);
+    const SHIB: TokenId = TokenId(4);
+
+    fn e(n: u64, dec: u32) -> U256 {
+        U256::from(n) * U256::from(10u64).pow(U256::from(dec))
+    }
+
+    #[test]
+    fn prices_follow_liquid_paths_and_go_stale() {
+        let mut reg = Registry::default();
+        for (t, dec) in [(WETH, 18u8), (USDC, 6), (DAI, 18), (SHIB, 18)] {
+            reg.upsert_token(
+                t,
+                TokenMeta {
+                    address: Address::repeat_byte(t.0 as u8),
+                    symbol: t.to_string(),
+                    decimals: dec,
+                },
+            );
+        }
+
+        let mut pools = HashMap::new();
+        let mut graph = AMMGraph::new();
+        let mut world = World::default();
+        world.block.number = 100;
+        for (id, a, b, ra, rb) in [
+            (1, WETH, USDC, e(10_000, 18), e(20_000_000, 6)),
+            (2, DAI, WETH, e(20_000_000, 18), e(10_000, 18)),
+            (3, DAI, USDC, e(10, 18), e(5, 6)),
+            (4, SHIB, WETH, e(1_000, 18), e(1, 18)),
+        ] {
+            pools.insert(PoolId(id), Cp::new(id, a.0, b.0));
+            graph.connect_bidirectional_pair(PoolId(id), a, b);
+            world.pool_states.insert(PoolId(id), (ra, rb));
+        }
+        let engine = Engine::new(&pools);
+
+        let mut oracle = PriceOracle::new(USDC, PriceConfig::default());
+        oracle.refresh(&engine, &graph, &reg, &world);
+
+        let weth = oracle.price(WETH, 100).unwrap();
+        assert!((weth - 2_000.0).abs() < 1.0, "{weth}");
+        let dai = oracle.price(DAI, 100).unwrap();
+        assert!((dai - 1.0).abs() < 0.01, "{dai}");
+        assert_eq!(
+            oracle.entry(DAI).unwrap().path.len(),
+            2,
+            "skips the shallow pool"
+        );
+        assert!(oracle.price(SHIB, 100).is_none(), "too illiquid at depth");
+
+        let v = oracle.value(&reg, WETH, e(3, 18), 100).unwrap();
+        assert!((v - 6_000.0).abs() < 5.0);
+
+        assert!(oracle.price(WETH, 105).is_some());
+        assert!(oracle.price(WETH, 106).is_none());
+        assert_eq!(oracle.stale_tokens(106), vec![WETH, USDC, DAI]);
+
+        let m = matrix(&engine, &graph, &reg, &world, &[WETH, DAI, SHIB], USDC);
+        let weth_dai = m.get(WETH, DAI).unwrap();
+        assert!((weth_dai - 2_000.0).abs() < 25.0, "{weth_dai}");
+        assert!((m.get(DAI, WETH).unwrap() * weth_dai - 1.0).abs() < 1e-9);
+        assert_eq!(m.get(WETH, WETH), Some(1.0));
+        assert!(m.get(SHIB, WETH).is_none() && m.get(WETH, USDC).is_none());
+        assert!((m.fixed(WETH, WETH).unwrap().to_f64() - 1.0).abs() < 1e-12);
+        assert_eq!(m.rows().count(), 3);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn matrix_round_trips_through_json_with_missing_prices() {
+        let m = PriceMatrix {
+            numeraire: USDC,
+            block: 7,
+            tokens: vec![WETH, SHIB],
+            values: vec![Some(1.0), None, None, Some(1.0)],
+        };
+        let json = serde_json::to_string(&m).unwrap();
+        let back: PriceMatrix = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, m);
+        assert!(back.get(WETH, SHIB).is_none());
+    }
+}
@@ -0,0 +1,348 @@
+//! Pool state sources that can be queried on demand, so a [`World`] only
+//! holds the pools a search actually touches.
+
+use crate::{
+    arb::Scanner,
+    engine::{Engine, Hop, Path},
+    error::WayfinderError,
+    graph::NodeKind,
+    ids::{PoolId, TokenId},
+    pool::Pool,
+    world::World,
+};
+use alloy_primitives::U256;
+use std::collections::{BTreeSet, HashSet};
+use std::future::Future;
+
+pub trait StateProvider<S> {
+    /// State of `pid` as of the end of `block`.
+    fn pool_state(
+        &self,
+        pid: PoolId,
+        block: u64,
+    ) -> impl Future<Output = Result<S, WayfinderError>> + Send;
+}
+
+/// A world answers for its own block, which makes a loaded snapshot a
+/// provider for a lazily hydrated one.
+impl<S: Clone + Send + Sync> StateProvider<S> for World<S> {
+    fn pool_state(
+        &self,
+        pid: PoolId,
+        block: u64,
+    ) -> impl Future<Output = Result<S, WayfinderError>> + Send {
+        let st = match self.pool_states.get(&pid) {
+            Some(st) if block == self.block.number => Ok(st.clone()),
+            _ => Err(WayfinderError::Unavailable { pool: pid, block }),
+        };
+        std::future::ready(st)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<S: Clone + Send + Sync> StateProvider<S> for crate::checkpoint::Checkpoint<S> {
+    fn pool_state(
+        &self,
+        pid: PoolId,
+        block: u64,
+    ) -> impl Future<Output = Result<S, WayfinderError>> + Send {
+        let st = match self.world.pool_states.get(&pid) {
+            Some(st) if block == self.block => Ok(st.clone()),
+            _ => Err(WayfinderError::Unavailable { pool: pid, block }),
+        };
+        std::future::ready(st)
+    }
+}
+
+impl<S> World<S> {
+    /// Fetches every pool in `pools` that has no state yet, at the world's
+    /// block, and returns how many were added.
+    pub async fn hydrate<P: StateProvider<S>>(
+        &mut self,
+        provider: &P,
+        pools: impl IntoIterator<Item = PoolId>,
+    ) -> Result<usize, WayfinderError> {
+        let missing: BTreeSet<_> = pools
+            .into_iter()
+            .filter(|pid| !self.pool_states.contains_key(pid))
+            .collect();
+        for &pid in &missing {
+            let st = provider.pool_state(pid, self.block.number).await?;
+            self.set_pool_state(pid, st);
+        }
+        Ok(missing.len())
+    }
+
+    /// [`World::hydrate`] for every pool the candidate `plans` pass through.
+    pub async fn hydrate_plans<P: StateProvider<S>>(
+        &mut self,
+        provider: &P,
+        plans: &[Vec<Hop>],
+    ) -> Result<usize, WayfinderError> {
+        let pools: Vec<_> = plans.iter().flatten().map(|h| h.pool).collect();
+        self.hydrate(provider, pools).await
+    }
+}
+
+impl<P: Pool> Engine<'_, P> {
+    /// [`Engine::simulate_chained`] that first fetches state for any pool on
+    /// `plan` the world is missing, instead of failing on it. Hops whose
+    /// math fails yield zero, as there; provider failures, and the malformed
+    /// plans and unknown pools `simulate_chained` panics on, are returned.
+    pub async fn simulate_chained_async<Q: StateProvider<P::State>>(
+        &self,
+        world: &mut World<P::State>,
+        provider: &Q,
+        plan: &[Hop],
+        first_in: U256,
+    ) -> Result<Path, WayfinderError> {
+        world.hydrate(provider, plan.iter().map(|h| h.pool)).await?;
+        match self.try_simulate(&*world, plan, first_in) {
+            Err(WayfinderError::Math(_)) => Ok(self.simulate_chained(&*world, plan, first_in)),
+            res => res,
+        }
+    }
+}
+
+impl<P: Pool> Scanner<'_, P> {
+    /// Pools the engine knows within `max_hops` of `from` in the graph,
+    /// whether or not the world has their state yet.
+    pub fn neighbourhood(&self, from: TokenId) -> BTreeSet<PoolId> {
+        let mut pools = BTreeSet::new();
+        let mut seen = HashSet::from([from]);
+        let mut layer = vec![from];
+        for _ in 0..self.config.max_hops {
+            let mut next = Vec::new();
+            for &t in &layer {
+                for pix in self.graph.pools_accepting(t).into_iter().flatten() {
+                    let NodeKind::Pool(pid) = self.graph.g[pix] else {
+                        continue;
+                    };
+                    if !self.engine.pools.contains_key(&pid) || !pools.insert(pid) {
+                        continue;
+                    }
+                    for tix in self.graph.tokens_emitted_by(pid).into_iter().flatten() {
+                        if let NodeKind::Token(t) = self.graph.g[tix]
+                            && seen.insert(t)
+                        {
+                            next.push(t);
+                        }
+                    }
+                }
+            }
+            layer = next;
+        }
+        pools
+    }
+
+    /// [`Scanner::best_route`] over a sparsely loaded world: hydrates the
+    /// [`neighbourhood`](Scanner::neighbourhood) of `from` before searching.
+    pub async fn best_route_async<Q: StateProvider<P::State>>(
+        &self,
+        world: &mut World<P::State>,
+        provider: &Q,
+        from: TokenId,
+        to: TokenId,
+        amt_in: U256,
+    ) -> Result<Option<Path>, WayfinderError> {
+        world.hydrate(provider, self.neighbourhood(from)).await?;
+        Ok(self.best_route(&*world, from, to, amt_in))
+    }
+}
+
+#[cfg(feature = "rpc")]
+pub use rpc::RpcStateProvider;
+
+#[cfg(feature = "rpc")]
+pub(crate) mod rpc {
+    use super::*;
+    use crate::{
+        registry::{PoolKind, Registry},
+        univ2::UniV2State,
+    };
+    use alloy_primitives::U256;
+    use alloy_provider::{
+        Provider,
+        network::{Ethereum, Network, TransactionBuilder},
+    };
+    use alloy_sol_types::{SolCall, sol};
+    use std::sync::Arc;
+
+    sol! {
+        function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+    }
+
+    /// Reads UniswapV2 reserves with `eth_call` at the requested block.
+    pub struct RpcStateProvider<P> {
+        pub provider: P,
+        pub registry: Arc<Registry>,
+    }
+
+    impl<P: Provider> RpcStateProvider<P> {
+        pub fn new(provider: P, registry: Arc<Registry>) -> Self {
+            Self { provider, registry }
+        }
+    }
+
+    impl<P: Provider> StateProvider<UniV2State> for RpcStateProvider<P> {
+        async fn pool_state(&self, pid: PoolId, block: u64) -> Result<UniV2State, WayfinderError> {
+            let meta = self.registry.require_pool(pid)?;
+            if meta.kind != PoolKind::UniV2 {
+                return Err(WayfinderError::Unavailable { pool: pid, block });
+            }
+            let tx = <Ethereum as Network>::TransactionRequest::default()
+                .with_to(meta.address)
+                .with_input(getReservesCall {}.abi_encode());
+            let data = self
+                .provider
+                .call(tx)
+                .block(block.into())
+                .await
+                .map_err(|e| WayfinderError::Rpc(e.to_string()))?;
+            let r = getReservesCall::abi_decode_returns(&data)
+                .map_err(|e| WayfinderError::Rpc(e.to_string()))?;
+            Ok(UniV2State::new(
+                U256::from(r.reserve0),
+                U256::from(r.reserve1),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::EngineError;
+    use crate::graph::AMMGraph;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::StateView;
+    use alloy_primitives::U256;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Counting {
+        source: World<(U256, U256)>,
+        calls: AtomicUsize,
+    }
+
+    impl StateProvider<(U256, U256)> for Counting {
+        fn pool_state(
+            &self,
+            pid: PoolId,
+            block: u64,
+        ) -> impl Future<Output = Result<(U256, U256), WayfinderError>> + Send {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            StateProvider::pool_state(&self.source, pid, block)
+        }
+    }
+
+    #[tokio::test]
+    async fn simulation_and_routing_fetch_missing_pools() {
+        let mut pools = HashMap::new();
+        let mut graph = AMMGraph::new();
+        let mut source = World::default();
+        // 1 -> 2 -> 3 plus an unrelated 4 <-> 5 pool.
+        for (id, t0, t1) in [(1, 1, 2), (2, 2, 3), (3, 4, 5)] {
+            pools.insert(PoolId(id), Cp::new(id, t0, t1));
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(t0), TokenId(t1));
+            source.set_pool_state(PoolId(id), reserves(1_000_000, 1_000_000));
+        }
+        let engine = Engine::new(&pools);
+        let amt = U256::from(1_000u64);
+        let plan = [hop(1, 1, 2), hop(2, 2, 3)];
+        let expected = engine.simulate_chained(&source, &plan, amt);
+
+        let mut world = World::default();
+        let path = engine
+            .simulate_chained_async(&mut world, &source, &plan, amt)
+            .await
+            .unwrap();
+        assert_eq!(path.steps[1].amt_out, expected.steps[1].amt_out);
+        assert_eq!(world.pool_states.len(), 2);
+
+        let scanner = Scanner::new(&engine, &graph);
+        let mut world = World::default();
+        let best = scanner
+            .best_route_async(&mut world, &source, TokenId(1), TokenId(3), amt)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(best.steps[1].amt_out, expected.steps[1].amt_out);
+        assert!(!world.pool_states.contains_key(&PoolId(3)));
+    }
+
+    #[tokio::test]
+    async fn hydrates_only_missing_pools_once() {
+        let mut source = World::default();
+        source.block.number = 7;
+        for id in 1..=3 {
+            source.set_pool_state(PoolId(id), reserves(1_000 * id, 1_000));
+        }
+        let provider = Counting {
+            source,
+            calls: AtomicUsize::new(0),
+        };
+
+        let mut world = World::default();
+        world.block.number = 7;
+        world.set_pool_state(PoolId(1), reserves(5, 5));
+        let plans = vec![vec![hop(1, 1, 2), hop(2, 2, 3)], vec![hop(2, 2, 3)]];
+        assert_eq!(world.hydrate_plans(&provider, &plans).await.unwrap(), 1);
+        assert_eq!(provider.calls.load(Ordering::Relaxed), 1);
+        assert_eq!(world.pool_states[&PoolId(2)], reserves(2_000, 1_000));
+        assert_eq!(world.pool_states[&PoolId(1)], reserves(5, 5));
+        assert!(world.pool_version(PoolId(2)).is_some());
+
+        assert_eq!(world.hydrate(&provider, [PoolId(2)]).await.unwrap(), 0);
+        assert!(matches!(
+            world.hydrate(&provider, [PoolId(9)]).await,
+            Err(WayfinderError::Unavailable {
+                pool: PoolId(9),
+                block: 7
+            })
+        ));
+
+        world.block.number = 8;
+        assert!(world.hydrate(&provider, [PoolId(3)]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn async_simulation_zeroes_math_failures_and_returns_the_rest() {
+        // Pool 2's fee is over 100%, so its math fails.
+        let pools = HashMap::from([
+            (PoolId(1), Cp::new(1, 1, 2)),
+            (PoolId(2), Cp::new(2, 2, 3).with_fee(20_000)),
+        ]);
+        let mut source = World::default();
+        for id in 1..=3 {
+            source.set_pool_state(PoolId(id), reserves(1_000_000, 1_000_000));
+        }
+        let engine = Engine::new(&pools);
+        let amt = U256::from(1_000u64);
+        let sim = |plan: Vec<Hop>| {
+            let (engine, source) = (&engine, &source);
+            async move {
+                engine
+                    .simulate_chained_async(&mut World::default(), source, &plan, amt)
+                    .await
+            }
+        };
+
+        let path = sim(vec![hop(1, 1, 2), hop(2, 2, 3)]).await.unwrap();
+        assert!(path.steps[0].amt_out > U256::ZERO);
+        assert_eq!(path.steps[1].amt_out, U256::ZERO);
+
+        assert!(matches!(
+            sim(vec![hop(9, 1, 2)]).await,
+            Err(WayfinderError::Unavailable { .. })
+        ));
+        assert!(matches!(
+            sim(vec![hop(3, 1, 2)]).await,
+            Err(WayfinderError::Engine(EngineError::MissingPool(PoolId(3))))
+        ));
+        assert!(matches!(
+            sim(vec![hop(1, 1, 2), hop(2, 3, 2)]).await,
+            Err(WayfinderError::Engine(EngineError::Discontinuity { .. }))
+        ));
+    }
+}
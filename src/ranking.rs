@@ -0,0 +1,143 @@
+//! How [`Scanner::best_route`](crate::arb::Scanner::best_route) orders
+//! candidate routes. The default ranks by output alone; a gas price in the
+//! output token, and heavier hop and token-risk weights, give a more
+//! conservative deployment.
+
+use crate::{engine::Path, ids::TokenId};
+use alloy_primitives::U256;
+use std::collections::HashMap;
+
+pub trait RankingPolicy: Sync {
+    /// Higher ranks first. `gas` is the route's gas cost in wei, as the
+    /// scanner's gas model or `gas_per_hop` prices it.
+    fn score(&self, path: &Path, gas: U256) -> f64;
+}
+
+/// `out * (output - hops * n - risk * max_token_risk) - gas * gas_cost`,
+/// where `n` is the hop count and token risk, from 0 to 1, is the highest
+/// of any token the route touches.
+#[derive(Clone, Debug)]
+pub struct WeightedScore {
+    pub output: f64,
+    /// Output-token units per wei of gas. Zero, the default, ignores gas,
+    /// which is priced in wei and so cannot be weighed against output
+    /// without this conversion.
+    pub gas: f64,
+    /// Fraction of output given up per hop.
+    pub hops: f64,
+    pub risk: f64,
+    pub token_risk: HashMap<TokenId, f64>,
+}
+
+impl Default for WeightedScore {
+    fn default() -> Self {
+        Self {
+            output: 1.0,
+            gas: 0.0,
+            hops: 0.0,
+            risk: 0.0,
+            token_risk: HashMap::new(),
+        }
+    }
+}
+
+impl WeightedScore {
+    /// Weighs gas at `price` output-token units per wei.
+    pub fn with_gas_price(mut self, price: f64) -> Self {
+        self.gas = price;
+        self
+    }
+
+    pub fn with_token_risk(mut self, token: TokenId, risk: f64) -> Self {
+        self.token_risk.insert(token, risk.clamp(0.0, 1.0));
+        self
+    }
+
+    fn route_risk(&self, path: &Path) -> f64 {
+        path.steps
+            .iter()
+            .flat_map(|s| [s.from, s.to])
+            .filter_map(|t| self.token_risk.get(&t))
+            .fold(0.0, |a, &b| f64::max(a, b))
+    }
+}
+
+impl RankingPolicy for WeightedScore {
+    fn score(&self, path: &Path, gas: U256) -> f64 {
+        let out = f64::from(path.steps.last().map_or(U256::ZERO, |s| s.amt_out));
+        let keep =
+            self.output - self.hops * path.steps.len() as f64 - self.risk * self.route_risk(path);
+        out * keep - self.gas * f64::from(gas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arb::ScanConfig;
+    use crate::arb::Scanner;
+    use crate::engine::Engine;
+    use crate::graph::AMMGraph;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, reserves};
+    use crate::world::World;
+
+    #[test]
+    fn conservative_policy_prefers_the_short_safe_route() {
+        // Direct 1->2 is slightly worse than 1->3->2 through a risky token.
+        let pools = HashMap::from([
+            (PoolId(1), Cp::new(1, 1, 2)),
+            (PoolId(2), Cp::new(2, 1, 3)),
+            (PoolId(3), Cp::new(3, 3, 2)),
+        ]);
+        let mut graph = AMMGraph::new();
+        let mut world = World::default();
+        for (id, a, b, r) in [
+            (1, 1, 2, 1_000_000),
+            (2, 1, 3, 4_000_000),
+            (3, 3, 2, 4_000_000),
+        ] {
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(a), TokenId(b));
+            world.set_pool_state(PoolId(id), reserves(r, r));
+        }
+        let engine = Engine::new(&pools);
+        let amt = U256::from(10_000u64);
+        let aggressive = Scanner::new(&engine, &graph)
+            .best_route(&world, TokenId(1), TokenId(2), amt)
+            .unwrap();
+        assert_eq!(aggressive.steps.len(), 2);
+
+        // Gas in wei dwarfs any output; by default it is left out of the
+        // score rather than deciding it by hop count.
+        let priced = ScanConfig {
+            gas_per_hop: U256::from(10u64).pow(U256::from(14u64)),
+            ..Default::default()
+        };
+        let scanner = Scanner::new(&engine, &graph).with_config(priced);
+        let best = scanner
+            .best_route(&world, TokenId(1), TokenId(2), amt)
+            .unwrap();
+        assert_eq!(best.steps.len(), 2);
+        let cheap = WeightedScore::default().with_gas_price(1e-9);
+        let best = scanner
+            .with_ranking(&cheap)
+            .best_route(&world, TokenId(1), TokenId(2), amt)
+            .unwrap();
+        assert_eq!(best.steps.len(), 1);
+
+        let conservative = WeightedScore {
+            hops: 0.01,
+            risk: 1.0,
+            ..Default::default()
+        }
+        .with_token_risk(TokenId(3), 0.5);
+        let safe = Scanner::new(&engine, &graph)
+            .with_ranking(&conservative)
+            .best_route(&world, TokenId(1), TokenId(2), amt)
+            .unwrap();
+        assert_eq!(safe.steps.len(), 1);
+        assert!(
+            conservative.score(&safe, U256::ZERO) > conservative.score(&aggressive, U256::ZERO)
+        );
+    }
+}
@@ -1,20 +1,37 @@
-use crate::ids::{PoolId, TokenId};
+use crate::error::RegistryError;
+use crate::ids::{ChainId, PoolId, TokenId, TokenSet, stable_pool_id, stable_token_id};
 use alloy_primitives::Address;
 use std::collections::HashMap;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct TokenMeta {
     pub address: Address,
     pub symbol: String,
     pub decimals: u8,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub enum PoolKind {
+    UniV2,
     UniV3,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct PoolMeta {
     pub address: Address,
     pub kind: PoolKind,
@@ -24,6 +41,11 @@ pub struct PoolMeta {
 }
 
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct Registry {
     pub token_meta: HashMap<TokenId, TokenMeta>,
     pub pool_meta: HashMap<PoolId, PoolMeta>,
@@ -33,13 +55,115 @@ pub struct Registry {
 
 impl Registry {
     pub fn upsert_token(&mut self, tid: TokenId, meta: TokenMeta) {
-        self.token_by_addr.insert(meta.address, tid);
-        self.token_meta.insert(tid, meta);
+        let address = meta.address;
+        if let Some(old) = self.token_meta.insert(tid, meta)
+            && old.address != address
+            && self.token_by_addr.get(&old.address) == Some(&tid)
+        {
+            self.token_by_addr.remove(&old.address);
+        }
+        self.token_by_addr.insert(address, tid);
     }
 
     pub fn upsert_pool(&mut self, pid: PoolId, meta: PoolMeta) {
-        self.pool_by_addr.insert(meta.address, pid);
-        self.pool_meta.insert(pid, meta);
+        let address = meta.address;
+        if let Some(old) = self.pool_meta.insert(pid, meta)
+            && old.address != address
+            && self.pool_by_addr.get(&old.address) == Some(&pid)
+        {
+            self.pool_by_addr.remove(&old.address);
+        }
+        self.pool_by_addr.insert(address, pid);
+    }
+
+    pub fn remove_token(&mut self, tid: TokenId) -> Option<TokenMeta> {
+        let meta = self.token_meta.remove(&tid)?;
+        if self.token_by_addr.get(&meta.address) == Some(&tid) {
+            self.token_by_addr.remove(&meta.address);
+        }
+        Some(meta)
+    }
+
+    pub fn remove_pool(&mut self, pid: PoolId) -> Option<PoolMeta> {
+        let meta = self.pool_meta.remove(&pid)?;
+        if self.pool_by_addr.get(&meta.address) == Some(&pid) {
+            self.pool_by_addr.remove(&meta.address);
+        }
+        Some(meta)
+    }
+
+    pub fn insert_token_hashed(&mut self, chain: ChainId, meta: TokenMeta) -> TokenId {
+        if let Some(&tid) = self.token_by_addr.get(&meta.address) {
+            self.token_meta.insert(tid, meta);
+            return tid;
+        }
+        let tid = (0..)
+            .map(|nonce| stable_token_id(chain, meta.address, nonce))
+            .find(|tid| !self.token_meta.contains_key(tid))
+            .expect("token id space exhausted");
+        self.upsert_token(tid, meta);
+        tid
+    }
+
+    pub fn insert_pool_hashed(&mut self, chain: ChainId, meta: PoolMeta) -> PoolId {
+        let pid = self.hashed_pool_id(chain, meta.address);
+        if self.pool_by_addr.contains_key(&meta.address) {
+            self.pool_meta.insert(pid, meta);
+        } else {
+            self.upsert_pool(pid, meta);
+        }
+        pid
+    }
+
+    /// The id [`Registry::insert_pool_hashed`] would use for `address`.
+    pub fn hashed_pool_id(&self, chain: ChainId, address: Address) -> PoolId {
+        if let Some(&pid) = self.pool_by_addr.get(&address) {
+            return pid;
+        }
+        (0..)
+            .map(|nonce| stable_pool_id(chain, address, nonce))
+            .find(|pid| !self.pool_meta.contains_key(pid))
+            .expect("pool id space exhausted")
+    }
+
+    pub fn find_pool(
+        &self,
+        kind: PoolKind,
+        a: TokenId,
+        b: TokenId,
+        fee: Option<u32>,
+    ) -> Option<PoolId> {
+        self.pool_meta
+            .iter()
+            .filter(|(_, m)| m.kind == kind && fee.is_none_or(|f| m.fee == f))
+            .filter(|(_, m)| (m.token0, m.token1) == (a, b) || (m.token0, m.token1) == (b, a))
+            .map(|(&pid, _)| pid)
+            .min()
+    }
+
+    pub fn resolve_token(&self, s: &str) -> Option<TokenId> {
+        if let Ok(addr) = s.parse::<Address>() {
+            return self.token_by_addr.get(&addr).copied();
+        }
+        if let Ok(tid) = s.parse::<TokenId>() {
+            return self.token_meta.contains_key(&tid).then_some(tid);
+        }
+        let mut hits = self
+            .token_meta
+            .iter()
+            .filter(|(_, m)| m.symbol.eq_ignore_ascii_case(s));
+        match (hits.next(), hits.next()) {
+            (Some((&tid, _)), None) => Some(tid),
+            _ => None,
+        }
+    }
+
+    /// The known tokens among `addrs`; unknown addresses are skipped.
+    pub fn token_set(&self, addrs: &[Address]) -> TokenSet {
+        addrs
+            .iter()
+            .filter_map(|a| self.token_by_addr.get(a).copied())
+            .collect()
     }
 
     pub fn token(&self, tid: TokenId) -> Option<&TokenMeta> {
@@ -49,4 +173,81 @@ impl Registry {
     pub fn pool(&self, pid: PoolId) -> Option<&PoolMeta> {
         self.pool_meta.get(&pid)
     }
+
+    pub fn require_token(&self, tid: TokenId) -> Result<&TokenMeta, RegistryError> {
+        self.token(tid).ok_or(RegistryError::UnknownToken(tid))
+    }
+
+    pub fn require_pool(&self, pid: PoolId) -> Result<&PoolMeta, RegistryError> {
+        self.pool(pid).ok_or(RegistryError::UnknownPool(pid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    fn token(address: Address) -> TokenMeta {
+        TokenMeta {
+            address,
+            symbol: "T".into(),
+            decimals: 18,
+        }
+    }
+
+    #[test]
+    fn hashed_ids_are_stable_and_chain_scoped() {
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let mut a = Registry::default();
+        let mut b = Registry::default();
+
+        let ta = a.insert_token_hashed(ChainId::MAINNET, token(weth));
+        let tb = b.insert_token_hashed(ChainId::MAINNET, token(weth));
+        assert_eq!(ta, tb);
+        assert_eq!(ta, stable_token_id(ChainId::MAINNET, weth, 0));
+        assert_eq!(a.insert_token_hashed(ChainId::MAINNET, token(weth)), ta);
+        assert_ne!(stable_token_id(ChainId(10), weth, 0), ta);
+    }
+
+    #[test]
+    fn hashed_id_collision_probes_next_nonce() {
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let other = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let mut r = Registry::default();
+        let squatted = stable_token_id(ChainId::MAINNET, other, 0);
+        r.upsert_token(squatted, token(weth));
+
+        let tid = r.insert_token_hashed(ChainId::MAINNET, token(other));
+        assert_eq!(tid, stable_token_id(ChainId::MAINNET, other, 1));
+    }
+
+    #[test]
+    fn resolves_tokens_by_address_id_or_unique_symbol() {
+        let weth = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let usdc = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let mut r = Registry::default();
+        r.upsert_token(TokenId(1), token(weth));
+        r.upsert_token(
+            TokenId(2),
+            TokenMeta {
+                symbol: "USDC".into(),
+                ..token(usdc)
+            },
+        );
+
+        assert_eq!(r.resolve_token(&weth.to_string()), Some(TokenId(1)));
+        assert_eq!(r.resolve_token("2"), Some(TokenId(2)));
+        assert_eq!(r.resolve_token("usdc"), Some(TokenId(2)));
+        assert_eq!(r.resolve_token("3"), None);
+
+        r.upsert_token(
+            TokenId(3),
+            TokenMeta {
+                symbol: "USDC".into(),
+                ..token(Address::ZERO)
+            },
+        );
+        assert_eq!(r.resolve_token("USDC"), None);
+    }
 }
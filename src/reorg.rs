@@ -0,0 +1,243 @@
+//! Reorg-safe block application for log-driven sync: every applied block
+//! keeps an undo record, so a block whose parent is not the current head
+//! rolls the world back to the fork point before it is applied.
+
+use crate::{
+    ids::PoolId,
+    world::{BlockContext, World, WorldDiff},
+};
+use alloy_primitives::B256;
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub number: u64,
+    pub hash: B256,
+    pub parent_hash: B256,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reorg {
+    /// Last block kept from the old chain.
+    pub common_ancestor: u64,
+    /// Rolled-back blocks, newest first.
+    pub orphaned: Vec<(u64, B256)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ReorgError {
+    #[error("reorg deeper than the {depth} blocks kept")]
+    TooDeep { depth: usize },
+    #[error("block {number} builds on unknown parent {parent_hash}")]
+    UnknownParent { number: u64, parent_hash: B256 },
+}
+
+struct Applied<S> {
+    header: BlockHeader,
+    prev_block: BlockContext,
+    /// State of each touched pool before the block; `None` if it was new.
+    undo: Vec<(PoolId, Option<S>)>,
+}
+
+type ReorgCallback = Box<dyn FnMut(&Reorg) + Send + Sync>;
+
+/// Ring buffer of the last `depth` applied blocks.
+pub struct ReorgGuard<S> {
+    pub depth: usize,
+    blocks: VecDeque<Applied<S>>,
+    callbacks: Vec<ReorgCallback>,
+}
+
+impl<S: Clone> ReorgGuard<S> {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth: depth.max(1),
+            blocks: VecDeque::new(),
+            callbacks: Vec::new(),
+        }
+    }
+
+    pub fn on_reorg(&mut self, f: impl FnMut(&Reorg) + Send + Sync + 'static) {
+        self.callbacks.push(Box::new(f));
+    }
+
+    pub fn head(&self) -> Option<BlockHeader> {
+        self.blocks.back().map(|b| b.header)
+    }
+
+    /// Applies `diff` as block `header`. If `header` does not extend the
+    /// current head, orphaned blocks are rolled back first and the reorg
+    /// is reported to callbacks and returned.
+    pub fn apply_block(
+        &mut self,
+        world: &mut World<S>,
+        header: BlockHeader,
+        mut diff: WorldDiff<S>,
+    ) -> Result<Option<Reorg>, ReorgError> {
+        let reorg = match self.head() {
+            Some(head) if head.hash != header.parent_hash => {
+                Some(self.rollback_to_parent(world, header)?)
+            }
+            _ => None,
+        };
+
+        let undo = diff
+            .pool_states
+            .keys()
+            .map(|&pid| (pid, world.pool_states.get(&pid).cloned()))
+            .collect();
+        self.blocks.push_back(Applied {
+            header,
+            prev_block: world.block,
+            undo,
+        });
+        if self.blocks.len() > self.depth {
+            self.blocks.pop_front();
+        }
+        let block = diff.block.unwrap_or(world.block);
+        diff.block = Some(BlockContext {
+            number: header.number,
+            ..block
+        });
+        world.apply(diff);
+
+        if let Some(reorg) = &reorg {
+            for f in &mut self.callbacks {
+                f(reorg);
+            }
+        }
+        Ok(reorg)
+    }
+
+    fn rollback_to_parent(
+        &mut self,
+        world: &mut World<S>,
+        header: BlockHeader,
+    ) -> Result<Reorg, ReorgError> {
+        let Some(keep) = self
+            .blocks
+            .iter()
+            .rposition(|b| b.header.hash == header.parent_hash)
+        else {
+            // The fork point may have scrolled out of the buffer.
+            if self
+                .blocks
+                .front()
+                .is_some_and(|b| b.header.number < header.number)
+            {
+                return Err(ReorgError::UnknownParent {
+                    number: header.number,
+                    parent_hash: header.parent_hash,
+                });
+            }
+            return Err(ReorgError::TooDeep { depth: self.depth });
+        };
+        let mut orphaned = Vec::new();
+        while self.blocks.len() > keep + 1 {
+            let b = self.blocks.pop_back().expect("len checked");
+            orphaned.push((b.header.number, b.header.hash));
+            Self::undo(world, b);
+        }
+        Ok(Reorg {
+            common_ancestor: self.blocks[keep].header.number,
+            orphaned,
+        })
+    }
+
+    fn undo(world: &mut World<S>, b: Applied<S>) {
+        for (pid, prev) in b.undo {
+            match prev {
+                Some(st) => world.set_pool_state(pid, st),
+                None => {
+                    world.pool_states.remove(&pid);
+                    world.pool_versions.remove(&pid);
+                }
+            }
+        }
+        world.block = b.prev_block;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::reserves;
+    use alloy_primitives::U256;
+    use std::sync::{Arc, Mutex};
+
+    fn header(number: u64, fork: u8, parent_fork: u8) -> BlockHeader {
+        let hash = |n: u64, f: u8| B256::left_padding_from(&[f, n as u8]);
+        BlockHeader {
+            number,
+            hash: hash(number, fork),
+            parent_hash: hash(number - 1, parent_fork),
+        }
+    }
+
+    fn diff(pid: u64, r: u64) -> WorldDiff<(U256, U256)> {
+        let mut d = WorldDiff::default();
+        d.set_pool_state(PoolId(pid), reserves(r, r));
+        d
+    }
+
+    #[test]
+    fn reorg_rolls_back_orphans_and_applies_new_branch() {
+        let mut world = World::default();
+        let mut guard = ReorgGuard::new(8);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        guard.on_reorg(move |r| sink.lock().unwrap().push(r.clone()));
+
+        guard
+            .apply_block(&mut world, header(1, 0, 0), diff(1, 10))
+            .unwrap();
+        guard
+            .apply_block(&mut world, header(2, 0, 0), diff(1, 20))
+            .unwrap();
+        guard
+            .apply_block(&mut world, header(3, 0, 0), diff(2, 30))
+            .unwrap();
+        assert_eq!(world.block.number, 3);
+
+        // Block 2' replaces 2 and 3.
+        let reorg = guard
+            .apply_block(&mut world, header(2, 1, 0), diff(1, 21))
+            .unwrap()
+            .unwrap();
+        assert_eq!(reorg.common_ancestor, 1);
+        assert_eq!(
+            reorg.orphaned.iter().map(|o| o.0).collect::<Vec<_>>(),
+            vec![3, 2]
+        );
+        assert_eq!(world.pool_states[&PoolId(1)], reserves(21, 21));
+        assert!(!world.pool_states.contains_key(&PoolId(2)));
+        assert!(!world.pool_versions.contains_key(&PoolId(2)));
+        assert_eq!(world.block.number, 2);
+        assert_eq!(seen.lock().unwrap().as_slice(), &[reorg]);
+
+        guard
+            .apply_block(&mut world, header(3, 1, 1), diff(2, 31))
+            .unwrap();
+        assert_eq!(guard.head().unwrap().hash, header(3, 1, 1).hash);
+    }
+
+    #[test]
+    fn reorg_past_the_buffer_is_an_error() {
+        let mut world = World::default();
+        let mut guard = ReorgGuard::new(2);
+        for n in 1..=4 {
+            guard
+                .apply_block(&mut world, header(n, 0, 0), diff(1, n))
+                .unwrap();
+        }
+        assert_eq!(
+            guard.apply_block(&mut world, header(2, 1, 0), diff(1, 0)),
+            Err(ReorgError::TooDeep { depth: 2 })
+        );
+        assert!(matches!(
+            guard.apply_block(&mut world, header(5, 0, 7), diff(1, 0)),
+            Err(ReorgError::UnknownParent { number: 5, .. })
+        ));
+        assert_eq!(world.pool_states[&PoolId(1)], reserves(4, 4));
+    }
+}
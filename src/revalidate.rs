@@ -0,0 +1,92 @@
+//! Re-checking a route found against an older world, so an execution layer
+//! can resend it as is or go back to search.
+
+use crate::{
+    engine::{Engine, Path},
+    pool::Pool,
+    world::StateView,
+};
+use alloy_primitives::U256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Revalidation {
+    /// The route still simulates cleanly and pays at least what it did.
+    pub still_valid: bool,
+    pub new_out: U256,
+    /// Change in output relative to the original, in basis points.
+    pub delta_bps: i64,
+}
+
+impl Path {
+    /// Re-simulates this path's hops, with its original input, on `world`.
+    /// Only the path's own pools are touched, and a memo on `engine` is
+    /// reused.
+    pub fn revalidate<P: Pool, V: StateView<P::State>>(
+        &self,
+        engine: &Engine<'_, P>,
+        world: &V,
+    ) -> Revalidation {
+        let (Some(first), Some(last)) = (self.steps.first(), self.steps.last()) else {
+            return Revalidation {
+                still_valid: false,
+                new_out: U256::ZERO,
+                delta_bps: 0,
+            };
+        };
+        let (new_out, ok) = match engine.try_simulate(world, &self.plan(), first.amt_in) {
+            Ok(path) => (path.steps.last().map_or(U256::ZERO, |s| s.amt_out), true),
+            Err(_) => (U256::ZERO, false),
+        };
+        let old_out = last.amt_out;
+        Revalidation {
+            still_valid: ok && new_out >= old_out && !new_out.is_zero(),
+            new_out,
+            delta_bps: delta_bps(old_out, new_out),
+        }
+    }
+}
+
+pub(crate) fn delta_bps(old: U256, new: U256) -> i64 {
+    if old.is_zero() {
+        return 0;
+    }
+    let bps = new.abs_diff(old).saturating_mul(U256::from(10_000u64)) / old;
+    let bps = i64::try_from(bps).unwrap_or(i64::MAX);
+    if new < old { -bps } else { bps }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::World;
+    use std::collections::HashMap;
+
+    #[test]
+    fn moved_pools_shift_the_output_and_missing_ones_invalidate() {
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2)), (PoolId(2), Cp::new(2, 2, 3))]);
+        let mut world = World::default();
+        world.set_pool_state(PoolId(1), reserves(1_000_000, 1_000_000));
+        world.set_pool_state(PoolId(2), reserves(1_000_000, 1_000_000));
+        let engine = Engine::new(&pools);
+        let path =
+            engine.simulate_chained(&world, &[hop(1, 1, 2), hop(2, 2, 3)], U256::from(10_000u64));
+
+        let same = path.revalidate(&engine, &world);
+        assert!(same.still_valid);
+        assert_eq!(same.delta_bps, 0);
+
+        world.set_pool_state(PoolId(2), reserves(1_000_000, 900_000));
+        let worse = path.revalidate(&engine, &world);
+        assert!(!worse.still_valid);
+        assert!((-1_010..=-990).contains(&worse.delta_bps), "{worse:?}");
+
+        world.set_pool_state(PoolId(2), reserves(1_000_000, 1_100_000));
+        let better = path.revalidate(&engine, &world);
+        assert!(better.still_valid && better.delta_bps > 0);
+
+        world.pool_states.remove(&PoolId(1));
+        assert!(!path.revalidate(&engine, &world).still_valid);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{
+    arb::Scanner,
+    engine::{Path, Step},
+    ids::{PoolId, SwapDirection, TokenId},
+    pool::Pool,
+    world::{BlockContext, StateView},
+};
+use alloy_primitives::U256;
+use smallvec::smallvec;
+
+/// A firm, fillable quote from an off-chain liquidity source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FirmQuote {
+    /// Synthetic pool id standing in for the source inside a [`Path`].
+    pub source: PoolId,
+    pub token_in: TokenId,
+    pub token_out: TokenId,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    /// Last block timestamp at which the maker honours the quote.
+    pub expiry: u64,
+}
+
+impl FirmQuote {
+    pub fn is_live(&self, ctx: &BlockContext) -> bool {
+        ctx.timestamp <= self.expiry
+    }
+
+    pub fn direction(&self) -> SwapDirection {
+        SwapDirection {
+            from: self.token_in,
+            to: self.token_out,
+        }
+    }
+
+    /// The quote as a single-hop path through its synthetic pool.
+    pub fn to_path(&self) -> Path {
+        Path {
+            steps: smallvec![Step {
+                pool: self.source,
+                from: self.token_in,
+                to: self.token_out,
+                amt_in: self.amount_in,
+                amt_out: self.amount_out,
+                failure: None,
+            }],
+        }
+    }
+}
+
+/// External liquidity such as RFQ makers or bridges. Quoters are shared
+/// across request tasks, hence the `Send + Sync` bound.
+pub trait Quoter: Send + Sync {
+    /// Synthetic pool id used for this source's quotes; must not collide
+    /// with a real pool in the router's graph.
+    fn source(&self) -> PoolId;
+
+    fn firm_quote(
+        &self,
+        ctx: &BlockContext,
+        dir: SwapDirection,
+        amount_in: U256,
+    ) -> Option<FirmQuote>;
+}
+
+impl<P: Pool> Scanner<'_, P> {
+    /// Like [`best_route`](Scanner::best_route), but also asks `quoters` for
+    /// the whole trade and competes their live quotes as single-hop routes.
+    pub fn best_route_with_quoters<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        quoters: &[&dyn Quoter],
+        from: TokenId,
+        to: TokenId,
+        amt_in: U256,
+    ) -> Option<Path> {
+        let ctx = world.block();
+        let dir = SwapDirection { from, to };
+        let quotes = quoters
+            .iter()
+            .filter_map(|q| q.firm_quote(&ctx, dir, amt_in))
+            .filter(|q| q.is_live(&ctx) && q.direction() == dir && q.amount_in == amt_in)
+            .map(|q| q.to_path());
+        self.best_route(world, from, to, amt_in)
+            .into_iter()
+            .chain(quotes)
+            .max_by_key(|path| path.steps.last().map(|s| s.amt_out).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::graph::AMMGraph;
+    use crate::test_utils::{Cp, reserves};
+    use crate::world::World;
+    use std::collections::HashMap;
+
+    struct Maker {
+        id: PoolId,
+        rate_bps: u64,
+        expiry: u64,
+    }
+
+    impl Quoter for Maker {
+        fn source(&self) -> PoolId {
+            self.id
+        }
+
+        fn firm_quote(
+            &self,
+            _ctx: &BlockContext,
+            dir: SwapDirection,
+            amount_in: U256,
+        ) -> Option<FirmQuote> {
+            (dir.from == TokenId(1)).then(|| FirmQuote {
+                source: self.id,
+                token_in: dir.from,
+                token_out: dir.to,
+                amount_in,
+                amount_out: amount_in * U256::from(self.rate_bps) / U256::from(10_000u64),
+                expiry: self.expiry,
+            })
+        }
+    }
+
+    #[test]
+    fn live_rfq_quote_beats_amm_only_when_better() {
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2))]);
+        let mut graph = AMMGraph::new();
+        graph.connect_bidirectional_pair(PoolId(1), TokenId(1), TokenId(2));
+        let mut world = World::default();
+        world
+            .pool_states
+            .insert(PoolId(1), reserves(100_000, 100_000));
+        world.block.timestamp = 10;
+
+        let engine = Engine::new(&pools);
+        let scanner = Scanner::new(&engine, &graph);
+        let amt = U256::from(1_000u64);
+        let (t1, t2) = (TokenId(1), TokenId(2));
+
+        let amm = scanner.best_route(&world, t1, t2, amt).unwrap();
+        let good = Maker {
+            id: PoolId(u64::MAX),
+            rate_bps: 9_950,
+            expiry: 10,
+        };
+        let best = scanner
+            .best_route_with_quoters(&world, &[&good], t1, t2, amt)
+            .unwrap();
+        assert_eq!(best.steps[0].pool, good.source());
+        assert!(best.steps[0].amt_out > amm.steps[0].amt_out);
+
+        let poor = Maker {
+            rate_bps: 9_000,
+            ..good
+        };
+        let best = scanner
+            .best_route_with_quoters(&world, &[&poor], t1, t2, amt)
+            .unwrap();
+        assert_eq!(best.steps[0].pool, PoolId(1));
+
+        world.block.timestamp = 11;
+        let best = scanner
+            .best_route_with_quoters(&world, &[&good], t1, t2, amt)
+            .unwrap();
+        assert_eq!(best.steps[0].pool, PoolId(1));
+    }
+}
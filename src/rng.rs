@@ -0,0 +1,31 @@
+/// Small deterministic generator for reproducible synthetic data and
+/// perturbation trials; not for anything security-sensitive.
+#[derive(Clone, Debug)]
+pub struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "empty range");
+        self.next_u64() % n
+    }
+
+    pub fn range_u128(&mut self, lo: u128, hi: u128) -> u128 {
+        if hi <= lo {
+            return lo;
+        }
+        let r = ((self.next_u64() as u128) << 64) | self.next_u64() as u128;
+        lo + r % (hi - lo + 1)
+    }
+}
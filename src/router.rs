@@ -0,0 +1,306 @@
+//! High-level entry point owning the whole routing stack, for application
+//! code that should not wire engines and scanners by hand.
+
+use crate::{
+    arb::{ScanConfig, Scanner},
+    engine::{ApprovalPolicy, Engine, Execution, Hop, Path},
+    error::{BuildError, WayfinderError},
+    gas::{BlockFees, GasTracker},
+    graph::AMMGraph,
+    ids::{AccountId, ChainId, PoolId, SwapDirection, TokenId},
+    memo::SwapMemo,
+    num::AmountRepr,
+    pool::Pool,
+    registry::{PoolMeta, Registry},
+    warmup::RouteIndex,
+    world::{World, WorldDiff},
+};
+use alloy_primitives::U256;
+use std::collections::HashMap;
+
+pub struct Router<P: Pool> {
+    registry: Registry,
+    graph: AMMGraph,
+    pools: HashMap<PoolId, P>,
+    world: World<P::State>,
+    pub config: ScanConfig,
+    pub approvals: ApprovalPolicy,
+    gas: Option<GasTracker>,
+    /// Gas charged per hop when pricing with the gas tracker.
+    pub hop_gas: u64,
+    index: Option<RouteIndex>,
+    /// Hop results shared by every quote until the pools they read change.
+    memo: SwapMemo<P::State>,
+}
+
+/// Gas assumed per hop until configured otherwise.
+pub const DEFAULT_HOP_GAS: u64 = 100_000;
+
+impl<P: Pool> Router<P> {
+    pub fn new(
+        registry: Registry,
+        graph: AMMGraph,
+        pools: HashMap<PoolId, P>,
+        world: World<P::State>,
+    ) -> Self {
+        Self {
+            registry,
+            graph,
+            pools,
+            world,
+            config: ScanConfig::default(),
+            approvals: ApprovalPolicy::default(),
+            gas: None,
+            hop_gas: DEFAULT_HOP_GAS,
+            index: None,
+            memo: SwapMemo::default(),
+        }
+    }
+
+    /// Prices `config.gas_per_hop` from `gas` at the median tip, and keeps
+    /// it repriced as [`Router::observe_fees`] is fed new blocks.
+    pub fn with_gas(mut self, gas: GasTracker) -> Self {
+        self.config.update_gas(&gas, self.hop_gas, 50.0);
+        self.gas = Some(gas);
+        self
+    }
+
+    pub fn gas(&self) -> Option<&GasTracker> {
+        self.gas.as_ref()
+    }
+
+    pub fn observe_fees(&mut self, fees: BlockFees) {
+        if let Some(gas) = &mut self.gas {
+            gas.observe(fees);
+            self.config.update_gas(gas, self.hop_gas, 50.0);
+        }
+    }
+
+    pub fn with_config(mut self, config: ScanConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn graph(&self) -> &AMMGraph {
+        &self.graph
+    }
+
+    pub(crate) fn set_graph(&mut self, graph: AMMGraph) {
+        self.graph = graph;
+    }
+
+    /// Built by [`Router::warm_up`].
+    pub fn index(&self) -> Option<&RouteIndex> {
+        self.index.as_ref()
+    }
+
+    pub(crate) fn set_index(&mut self, index: Option<RouteIndex>) {
+        self.index = index;
+    }
+
+    pub fn pools(&self) -> &HashMap<PoolId, P> {
+        &self.pools
+    }
+
+    pub fn world(&self) -> &World<P::State> {
+        &self.world
+    }
+
+    /// Direct edits may skip [`World::touch`], so the quote memo is
+    /// dropped rather than trusted across them.
+    pub fn world_mut(&mut self) -> &mut World<P::State> {
+        self.memo.clear();
+        &mut self.world
+    }
+
+    pub fn memo(&self) -> &SwapMemo<P::State> {
+        &self.memo
+    }
+
+    /// Applies a state update, e.g. one block from the sync subsystem.
+    pub fn apply(&mut self, diff: WorldDiff<P::State>) {
+        self.world.apply(diff);
+    }
+
+    /// Best output for selling `amt_in` of `from` for `to`.
+    pub fn quote(&self, from: TokenId, to: TokenId, amt_in: U256) -> Option<U256> {
+        self.route(from, to, amt_in)?
+            .steps
+            .last()
+            .map(|s| s.amt_out)
+    }
+
+    /// [`Router::quote`] in the caller's own integer type.
+    pub fn quote_as<A: AmountRepr>(&self, from: TokenId, to: TokenId, amt_in: A) -> Option<A> {
+        self.quote(from, to, amt_in.into_amount())
+            .map(A::from_amount)
+    }
+
+    /// Best route for selling `amt_in` of `from` for `to`.
+    pub fn route(&self, from: TokenId, to: TokenId, amt_in: U256) -> Option<Path> {
+        let engine = self.engine();
+        let mut scanner = Scanner::new(&engine, &self.graph);
+        scanner.config = self.config;
+        scanner.best_route(&self.world, from, to, amt_in)
+    }
+
+    /// Executes `plan` for `owner` against the router's world. Malformed
+    /// plans and failing math are errors and leave the world untouched; a
+    /// missing balance or approval is reported on the uncommitted
+    /// [`Execution`].
+    pub fn execute_plan(
+        &mut self,
+        owner: AccountId,
+        spender: AccountId,
+        plan: &[Hop],
+        amt_in: U256,
+    ) -> Result<Execution, WayfinderError> {
+        let engine = Engine::new(&self.pools).with_approvals(self.approvals);
+        engine.try_simulate(&self.world, plan, amt_in)?;
+        Ok(engine.execute(&mut self.world, owner, spender, plan, amt_in))
+    }
+
+    /// Makes a new pool routable: registry, graph, implementation and
+    /// state are all updated, or none are if the parts disagree.
+    pub fn add_pool(
+        &mut self,
+        pid: PoolId,
+        meta: PoolMeta,
+        pool: P,
+        state: P::State,
+    ) -> Result<(), BuildError> {
+        if self.pools.contains_key(&pid) || self.graph.pool_idx.contains_key(&pid) {
+            return Err(BuildError::DuplicatePool(pid));
+        }
+        let supported = SwapDirection::new(meta.token0, meta.token1)
+            .is_some_and(|dir| pool.supports(dir) && pool.supports(dir.reverse()));
+        if pool.id() != pid || !supported {
+            return Err(BuildError::PoolMismatch(pid));
+        }
+        self.graph
+            .connect_bidirectional_pair(pid, meta.token0, meta.token1);
+        self.index = None;
+        self.registry.upsert_pool(pid, meta);
+        self.pools.insert(pid, pool);
+        self.world.set_pool_state(pid, state);
+        Ok(())
+    }
+
+    /// [`Router::add_pool`] under the registry's hashed id for the pool's
+    /// address, e.g. straight from a factory's creation event.
+    pub fn add_pool_hashed(
+        &mut self,
+        chain: ChainId,
+        meta: PoolMeta,
+        pool: impl FnOnce(PoolId) -> P,
+        state: P::State,
+    ) -> Result<PoolId, BuildError> {
+        let pid = self.registry.hashed_pool_id(chain, meta.address);
+        self.add_pool(pid, meta, pool(pid), state)?;
+        Ok(pid)
+    }
+
+    pub fn engine(&self) -> Engine<'_, P> {
+        Engine::new(&self.pools)
+            .with_approvals(self.approvals)
+            .with_memo(&self.memo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::EngineError;
+    use crate::test_utils::{Cp, hop, reserves};
+
+    fn router() -> Router<Cp> {
+        let mut graph = AMMGraph::new();
+        let mut pools = HashMap::new();
+        let mut world = World::default();
+        for (id, t0, t1, r) in [(1, 1, 2, 1_000_000), (2, 2, 3, 1_000_000), (3, 1, 3, 1_000)] {
+            pools.insert(PoolId(id), Cp::new(id, t0, t1));
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(t0), TokenId(t1));
+            world.set_pool_state(PoolId(id), reserves(r, r));
+        }
+        Router::new(Registry::default(), graph, pools, world)
+    }
+
+    #[test]
+    fn quotes_routes_and_executes() {
+        let mut router = router();
+        let amt = U256::from(1_000u64);
+        let path = router.route(TokenId(1), TokenId(3), amt).unwrap();
+        assert_eq!(path.steps.len(), 2, "avoids the shallow direct pool");
+        assert_eq!(
+            router.quote(TokenId(1), TokenId(3), amt),
+            Some(path.steps[1].amt_out)
+        );
+
+        let owner = AccountId(7);
+        router.approvals = ApprovalPolicy::AssumeInfinite;
+        router.world_mut().credit(owner, TokenId(1), amt);
+        let plan = [hop(1, 1, 2), hop(2, 2, 3)];
+        let exec = router.execute_plan(owner, owner, &plan, amt).unwrap();
+        assert!(exec.committed());
+        assert_eq!(
+            router.world().holding(owner, TokenId(3)),
+            path.steps[1].amt_out
+        );
+
+        assert!(matches!(
+            router.execute_plan(owner, owner, &[], amt),
+            Err(WayfinderError::Engine(EngineError::EmptyPlan))
+        ));
+        assert!(matches!(
+            router.execute_plan(owner, owner, &[hop(9, 3, 1)], amt),
+            Err(WayfinderError::Engine(EngineError::MissingPool(PoolId(9))))
+        ));
+    }
+
+    #[test]
+    fn pools_added_at_runtime_become_routable() {
+        use crate::registry::PoolKind;
+        use crate::world::StateView;
+        use alloy_primitives::Address;
+
+        let mut router = router();
+        let amt = U256::from(1_000u64);
+        assert_eq!(router.quote(TokenId(3), TokenId(4), amt), None);
+
+        let meta = PoolMeta {
+            address: Address::repeat_byte(4),
+            kind: PoolKind::UniV2,
+            token0: TokenId(3),
+            token1: TokenId(4),
+            fee: 0,
+        };
+        assert_eq!(
+            router.add_pool(PoolId(4), meta.clone(), Cp::new(4, 1, 2), reserves(1, 1)),
+            Err(BuildError::PoolMismatch(PoolId(4)))
+        );
+        assert!(router.registry().pool(PoolId(4)).is_none());
+        assert!(!router.graph().pool_idx.contains_key(&PoolId(4)));
+
+        let pid = router
+            .add_pool_hashed(
+                ChainId::MAINNET,
+                meta.clone(),
+                |pid| Cp {
+                    id: pid,
+                    ..Cp::new(0, 3, 4)
+                },
+                reserves(1_000_000, 1_000_000),
+            )
+            .unwrap();
+        assert!(router.quote(TokenId(1), TokenId(4), amt).is_some());
+        assert!(router.world().pool_version(pid).is_some());
+        assert_eq!(
+            router.add_pool(pid, meta, Cp::new(pid.0, 3, 4), reserves(1, 1)),
+            Err(BuildError::DuplicatePool(pid))
+        );
+    }
+}
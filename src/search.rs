@@ -0,0 +1,461 @@
+//! Interchangeable route search. Each strategy yields candidate plans for a
+//! pair; [`Scanner::best_route_with`] simulates and ranks whatever it is
+//! given, so strategies can be swapped or composed without touching the
+//! engine.
+
+use crate::{
+    arb::{ScanConfig, Scanner},
+    engine::{Engine, Hop, Path},
+    graph::AMMGraph,
+    heuristics::{Candidates, HeuristicConfig},
+    ids::{TokenId, TokenSet},
+    pool::Pool,
+    world::StateView,
+};
+use alloy_primitives::U256;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+
+/// What a search may consult besides the graph.
+pub struct SearchContext<'a, P: Pool, V> {
+    pub engine: &'a Engine<'a, P>,
+    pub world: &'a V,
+    pub amt_in: U256,
+    pub max_hops: usize,
+    pub intermediates: Option<&'a TokenSet>,
+}
+
+type Plans = Vec<Vec<Hop>>;
+
+pub type CandidateIter<'a> = Box<dyn Iterator<Item = Vec<Hop>> + 'a>;
+
+pub trait PathSearch<P: Pool, V: StateView<P::State>> {
+    fn candidates<'a>(
+        &'a self,
+        graph: &'a AMMGraph,
+        from: TokenId,
+        to: TokenId,
+        ctx: &'a SearchContext<'a, P, V>,
+    ) -> CandidateIter<'a>;
+}
+
+fn scanner<'a, P: Pool, V>(graph: &'a AMMGraph, ctx: &SearchContext<'a, P, V>) -> Scanner<'a, P> {
+    let mut scanner = Scanner::new(ctx.engine, graph).with_config(ScanConfig {
+        max_hops: ctx.max_hops,
+        ..Default::default()
+    });
+    scanner.intermediates = ctx.intermediates;
+    scanner
+}
+
+fn out_of<P: Pool, V: StateView<P::State>>(ctx: &SearchContext<'_, P, V>, plan: &[Hop]) -> U256 {
+    let path = ctx.engine.simulate_chained(ctx.world, plan, ctx.amt_in);
+    path.steps.last().map_or(U256::ZERO, |s| s.amt_out)
+}
+
+/// Every simple route, fewest hops first, produced lazily.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Bfs;
+
+impl<P: Pool, V: StateView<P::State>> PathSearch<P, V> for Bfs {
+    fn candidates<'a>(
+        &'a self,
+        graph: &'a AMMGraph,
+        from: TokenId,
+        to: TokenId,
+        ctx: &'a SearchContext<'a, P, V>,
+    ) -> CandidateIter<'a> {
+        let scanner = scanner(graph, ctx);
+        let mut queue = VecDeque::from([Vec::new()]);
+        Box::new(std::iter::from_fn(move || {
+            while let Some(plan) = queue.pop_front() {
+                let at = plan.last().map_or(from, |h: &Hop| h.dir.to);
+                if at == to && !plan.is_empty() {
+                    return Some(plan);
+                }
+                if plan.len() == ctx.max_hops || from == to {
+                    continue;
+                }
+                for hop in scanner.next_hops(ctx.world, at, to, &plan) {
+                    let next = hop.dir.to;
+                    if next == from || plan.iter().any(|h| h.dir.from == next) {
+                        continue;
+                    }
+                    let mut longer = plan.clone();
+                    longer.push(hop);
+                    queue.push_back(longer);
+                }
+            }
+            None
+        }))
+    }
+}
+
+/// Yen's `k` loopless routes paying most for `ctx.amt_in`, best first. The
+/// underlying shortest-path step is a hop-bounded label correction on
+/// simulated amounts, so route cost is the real output rather than a
+/// static edge weight.
+#[derive(Clone, Copy, Debug)]
+pub struct Yen {
+    pub k: usize,
+}
+
+impl Yen {
+    pub fn new(k: usize) -> Self {
+        Self { k }
+    }
+}
+
+/// Exclusions for one spur search.
+struct Spur<'s> {
+    hops: &'s HashSet<Hop>,
+    tokens: &'s HashSet<TokenId>,
+}
+
+fn best_path<P: Pool, V: StateView<P::State>>(
+    scanner: &Scanner<'_, P>,
+    world: &V,
+    (from, to): (TokenId, TokenId),
+    amt_in: U256,
+    max_hops: usize,
+    spur: &Spur<'_>,
+) -> Option<Vec<Hop>> {
+    let mut frontier: HashMap<TokenId, (Vec<Hop>, U256)> =
+        HashMap::from([(from, (Vec::new(), amt_in))]);
+    let mut best: Option<(Vec<Hop>, U256)> = None;
+    for _ in 0..max_hops {
+        let mut next: HashMap<TokenId, (Vec<Hop>, U256)> = HashMap::new();
+        for (at, (plan, amt)) in &frontier {
+            for hop in scanner.next_hops(world, *at, to, plan) {
+                let t = hop.dir.to;
+                if spur.hops.contains(&hop)
+                    || spur.tokens.contains(&t)
+                    || t == from
+                    || plan.iter().any(|h| h.dir.from == t)
+                {
+                    continue;
+                }
+                let out = scanner.engine.simulate_chained(world, &[hop], *amt).steps[0].amt_out;
+                if out.is_zero() {
+                    continue;
+                }
+                let extended = || {
+                    let mut plan = plan.clone();
+                    plan.push(hop);
+                    (plan, out)
+                };
+                if t == to {
+                    if best.as_ref().is_none_or(|b| out > b.1) {
+                        best = Some(extended());
+                    }
+                } else if next.get(&t).is_none_or(|c| out > c.1) {
+                    next.insert(t, extended());
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+    best.map(|(plan, _)| plan)
+}
+
+impl<P: Pool, V: StateView<P::State>> PathSearch<P, V> for Yen {
+    fn candidates<'a>(
+        &'a self,
+        graph: &'a AMMGraph,
+        from: TokenId,
+        to: TokenId,
+        ctx: &'a SearchContext<'a, P, V>,
+    ) -> CandidateIter<'a> {
+        let scanner = scanner(graph, ctx);
+        let none = Spur {
+            hops: &HashSet::new(),
+            tokens: &HashSet::new(),
+        };
+        let first = (from != to)
+            .then(|| {
+                best_path(
+                    &scanner,
+                    ctx.world,
+                    (from, to),
+                    ctx.amt_in,
+                    ctx.max_hops,
+                    &none,
+                )
+            })
+            .flatten();
+        let mut found: Vec<Vec<Hop>> = Vec::new();
+        let mut pending: Vec<(U256, Vec<Hop>)> =
+            first.into_iter().map(|p| (U256::ZERO, p)).collect();
+        Box::new(std::iter::from_fn(move || {
+            if found.len() == self.k {
+                return None;
+            }
+            let i = (0..pending.len()).max_by_key(|&i| pending[i].0)?;
+            let (_, plan) = pending.swap_remove(i);
+            found.push(plan.clone());
+            if found.len() == self.k {
+                return Some(plan);
+            }
+            for spur_at in 0..plan.len() {
+                let root = &plan[..spur_at];
+                let hops: HashSet<Hop> = found
+                    .iter()
+                    .filter(|p| p.len() > spur_at && p[..spur_at] == *root)
+                    .map(|p| p[spur_at])
+                    .collect();
+                let mut tokens: HashSet<TokenId> = root.iter().map(|h| h.dir.from).collect();
+                tokens.remove(&plan[spur_at].dir.from);
+                let root_out = if root.is_empty() {
+                    ctx.amt_in
+                } else {
+                    out_of(ctx, root)
+                };
+                let spur = Spur {
+                    hops: &hops,
+                    tokens: &tokens,
+                };
+                let budget = ctx.max_hops - spur_at;
+                let Some(tail) = best_path(
+                    &scanner,
+                    ctx.world,
+                    (plan[spur_at].dir.from, to),
+                    root_out,
+                    budget,
+                    &spur,
+                ) else {
+                    continue;
+                };
+                let mut total = root.to_vec();
+                total.extend(tail);
+                let reuses_pool = total
+                    .iter()
+                    .enumerate()
+                    .any(|(i, h)| total[..i].iter().any(|g| g.pool == h.pool));
+                if reuses_pool || found.contains(&total) || pending.iter().any(|(_, p)| *p == total)
+                {
+                    continue;
+                }
+                pending.push((out_of(ctx, &total), total));
+            }
+            Some(plan)
+        }))
+    }
+}
+
+/// Direct pools, connector routes and top intermediates; see
+/// [`Candidates::generate`].
+#[derive(Clone, Debug, Default)]
+pub struct Heuristic(pub HeuristicConfig);
+
+impl<P: Pool, V: StateView<P::State>> PathSearch<P, V> for Heuristic {
+    fn candidates<'a>(
+        &'a self,
+        graph: &'a AMMGraph,
+        from: TokenId,
+        to: TokenId,
+        ctx: &'a SearchContext<'a, P, V>,
+    ) -> CandidateIter<'a> {
+        let plans = Candidates::new(ctx.engine, graph)
+            .with_config(self.0.clone())
+            .generate(ctx.world, from, to, ctx.amt_in);
+        Box::new(plans.into_iter().filter(move |plan| {
+            ctx.intermediates.is_none_or(|set| {
+                plan[..plan.len() - 1]
+                    .iter()
+                    .all(|h| set.contains(h.dir.to))
+            })
+        }))
+    }
+}
+
+/// Remembers another search's plans per pair and hop limit. Plans depend on
+/// topology and on the state when first searched, so clear the cache when
+/// pools are added or states move far. Searches restricted to
+/// `intermediates` are passed through uncached.
+#[derive(Debug, Default)]
+pub struct Cached<S> {
+    pub inner: S,
+    plans: Mutex<HashMap<(TokenId, TokenId, usize), Plans>>,
+}
+
+impl<S> Cached<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            plans: Mutex::default(),
+        }
+    }
+
+    pub fn clear(&self) {
+        self.plans.lock().unwrap().clear();
+    }
+}
+
+impl<P: Pool, V: StateView<P::State>, S: PathSearch<P, V>> PathSearch<P, V> for Cached<S> {
+    fn candidates<'a>(
+        &'a self,
+        graph: &'a AMMGraph,
+        from: TokenId,
+        to: TokenId,
+        ctx: &'a SearchContext<'a, P, V>,
+    ) -> CandidateIter<'a> {
+        if ctx.intermediates.is_some() {
+            return self.inner.candidates(graph, from, to, ctx);
+        }
+        let mut plans = self.plans.lock().unwrap();
+        let plans = plans
+            .entry((from, to, ctx.max_hops))
+            .or_insert_with(|| self.inner.candidates(graph, from, to, ctx).collect())
+            .clone();
+        Box::new(plans.into_iter())
+    }
+}
+
+/// Candidates of `A`, then those of `B` that `A` did not produce.
+#[derive(Clone, Debug, Default)]
+pub struct Chain<A, B>(pub A, pub B);
+
+impl<P: Pool, V: StateView<P::State>, A: PathSearch<P, V>, B: PathSearch<P, V>> PathSearch<P, V>
+    for Chain<A, B>
+{
+    fn candidates<'a>(
+        &'a self,
+        graph: &'a AMMGraph,
+        from: TokenId,
+        to: TokenId,
+        ctx: &'a SearchContext<'a, P, V>,
+    ) -> CandidateIter<'a> {
+        let mut seen = HashSet::new();
+        Box::new(
+            self.0
+                .candidates(graph, from, to, ctx)
+                .chain(self.1.candidates(graph, from, to, ctx))
+                .filter(move |plan| seen.insert(plan.clone())),
+        )
+    }
+}
+
+impl<'s, P: Pool> Scanner<'s, P> {
+    /// [`Scanner::best_route`] over the candidates of `search` rather than
+    /// exhaustive enumeration.
+    pub fn best_route_with<V: StateView<P::State>>(
+        &self,
+        search: &impl PathSearch<P, V>,
+        world: &V,
+        from: TokenId,
+        to: TokenId,
+        amt_in: U256,
+    ) -> Option<Path> {
+        let ctx = SearchContext {
+            engine: self.engine,
+            world,
+            amt_in,
+            max_hops: self.config.max_hops,
+            intermediates: self.intermediates,
+        };
+        let plans: Vec<_> = search.candidates(self.graph, from, to, &ctx).collect();
+        self.rank(world, &plans, amt_in)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, reserves};
+    use crate::world::World;
+
+    #[test]
+    fn strategies_agree_on_the_best_route_and_order_their_candidates() {
+        // 1 -> 2 directly (shallow), via 3 (deep), and via 3 then 4.
+        let pools = HashMap::from([
+            (PoolId(1), Cp::new(1, 1, 2)),
+            (PoolId(2), Cp::new(2, 1, 3)),
+            (PoolId(3), Cp::new(3, 3, 2)),
+            (PoolId(4), Cp::new(4, 3, 4)),
+            (PoolId(5), Cp::new(5, 4, 2)),
+        ]);
+        let mut graph = AMMGraph::new();
+        let mut world = World::default();
+        for (id, a, b, r) in [
+            (1, 1, 2, 100_000),
+            (2, 1, 3, 10_000_000),
+            (3, 3, 2, 10_000_000),
+            (4, 3, 4, 1_000_000),
+            (5, 4, 2, 1_000_000),
+        ] {
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(a), TokenId(b));
+            world.set_pool_state(PoolId(id), reserves(r, r));
+        }
+        let engine = Engine::new(&pools);
+        let ctx = SearchContext {
+            engine: &engine,
+            world: &world,
+            amt_in: U256::from(10_000u64),
+            max_hops: 3,
+            intermediates: None,
+        };
+        let (a, b) = (TokenId(1), TokenId(2));
+
+        let bfs: Vec<_> = Bfs.candidates(&graph, a, b, &ctx).collect();
+        assert_eq!(bfs.len(), 3);
+        assert!(bfs.windows(2).all(|w| w[0].len() <= w[1].len()));
+
+        let yen: Vec<_> = Yen::new(3).candidates(&graph, a, b, &ctx).collect();
+        assert_eq!(yen.len(), 3);
+        let outs: Vec<U256> = yen.iter().map(|p| out_of(&ctx, p)).collect();
+        assert!(outs.windows(2).all(|w| w[0] >= w[1]), "{outs:?}");
+        assert_eq!(
+            yen[0],
+            vec![
+                crate::test_utils::hop(2, 1, 3),
+                crate::test_utils::hop(3, 3, 2)
+            ]
+        );
+
+        let cached = Cached::new(Yen::new(1));
+        let scanner = Scanner::new(&engine, &graph);
+        let best = scanner.best_route(&world, a, b, ctx.amt_in).unwrap();
+        for path in [
+            scanner.best_route_with(&Bfs, &world, a, b, ctx.amt_in),
+            scanner.best_route_with(&cached, &world, a, b, ctx.amt_in),
+            scanner.best_route_with(&Chain(Heuristic::default(), Bfs), &world, a, b, ctx.amt_in),
+        ] {
+            assert_eq!(path.unwrap().plan(), best.plan());
+        }
+        assert_eq!(cached.plans.lock().unwrap().len(), 1);
+
+        // Only 3 may be passed through: the route via 4 is gone everywhere.
+        let majors: TokenSet = [TokenId(3)].into_iter().collect();
+        let ctx = SearchContext {
+            intermediates: Some(&majors),
+            ..ctx
+        };
+        assert_eq!(Bfs.candidates(&graph, a, b, &ctx).count(), 2);
+        assert_eq!(Yen::new(3).candidates(&graph, a, b, &ctx).count(), 2);
+        // An unrestricted search already cached the pair.
+        let cached = Cached::new(Bfs);
+        let open = SearchContext {
+            intermediates: None,
+            ..ctx
+        };
+        assert_eq!(cached.candidates(&graph, a, b, &open).count(), 3);
+        assert_eq!(cached.candidates(&graph, a, b, &ctx).count(), 2);
+        let short = SearchContext {
+            max_hops: 1,
+            ..open
+        };
+        assert_eq!(cached.candidates(&graph, a, b, &short).count(), 1);
+        let restricted = Scanner::new(&engine, &graph).with_intermediates(&majors);
+        assert_eq!(restricted.routes(&world, a, b).len(), 2);
+        let none = TokenSet::default();
+        let direct = Scanner::new(&engine, &graph).with_intermediates(&none);
+        assert_eq!(
+            direct.routes(&world, a, b),
+            vec![vec![crate::test_utils::hop(1, 1, 2)]]
+        );
+    }
+}
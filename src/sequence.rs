@@ -0,0 +1,226 @@
+//! Multi-transaction strategies: trades executed in order against a world
+//! that moves on between them, each with its own success criterion.
+
+use crate::{
+    backtest::Trade,
+    engine::{Engine, Path},
+    ids::AccountId,
+    pool::Pool,
+    world::World,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SequenceStep {
+    pub trade: Trade,
+    /// Blocks to wait after the previous step; zero lands in the same block
+    /// right behind it, as the next nonce would.
+    pub delay: u64,
+}
+
+/// What to do with the rest of a sequence once a step fails.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AbortPolicy {
+    /// Go on with the remaining steps.
+    Continue,
+    /// Skip the remaining steps, keeping what already executed.
+    #[default]
+    Stop,
+    /// Skip the remaining steps and undo the executed ones.
+    Revert,
+}
+
+#[derive(Clone, Debug)]
+pub enum StepOutcome {
+    Filled(Path),
+    /// Output below `min_out`; the step reverted.
+    BelowMin(Path),
+    /// Missing balance or approval, or a malformed or failing plan.
+    Failed(String),
+    /// Not attempted after an earlier abort.
+    Skipped,
+}
+
+impl StepOutcome {
+    pub fn is_filled(&self) -> bool {
+        matches!(self, StepOutcome::Filled(_))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Sequence {
+    pub owner: AccountId,
+    pub spender: AccountId,
+    pub steps: Vec<SequenceStep>,
+    pub on_failure: AbortPolicy,
+}
+
+#[derive(Clone, Debug)]
+pub struct SequenceReport {
+    pub outcomes: Vec<StepOutcome>,
+    /// Step that triggered an abort.
+    pub aborted_at: Option<usize>,
+    /// Whether executed steps were rolled back.
+    pub reverted: bool,
+    pub end_block: u64,
+}
+
+impl SequenceReport {
+    pub fn completed(&self) -> bool {
+        self.outcomes.iter().all(StepOutcome::is_filled)
+    }
+}
+
+impl Sequence {
+    pub fn new(owner: AccountId) -> Self {
+        Self {
+            owner,
+            spender: owner,
+            steps: Vec::new(),
+            on_failure: AbortPolicy::default(),
+        }
+    }
+
+    pub fn then(mut self, trade: Trade, delay: u64) -> Self {
+        self.steps.push(SequenceStep { trade, delay });
+        self
+    }
+
+    pub fn on_failure(mut self, policy: AbortPolicy) -> Self {
+        self.on_failure = policy;
+        self
+    }
+}
+
+impl<P: Pool> Engine<'_, P>
+where
+    P::State: Clone,
+{
+    /// Runs `seq` on `world`. Before each block boundary a step waits out,
+    /// `advance` is called with the new block number to move the rest of
+    /// the market, e.g. by applying that block's diff.
+    pub fn run_sequence<F>(
+        &self,
+        world: &mut World<P::State>,
+        seq: &Sequence,
+        mut advance: F,
+    ) -> SequenceReport
+    where
+        F: FnMut(u64, &mut World<P::State>),
+    {
+        let snapshot = (seq.on_failure == AbortPolicy::Revert).then(|| world.clone());
+        let mut outcomes = Vec::with_capacity(seq.steps.len());
+        let mut aborted_at = None;
+
+        for (i, step) in seq.steps.iter().enumerate() {
+            if aborted_at.is_some() {
+                outcomes.push(StepOutcome::Skipped);
+                continue;
+            }
+            for _ in 0..step.delay {
+                world.block.number += 1;
+                advance(world.block.number, world);
+            }
+
+            let outcome = self.run_step(world, seq, &step.trade);
+            if !outcome.is_filled() && seq.on_failure != AbortPolicy::Continue {
+                aborted_at = Some(i);
+            }
+            outcomes.push(outcome);
+        }
+
+        let reverted = aborted_at.is_some() && snapshot.is_some();
+        if let (Some(_), Some(snapshot)) = (aborted_at, snapshot) {
+            *world = snapshot;
+        }
+        SequenceReport {
+            outcomes,
+            aborted_at,
+            reverted,
+            end_block: world.block.number,
+        }
+    }
+
+    fn run_step(&self, world: &mut World<P::State>, seq: &Sequence, trade: &Trade) -> StepOutcome {
+        let path = match self.try_simulate(&*world, &trade.plan, trade.amount_in) {
+            Ok(path) => path,
+            Err(e) => return StepOutcome::Failed(e.to_string()),
+        };
+        if path.steps.last().is_none_or(|s| s.amt_out < trade.min_out) {
+            return StepOutcome::BelowMin(path);
+        }
+        let exec = self.execute(world, seq.owner, seq.spender, &trade.plan, trade.amount_in);
+        if exec.committed() {
+            StepOutcome::Filled(exec.path)
+        } else if let Some((hop, failure)) = exec.failure {
+            StepOutcome::Failed(format!("hop {hop} failed: {failure:?}"))
+        } else if exec.insufficient_balance {
+            StepOutcome::Failed("insufficient balance".into())
+        } else {
+            StepOutcome::Failed(format!("unapproved hops {:?}", exec.unapproved_hops))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::ApprovalPolicy;
+    use crate::ids::{PoolId, TokenId};
+    use crate::test_utils::{Cp, hop, reserves};
+    use alloy_primitives::U256;
+    use std::collections::HashMap;
+
+    fn sell(amount: u64, min_out: u64) -> Trade {
+        Trade {
+            plan: vec![hop(1, 1, 2)],
+            amount_in: U256::from(amount),
+            min_out: U256::from(min_out),
+        }
+    }
+
+    #[test]
+    fn unwinds_over_blocks_with_abort_policies() {
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2))]);
+        let engine = Engine::new(&pools).with_approvals(ApprovalPolicy::AssumeInfinite);
+        let owner = AccountId(1);
+        let mut base = World::default();
+        base.set_pool_state(PoolId(1), reserves(1_000_000, 1_000_000));
+        base.credit(owner, TokenId(1), U256::from(3_000u64));
+
+        // Someone else dumps token 1 in block 2, so the third tranche misses its minimum.
+        let market = |block: u64, w: &mut World<(U256, U256)>| {
+            if block == 2 {
+                w.set_pool_state(PoolId(1), reserves(2_000_000, 500_000));
+            }
+        };
+        let unwind = |policy| {
+            Sequence::new(owner)
+                .then(sell(1_000, 990), 0)
+                .then(sell(1_000, 990), 1)
+                .then(sell(1_000, 990), 1)
+                .on_failure(policy)
+        };
+
+        let mut world = base.clone();
+        let report = engine.run_sequence(&mut world, &unwind(AbortPolicy::Stop), market);
+        assert_eq!(report.aborted_at, Some(2));
+        assert!(!report.reverted);
+        assert!(matches!(report.outcomes[2], StepOutcome::BelowMin(_)));
+        assert_eq!(report.end_block, 2);
+        assert_eq!(world.holding(owner, TokenId(1)), U256::from(1_000u64));
+
+        let mut world = base.clone();
+        let report = engine.run_sequence(&mut world, &unwind(AbortPolicy::Revert), market);
+        assert!(report.reverted);
+        assert_eq!(world.holding(owner, TokenId(1)), U256::from(3_000u64));
+        assert_eq!(world.block.number, 0);
+
+        // Continue runs every step; the fourth has nothing left to sell.
+        let seq = unwind(AbortPolicy::Continue).then(sell(1_000, 0), 0);
+        let mut world = base;
+        let report = engine.run_sequence(&mut world, &seq, market);
+        assert_eq!(report.aborted_at, None);
+        assert!(report.outcomes[3].is_filled());
+        assert!(!report.completed());
+    }
+}
@@ -0,0 +1,322 @@
+use crate::{
+    arb::{ScanConfig, Scanner},
+    engine::Engine,
+    graph::AMMGraph,
+    ids::PoolId,
+    pool::Pool,
+    registry::{PoolMeta, Registry},
+    wire::{QuoteRequest, QuoteResponse, WIRE_VERSION},
+    world::{World, WorldDiff},
+};
+use alloy_primitives::U256;
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+pub struct Market<P: Pool> {
+    pub registry: Registry,
+    pub pools: HashMap<PoolId, P>,
+    pub graph: AMMGraph,
+    pub world: World<P::State>,
+}
+
+pub struct QuoteServer<P: Pool> {
+    market: Arc<RwLock<Market<P>>>,
+    config: ScanConfig,
+}
+
+impl<P: Pool> Clone for QuoteServer<P> {
+    fn clone(&self) -> Self {
+        Self {
+            market: Arc::clone(&self.market),
+            config: self.config,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RouteResponse {
+    pub version: u32,
+    pub block: u64,
+    pub routes: Vec<QuoteResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PoolEntry<S> {
+    pub id: PoolId,
+    pub meta: Option<PoolMeta>,
+    pub state: S,
+}
+
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    NotFound(String),
+    Internal(String),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, error) = match self {
+            Self::BadRequest(e) => (StatusCode::BAD_REQUEST, e),
+            Self::NotFound(e) => (StatusCode::NOT_FOUND, e),
+            Self::Internal(e) => (StatusCode::INTERNAL_SERVER_ERROR, e),
+        };
+        (status, Json(serde_json::json!({ "error": error }))).into_response()
+    }
+}
+
+impl<P> QuoteServer<P>
+where
+    P: Pool + Send + Sync + 'static,
+    P::State: Serialize + Send + Sync,
+{
+    pub fn new(market: Market<P>) -> Self {
+        Self {
+            market: Arc::new(RwLock::new(market)),
+            config: ScanConfig::default(),
+        }
+    }
+
+    pub fn with_config(mut self, config: ScanConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn market(&self) -> &Arc<RwLock<Market<P>>> {
+        &self.market
+    }
+
+    pub fn sync(&self, diff: WorldDiff<P::State>) {
+        self.market.write().unwrap().world.apply(diff);
+    }
+
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/quote", get(quote::<P>))
+            .route("/route", get(route::<P>))
+            .route("/pools", get(pools::<P>))
+            .with_state(self.clone())
+    }
+
+    /// [`QuoteServer::routes`] on the blocking pool, so a deep search never
+    /// stalls the runtime's workers.
+    async fn routes_blocking(self, q: QuoteRequest) -> Result<RouteResponse, ApiError> {
+        tokio::task::spawn_blocking(move || self.routes(&q))
+            .await
+            .map_err(|e| ApiError::Internal(e.to_string()))?
+    }
+
+    /// Requests may lower `max_hops` below the server's config, never raise it.
+    fn routes(&self, q: &QuoteRequest) -> Result<RouteResponse, ApiError> {
+        let market = self.market.read().unwrap();
+        let token = |s: &str| {
+            market
+                .registry
+                .resolve_token(s)
+                .ok_or_else(|| ApiError::NotFound(format!("unknown or ambiguous token {s}")))
+        };
+        let (from, to) = (token(&q.from)?, token(&q.to)?);
+        let amount_in: U256 = q
+            .amount
+            .parse()
+            .map_err(|_| ApiError::BadRequest(format!("invalid amount {}", q.amount)))?;
+
+        let engine = Engine::new(&market.pools);
+        let scanner = Scanner::new(&engine, &market.graph).with_config(ScanConfig {
+            max_hops: q
+                .max_hops
+                .map_or(self.config.max_hops, |h| h.min(self.config.max_hops)),
+            ..self.config
+        });
+        let mut routes: Vec<QuoteResponse> = scanner
+            .routes(&market.world, from, to)
+            .iter()
+            .map(|plan| {
+                let path = engine.simulate_chained(&market.world, plan, amount_in);
+                QuoteResponse::new(market.world.block.number, from, to, &path)
+            })
+            .collect();
+        routes.sort_by_key(|r| Reverse(r.route.amount_out));
+        routes.truncate(q.top.unwrap_or(5));
+
+        Ok(RouteResponse {
+            version: WIRE_VERSION,
+            block: market.world.block.number,
+            routes,
+        })
+    }
+}
+
+async fn quote<P>(
+    State(srv): State<QuoteServer<P>>,
+    Query(q): Query<QuoteRequest>,
+) -> Result<Json<QuoteResponse>, ApiError>
+where
+    P: Pool + Send + Sync + 'static,
+    P::State: Serialize + Send + Sync,
+{
+    let q = QuoteRequest { top: Some(1), ..q };
+    let (from, to) = (q.from.clone(), q.to.clone());
+    srv.routes_blocking(q)
+        .await?
+        .routes
+        .pop()
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("no route from {from} to {to}")))
+}
+
+async fn route<P>(
+    State(srv): State<QuoteServer<P>>,
+    Query(q): Query<QuoteRequest>,
+) -> Result<Json<RouteResponse>, ApiError>
+where
+    P: Pool + Send + Sync + 'static,
+    P::State: Serialize + Send + Sync,
+{
+    srv.routes_blocking(q).await.map(Json)
+}
+
+async fn pools<P>(State(srv): State<QuoteServer<P>>) -> Json<Vec<PoolEntry<P::State>>>
+where
+    P: Pool + Send + Sync + 'static,
+    P::State: Serialize + Send + Sync,
+{
+    let market = srv.market.read().unwrap();
+    let mut out: Vec<_> = market
+        .world
+        .pool_states
+        .iter()
+        .map(|(&id, st)| PoolEntry {
+            id,
+            meta: market.registry.pool(id).cloned(),
+            state: st.clone(),
+        })
+        .collect();
+    out.sort_by_key(|e| e.id);
+    Json(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::TokenMeta;
+    use crate::univ2::{UniV2Pool, UniV2State, pools_from_registry};
+    use crate::{ChainId, PoolKind, TokenId};
+    use alloy_primitives::Address;
+    use axum::body::{Body, to_bytes};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    fn server() -> QuoteServer<UniV2Pool> {
+        let mut registry = Registry::default();
+        for (i, sym) in ["WETH", "USDC", "DAI"].iter().enumerate() {
+            registry.upsert_token(
+                TokenId(i as u32 + 1),
+                TokenMeta {
+                    address: Address::repeat_byte(i as u8 + 1),
+                    symbol: sym.to_string(),
+                    decimals: 18,
+                },
+            );
+        }
+        let mut world = World::default();
+        for (id, t0, t1, r0, r1) in [
+            (1, 1, 2, 1_000u64, 2_000_000u64),
+            (2, 2, 3, 2_000_000, 2_000_000),
+            (3, 1, 3, 1_000, 1_900_000),
+        ] {
+            let pid = registry.insert_pool_hashed(
+                ChainId::MAINNET,
+                PoolMeta {
+                    address: Address::repeat_byte(0x10 + id),
+                    kind: PoolKind::UniV2,
+                    token0: TokenId(t0),
+                    token1: TokenId(t1),
+                    fee: 3000,
+                },
+            );
+            world
+                .pool_states
+                .insert(pid, UniV2State::new(U256::from(r0), U256::from(r1)));
+        }
+        let (pools, graph) = pools_from_registry(&registry);
+        QuoteServer::new(Market {
+            registry,
+            pools,
+            graph,
+            world,
+        })
+    }
+
+    async fn get(srv: &QuoteServer<UniV2Pool>, uri: &str) -> (StatusCode, serde_json::Value) {
+        let res = srv
+            .router()
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = res.status();
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn quote_picks_best_route_and_route_lists_alternatives() {
+        let srv = server();
+        let (status, q) = get(&srv, "/quote?from=weth&to=USDC&amount=10").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(q["route"]["steps"].as_array().unwrap().len(), 1);
+
+        let (_, r) = get(&srv, "/route?from=WETH&to=USDC&amount=10&top=5").await;
+        let routes = r["routes"].as_array().unwrap();
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0]["route"]["amount_out"], q["route"]["amount_out"]);
+
+        // Clients cannot search deeper than the server allows.
+        let shallow = server().with_config(ScanConfig {
+            max_hops: 1,
+            ..ScanConfig::default()
+        });
+        let (_, r) = get(&shallow, "/route?from=WETH&to=USDC&amount=10&max_hops=64").await;
+        assert_eq!(r["routes"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn quotes_follow_synced_world() {
+        let srv = server();
+        let (_, before) = get(&srv, "/quote?from=1&to=2&amount=10").await;
+
+        let pid = srv.market().read().unwrap().registry.pool_by_addr[&Address::repeat_byte(0x11)];
+        let mut diff = WorldDiff::default();
+        diff.set_pool_state(
+            pid,
+            UniV2State::new(U256::from(1_000u64), U256::from(4_000_000u64)),
+        );
+        srv.sync(diff);
+
+        let (_, after) = get(&srv, "/quote?from=1&to=2&amount=10").await;
+        assert_ne!(before["route"]["amount_out"], after["route"]["amount_out"]);
+
+        let (_, pools) = get(&srv, "/pools").await;
+        assert_eq!(pools.as_array().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn unknown_tokens_and_bad_amounts_are_client_errors() {
+        let srv = server();
+        let (status, body) = get(&srv, "/quote?from=FOO&to=USDC&amount=1").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(body["error"].as_str().unwrap().contains("FOO"));
+
+        let (status, _) = get(&srv, "/quote?from=WETH&to=USDC&amount=lots").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
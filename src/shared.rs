@@ -0,0 +1,136 @@
+//! One writer publishing world versions to many readers without locks:
+//! readers grab an immutable snapshot and simulate against it for as long
+//! as they like while the writer moves on to the next block.
+
+use crate::world::{World, WorldDiff};
+use arc_swap::ArcSwap;
+use std::sync::Arc;
+
+/// Reader handle; clone one per thread.
+pub struct SharedWorld<S> {
+    current: Arc<ArcSwap<World<S>>>,
+}
+
+impl<S> Clone for SharedWorld<S> {
+    fn clone(&self) -> Self {
+        Self {
+            current: Arc::clone(&self.current),
+        }
+    }
+}
+
+/// The single writer. Updates land in a private working copy and become
+/// visible to readers only on [`WorldWriter::publish`].
+pub struct WorldWriter<S> {
+    working: World<S>,
+    current: Arc<ArcSwap<World<S>>>,
+}
+
+impl<S: Clone> SharedWorld<S> {
+    pub fn new(world: World<S>) -> (WorldWriter<S>, SharedWorld<S>) {
+        let current = Arc::new(ArcSwap::from_pointee(world.clone()));
+        let writer = WorldWriter {
+            working: world,
+            current: Arc::clone(&current),
+        };
+        (writer, SharedWorld { current })
+    }
+}
+
+impl<S> SharedWorld<S> {
+    /// The latest published world. It never changes underneath the caller.
+    pub fn snapshot(&self) -> Arc<World<S>> {
+        self.current.load_full()
+    }
+
+    /// Block number of the latest published world.
+    pub fn block(&self) -> u64 {
+        self.current.load().block.number
+    }
+}
+
+impl<S: Clone> WorldWriter<S> {
+    pub fn apply(&mut self, diff: WorldDiff<S>) {
+        self.working.apply(diff);
+    }
+
+    /// Direct access to the unpublished working copy.
+    pub fn working_mut(&mut self) -> &mut World<S> {
+        &mut self.working
+    }
+
+    /// Makes the working copy the version readers see. Snapshots taken
+    /// before stay valid and are freed when their last reader drops them.
+    ///
+    /// Each publish clones the whole working world, pool states, holdings
+    /// and allowances alike, so it costs time and memory in proportion to
+    /// the world's size, not to what changed since the last one. Batch
+    /// updates with [`WorldWriter::apply`] and publish once per block.
+    pub fn publish(&mut self) -> Arc<World<S>> {
+        let published = Arc::new(self.working.clone());
+        self.current.store(Arc::clone(&published));
+        published
+    }
+
+    pub fn readers(&self) -> SharedWorld<S> {
+        SharedWorld {
+            current: Arc::clone(&self.current),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::BlockContext;
+    use alloy_primitives::U256;
+    use std::collections::HashMap;
+    use std::thread;
+
+    #[test]
+    fn readers_keep_their_snapshot_while_the_writer_publishes() {
+        let mut world = World::default();
+        world.set_pool_state(PoolId(1), reserves(1_000_000, 1_000_000));
+        let (mut writer, readers) = SharedWorld::new(world);
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2))]);
+        let amt = U256::from(1_000u64);
+
+        let held = readers.snapshot();
+        let mut diff = WorldDiff {
+            block: Some(BlockContext {
+                number: 1,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        diff.set_pool_state(PoolId(1), reserves(2_000_000, 1_000_000));
+        writer.apply(diff);
+        assert_eq!(readers.block(), 0, "nothing visible before publish");
+        writer.publish();
+
+        let outs: Vec<(u64, U256)> = thread::scope(|s| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    let readers = readers.clone();
+                    let pools = &pools;
+                    s.spawn(move || {
+                        let world = readers.snapshot();
+                        let path =
+                            Engine::new(pools).simulate_chained(&*world, &[hop(1, 1, 2)], amt);
+                        (world.block.number, path.steps[0].amt_out)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert!(outs.windows(2).all(|w| w[0] == w[1]));
+        assert_eq!(outs[0].0, 1);
+
+        let old = Engine::new(&pools).simulate_chained(&*held, &[hop(1, 1, 2)], amt);
+        assert_eq!(held.block.number, 0);
+        assert!(old.steps[0].amt_out > outs[0].1);
+    }
+}
@@ -0,0 +1,139 @@
+//! The window of input sizes over which a cycle pays for its gas.
+
+use crate::{
+    arb::{ScanConfig, optimal_input},
+    engine::{Engine, Hop},
+    pool::Pool,
+    world::StateView,
+};
+use alloy_primitives::U256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SizeWindow {
+    /// Smallest input with positive net profit.
+    pub min: U256,
+    /// Input with the highest net profit.
+    pub optimal: U256,
+    /// Largest input with positive net profit.
+    pub max: U256,
+    pub peak_profit: U256,
+}
+
+impl SizeWindow {
+    pub fn contains(&self, amount: U256) -> bool {
+        (self.min..=self.max).contains(&amount)
+    }
+}
+
+impl<P: Pool> Engine<'_, P> {
+    /// Net profit of running cycle `plan` with `amount`, after `gas_cost`
+    /// in the cycle's token; `None` at a loss.
+    pub fn net_profit<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plan: &[Hop],
+        amount: U256,
+        gas_cost: U256,
+    ) -> Option<U256> {
+        let path = self.simulate_chained(world, plan, amount);
+        let out = path.steps.last().map_or(U256::ZERO, |s| s.amt_out);
+        out.checked_sub(amount)?
+            .checked_sub(gas_cost)
+            .filter(|p| !p.is_zero())
+    }
+
+    /// Sizes between which cycle `plan` nets a profit after `gas_cost`,
+    /// searched up to [`ScanConfig::default`]'s `max_in`; `None` if no size
+    /// is profitable. Profit is assumed concave in size, as it is for
+    /// constant-function pools.
+    ///
+    /// # Panics
+    ///
+    /// On a malformed plan, like [`Engine::simulate_chained`].
+    pub fn break_even_size<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plan: &[Hop],
+        gas_cost: U256,
+    ) -> Option<SizeWindow> {
+        let config = ScanConfig::default();
+        let profit = |x| self.net_profit(world, plan, x, gas_cost);
+        let (optimal, _) = optimal_input(
+            |x| {
+                let path = self.simulate_chained(world, plan, x);
+                path.steps.last().map_or(U256::ZERO, |s| s.amt_out)
+            },
+            config.max_in,
+            config.max_iters,
+        );
+        let peak_profit = profit(optimal)?;
+
+        // Profit is positive at `optimal`, so each search keeps one end
+        // inside the window and one outside it.
+        let boundary = |mut inside: U256, mut outside: U256| {
+            while inside.abs_diff(outside) > U256::from(1u64) {
+                let mid = (inside >> 1) + (outside >> 1) + (inside & outside & U256::from(1u64));
+                if profit(mid).is_some() {
+                    inside = mid;
+                } else {
+                    outside = mid;
+                }
+            }
+            inside
+        };
+        let min = boundary(optimal, U256::ZERO);
+        let max = if profit(config.max_in).is_some() {
+            config.max_in
+        } else {
+            boundary(optimal, config.max_in)
+        };
+        Some(SizeWindow {
+            min,
+            optimal,
+            max,
+            peak_profit,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::World;
+    use std::collections::HashMap;
+
+    #[test]
+    fn finds_the_profitable_window() {
+        let pools = HashMap::from([
+            (PoolId(1), Cp::new(1, 1, 2).with_fee(30)),
+            (PoolId(2), Cp::new(2, 1, 2).with_fee(30)),
+        ]);
+        let engine = Engine::new(&pools);
+        let mut world = World::default();
+        world.set_pool_state(PoolId(1), reserves(1_000_000, 1_100_000));
+        world.set_pool_state(PoolId(2), reserves(1_000_000, 1_000_000));
+        let cycle = [hop(1, 1, 2), hop(2, 2, 1)];
+        let gas = U256::from(100u64);
+
+        let w = engine.break_even_size(&world, &cycle, gas).unwrap();
+        assert!(w.min < w.optimal && w.optimal < w.max, "{w:?}");
+        let one = U256::from(1u64);
+        for inside in [w.min, w.optimal, w.max] {
+            assert!(engine.net_profit(&world, &cycle, inside, gas).is_some());
+        }
+        for outside in [w.min - one, w.max + one] {
+            assert_eq!(engine.net_profit(&world, &cycle, outside, gas), None);
+        }
+        assert!(w.contains(w.optimal) && !w.contains(w.max + one));
+
+        // Gas above the peak profit closes the window.
+        assert_eq!(
+            engine.break_even_size(&world, &cycle, w.peak_profit + gas),
+            None
+        );
+        let reverse = [hop(2, 1, 2), hop(1, 2, 1)];
+        assert_eq!(engine.break_even_size(&world, &reverse, gas), None);
+    }
+}
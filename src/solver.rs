@@ -0,0 +1,296 @@
+use crate::{
+    arb::{ScanConfig, Scanner},
+    engine::{Engine, Hop, Path},
+    graph::AMMGraph,
+    ids::TokenId,
+    pool::Pool,
+    split::SplitRoute,
+    world::{StateView, World},
+};
+use alloy_primitives::U256;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Order {
+    pub sell_token: TokenId,
+    pub buy_token: TokenId,
+    pub sell_amount: U256,
+    pub min_buy_amount: U256,
+    /// Last block timestamp at which the order may settle.
+    pub deadline: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Solution {
+    pub paths: Vec<Path>,
+    pub fill_amount: U256,
+    pub buy_amount: U256,
+    pub surplus: U256,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Split {
+    /// Input per route, in the order the routes were given.
+    pub allocation: Vec<U256>,
+    /// The routes given any input, simulated in order on shared state.
+    pub paths: Vec<Path>,
+    pub amount_out: U256,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SolveError {
+    #[error("order expired at {deadline}, block time is {timestamp}")]
+    Expired { deadline: u64, timestamp: u64 },
+    #[error("no route between order tokens")]
+    NoRoute,
+    #[error("best execution {best} is below limit {min}")]
+    BelowLimit { best: U256, min: U256 },
+}
+
+pub struct Solver<'a, P: Pool> {
+    pub engine: &'a Engine<'a, P>,
+    pub graph: &'a AMMGraph,
+    pub config: ScanConfig,
+    pub chunks: usize,
+}
+
+impl<'a, P: Pool> Solver<'a, P> {
+    pub fn new(engine: &'a Engine<'a, P>, graph: &'a AMMGraph) -> Self {
+        Self {
+            engine,
+            graph,
+            config: ScanConfig::default(),
+            chunks: 20,
+        }
+    }
+
+    pub fn with_config(mut self, config: ScanConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_chunks(mut self, chunks: usize) -> Self {
+        self.chunks = chunks.max(1);
+        self
+    }
+
+    /// Fills `order` by greedily routing equal chunks of the sell amount to
+    /// whichever route pays most given the chunks already placed, so routes
+    /// sharing pools see each other's price impact.
+    pub fn solve<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        order: &Order,
+    ) -> Result<Solution, SolveError> {
+        let timestamp = world.block().timestamp;
+        if timestamp > order.deadline {
+            return Err(SolveError::Expired {
+                deadline: order.deadline,
+                timestamp,
+            });
+        }
+
+        let scanner = Scanner::new(self.engine, self.graph).with_config(self.config);
+        let routes = scanner.routes(world, order.sell_token, order.buy_token);
+        if routes.is_empty() || order.sell_amount.is_zero() {
+            return Err(SolveError::NoRoute);
+        }
+
+        let Split {
+            paths,
+            amount_out: buy_amount,
+            ..
+        } = self.split(world, &routes, order.sell_amount);
+
+        if buy_amount < order.min_buy_amount {
+            return Err(SolveError::BelowLimit {
+                best: buy_amount,
+                min: order.min_buy_amount,
+            });
+        }
+        Ok(Solution {
+            paths,
+            fill_amount: order.sell_amount,
+            buy_amount,
+            surplus: buy_amount - order.min_buy_amount,
+        })
+    }
+
+    /// Splits `total` across `routes` between the same two tokens a chunk at
+    /// a time, each chunk to the route paying most for it given the chunks
+    /// already placed, which leaves the used routes' marginal prices about
+    /// equal. Routes sharing pools see each other's price impact.
+    pub fn split<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        routes: &[Vec<Hop>],
+        total: U256,
+    ) -> Split {
+        if routes.is_empty() {
+            return Split::default();
+        }
+        let allocation = self.allocate(&route_scratch(world, routes), routes, total);
+        let route = SplitRoute {
+            legs: routes
+                .iter()
+                .cloned()
+                .zip(allocation.iter().copied())
+                .collect(),
+        };
+        let sim = self.engine.simulate_split(world, &route);
+        Split {
+            allocation,
+            paths: sim.paths,
+            amount_out: sim.amount_out,
+        }
+    }
+
+    fn allocate(&self, start: &World<P::State>, routes: &[Vec<Hop>], total: U256) -> Vec<U256> {
+        let mut scratch = start.clone();
+        let mut allocation = vec![U256::ZERO; routes.len()];
+        let chunk = total / U256::from(self.chunks);
+        let mut left = total;
+
+        while !left.is_zero() {
+            let amt = if chunk.is_zero() || left < chunk * U256::from(2u64) {
+                left
+            } else {
+                chunk
+            };
+            let (best, _) = routes
+                .iter()
+                .enumerate()
+                .map(|(i, plan)| {
+                    let path = self.engine.simulate_chained(&scratch, plan, amt);
+                    (i, path.steps.last().map(|s| s.amt_out).unwrap_or_default())
+                })
+                .max_by_key(|&(_, out)| out)
+                .expect("routes is non-empty");
+            self.engine.apply(&mut scratch, &routes[best], amt);
+            allocation[best] += amt;
+            left -= amt;
+        }
+        allocation
+    }
+}
+
+/// A world holding just the states of the pools `routes` touch, for
+/// applying trial fills without cloning the caller's whole world.
+pub(crate) fn route_scratch<S: Clone, V: StateView<S>>(world: &V, routes: &[Vec<Hop>]) -> World<S> {
+    let mut scratch = World {
+        block: world.block(),
+        pool_states: HashMap::new(),
+        holdings: HashMap::new(),
+        allowances: HashMap::new(),
+        version: 0,
+        pool_versions: HashMap::new(),
+    };
+    for hop in routes.iter().flatten() {
+        if let Some(st) = world.pool_state(hop.pool) {
+            scratch.pool_states.insert(hop.pool, st.clone());
+        }
+    }
+    scratch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, hop, reserves};
+
+    fn setup() -> (HashMap<PoolId, Cp>, AMMGraph, World<(U256, U256)>) {
+        let mut pools = HashMap::new();
+        let mut graph = AMMGraph::new();
+        let mut world = World::default();
+        for (id, t0, t1, r0, r1) in [
+            (1, 1, 2, 10_000, 20_000),
+            (2, 1, 2, 10_000, 20_000),
+            (3, 1, 3, 10_000, 10_000),
+        ] {
+            pools.insert(PoolId(id), Cp::new(id, t0, t1));
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(t0), TokenId(t1));
+            world.pool_states.insert(PoolId(id), reserves(r0, r1));
+        }
+        world.block.timestamp = 100;
+        (pools, graph, world)
+    }
+
+    fn order(sell: u64, min_buy: u64) -> Order {
+        Order {
+            sell_token: TokenId(1),
+            buy_token: TokenId(2),
+            sell_amount: U256::from(sell),
+            min_buy_amount: U256::from(min_buy),
+            deadline: 200,
+        }
+    }
+
+    #[test]
+    fn splits_across_parallel_pools_for_better_fill() {
+        let (pools, graph, world) = setup();
+        let engine = Engine::new(&pools);
+        let solver = Solver::new(&engine, &graph);
+
+        let sol = solver.solve(&world, &order(2_000, 3_000)).unwrap();
+        assert_eq!(sol.paths.len(), 2);
+        assert_eq!(sol.fill_amount, U256::from(2_000u64));
+        let sold: U256 = sol.paths.iter().map(|p| p.steps[0].amt_in).sum();
+        assert_eq!(sold, sol.fill_amount);
+
+        let single = engine.simulate_chained(&world, &[hop(1, 1, 2)], U256::from(2_000u64));
+        assert!(sol.buy_amount > single.steps[0].amt_out);
+        assert_eq!(sol.surplus, sol.buy_amount - U256::from(3_000u64));
+    }
+
+    #[test]
+    fn split_equalizes_marginal_prices_across_routes() {
+        let (pools, graph, mut world) = setup();
+        world
+            .pool_states
+            .insert(PoolId(2), reserves(30_000, 60_000));
+        let engine = Engine::new(&pools);
+        let solver = Solver::new(&engine, &graph).with_chunks(40);
+        let routes = [vec![hop(1, 1, 2)], vec![hop(2, 1, 2)]];
+
+        let split = solver.split(&world, &routes, U256::from(4_000u64));
+        // Three times the depth takes about three times the size.
+        assert_eq!(
+            split.allocation,
+            [U256::from(1_000u64), U256::from(3_000u64)]
+        );
+        let out: U256 = split.paths.iter().map(|p| p.steps[0].amt_out).sum();
+        assert_eq!(split.amount_out, out);
+        let all_in_deep = engine.simulate_chained(&world, &routes[1], U256::from(4_000u64));
+        assert!(split.amount_out > all_in_deep.steps[0].amt_out);
+
+        assert!(solver.split(&world, &[], U256::from(1u64)).paths.is_empty());
+    }
+
+    #[test]
+    fn reports_expiry_missing_routes_and_limit_misses() {
+        let (pools, graph, mut world) = setup();
+        let engine = Engine::new(&pools);
+        let solver = Solver::new(&engine, &graph).with_chunks(4);
+
+        assert!(matches!(
+            solver.solve(&world, &order(1_000, 100_000)),
+            Err(SolveError::BelowLimit { .. })
+        ));
+        let no_route = Order {
+            buy_token: TokenId(9),
+            ..order(1_000, 0)
+        };
+        assert_eq!(
+            solver.solve(&world, &no_route).unwrap_err(),
+            SolveError::NoRoute
+        );
+
+        world.block.timestamp = 201;
+        assert!(matches!(
+            solver.solve(&world, &order(1_000, 0)),
+            Err(SolveError::Expired { .. })
+        ));
+    }
+}
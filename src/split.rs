@@ -0,0 +1,122 @@
+//! Routes that split one input across several paths. Paths through the same
+//! pool move its price for each other, so simulating each alone over-counts
+//! what the split pays; here the legs run one after another on shared state.
+
+use crate::{
+    engine::{Engine, Hop, Path},
+    ids::PoolId,
+    pool::Pool,
+    solver::route_scratch,
+    world::StateView,
+};
+use alloy_primitives::U256;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SplitRoute {
+    /// Each leg's plan and input, in execution order.
+    pub legs: Vec<(Vec<Hop>, U256)>,
+}
+
+impl SplitRoute {
+    pub fn amount_in(&self) -> U256 {
+        self.legs.iter().map(|(_, amt)| *amt).sum()
+    }
+
+    /// Pools more than one leg swaps through, in id order.
+    pub fn shared_pools(&self) -> Vec<PoolId> {
+        let mut legs_through: HashMap<PoolId, usize> = HashMap::new();
+        for (plan, _) in &self.legs {
+            let mut pools: Vec<_> = plan.iter().map(|h| h.pool).collect();
+            pools.sort();
+            pools.dedup();
+            for pid in pools {
+                *legs_through.entry(pid).or_default() += 1;
+            }
+        }
+        let mut shared: Vec<_> = legs_through
+            .into_iter()
+            .filter(|&(_, n)| n > 1)
+            .map(|(pid, _)| pid)
+            .collect();
+        shared.sort();
+        shared
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SplitSimulation {
+    /// One per leg with input, in execution order.
+    pub paths: Vec<Path>,
+    /// What executing the legs in order pays.
+    pub amount_out: U256,
+    /// The sum of each leg simulated alone; above `amount_out` when legs
+    /// share pools.
+    pub independent_out: U256,
+}
+
+impl<P: Pool> Engine<'_, P> {
+    /// Runs the legs of `split` in order against one scratch copy of the
+    /// pools they touch, each leg seeing the state the earlier ones left, so
+    /// `amount_out` is what executing them in that order achieves.
+    ///
+    /// # Panics
+    ///
+    /// Like [`Engine::simulate_chained`], on a malformed leg.
+    pub fn simulate_split<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        split: &SplitRoute,
+    ) -> SplitSimulation {
+        let plans: Vec<_> = split.legs.iter().map(|(plan, _)| plan.clone()).collect();
+        let mut scratch = route_scratch(world, &plans);
+        let mut sim = SplitSimulation::default();
+        let out = |p: &Path| p.steps.last().map(|s| s.amt_out).unwrap_or_default();
+        for (plan, amt) in split.legs.iter().filter(|(_, amt)| !amt.is_zero()) {
+            sim.independent_out += out(&self.simulate_chained(world, plan, *amt));
+            let path = self.apply(&mut scratch, plan, *amt);
+            sim.amount_out += out(&path);
+            sim.paths.push(path);
+        }
+        sim
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::World;
+
+    #[test]
+    fn legs_through_a_shared_pool_see_each_others_impact() {
+        let pools = HashMap::from([
+            (PoolId(1), Cp::new(1, 1, 2)),
+            (PoolId(2), Cp::new(2, 1, 3)),
+            (PoolId(3), Cp::new(3, 3, 2)),
+        ]);
+        let mut world = World::default();
+        for id in 1..=3 {
+            world.set_pool_state(PoolId(id), reserves(10_000, 10_000));
+        }
+        let split = SplitRoute {
+            legs: vec![
+                (vec![hop(1, 1, 2)], U256::from(1_000u64)),
+                (vec![hop(2, 1, 3), hop(3, 3, 2)], U256::ZERO),
+                (vec![hop(1, 1, 2)], U256::from(1_000u64)),
+            ],
+        };
+        assert_eq!(split.amount_in(), U256::from(2_000u64));
+        assert_eq!(split.shared_pools(), [PoolId(1)]);
+
+        let engine = Engine::new(&pools);
+        let sim = engine.simulate_split(&world, &split);
+        assert_eq!(sim.paths.len(), 2);
+        let whole = engine.simulate_chained(&world, &[hop(1, 1, 2)], U256::from(2_000u64));
+        // Two 1,000 swaps back to back pay what one 2,000 swap does, give
+        // or take rounding; each alone would pay 909.
+        assert!(sim.amount_out.abs_diff(whole.steps[0].amt_out) <= U256::from(1u64));
+        assert_eq!(sim.independent_out, U256::from(2 * 909u64));
+        assert_eq!(world.pool_state(PoolId(1)), Some(&reserves(10_000, 10_000)));
+    }
+}
@@ -0,0 +1,199 @@
+//! Route stability under random state shocks, for deciding whether a route
+//! can be locked in at quote time or must be re-quoted at execution.
+
+use crate::{
+    engine::{Engine, Hop},
+    pool::Pool,
+    rng::SplitMix64,
+    univ2::UniV2State,
+    world::{StateView, World, WorldDiff},
+};
+use alloy_primitives::U256;
+use std::collections::BTreeSet;
+
+/// Pool state that can be nudged by independent relative shocks, e.g. one
+/// per reserve. Shocks are in basis points and may be negative.
+pub trait Perturb: Sized {
+    fn perturb(&self, shocks_bps: [i32; 2]) -> Self;
+}
+
+fn scale(x: U256, bps: i32) -> U256 {
+    let factor = U256::from((10_000 + bps.max(-10_000)) as u64);
+    x * factor / U256::from(10_000u64)
+}
+
+impl Perturb for UniV2State {
+    fn perturb(&self, [a, b]: [i32; 2]) -> Self {
+        Self::new(scale(self.reserve0, a), scale(self.reserve1, b))
+    }
+}
+
+impl Perturb for (U256, U256) {
+    fn perturb(&self, [a, b]: [i32; 2]) -> Self {
+        (scale(self.0, a), scale(self.1, b))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct StabilityConfig {
+    /// Each shock is drawn uniformly from `[-max_shock_bps, max_shock_bps]`.
+    pub max_shock_bps: u32,
+    pub trials: usize,
+    pub seed: u64,
+}
+
+impl Default for StabilityConfig {
+    fn default() -> Self {
+        Self {
+            max_shock_bps: 100,
+            trials: 64,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StabilityReport {
+    /// Index into the candidate plans of the unperturbed winner.
+    pub baseline: usize,
+    pub trials: usize,
+    /// Trials in which some other plan beat the baseline.
+    pub changed: usize,
+    /// Trial wins per candidate plan.
+    pub wins: Vec<usize>,
+    /// Largest shortfall of the baseline against that trial's winner, in bps
+    /// of the winner's output.
+    pub worst_regret_bps: u64,
+}
+
+impl StabilityReport {
+    pub fn flip_rate(&self) -> f64 {
+        if self.trials == 0 {
+            return 0.0;
+        }
+        self.changed as f64 / self.trials as f64
+    }
+
+    /// True when locking the baseline route costs at most `max_regret_bps`
+    /// in every trial.
+    pub fn lockable(&self, max_regret_bps: u64) -> bool {
+        self.worst_regret_bps <= max_regret_bps
+    }
+}
+
+fn best(outs: &[U256]) -> usize {
+    // First index wins ties so unchanged rankings never count as flips.
+    let mut best = 0;
+    for (i, out) in outs.iter().enumerate() {
+        if *out > outs[best] {
+            best = i;
+        }
+    }
+    best
+}
+
+fn outputs<P: Pool, V: StateView<P::State>>(
+    engine: &Engine<'_, P>,
+    world: &V,
+    plans: &[Vec<Hop>],
+    amt_in: U256,
+) -> Vec<U256> {
+    plans
+        .iter()
+        .map(|plan| {
+            let path = engine.simulate_chained(world, plan, amt_in);
+            path.steps.last().map(|s| s.amt_out).unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Re-ranks `plans` for `amt_in` across `config.trials` independently
+/// perturbed copies of every pool the plans touch.
+pub fn route_stability<P: Pool>(
+    engine: &Engine<'_, P>,
+    world: &World<P::State>,
+    plans: &[Vec<Hop>],
+    amt_in: U256,
+    config: StabilityConfig,
+) -> Option<StabilityReport>
+where
+    P::State: Perturb,
+{
+    if plans.is_empty() {
+        return None;
+    }
+    let baseline = best(&outputs(engine, world, plans, amt_in));
+
+    let pools: BTreeSet<_> = plans.iter().flatten().map(|h| h.pool).collect();
+    let mut rng = SplitMix64::new(config.seed);
+    let span = 2 * config.max_shock_bps as u64 + 1;
+    let mut shock = || (rng.below(span) as i64 - config.max_shock_bps as i64) as i32;
+
+    let mut report = StabilityReport {
+        baseline,
+        trials: config.trials,
+        changed: 0,
+        wins: vec![0; plans.len()],
+        worst_regret_bps: 0,
+    };
+    for _ in 0..config.trials {
+        let mut diff = WorldDiff::default();
+        for &pid in &pools {
+            if let Some(st) = world.pool_states.get(&pid) {
+                diff.pool_states.insert(pid, st.perturb([shock(), shock()]));
+            }
+        }
+        let outs = outputs(engine, &world.with_overlay(diff), plans, amt_in);
+        let winner = best(&outs);
+        report.wins[winner] += 1;
+        if winner != baseline {
+            report.changed += 1;
+            let regret = (outs[winner] - outs[baseline]) * U256::from(10_000u64) / outs[winner];
+            report.worst_regret_bps = report.worst_regret_bps.max(regret.saturating_to());
+        }
+    }
+    Some(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, hop, reserves};
+    use std::collections::HashMap;
+
+    fn setup(r2: u64) -> (HashMap<PoolId, Cp>, World<(U256, U256)>) {
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2)), (PoolId(2), Cp::new(2, 1, 2))]);
+        let mut world = World::default();
+        world
+            .pool_states
+            .insert(PoolId(1), reserves(1_000_000, 1_000_000));
+        world.pool_states.insert(PoolId(2), reserves(1_000_000, r2));
+        (pools, world)
+    }
+
+    #[test]
+    fn near_tie_flips_but_clear_winner_is_stable() {
+        let plans = vec![vec![hop(1, 1, 2)], vec![hop(2, 1, 2)]];
+        let amt = U256::from(1_000u64);
+        let cfg = StabilityConfig {
+            max_shock_bps: 50,
+            trials: 200,
+            seed: 7,
+        };
+
+        let (pools, world) = setup(1_001_000);
+        let engine = Engine::new(&pools);
+        let tie = route_stability(&engine, &world, &plans, amt, cfg).unwrap();
+        assert_eq!(tie.baseline, 1);
+        assert_eq!(tie.wins.iter().sum::<usize>(), 200);
+        assert!(tie.flip_rate() > 0.2, "{tie:?}");
+        assert!(!tie.lockable(10) && tie.lockable(200));
+
+        let (pools, world) = setup(1_100_000);
+        let engine = Engine::new(&pools);
+        let clear = route_stability(&engine, &world, &plans, amt, cfg).unwrap();
+        assert_eq!((clear.baseline, clear.changed), (1, 0));
+        assert!(clear.lockable(0));
+    }
+}
@@ -0,0 +1,112 @@
+//! Batch simulation that hands over each result as soon as it is ready, so
+//! a consumer can act on an early winner while the rest still run.
+
+use crate::{
+    engine::{Engine, Hop, Path},
+    error::WayfinderError,
+    pool::Pool,
+    world::StateView,
+};
+use alloy_primitives::U256;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+#[derive(Debug)]
+pub struct PathResult {
+    /// Position of the plan in the batch.
+    pub index: usize,
+    pub path: Result<Path, WayfinderError>,
+}
+
+impl<P: Pool + Sync> Engine<'_, P>
+where
+    P::State: Send + Sync,
+{
+    /// Simulates each of `plans` from `first_in` across the available cores,
+    /// sending each result on `tx` as it completes, in no particular order.
+    /// Stops early once the receiver hangs up. Returns how many were sent.
+    pub fn simulate_many_streaming<V: StateView<P::State> + Sync>(
+        &self,
+        world: &V,
+        plans: &[Vec<Hop>],
+        first_in: U256,
+        tx: Sender<PathResult>,
+    ) -> usize {
+        let workers = thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(plans.len());
+        let next = AtomicUsize::new(0);
+        let sent = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for _ in 0..workers {
+                let tx = tx.clone();
+                let (next, sent) = (&next, &sent);
+                s.spawn(move || {
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(plan) = plans.get(index) else { break };
+                        let path = self.try_simulate(world, plan, first_in);
+                        if tx.send(PathResult { index, path }).is_err() {
+                            // Leave nothing for the other workers either.
+                            next.store(plans.len(), Ordering::Relaxed);
+                            break;
+                        }
+                        sent.fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+            }
+        });
+        sent.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::EngineError;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::World;
+    use std::collections::HashMap;
+    use std::sync::mpsc;
+
+    #[test]
+    fn streams_every_result_tagged_with_its_plan() {
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2)), (PoolId(2), Cp::new(2, 2, 3))]);
+        let mut world = World::default();
+        world.set_pool_state(PoolId(1), reserves(1_000_000, 2_000_000));
+        world.set_pool_state(PoolId(2), reserves(1_000_000, 1_000_000));
+        let mut plans: Vec<_> = (0..16)
+            .map(|i| {
+                if i % 2 == 0 {
+                    vec![hop(1, 1, 2)]
+                } else {
+                    vec![hop(1, 1, 2), hop(2, 2, 3)]
+                }
+            })
+            .collect();
+        plans.push(vec![hop(1, 1, 2), hop(2, 1, 3)]);
+
+        let engine = Engine::new(&pools);
+        let amt = U256::from(1_000u64);
+        let (tx, rx) = mpsc::channel();
+        assert_eq!(engine.simulate_many_streaming(&world, &plans, amt, tx), 17);
+
+        let mut results: Vec<_> = rx.iter().collect();
+        results.sort_by_key(|r| r.index);
+        assert_eq!(results.len(), plans.len());
+        for (r, plan) in results.iter().zip(&plans[..16]) {
+            let expected = engine.simulate_chained(&world, plan, amt);
+            assert_eq!(r.path.as_ref().unwrap().steps, expected.steps);
+        }
+        assert!(matches!(
+            results[16].path,
+            Err(WayfinderError::Engine(EngineError::Discontinuity { .. }))
+        ));
+
+        let (tx, rx) = mpsc::channel();
+        drop(rx);
+        assert_eq!(engine.simulate_many_streaming(&world, &plans, amt, tx), 0);
+    }
+}
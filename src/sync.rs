@@ -0,0 +1,502 @@
+//! Batched pool state refresh over RPC via Multicall3, and V2 reserves kept
+//! current from `Sync` events between refreshes.
+
+use crate::{
+    activity::ActivityTracker,
+    decode::{decode_logs, univ2_diff},
+    error::WayfinderError,
+    ids::PoolId,
+    provider::rpc::getReservesCall,
+    registry::{PoolKind, Registry},
+    univ2::UniV2State,
+    world::{BlockContext, World, WorldDiff},
+};
+use alloy_primitives::{Address, Log, U256, address};
+use alloy_provider::{
+    Provider,
+    network::{Ethereum, Network, TransactionBuilder},
+};
+use alloy_sol_types::{SolCall, sol};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+/// Multicall3, deployed at the same address on most EVM chains.
+pub const MULTICALL3: Address = address!("cA11bde05977b3631167028862bE2a173976CA11");
+
+sol! {
+    struct Call3 {
+        address target;
+        bool allowFailure;
+        bytes callData;
+    }
+
+    struct Result3 {
+        bool success;
+        bytes returnData;
+    }
+
+    function aggregate3(Call3[] calldata calls) external payable returns (Result3[] memory returnData);
+    function getCurrentBlockTimestamp() external view returns (uint256 timestamp);
+}
+
+/// Blocks of swap volume [`WorldSync::activity`] keeps, about an hour on
+/// mainnet.
+pub const DEFAULT_ACTIVITY_WINDOW: u64 = 300;
+
+/// Blocks between spot checks of event-maintained reserves against RPC.
+pub const DEFAULT_SPOT_CHECK_EVERY: u64 = 50;
+
+/// When a pool's state was last read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Freshness {
+    pub block: u64,
+    pub timestamp: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RefreshReport {
+    pub updated: Vec<PoolId>,
+    /// Pools missing from the registry, of an unsupported kind, or whose
+    /// call reverted; their state is left as it was.
+    pub failed: Vec<PoolId>,
+    /// RPC round trips made.
+    pub calls: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IngestReport {
+    /// Pools whose reserves moved with the block's `Sync` events.
+    pub applied: Vec<PoolId>,
+    /// Pools with events but no initial fetch yet; their events are ignored
+    /// until [`WorldSync::refresh`] reads them.
+    pub skipped: Vec<PoolId>,
+    /// Pools spot-checked against RPC after the block was applied.
+    pub checked: Vec<PoolId>,
+    /// Checked pools whose reserves disagreed with RPC and were resynced.
+    pub diverged: Vec<PoolId>,
+}
+
+/// Reads of one [`WorldSync::refresh`] or spot check, not yet applied.
+#[derive(Default)]
+struct Reads {
+    states: Vec<(PoolId, UniV2State)>,
+    failed: Vec<PoolId>,
+    calls: usize,
+    timestamp: Option<u64>,
+}
+
+pub struct WorldSync<P> {
+    pub provider: P,
+    pub registry: Arc<Registry>,
+    pub multicall: Address,
+    /// Pools per `aggregate3` call.
+    pub batch_size: usize,
+    /// Swap activity seen through [`WorldSync::observe_logs`].
+    pub activity: ActivityTracker,
+    /// Blocks between [`WorldSync::ingest_block`] spot checks.
+    pub spot_check_every: u64,
+    /// Pools read per spot check, taken in turn from those being tracked.
+    pub spot_check_size: usize,
+    freshness: HashMap<PoolId, Freshness>,
+    last_spot_check: Option<u64>,
+    check_cursor: usize,
+}
+
+impl<P: Provider> WorldSync<P> {
+    pub fn new(provider: P, registry: Arc<Registry>) -> Self {
+        Self {
+            provider,
+            registry,
+            multicall: MULTICALL3,
+            batch_size: 500,
+            activity: ActivityTracker::new(DEFAULT_ACTIVITY_WINDOW),
+            spot_check_every: DEFAULT_SPOT_CHECK_EVERY,
+            spot_check_size: 20,
+            freshness: HashMap::new(),
+            last_spot_check: None,
+            check_cursor: 0,
+        }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn freshness(&self, pid: PoolId) -> Option<Freshness> {
+        self.freshness.get(&pid).copied()
+    }
+
+    /// Blocks since `pid` was last refreshed, or `None` if it never was.
+    pub fn staleness(&self, pid: PoolId, block: u64) -> Option<u64> {
+        self.freshness(pid).map(|f| block.saturating_sub(f.block))
+    }
+
+    /// Pools among `pools` never refreshed or more than `max_age` blocks old.
+    pub fn stale(&self, pools: &[PoolId], block: u64, max_age: u64) -> Vec<PoolId> {
+        pools
+            .iter()
+            .copied()
+            .filter(|&pid| self.staleness(pid, block).is_none_or(|age| age > max_age))
+            .collect()
+    }
+
+    /// Records the swaps among `block`'s logs for pools in the registry.
+    pub fn observe_logs<'a>(&mut self, block: u64, logs: impl IntoIterator<Item = &'a Log>) {
+        let updates = decode_logs(&self.registry, logs);
+        self.activity.record_updates(block, &updates);
+    }
+
+    /// Reads `pools` at `block` in `batch_size` chunks and applies every read
+    /// to `world` in one diff once all calls have succeeded. A transport
+    /// error leaves `world` untouched.
+    pub async fn refresh(
+        &mut self,
+        world: &mut World<UniV2State>,
+        pools: &[PoolId],
+        block: u64,
+    ) -> Result<RefreshReport, WayfinderError> {
+        let reads = self.read(pools, block).await?;
+        let mut diff = WorldDiff::default();
+        let timestamp = reads.timestamp.unwrap_or(world.block.timestamp);
+        if block >= world.block.number {
+            diff.block = Some(BlockContext {
+                number: block,
+                timestamp,
+                ..world.block
+            });
+        }
+        let mut updated = Vec::with_capacity(reads.states.len());
+        for (pid, st) in reads.states {
+            diff.set_pool_state(pid, st);
+            self.freshness.insert(pid, Freshness { block, timestamp });
+            updated.push(pid);
+        }
+        world.apply(diff);
+        Ok(RefreshReport {
+            updated,
+            failed: reads.failed,
+            calls: reads.calls,
+        })
+    }
+
+    /// Moves the reserves of pools already read by [`WorldSync::refresh`]
+    /// along the `Sync` events among `logs`, and records swap activity. The
+    /// logs must be all of `ctx`'s logs for the registry's pools: tracked
+    /// pools without events are taken to be current as of `ctx`.
+    pub fn apply_logs<'a>(
+        &mut self,
+        world: &mut World<UniV2State>,
+        ctx: BlockContext,
+        logs: impl IntoIterator<Item = &'a Log>,
+    ) -> IngestReport {
+        let updates = decode_logs(&self.registry, logs);
+        self.activity.record_updates(ctx.number, &updates);
+
+        let mut skipped = BTreeSet::new();
+        let tracked: Vec<_> = updates
+            .into_iter()
+            .filter(|(pid, _)| {
+                let known = self.freshness.contains_key(pid);
+                if !known {
+                    skipped.insert(*pid);
+                }
+                known
+            })
+            .collect();
+        let mut diff = univ2_diff(world, tracked);
+        let mut applied: Vec<_> = diff.pool_states.keys().copied().collect();
+        applied.sort();
+        if ctx.number >= world.block.number {
+            diff.block = Some(ctx);
+        }
+        world.apply(diff);
+        for f in self.freshness.values_mut() {
+            if f.block < ctx.number {
+                *f = Freshness {
+                    block: ctx.number,
+                    timestamp: ctx.timestamp,
+                };
+            }
+        }
+        IngestReport {
+            applied,
+            skipped: skipped.into_iter().collect(),
+            ..IngestReport::default()
+        }
+    }
+
+    /// Reads `pools` at `block` and compares them with `world`. Pools that
+    /// disagree are resynced to what RPC returned and listed in `diverged`.
+    pub async fn spot_check(
+        &mut self,
+        world: &mut World<UniV2State>,
+        pools: &[PoolId],
+        block: u64,
+    ) -> Result<IngestReport, WayfinderError> {
+        let reads = self.read(pools, block).await?;
+        let timestamp = reads.timestamp.unwrap_or(world.block.timestamp);
+        let mut report = IngestReport::default();
+        let mut diff = WorldDiff::default();
+        for (pid, st) in reads.states {
+            report.checked.push(pid);
+            if world.pool_states.get(&pid) != Some(&st) {
+                report.diverged.push(pid);
+                diff.set_pool_state(pid, st);
+            }
+            self.freshness.insert(pid, Freshness { block, timestamp });
+        }
+        world.apply(diff);
+        self.last_spot_check = Some(block);
+        Ok(report)
+    }
+
+    /// [`WorldSync::apply_logs`], then every `spot_check_every` blocks a
+    /// [`WorldSync::spot_check`] of the next `spot_check_size` tracked pools.
+    /// An RPC error is returned after the logs were applied.
+    pub async fn ingest_block<'a>(
+        &mut self,
+        world: &mut World<UniV2State>,
+        ctx: BlockContext,
+        logs: impl IntoIterator<Item = &'a Log>,
+    ) -> Result<IngestReport, WayfinderError> {
+        let mut report = self.apply_logs(world, ctx, logs);
+        let due = self
+            .last_spot_check
+            .is_none_or(|b| ctx.number >= b.saturating_add(self.spot_check_every));
+        if !due || self.freshness.is_empty() {
+            return Ok(report);
+        }
+        let mut tracked: Vec<_> = self.freshness.keys().copied().collect();
+        tracked.sort();
+        let n = self.spot_check_size.min(tracked.len());
+        let sample: Vec<_> = (0..n)
+            .map(|i| tracked[(self.check_cursor + i) % tracked.len()])
+            .collect();
+        self.check_cursor = (self.check_cursor + n) % tracked.len();
+        let check = self.spot_check(world, &sample, ctx.number).await?;
+        report.checked = check.checked;
+        report.diverged = check.diverged;
+        Ok(report)
+    }
+
+    async fn read(&self, pools: &[PoolId], block: u64) -> Result<Reads, WayfinderError> {
+        let mut reads = Reads::default();
+        let mut readable = Vec::new();
+        for &pid in pools {
+            match self.registry.pool(pid) {
+                Some(meta) if meta.kind == PoolKind::UniV2 => readable.push((pid, meta.address)),
+                _ => reads.failed.push(pid),
+            }
+        }
+
+        for (i, chunk) in readable.chunks(self.batch_size).enumerate() {
+            let mut calls: Vec<Call3> = chunk
+                .iter()
+                .map(|&(_, target)| Call3 {
+                    target,
+                    allowFailure: true,
+                    callData: getReservesCall {}.abi_encode().into(),
+                })
+                .collect();
+            if i == 0 {
+                calls.push(Call3 {
+                    target: self.multicall,
+                    allowFailure: true,
+                    callData: getCurrentBlockTimestampCall {}.abi_encode().into(),
+                });
+            }
+            let results = self.aggregate(calls, block).await?;
+            reads.calls += 1;
+
+            for (j, res) in results.iter().enumerate() {
+                if j == chunk.len() {
+                    reads.timestamp = res
+                        .success
+                        .then(|| getCurrentBlockTimestampCall::abi_decode_returns(&res.returnData))
+                        .and_then(Result::ok)
+                        .map(|t| t.saturating_to::<u64>());
+                    continue;
+                }
+                let pid = chunk[j].0;
+                let reserves = res
+                    .success
+                    .then(|| getReservesCall::abi_decode_returns(&res.returnData))
+                    .and_then(Result::ok);
+                match reserves {
+                    Some(r) => {
+                        let st = UniV2State::new(U256::from(r.reserve0), U256::from(r.reserve1));
+                        reads.states.push((pid, st));
+                    }
+                    None => reads.failed.push(pid),
+                }
+            }
+        }
+        Ok(reads)
+    }
+
+    async fn aggregate(
+        &self,
+        calls: Vec<Call3>,
+        block: u64,
+    ) -> Result<Vec<Result3>, WayfinderError> {
+        let tx = <Ethereum as Network>::TransactionRequest::default()
+            .with_to(self.multicall)
+            .with_input(aggregate3Call { calls }.abi_encode());
+        let data = self
+            .provider
+            .call(tx)
+            .block(block.into())
+            .await
+            .map_err(|e| WayfinderError::Rpc(e.to_string()))?;
+        aggregate3Call::abi_decode_returns(&data).map_err(|e| WayfinderError::Rpc(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::IUniswapV2Pair;
+    use crate::ids::TokenId;
+    use crate::provider::rpc::getReservesReturn;
+    use crate::registry::PoolMeta;
+    use alloy_primitives::{Bytes, aliases::U112};
+    use alloy_provider::{ProviderBuilder, mock::Asserter};
+
+    fn registry(n: u8) -> Registry {
+        let mut reg = Registry::default();
+        for i in 1..=n {
+            reg.upsert_pool(
+                PoolId(i as u64),
+                PoolMeta {
+                    address: Address::repeat_byte(i),
+                    kind: PoolKind::UniV2,
+                    token0: TokenId(1),
+                    token1: TokenId(2),
+                    fee: 3000,
+                },
+            );
+        }
+        reg
+    }
+
+    fn reserves(r0: u64, r1: u64) -> Result3 {
+        let ret = getReservesCall::abi_encode_returns(&getReservesReturn {
+            reserve0: alloy_primitives::Uint::from(r0),
+            reserve1: alloy_primitives::Uint::from(r1),
+            blockTimestampLast: 0,
+        });
+        Result3 {
+            success: true,
+            returnData: ret.into(),
+        }
+    }
+
+    fn response(results: Vec<Result3>) -> Bytes {
+        aggregate3Call::abi_encode_returns(&results).into()
+    }
+
+    #[tokio::test]
+    async fn batches_reads_and_tracks_freshness() {
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+        let mut sync = WorldSync::new(provider, Arc::new(registry(3))).with_batch_size(2);
+
+        let ts = Result3 {
+            success: true,
+            returnData: getCurrentBlockTimestampCall::abi_encode_returns(&U256::from(1_700u64))
+                .into(),
+        };
+        asserter.push_success(&response(vec![reserves(10, 20), reserves(30, 40), ts]));
+        let reverted = Result3 {
+            success: false,
+            returnData: Bytes::new(),
+        };
+        asserter.push_success(&response(vec![reverted]));
+
+        let mut world = World::default();
+        let pools = [PoolId(1), PoolId(2), PoolId(3), PoolId(9)];
+        let report = sync.refresh(&mut world, &pools, 100).await.unwrap();
+        assert_eq!(report.calls, 2);
+        assert_eq!(report.updated, vec![PoolId(1), PoolId(2)]);
+        assert_eq!(report.failed, vec![PoolId(9), PoolId(3)]);
+        assert_eq!(
+            world.pool_states[&PoolId(2)],
+            UniV2State::new(U256::from(30u64), U256::from(40u64))
+        );
+        assert_eq!((world.block.number, world.block.timestamp), (100, 1_700));
+
+        assert_eq!(sync.staleness(PoolId(1), 105), Some(5));
+        assert_eq!(sync.staleness(PoolId(3), 105), None);
+        assert_eq!(sync.stale(&pools[..3], 105, 10), vec![PoolId(3)]);
+    }
+
+    #[tokio::test]
+    async fn transport_errors_leave_world_untouched() {
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+        let mut sync = WorldSync::new(provider, Arc::new(registry(3))).with_batch_size(2);
+        asserter.push_success(&response(vec![reserves(1, 1), reserves(2, 2)]));
+        asserter.push_failure_msg("boom");
+
+        let mut world = World::default();
+        let err = sync
+            .refresh(&mut world, &[PoolId(1), PoolId(2), PoolId(3)], 5)
+            .await;
+        assert!(matches!(err, Err(WayfinderError::Rpc(_))));
+        assert!(world.pool_states.is_empty());
+        assert_eq!(sync.freshness(PoolId(1)), None);
+    }
+
+    #[tokio::test]
+    async fn sync_events_maintain_reserves_and_spot_checks_resync() {
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+        let mut sync = WorldSync::new(provider, Arc::new(registry(3)));
+        let mut world = World::default();
+        asserter.push_success(&response(vec![reserves(10, 20), reserves(30, 40)]));
+        sync.refresh(&mut world, &[PoolId(1), PoolId(2)], 100)
+            .await
+            .unwrap();
+
+        let sync_log = |addr: u8, r0: u64, r1: u64| {
+            let e = IUniswapV2Pair::Sync {
+                reserve0: U112::from(r0),
+                reserve1: U112::from(r1),
+            };
+            Log {
+                address: Address::repeat_byte(addr),
+                data: alloy_sol_types::SolEvent::encode_log_data(&e),
+            }
+        };
+        let ctx = |number| BlockContext {
+            number,
+            timestamp: number * 12,
+            basefee: 0,
+        };
+        // Pool 2 moved without the event reaching us; the check catches it.
+        asserter.push_success(&response(vec![reserves(11, 19), reserves(31, 41)]));
+        let logs = [sync_log(1, 11, 19), sync_log(3, 5, 5)];
+        let report = sync
+            .ingest_block(&mut world, ctx(101), &logs)
+            .await
+            .unwrap();
+        assert_eq!(report.applied, vec![PoolId(1)]);
+        assert_eq!(report.skipped, vec![PoolId(3)]);
+        assert_eq!(report.checked, vec![PoolId(1), PoolId(2)]);
+        assert_eq!(report.diverged, vec![PoolId(2)]);
+        let v = |a: u64, b: u64| UniV2State::new(U256::from(a), U256::from(b));
+        assert_eq!(world.pool_states[&PoolId(2)], v(31, 41));
+        assert!(!world.pool_states.contains_key(&PoolId(3)));
+
+        // Not due for a check: no RPC at all.
+        let report = sync
+            .ingest_block(&mut world, ctx(102), &[sync_log(2, 32, 40)])
+            .await
+            .unwrap();
+        assert!(report.checked.is_empty());
+        assert_eq!(world.pool_states[&PoolId(2)], v(32, 40));
+        assert_eq!(world.block.number, 102);
+        assert_eq!(sync.staleness(PoolId(1), 102), Some(0));
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{
+    engine::Hop,
+    graph::AMMGraph,
+    ids::{PoolId, SwapDirection, TokenId},
+    registry::{PoolKind, PoolMeta, Registry, TokenMeta},
+    univ2::{UniV2Pool, UniV2State},
+    world::World,
+};
+use alloy_primitives::{Address, U256};
+use std::collections::{HashMap, HashSet};
+
+pub use crate::rng::SplitMix64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Topology {
+    Random,
+    HubAndSpoke { hubs: usize },
+    Ring,
+}
+
+/// How pool reserves are drawn between `min_reserve` and `max_reserve`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LiquidityDist {
+    #[default]
+    Uniform,
+    /// Uniform in orders of magnitude.
+    LogUniform,
+    /// Mostly near `min_reserve` with a heavy tail of deep pools; lower
+    /// `alpha` means a heavier tail.
+    Pareto { alpha: f64 },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SyntheticConfig {
+    pub tokens: usize,
+    pub pools: usize,
+    pub topology: Topology,
+    pub seed: u64,
+    pub min_reserve: u128,
+    pub max_reserve: u128,
+    pub liquidity: LiquidityDist,
+    pub fee_bps: u32,
+}
+
+impl Default for SyntheticConfig {
+    fn default() -> Self {
+        Self {
+            tokens: 100,
+            pools: 400,
+            topology: Topology::Random,
+            seed: 1,
+            min_reserve: 10u128.pow(21),
+            max_reserve: 10u128.pow(24),
+            liquidity: LiquidityDist::Uniform,
+            fee_bps: 30,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SyntheticMarket {
+    pub registry: Registry,
+    pub graph: AMMGraph,
+    pub pools: HashMap<PoolId, UniV2Pool>,
+    pub world: World<UniV2State>,
+}
+
+fn pick_pair(rng: &mut SplitMix64, cfg: &SyntheticConfig, i: usize) -> (usize, usize) {
+    let n = cfg.tokens as u64;
+    match cfg.topology {
+        Topology::Random => {
+            let a = rng.below(n);
+            let b = (a + 1 + rng.below(n - 1)) % n;
+            (a as usize, b as usize)
+        }
+        Topology::HubAndSpoke { hubs } => {
+            let hubs = hubs.clamp(1, cfg.tokens - 1) as u64;
+            let hub = rng.below(hubs);
+            let spoke = hubs + rng.below(n - hubs);
+            (hub as usize, spoke as usize)
+        }
+        Topology::Ring => (i % cfg.tokens, (i + 1) % cfg.tokens),
+    }
+}
+
+fn draw_reserve(rng: &mut SplitMix64, cfg: &SyntheticConfig) -> u128 {
+    let (lo, hi) = (cfg.min_reserve, cfg.max_reserve.max(cfg.min_reserve));
+    // Uniform in [0, 1) from the top 53 bits.
+    let mut unit = || (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+    match cfg.liquidity {
+        LiquidityDist::Uniform => rng.range_u128(lo, hi),
+        LiquidityDist::LogUniform => {
+            let (a, b) = ((lo.max(1) as f64).ln(), (hi.max(1) as f64).ln());
+            ((a + unit() * (b - a)).exp() as u128).clamp(lo, hi)
+        }
+        LiquidityDist::Pareto { alpha } => {
+            let r = lo.max(1) as f64 / (1.0 - unit()).powf(1.0 / alpha.max(f64::EPSILON));
+            (r as u128).clamp(lo, hi)
+        }
+    }
+}
+
+pub fn generate(cfg: &SyntheticConfig) -> SyntheticMarket {
+    generate_with_fees(cfg, &[cfg.fee_bps])
+}
+
+/// [`generate`] with each pool's fee drawn from `fee_tiers`. Without a fee
+/// tier or a second token no pool can be drawn, so the market is empty.
+pub fn generate_with_fees(cfg: &SyntheticConfig, fee_tiers: &[u32]) -> SyntheticMarket {
+    if fee_tiers.is_empty() || cfg.tokens < 2 {
+        return SyntheticMarket::default();
+    }
+    let mut rng = SplitMix64::new(cfg.seed);
+    let mut registry = Registry::default();
+    let mut graph = AMMGraph::new();
+    let mut pools = HashMap::new();
+    let mut world = World::default();
+
+    for i in 0..cfg.tokens {
+        let tid = TokenId(i as u32);
+        registry.upsert_token(
+            tid,
+            TokenMeta {
+                address: Address::left_padding_from(&(i as u64 + 1).to_be_bytes()),
+                symbol: format!("T{i}"),
+                decimals: 18,
+            },
+        );
+        graph.add_token(tid);
+    }
+
+    for i in 0..cfg.pools {
+        let (a, b) = pick_pair(&mut rng, cfg, i);
+        let pid = PoolId(i as u64);
+        let (t0, t1) = (TokenId(a as u32), TokenId(b as u32));
+        let r0 = draw_reserve(&mut rng, cfg);
+        let r1 = draw_reserve(&mut rng, cfg);
+        // A single tier draws nothing, keeping older seeds' markets intact.
+        let fee_bps = match fee_tiers {
+            [fee] => *fee,
+            tiers => tiers[rng.below(tiers.len() as u64) as usize],
+        };
+
+        registry.upsert_pool(
+            pid,
+            PoolMeta {
+                address: Address::left_padding_from(&(u64::MAX - i as u64).to_be_bytes()),
+                kind: PoolKind::UniV2,
+                token0: t0,
+                token1: t1,
+                fee: fee_bps * 100,
+            },
+        );
+        graph.connect_bidirectional_pair(pid, t0, t1);
+        pools.insert(pid, UniV2Pool::new(pid, t0, t1).with_fee_bps(fee_bps));
+        world
+            .pool_states
+            .insert(pid, UniV2State::new(U256::from(r0), U256::from(r1)));
+    }
+
+    SyntheticMarket {
+        registry,
+        graph,
+        pools,
+        world,
+    }
+}
+
+pub fn random_plans(market: &SyntheticMarket, n: usize, hops: usize, seed: u64) -> Vec<Vec<Hop>> {
+    let mut rng = SplitMix64::new(seed);
+    let mut by_token: HashMap<TokenId, Vec<&UniV2Pool>> = HashMap::new();
+    let mut ids: Vec<_> = market.pools.keys().copied().collect();
+    ids.sort();
+    for pid in &ids {
+        let p = &market.pools[pid];
+        by_token.entry(p.token0).or_default().push(p);
+        by_token.entry(p.token1).or_default().push(p);
+    }
+    let mut starts: Vec<_> = by_token.keys().copied().collect();
+    starts.sort();
+
+    let mut plans = Vec::with_capacity(n);
+    let mut attempts = 0;
+    while plans.len() < n && attempts < n * 20 && !starts.is_empty() {
+        attempts += 1;
+        let mut at = starts[rng.below(starts.len() as u64) as usize];
+        let mut used = HashSet::new();
+        let mut plan = Vec::with_capacity(hops);
+        for _ in 0..hops {
+            let Some(cands) = by_token.get(&at) else {
+                break;
+            };
+            let p = cands[rng.below(cands.len() as u64) as usize];
+            if !used.insert(p.id) {
+                break;
+            }
+            let to = if p.token0 == at { p.token1 } else { p.token0 };
+            plan.push(Hop::new(p.id, SwapDirection { from: at, to }));
+            at = to;
+        }
+        if plan.len() == hops {
+            plans.push(plan);
+        }
+    }
+    plans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generation_is_deterministic_per_seed() {
+        let cfg = SyntheticConfig {
+            tokens: 20,
+            pools: 60,
+            topology: Topology::HubAndSpoke { hubs: 3 },
+            ..SyntheticConfig::default()
+        };
+        let a = generate(&cfg);
+        let b = generate(&cfg);
+        assert_eq!(a.pools, b.pools);
+        assert!(a.world.diff(&b.world).is_empty());
+        assert!(a.pools.values().all(|p| p.token0.0 < 3 && p.token1.0 >= 3));
+
+        let c = generate(&SyntheticConfig { seed: 2, ..cfg });
+        assert_ne!(a.pools, c.pools);
+
+        let plans = random_plans(&a, 10, 3, 7);
+        assert_eq!(plans.len(), 10);
+        assert_eq!(plans, random_plans(&a, 10, 3, 7));
+        for plan in &plans {
+            assert!(plan.windows(2).all(|w| w[0].dir.to == w[1].dir.from));
+        }
+    }
+}
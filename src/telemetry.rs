@@ -0,0 +1,109 @@
+//! Metric names and recording hooks. Every hook compiles to nothing unless
+//! the `metrics` feature is enabled; install any `metrics` recorder (e.g. a
+//! Prometheus exporter) to collect them.
+
+use crate::world::BlockContext;
+
+pub const PATHS_SIMULATED: &str = "wayfinder_paths_simulated_total";
+pub const HOPS_SIMULATED: &str = "wayfinder_hops_simulated_total";
+pub const QUOTE_SECONDS: &str = "wayfinder_quote_seconds";
+pub const SCAN_SECONDS: &str = "wayfinder_scan_seconds";
+pub const PRICE_CACHE_HITS: &str = "wayfinder_price_cache_hits_total";
+pub const PRICE_CACHE_MISSES: &str = "wayfinder_price_cache_misses_total";
+pub const SWAP_MEMO_HITS: &str = "wayfinder_swap_memo_hits_total";
+pub const SWAP_MEMO_MISSES: &str = "wayfinder_swap_memo_misses_total";
+pub const WORLD_BLOCK: &str = "wayfinder_world_block";
+pub const SYNC_LAG_SECONDS: &str = "wayfinder_state_sync_lag_seconds";
+
+#[cfg(feature = "metrics")]
+pub fn describe() {
+    use metrics::{Unit, describe_counter, describe_gauge, describe_histogram};
+
+    describe_counter!(PATHS_SIMULATED, "Paths simulated by the engine");
+    describe_counter!(HOPS_SIMULATED, "Hops simulated by the engine");
+    describe_histogram!(QUOTE_SECONDS, Unit::Seconds, "Best-route quote latency");
+    describe_histogram!(SCAN_SECONDS, Unit::Seconds, "Arbitrage scan latency");
+    describe_counter!(PRICE_CACHE_HITS, "Price lookups served from fresh entries");
+    describe_counter!(
+        PRICE_CACHE_MISSES,
+        "Price lookups that were missing or stale"
+    );
+    describe_counter!(SWAP_MEMO_HITS, "Hop simulations served from a swap memo");
+    describe_counter!(
+        SWAP_MEMO_MISSES,
+        "Hop simulations a swap memo had no entry for"
+    );
+    describe_gauge!(
+        WORLD_BLOCK,
+        "Block number of the latest applied world update"
+    );
+    describe_gauge!(
+        SYNC_LAG_SECONDS,
+        Unit::Seconds,
+        "Wall-clock lag behind the timestamp of the latest applied block"
+    );
+}
+
+#[inline]
+pub(crate) fn path_simulated(_hops: usize) {
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!(PATHS_SIMULATED).increment(1);
+        metrics::counter!(HOPS_SIMULATED).increment(_hops as u64);
+    }
+}
+
+#[inline]
+pub(crate) fn price_lookup(_hit: bool) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(if _hit {
+        PRICE_CACHE_HITS
+    } else {
+        PRICE_CACHE_MISSES
+    })
+    .increment(1);
+}
+
+#[inline]
+pub(crate) fn memo_lookup(_hit: bool) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(if _hit {
+        SWAP_MEMO_HITS
+    } else {
+        SWAP_MEMO_MISSES
+    })
+    .increment(1);
+}
+
+#[inline]
+pub(crate) fn world_synced(_block: &BlockContext) {
+    #[cfg(feature = "metrics")]
+    {
+        metrics::gauge!(WORLD_BLOCK).set(_block.number as f64);
+        if let Ok(now) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+            let lag = now.as_secs_f64() - _block.timestamp as f64;
+            metrics::gauge!(SYNC_LAG_SECONDS).set(lag.max(0.0));
+        }
+    }
+}
+
+pub(crate) struct Timer {
+    #[cfg(feature = "metrics")]
+    start: std::time::Instant,
+}
+
+impl Timer {
+    #[inline]
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(feature = "metrics")]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn observe(self, _histogram: &'static str) {
+        #[cfg(feature = "metrics")]
+        metrics::histogram!(_histogram).record(self.start.elapsed().as_secs_f64());
+    }
+}
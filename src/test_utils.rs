@@ -0,0 +1,86 @@
+use crate::{
+    engine::Hop,
+    ids::{PoolId, SwapDirection, TokenId},
+    num::{self, MathError, MathResult},
+    pool::Pool,
+    world::BlockContext,
+};
+use alloy_primitives::U256;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Cp {
+    pub id: PoolId,
+    pub t0: TokenId,
+    pub t1: TokenId,
+    pub fee_bps: u32,
+}
+
+impl Cp {
+    pub fn new(id: u64, t0: u32, t1: u32) -> Self {
+        Self {
+            id: PoolId(id),
+            t0: TokenId(t0),
+            t1: TokenId(t1),
+            fee_bps: 0,
+        }
+    }
+
+    pub fn with_fee(mut self, fee_bps: u32) -> Self {
+        self.fee_bps = fee_bps;
+        self
+    }
+}
+
+impl Pool for Cp {
+    type State = (U256, U256);
+
+    fn id(&self) -> PoolId {
+        self.id
+    }
+
+    fn supports(&self, dir: SwapDirection) -> bool {
+        (dir.from, dir.to) == (self.t0, self.t1) || (dir.from, dir.to) == (self.t1, self.t0)
+    }
+
+    fn swap(
+        &self,
+        st: &mut Self::State,
+        _ctx: &BlockContext,
+        dir: SwapDirection,
+        amt_in: U256,
+    ) -> MathResult<U256> {
+        let (r_in, r_out) = if dir.from == self.t0 {
+            (&mut st.0, &mut st.1)
+        } else {
+            (&mut st.1, &mut st.0)
+        };
+        let eff = num::mul_div(
+            amt_in,
+            U256::from(
+                10_000u32
+                    .checked_sub(self.fee_bps)
+                    .ok_or(MathError::Underflow)?,
+            ),
+            U256::from(10_000u64),
+        )?;
+        let den = num::add(*r_in, eff)?;
+        let out = if den.is_zero() {
+            U256::ZERO
+        } else {
+            num::mul_div(*r_out, eff, den)?
+        };
+        (*r_in, *r_out) = (num::add(*r_in, amt_in)?, num::sub(*r_out, out)?);
+        Ok(out)
+    }
+}
+
+pub fn hop(pool: u64, from: u32, to: u32) -> Hop {
+    Hop::new(
+        PoolId(pool),
+        SwapDirection::new(TokenId(from), TokenId(to)).unwrap(),
+    )
+}
+
+pub fn reserves(r0: u64, r1: u64) -> (U256, U256) {
+    (U256::from(r0), U256::from(r1))
+}
@@ -0,0 +1,427 @@
+use crate::{
+    engine::Hop,
+    ids::SwapDirection,
+    num::MathError,
+    pool::Pool,
+    synth::{
+        LiquidityDist, SyntheticConfig, SyntheticMarket, Topology, generate, generate_with_fees,
+        random_plans,
+    },
+    world::BlockContext,
+};
+use alloy_primitives::U256;
+use proptest::prelude::*;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LawViolation {
+    OutputFromZeroInput {
+        dir: SwapDirection,
+        out: U256,
+    },
+    NotMonotonic {
+        dir: SwapDirection,
+        smaller_in: U256,
+        larger_in: U256,
+    },
+    RoundTripProfit {
+        dir: SwapDirection,
+        amt_in: U256,
+        back: U256,
+    },
+    FeeBelowBound {
+        dir: SwapDirection,
+        probe: U256,
+        back: U256,
+        min_fee_bps: u32,
+    },
+    DirtyFailure {
+        dir: SwapDirection,
+        amt_in: U256,
+        error: MathError,
+    },
+}
+
+impl fmt::Display for LawViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutputFromZeroInput { dir, out } => {
+                write!(f, "{dir}: zero input produced {out}")
+            }
+            Self::NotMonotonic {
+                dir,
+                smaller_in,
+                larger_in,
+            } => write!(
+                f,
+                "{dir}: input {larger_in} yields less than smaller input {smaller_in}"
+            ),
+            Self::RoundTripProfit { dir, amt_in, back } => {
+                write!(f, "{dir}: round trip of {amt_in} returned {back}")
+            }
+            Self::FeeBelowBound {
+                dir,
+                probe,
+                back,
+                min_fee_bps,
+            } => write!(
+                f,
+                "{dir}: round trip of {probe} returned {back}, above the {min_fee_bps} bps fee bound"
+            ),
+            Self::DirtyFailure { dir, amt_in, error } => {
+                write!(
+                    f,
+                    "{dir}: swap of {amt_in} failed ({error}) but changed state"
+                )
+            }
+        }
+    }
+}
+
+fn quote<P: Pool>(
+    pool: &P,
+    st: &P::State,
+    ctx: &BlockContext,
+    dir: SwapDirection,
+    amt: U256,
+) -> U256 {
+    // An overflowing quote has no output; the laws only constrain real ones.
+    pool.swap(&mut st.clone(), ctx, dir, amt)
+        .unwrap_or_default()
+}
+
+pub fn check_zero_input<P: Pool>(
+    pool: &P,
+    st: &P::State,
+    ctx: &BlockContext,
+    dir: SwapDirection,
+) -> Result<(), LawViolation> {
+    let out = quote(pool, st, ctx, dir, U256::ZERO);
+    if out.is_zero() {
+        Ok(())
+    } else {
+        Err(LawViolation::OutputFromZeroInput { dir, out })
+    }
+}
+
+pub fn check_monotonic<P: Pool>(
+    pool: &P,
+    st: &P::State,
+    ctx: &BlockContext,
+    dir: SwapDirection,
+    amounts: &[U256],
+) -> Result<(), LawViolation> {
+    let mut sorted = amounts.to_vec();
+    sorted.sort();
+    sorted.dedup();
+    let outs: Vec<_> = sorted
+        .iter()
+        .map(|&a| (a, quote(pool, st, ctx, dir, a)))
+        .collect();
+    for w in outs.windows(2) {
+        if w[1].1 < w[0].1 {
+            return Err(LawViolation::NotMonotonic {
+                dir,
+                smaller_in: w[0].0,
+                larger_in: w[1].0,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn round_trip<P: Pool>(
+    pool: &P,
+    st: &P::State,
+    ctx: &BlockContext,
+    dir: SwapDirection,
+    amt_in: U256,
+) -> U256 {
+    let mut st = st.clone();
+    let Ok(out) = pool.swap(&mut st, ctx, dir, amt_in) else {
+        return U256::ZERO;
+    };
+    pool.swap(&mut st, ctx, dir.reverse(), out)
+        .unwrap_or_default()
+}
+
+pub fn check_round_trip<P: Pool>(
+    pool: &P,
+    st: &P::State,
+    ctx: &BlockContext,
+    dir: SwapDirection,
+    amt_in: U256,
+) -> Result<(), LawViolation> {
+    let back = round_trip(pool, st, ctx, dir, amt_in);
+    if back > amt_in {
+        return Err(LawViolation::RoundTripProfit { dir, amt_in, back });
+    }
+    Ok(())
+}
+
+pub fn check_fee_bound<P: Pool>(
+    pool: &P,
+    st: &P::State,
+    ctx: &BlockContext,
+    dir: SwapDirection,
+    probe: U256,
+    min_fee_bps: u32,
+) -> Result<(), LawViolation> {
+    let back = round_trip(pool, st, ctx, dir, probe);
+    let keep = U256::from(10_000 - min_fee_bps.min(10_000));
+    if back * U256::from(10_000u64) > probe * keep {
+        return Err(LawViolation::FeeBelowBound {
+            dir,
+            probe,
+            back,
+            min_fee_bps,
+        });
+    }
+    Ok(())
+}
+
+/// A failed swap must leave the state it was given untouched.
+pub fn check_clean_failure<P: Pool>(
+    pool: &P,
+    st: &P::State,
+    ctx: &BlockContext,
+    dir: SwapDirection,
+    amt_in: U256,
+) -> Result<(), LawViolation>
+where
+    P::State: PartialEq,
+{
+    let mut after = st.clone();
+    match pool.swap(&mut after, ctx, dir, amt_in) {
+        Err(error) if after != *st => Err(LawViolation::DirtyFailure { dir, amt_in, error }),
+        _ => Ok(()),
+    }
+}
+
+pub fn check_pool_laws<P: Pool>(
+    pool: &P,
+    st: &P::State,
+    dirs: &[SwapDirection],
+    amounts: &[U256],
+) -> Vec<LawViolation> {
+    let ctx = BlockContext::default();
+    let mut out = Vec::new();
+    for &dir in dirs.iter().filter(|d| pool.supports(**d)) {
+        out.extend(check_zero_input(pool, st, &ctx, dir).err());
+        out.extend(check_monotonic(pool, st, &ctx, dir, amounts).err());
+        for &amt in amounts {
+            out.extend(check_round_trip(pool, st, &ctx, dir, amt).err());
+        }
+    }
+    out
+}
+
+pub fn assert_pool_laws<P: Pool>(
+    pool: &P,
+    st: &P::State,
+    dirs: &[SwapDirection],
+    amounts: &[U256],
+) {
+    let violations = check_pool_laws(pool, st, dirs, amounts);
+    if let Some(v) = violations.first() {
+        panic!("{} pool law violation(s), first: {v}", violations.len());
+    }
+}
+
+pub fn amount(max_bits: usize) -> impl Strategy<Value = U256> {
+    (0..=max_bits, any::<[u64; 4]>()).prop_map(|(bits, limbs)| {
+        let v = U256::from_limbs(limbs);
+        if bits == 0 {
+            U256::ZERO
+        } else {
+            v >> (256 - bits)
+        }
+    })
+}
+
+pub fn amounts(max_bits: usize, len: usize) -> impl Strategy<Value = Vec<U256>> {
+    prop::collection::vec(amount(max_bits), 1..=len)
+}
+
+/// A reproducible market for large router tests and benchmarks: registry,
+/// graph, pools and world from one `seed`. Every pool is a V2 pair, the only
+/// kind the generator simulates; `fee_tiers` (in bps) is what varies between
+/// them. Empty, with no tiers or fewer than two tokens.
+pub fn synthetic_world(
+    seed: u64,
+    n_tokens: usize,
+    n_pools: usize,
+    fee_tiers: &[u32],
+    liquidity_dist: LiquidityDist,
+) -> SyntheticMarket {
+    generate_with_fees(
+        &SyntheticConfig {
+            tokens: n_tokens,
+            pools: n_pools,
+            seed,
+            liquidity: liquidity_dist,
+            ..SyntheticConfig::default()
+        },
+        fee_tiers,
+    )
+}
+
+pub fn topology() -> impl Strategy<Value = Topology> {
+    prop_oneof![
+        Just(Topology::Random),
+        Just(Topology::Ring),
+        (1usize..4).prop_map(|hubs| Topology::HubAndSpoke { hubs }),
+    ]
+}
+
+pub fn market(max_tokens: usize, max_pools: usize) -> impl Strategy<Value = SyntheticMarket> {
+    (
+        5..=max_tokens.max(5),
+        1..=max_pools.max(1),
+        topology(),
+        any::<u64>(),
+    )
+        .prop_map(|(tokens, pools, topology, seed)| {
+            generate(&SyntheticConfig {
+                tokens,
+                pools,
+                topology,
+                seed,
+                ..SyntheticConfig::default()
+            })
+        })
+}
+
+pub fn market_with_plans(
+    max_tokens: usize,
+    max_pools: usize,
+    hops: usize,
+) -> impl Strategy<Value = (SyntheticMarket, Vec<Vec<Hop>>)> {
+    (market(max_tokens, max_pools), any::<u64>()).prop_map(move |(m, seed)| {
+        let plans = random_plans(&m, 8, hops, seed);
+        (m, plans)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::ids::{PoolId, TokenId};
+    use crate::num::MathResult;
+    use crate::test_utils::Cp;
+    use crate::univ2::{UniV2Pool, UniV2State};
+
+    struct Leaky;
+
+    impl Pool for Leaky {
+        type State = ();
+
+        fn id(&self) -> PoolId {
+            PoolId(0)
+        }
+
+        fn supports(&self, _dir: SwapDirection) -> bool {
+            true
+        }
+
+        fn swap(
+            &self,
+            _: &mut (),
+            _: &BlockContext,
+            _: SwapDirection,
+            amt_in: U256,
+        ) -> MathResult<U256> {
+            crate::num::add(U256::from(1u64), amt_in)
+        }
+    }
+
+    #[test]
+    fn laws_catch_broken_pools() {
+        let dir = SwapDirection::new(TokenId(1), TokenId(2)).unwrap();
+        let v = check_pool_laws(&Leaky, &(), &[dir], &[U256::from(10u64)]);
+        assert!(matches!(v[0], LawViolation::OutputFromZeroInput { .. }));
+        assert!(
+            v.iter()
+                .any(|v| matches!(v, LawViolation::RoundTripProfit { .. }))
+        );
+        let ctx = BlockContext::default();
+        assert!(matches!(
+            check_fee_bound(&Leaky, &(), &ctx, dir, U256::from(10u64), 0),
+            Err(LawViolation::FeeBelowBound { .. })
+        ));
+    }
+
+    #[test]
+    fn synthetic_worlds_are_reproducible_and_follow_their_parameters() {
+        let pareto = LiquidityDist::Pareto { alpha: 1.2 };
+        let a = synthetic_world(9, 40, 300, &[5, 30, 100], pareto);
+        let b = synthetic_world(9, 40, 300, &[5, 30, 100], pareto);
+        assert_eq!(a.pools, b.pools);
+        assert!(a.world.diff(&b.world).is_empty());
+        assert_eq!(a.registry.pool_meta.len(), 300);
+        assert_eq!(a.graph.pool_idx.len(), 300);
+
+        let fees: std::collections::HashSet<u32> = a.pools.values().map(|p| p.fee_bps).collect();
+        assert_eq!(fees.len(), 3);
+        // Heavy tail: the median pool is far shallower than the mean.
+        let mut r0: Vec<U256> = a.world.pool_states.values().map(|s| s.reserve0).collect();
+        r0.sort();
+        let mean = r0.iter().sum::<U256>() / U256::from(r0.len());
+        assert!(r0[r0.len() / 2] < mean);
+        assert!(r0[0] >= U256::from(SyntheticConfig::default().min_reserve));
+
+        assert!(synthetic_world(9, 40, 300, &[], pareto).pools.is_empty());
+        assert!(synthetic_world(9, 1, 300, &[30], pareto).pools.is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn univ2_obeys_pool_laws(
+            r0 in amount(112).prop_filter("non-empty", |r| !r.is_zero()),
+            r1 in amount(112).prop_filter("non-empty", |r| !r.is_zero()),
+            amts in amounts(100, 6),
+        ) {
+            let pool = UniV2Pool::new(PoolId(1), TokenId(1), TokenId(2));
+            let dir = SwapDirection::new(TokenId(1), TokenId(2)).unwrap();
+            let st = UniV2State::new(r0, r1);
+            assert_pool_laws(&pool, &st, &[dir, dir.reverse()], &amts);
+
+            let probe = r0.min(r1) / U256::from(1_000_000u64);
+            prop_assume!(probe > U256::from(1_000_000u64));
+            let ctx = BlockContext::default();
+            prop_assert!(check_fee_bound(&pool, &st, &ctx, dir, probe, 30).is_ok());
+        }
+
+        #[test]
+        fn full_width_swaps_never_panic(
+            r0 in amount(256),
+            r1 in amount(256),
+            amt in amount(256),
+            fee_bps in 0u32..20_000,
+        ) {
+            let dir = SwapDirection::new(TokenId(1), TokenId(2)).unwrap();
+            let ctx = BlockContext::default();
+            let univ2 = UniV2Pool::new(PoolId(1), TokenId(1), TokenId(2)).with_fee_bps(fee_bps);
+            let st = UniV2State::new(r0, r1);
+            for d in [dir, dir.reverse()] {
+                prop_assert_eq!(check_clean_failure(&univ2, &st, &ctx, d, amt), Ok(()));
+            }
+            let cp = Cp::new(1, 1, 2).with_fee(fee_bps);
+            for d in [dir, dir.reverse()] {
+                prop_assert_eq!(check_clean_failure(&cp, &(r0, r1), &ctx, d, amt), Ok(()));
+            }
+        }
+
+        #[test]
+        fn simulation_never_mutates_world((m, plans) in market_with_plans(12, 30, 3)) {
+            let engine = Engine::new(&m.pools);
+            let before = m.world.clone();
+            for plan in &plans {
+                let path = engine.simulate_chained(&m.world, plan, U256::from(10u64).pow(U256::from(18u64)));
+                prop_assert_eq!(path.steps.len(), plan.len());
+            }
+            prop_assert!(before.diff(&m.world).is_empty());
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{
+    ids::PoolId,
+    world::{BlockContext, StateView, World, WorldDiff},
+};
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+pub struct Timeline<S> {
+    snapshots: BTreeMap<u64, World<S>>,
+    diffs: BTreeMap<u64, WorldDiff<S>>,
+}
+
+impl<S> Default for Timeline<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Timeline<S> {
+    pub fn new() -> Self {
+        Self {
+            snapshots: BTreeMap::new(),
+            diffs: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert_snapshot(&mut self, block: u64, world: World<S>) {
+        self.snapshots.insert(block, world);
+    }
+
+    pub fn insert_diff(&mut self, block: u64, diff: WorldDiff<S>) {
+        self.diffs.insert(block, diff);
+    }
+
+    pub fn first_block(&self) -> Option<u64> {
+        self.snapshots.keys().next().copied()
+    }
+
+    pub fn last_block(&self) -> Option<u64> {
+        let snap = self.snapshots.keys().next_back().copied();
+        let diff = self.diffs.keys().next_back().copied();
+        snap.max(diff)
+    }
+
+    pub fn at(&self, block: u64) -> Option<WorldView<'_, S>> {
+        let (&snap_block, base) = self.snapshots.range(..=block).next_back()?;
+        Some(WorldView {
+            block,
+            base,
+            diffs: self
+                .diffs
+                .range((Bound::Excluded(snap_block), Bound::Included(block)))
+                .map(|(_, d)| d)
+                .collect(),
+        })
+    }
+
+    pub fn prune_before(&mut self, block: u64) {
+        let Some(&keep) = self.snapshots.range(..=block).next_back().map(|(b, _)| b) else {
+            return;
+        };
+        self.snapshots = self.snapshots.split_off(&keep);
+        self.diffs = self.diffs.split_off(&keep);
+    }
+}
+
+pub struct WorldView<'a, S> {
+    pub block: u64,
+    base: &'a World<S>,
+    diffs: Vec<&'a WorldDiff<S>>,
+}
+
+impl<S> StateView<S> for WorldView<'_, S> {
+    fn block(&self) -> BlockContext {
+        self.diffs
+            .iter()
+            .rev()
+            .find_map(|d| d.block)
+            .unwrap_or(self.base.block)
+    }
+
+    fn pool_state(&self, pid: PoolId) -> Option<&S> {
+        self.diffs
+            .iter()
+            .rev()
+            .find_map(|d| d.pool_states.get(&pid))
+            .or_else(|| self.base.pool_states.get(&pid))
+    }
+}
+
+impl<S: Clone> WorldView<'_, S> {
+    pub fn materialize(&self) -> World<S> {
+        let mut world = self.base.clone();
+        for d in &self.diffs {
+            world.apply((*d).clone());
+        }
+        world
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(pid: u64, st: u64) -> WorldDiff<u64> {
+        let mut d = WorldDiff::default();
+        d.set_pool_state(PoolId(pid), st);
+        d
+    }
+
+    #[test]
+    fn at_resolves_nearest_snapshot_plus_diffs() {
+        let mut base = World::default();
+        base.pool_states.insert(PoolId(1), 100);
+        base.pool_states.insert(PoolId(2), 200);
+
+        let mut tl = Timeline::new();
+        tl.insert_snapshot(10, base);
+        tl.insert_diff(11, diff(1, 101));
+        tl.insert_diff(12, diff(2, 202));
+        tl.insert_diff(13, diff(1, 103));
+
+        assert!(tl.at(9).is_none());
+        assert_eq!(tl.at(10).unwrap().pool_state(PoolId(1)), Some(&100));
+        assert_eq!(tl.at(12).unwrap().pool_state(PoolId(1)), Some(&101));
+        assert_eq!(tl.at(12).unwrap().pool_state(PoolId(2)), Some(&202));
+        assert_eq!(tl.at(50).unwrap().pool_state(PoolId(1)), Some(&103));
+        assert_eq!(tl.last_block(), Some(13));
+
+        let mut snap = tl.at(12).unwrap().materialize();
+        snap.pool_states.insert(PoolId(3), 300);
+        tl.insert_snapshot(12, snap);
+        assert_eq!(tl.at(13).unwrap().pool_state(PoolId(3)), Some(&300));
+        assert_eq!(tl.at(13).unwrap().pool_state(PoolId(1)), Some(&103));
+
+        tl.prune_before(13);
+        assert_eq!(tl.first_block(), Some(12));
+        assert!(tl.at(11).is_none());
+    }
+}
@@ -0,0 +1,121 @@
+//! Tokens that skim every transfer. Pools see less than the sender paid,
+//! and each hop's output shrinks again on its way to the next pool or the
+//! recipient.
+
+use crate::ids::TokenId;
+use alloy_primitives::U256;
+use std::collections::HashMap;
+
+const BPS: u64 = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferModel {
+    /// Destroys `bps` of every transfer.
+    Burn { bps: u32 },
+    /// Redistributes `reflect_bps` to all holders and routes `liquidity_bps`
+    /// to the token's own pool. The recipient's reflection share is
+    /// negligible, so both are modeled as lost.
+    Reflection {
+        reflect_bps: u32,
+        liquidity_bps: u32,
+    },
+}
+
+impl TransferModel {
+    pub fn fee_bps(&self) -> u32 {
+        match *self {
+            TransferModel::Burn { bps } => bps,
+            TransferModel::Reflection {
+                reflect_bps,
+                liquidity_bps,
+            } => reflect_bps.saturating_add(liquidity_bps),
+        }
+        .min(BPS as u32)
+    }
+
+    /// What arrives when `amount` is sent. Fees round down, as tokens
+    /// compute them.
+    pub fn received(&self, amount: U256) -> U256 {
+        let fee = amount / U256::from(BPS) * U256::from(self.fee_bps())
+            + amount % U256::from(BPS) * U256::from(self.fee_bps()) / U256::from(BPS);
+        amount - fee
+    }
+}
+
+/// Transfer models by token; tokens without one transfer in full.
+#[derive(Clone, Debug, Default)]
+pub struct TransferModels {
+    pub models: HashMap<TokenId, TransferModel>,
+}
+
+impl TransferModels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, token: TokenId, model: TransferModel) -> Self {
+        self.models.insert(token, model);
+        self
+    }
+
+    pub fn received(&self, token: TokenId, amount: U256) -> U256 {
+        match self.models.get(&token) {
+            Some(model) => model.received(amount),
+            None => amount,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::ids::PoolId;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::World;
+
+    #[test]
+    fn every_transfer_of_a_taxed_token_is_skimmed() {
+        let burn = TransferModel::Burn { bps: 100 };
+        assert_eq!(burn.received(U256::from(1_000u64)), U256::from(990u64));
+        let reflect = TransferModel::Reflection {
+            reflect_bps: 200,
+            liquidity_bps: 300,
+        };
+        assert_eq!(reflect.received(U256::from(999u64)), U256::from(950u64));
+
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2)), (PoolId(2), Cp::new(2, 2, 3))]);
+        let mut world = World::default();
+        world.set_pool_state(PoolId(1), reserves(1_000_000, 1_000_000));
+        world.set_pool_state(PoolId(2), reserves(1_000_000, 1_000_000));
+        let plan = [hop(1, 1, 2), hop(2, 2, 3)];
+        let amt = U256::from(10_000u64);
+
+        let plain = Engine::new(&pools).simulate_chained(&world, &plan, amt);
+        let models = TransferModels::new().with(TokenId(2), burn);
+        let taxed = Engine::new(&pools)
+            .with_transfers(&models)
+            .simulate_chained(&world, &plan, amt);
+        // Token 2 arrives at pool 2 short by 1%, and pool 1's raw output
+        // is unchanged.
+        let raw = plain.steps[0].amt_out;
+        assert_eq!(taxed.steps[0].amt_out, burn.received(raw));
+        assert_eq!(taxed.steps[1].amt_in, taxed.steps[0].amt_out);
+        assert!(taxed.steps[1].amt_out < plain.steps[1].amt_out);
+
+        // Taxing the input and output tokens skims the first and last
+        // transfers instead.
+        let ends = TransferModels::new()
+            .with(TokenId(1), burn)
+            .with(TokenId(3), burn);
+        let path = Engine::new(&pools)
+            .with_transfers(&ends)
+            .simulate_chained(&world, &plan, amt);
+        assert_eq!(path.steps[0].amt_in, amt);
+        let arrived = Engine::new(&pools).simulate_chained(&world, &plan, burn.received(amt));
+        assert_eq!(
+            path.steps[1].amt_out,
+            burn.received(arrived.steps[1].amt_out)
+        );
+    }
+}
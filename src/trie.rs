@@ -0,0 +1,320 @@
+use crate::{
+    engine::{Cursor, Engine, Fault, Hop, Path, PathSteps},
+    error::{EngineError, WayfinderError},
+    ids::PoolId,
+    pool::Pool,
+    world::StateView,
+};
+use alloy_primitives::U256;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Default)]
+struct Node {
+    hop: Option<Hop>,
+    children: Vec<usize>,
+    /// Plans ending at this node.
+    ends: Vec<usize>,
+}
+
+/// Candidate plans merged by common prefix, so each shared prefix is
+/// simulated once and scratch state is only cloned where plans diverge.
+#[derive(Clone, Debug)]
+pub struct PlanTrie {
+    nodes: Vec<Node>,
+    plans: usize,
+    hops: usize,
+}
+
+impl PlanTrie {
+    pub fn new(plans: &[Vec<Hop>]) -> Self {
+        let mut nodes = vec![Node::default()];
+        for (i, plan) in plans.iter().enumerate() {
+            // Empty plans end at the root, and simulate to an error.
+            let mut at = 0;
+            for &hop in plan {
+                let existing = nodes[at]
+                    .children
+                    .iter()
+                    .copied()
+                    .find(|&c| nodes[c].hop == Some(hop));
+                at = existing.unwrap_or_else(|| {
+                    nodes.push(Node {
+                        hop: Some(hop),
+                        ..Node::default()
+                    });
+                    let child = nodes.len() - 1;
+                    nodes[at].children.push(child);
+                    child
+                });
+            }
+            nodes[at].ends.push(i);
+        }
+        Self {
+            nodes,
+            plans: plans.len(),
+            hops: plans.iter().map(Vec::len).sum(),
+        }
+    }
+
+    pub fn plans(&self) -> usize {
+        self.plans
+    }
+
+    /// Hops actually simulated per evaluation.
+    pub fn unique_hops(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    /// Hop simulations saved versus evaluating every plan separately.
+    pub fn shared_hops(&self) -> usize {
+        self.hops - self.unique_hops()
+    }
+}
+
+/// A simulated plan and its first fault, or why it could not be simulated.
+type Walked = Result<(Path, Option<Fault>), EngineError>;
+
+struct Walk<'t, 'w, 'e, P: Pool, V> {
+    engine: &'e Engine<'e, P>,
+    world: &'w V,
+    trie: &'t PlanTrie,
+    out: Vec<Option<Walked>>,
+    steps: PathSteps,
+}
+
+impl<P: Pool, V: StateView<P::State>> Walk<'_, '_, '_, P, V> {
+    fn start(&mut self, first_in: U256) {
+        let trie = self.trie;
+        for &plan in &trie.nodes[0].ends {
+            self.out[plan] = Some(Err(EngineError::EmptyPlan));
+        }
+        for &child in &trie.nodes[0].children {
+            let hop = trie.nodes[child].hop.expect("only the root has no hop");
+            self.visit(child, HashMap::new(), Cursor::new(hop.dir.from, first_in));
+        }
+    }
+
+    /// Runs each hop through [`Engine::step`], as a single simulation does.
+    fn visit(&mut self, node: usize, mut scratch: HashMap<PoolId, P::State>, mut at: Cursor) {
+        let trie = self.trie;
+        let n = &trie.nodes[node];
+        let hop = n.hop.expect("only the root has no hop");
+        let step = match self.engine.step(self.world, &mut scratch, hop, &mut at) {
+            Ok(step) => step,
+            Err(e) => return self.fail(node, e),
+        };
+        self.steps.push(step);
+        for &plan in &n.ends {
+            let path = Path {
+                steps: self.steps.clone(),
+            };
+            self.out[plan] = Some(Ok((path, at.fault)));
+        }
+        if let Some((&last, rest)) = n.children.split_last() {
+            for &child in rest {
+                self.visit(child, scratch.clone(), at);
+            }
+            self.visit(last, scratch, at);
+        }
+        self.steps.pop();
+    }
+
+    /// Every plan through `node` fails with `e`.
+    fn fail(&mut self, node: usize, e: EngineError) {
+        let trie = self.trie;
+        let mut stack = vec![node];
+        while let Some(n) = stack.pop() {
+            for &plan in &trie.nodes[n].ends {
+                self.out[plan] = Some(Err(e));
+            }
+            stack.extend(&trie.nodes[n].children);
+        }
+    }
+}
+
+impl<P: Pool> Engine<'_, P> {
+    fn walk<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        trie: &PlanTrie,
+        first_in: U256,
+    ) -> Vec<Walked> {
+        let mut walk = Walk {
+            engine: self,
+            world,
+            trie,
+            out: vec![None; trie.plans],
+            steps: PathSteps::new(),
+        };
+        walk.start(first_in);
+        walk.out
+            .into_iter()
+            .map(|p| p.expect("every plan ends at a node"))
+            .collect()
+    }
+
+    /// Simulates every plan in `trie` from `first_in`, returning paths in the
+    /// order the plans were given. Results match [`Engine::simulate_chained`].
+    ///
+    /// # Panics
+    ///
+    /// Like [`Engine::simulate_chained`]; see [`Engine::try_simulate_trie`].
+    pub fn simulate_trie<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        trie: &PlanTrie,
+        first_in: U256,
+    ) -> Vec<Path> {
+        self.walk(world, trie, first_in)
+            .into_iter()
+            .map(|w| w.unwrap_or_else(|e| panic!("{e}")).0)
+            .collect()
+    }
+
+    /// Per-plan results matching [`Engine::try_simulate`].
+    pub fn try_simulate_trie<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        trie: &PlanTrie,
+        first_in: U256,
+    ) -> Vec<Result<Path, WayfinderError>> {
+        self.walk(world, trie, first_in)
+            .into_iter()
+            .map(|w| match w? {
+                (_, Some(fault)) => Err(fault.into()),
+                (path, None) => Ok(path),
+            })
+            .collect()
+    }
+
+    pub fn simulate_many<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plans: &[Vec<Hop>],
+        first_in: U256,
+    ) -> Vec<Path> {
+        self.simulate_trie(world, &PlanTrie::new(plans), first_in)
+    }
+
+    pub fn try_simulate_many<V: StateView<P::State>>(
+        &self,
+        world: &V,
+        plans: &[Vec<Hop>],
+        first_in: U256,
+    ) -> Vec<Result<Path, WayfinderError>> {
+        self.try_simulate_trie(world, &PlanTrie::new(plans), first_in)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{Accuracy, Step};
+    use crate::ids::TokenId;
+    use crate::memo::SwapMemo;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::transfer::{TransferModel, TransferModels};
+    use crate::world::World;
+    use std::ops::ControlFlow;
+
+    #[test]
+    fn trie_matches_independent_simulation() {
+        let mut pools = HashMap::new();
+        let mut world = World::default();
+        for (id, t0, t1) in [(1, 1, 2), (2, 2, 3), (3, 2, 4), (4, 3, 1), (5, 4, 1)] {
+            pools.insert(PoolId(id), Cp::new(id, t0, t1).with_fee(30));
+            world
+                .pool_states
+                .insert(PoolId(id), reserves(1_000_000 * id, 900_000 * id));
+        }
+        let plans = vec![
+            vec![hop(1, 1, 2), hop(2, 2, 3), hop(4, 3, 1)],
+            vec![hop(1, 1, 2), hop(3, 2, 4), hop(5, 4, 1)],
+            vec![hop(1, 1, 2), hop(2, 2, 3)],
+            vec![hop(1, 1, 2)],
+            // Revisits pool 1 after the shared prefix.
+            vec![hop(1, 1, 2), hop(1, 2, 1)],
+            vec![hop(4, 1, 3)],
+        ];
+        let trie = PlanTrie::new(&plans);
+        assert_eq!(trie.unique_hops(), 7);
+        assert_eq!(trie.shared_hops(), 5);
+
+        let engine = Engine::new(&pools);
+        let amt = U256::from(50_000u64);
+        let many = engine.simulate_trie(&world, &trie, amt);
+        for (plan, path) in plans.iter().zip(&many) {
+            let single = engine.simulate_chained(&world, plan, amt);
+            let outs = |p: &Path| p.steps.iter().map(|s| s.amt_out).collect::<Vec<_>>();
+            assert_eq!(outs(path), outs(&single));
+        }
+
+        // Memo, transfer taxes, constraints and accuracy apply per hop
+        // just as they do to a single simulation.
+        for id in 1..=5 {
+            world.touch(PoolId(id));
+        }
+        let memo = SwapMemo::default();
+        let taxes = TransferModels::new().with(TokenId(2), TransferModel::Burn { bps: 100 });
+        let band = |step: &Step, _: &(U256, U256)| {
+            if step.pool == PoolId(3) {
+                ControlFlow::Break("pool 3 out of band")
+            } else {
+                ControlFlow::Continue(())
+            }
+        };
+        let configured = Engine::new(&pools)
+            .with_memo(&memo)
+            .with_transfers(&taxes)
+            .with_constraint(&band);
+        for engine in [
+            configured,
+            Engine::new(&pools).with_accuracy(Accuracy::Fast),
+        ] {
+            for _ in 0..2 {
+                let many = engine.simulate_trie(&world, &trie, amt);
+                for (plan, path) in plans.iter().zip(&many) {
+                    assert_eq!(path, &engine.simulate_chained(&world, plan, amt));
+                }
+            }
+        }
+        assert!(memo.stats().hits > 0);
+    }
+
+    #[test]
+    fn try_simulate_many_reports_errors_per_plan() {
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2)), (PoolId(2), Cp::new(2, 2, 3))]);
+        let mut world = World::default();
+        world.set_pool_state(PoolId(1), reserves(1_000_000, 1_000_000));
+        let plans = vec![
+            vec![hop(1, 1, 2)],
+            vec![hop(1, 1, 2), hop(2, 2, 3)],
+            vec![hop(1, 1, 2), hop(9, 2, 3)],
+            vec![hop(1, 1, 2), hop(1, 1, 2)],
+            vec![],
+        ];
+        let engine = Engine::new(&pools);
+        let got = engine.try_simulate_many(&world, &plans, U256::from(1_000u64));
+        assert_eq!(
+            got[0].as_ref().unwrap(),
+            &engine.simulate_chained(&world, &plans[0], U256::from(1_000u64))
+        );
+        let engine_err = |r: &Result<Path, WayfinderError>| match r {
+            Err(WayfinderError::Engine(e)) => Some(*e),
+            _ => None,
+        };
+        assert_eq!(
+            engine_err(&got[1]),
+            Some(EngineError::MissingPoolState(PoolId(2)))
+        );
+        assert_eq!(
+            engine_err(&got[2]),
+            Some(EngineError::MissingPool(PoolId(9)))
+        );
+        assert!(matches!(
+            engine_err(&got[3]),
+            Some(EngineError::Discontinuity { .. })
+        ));
+        assert_eq!(engine_err(&got[4]), Some(EngineError::EmptyPlan));
+    }
+}
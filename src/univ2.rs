@@ -0,0 +1,195 @@
+use crate::{
+    graph::AMMGraph,
+    ids::{PoolId, SwapDirection, TokenId},
+    num::{self, MathError, MathResult, Price},
+    pool::Pool,
+    registry::{PoolKind, Registry},
+    world::BlockContext,
+};
+use alloy_primitives::U256;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct UniV2State {
+    pub reserve0: U256,
+    pub reserve1: U256,
+}
+
+impl UniV2State {
+    pub fn new(reserve0: U256, reserve1: U256) -> Self {
+        Self { reserve0, reserve1 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UniV2Pool {
+    pub id: PoolId,
+    pub token0: TokenId,
+    pub token1: TokenId,
+    pub fee_bps: u32,
+}
+
+impl UniV2Pool {
+    pub fn new(id: PoolId, token0: TokenId, token1: TokenId) -> Self {
+        Self {
+            id,
+            token0,
+            token1,
+            fee_bps: 30,
+        }
+    }
+
+    pub fn with_fee_bps(mut self, fee_bps: u32) -> Self {
+        self.fee_bps = fee_bps;
+        self
+    }
+
+    pub fn amount_out(&self, r_in: U256, r_out: U256, amt_in: U256) -> MathResult<U256> {
+        num::cp_amount_out(r_in, r_out, amt_in, self.keep_bps()?)
+    }
+
+    /// The share of the input that reaches the curve; fees above 100% are
+    /// an error rather than a wrapped subtraction.
+    fn keep_bps(&self) -> MathResult<u32> {
+        10_000u32
+            .checked_sub(self.fee_bps)
+            .ok_or(MathError::Underflow)
+    }
+
+    /// Marginal price of `dir.from` in `dir.to`, before fees.
+    pub fn spot_price(&self, st: &UniV2State, dir: SwapDirection) -> Option<Price> {
+        let (r_in, r_out) = if dir.from == self.token0 {
+            (st.reserve0, st.reserve1)
+        } else {
+            (st.reserve1, st.reserve0)
+        };
+        Price::from_ratio(r_out, r_in)
+    }
+}
+
+impl Pool for UniV2Pool {
+    type State = UniV2State;
+
+    fn id(&self) -> PoolId {
+        self.id
+    }
+
+    fn supports(&self, dir: SwapDirection) -> bool {
+        (dir.from == self.token0 && dir.to == self.token1)
+            || (dir.from == self.token1 && dir.to == self.token0)
+    }
+
+    fn swap(
+        &self,
+        st: &mut Self::State,
+        _ctx: &BlockContext,
+        dir: SwapDirection,
+        amt_in: U256,
+    ) -> MathResult<U256> {
+        let (r_in, r_out) = if dir.from == self.token0 {
+            (&mut st.reserve0, &mut st.reserve1)
+        } else {
+            (&mut st.reserve1, &mut st.reserve0)
+        };
+        let out = self.amount_out(*r_in, *r_out, amt_in)?;
+        let (new_in, new_out) = (num::add(*r_in, amt_in)?, num::sub(*r_out, out)?);
+        (*r_in, *r_out) = (new_in, new_out);
+        Ok(out)
+    }
+
+    fn marginal_price(&self, st: &UniV2State, dir: SwapDirection) -> Option<Price> {
+        let spot = UniV2Pool::spot_price(self, st, dir)?;
+        let keep = U256::from(self.keep_bps().ok()?);
+        num::mul_div(spot.0, keep, U256::from(10_000u64))
+            .ok()
+            .map(Price)
+    }
+}
+
+pub fn pools_from_registry(reg: &Registry) -> (HashMap<PoolId, UniV2Pool>, AMMGraph) {
+    let mut pools = HashMap::new();
+    let mut graph = AMMGraph::new();
+    for (&pid, meta) in &reg.pool_meta {
+        if meta.kind != PoolKind::UniV2 {
+            continue;
+        }
+        graph.connect_bidirectional_pair(pid, meta.token0, meta.token1);
+        pools.insert(
+            pid,
+            UniV2Pool::new(pid, meta.token0, meta.token1).with_fee_bps(meta.fee / 100),
+        );
+    }
+    (pools, graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_uniswap_v2_get_amount_out() {
+        let pool = UniV2Pool::new(PoolId(1), TokenId(1), TokenId(2));
+        let mut st = UniV2State::new(U256::from(1_000u64), U256::from(1_000u64));
+        let dir = SwapDirection::new(TokenId(1), TokenId(2)).unwrap();
+
+        let out = pool
+            .swap(&mut st, &BlockContext::default(), dir, U256::from(100u64))
+            .unwrap();
+        assert_eq!(out, U256::from(90u64));
+        assert_eq!(
+            st,
+            UniV2State::new(U256::from(1_100u64), U256::from(910u64))
+        );
+
+        let back = pool
+            .swap(&mut st, &BlockContext::default(), dir.reverse(), out)
+            .unwrap();
+        assert!(back < U256::from(100u64), "round trip pays the fee twice");
+    }
+
+    #[test]
+    fn overflowing_swaps_fail_without_touching_state() {
+        let mut rng = crate::rng::SplitMix64::new(3);
+        let mut wide = || {
+            let v = U256::from_limbs([
+                rng.next_u64(),
+                rng.next_u64(),
+                rng.next_u64(),
+                rng.next_u64(),
+            ]);
+            v >> rng.below(256) as usize
+        };
+        let pool = UniV2Pool::new(PoolId(1), TokenId(1), TokenId(2));
+        let dir = SwapDirection::new(TokenId(1), TokenId(2)).unwrap();
+        let ctx = BlockContext::default();
+        let mut failures = 0;
+        for _ in 0..20_000 {
+            let before = UniV2State::new(wide(), wide());
+            let mut st = before;
+            match pool.swap(&mut st, &ctx, dir, wide()) {
+                Ok(out) => assert!(out <= before.reserve1),
+                Err(_) => {
+                    failures += 1;
+                    assert_eq!(st, before);
+                }
+            }
+        }
+        assert!(failures > 0);
+
+        let mut st = UniV2State::new(U256::MAX - U256::from(10u64), U256::MAX);
+        let out = pool.swap(&mut st, &ctx, dir, U256::from(100u64));
+        assert_eq!(out, Err(num::MathError::Overflow));
+
+        // A registry fee above 100% is an error, not a panic.
+        let greedy = pool.with_fee_bps(10_001);
+        let mut st = UniV2State::new(U256::from(1_000u64), U256::from(1_000u64));
+        let out = greedy.swap(&mut st, &ctx, dir, U256::from(100u64));
+        assert_eq!(out, Err(MathError::Underflow));
+        assert_eq!(greedy.marginal_price(&st, dir), None);
+    }
+}
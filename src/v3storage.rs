@@ -0,0 +1,211 @@
+//! Reads UniswapV3 pool state straight from storage with `eth_getStorageAt`
+//! and rebuilds the initialized tick map locally, instead of probing a
+//! quoter contract per pool.
+
+use crate::error::WayfinderError;
+use alloy_primitives::{Address, B256, U256, keccak256};
+use alloy_provider::Provider;
+use std::collections::BTreeMap;
+
+/// Storage slots of `UniswapV3Pool`.
+pub const SLOT0_SLOT: u64 = 0;
+pub const LIQUIDITY_SLOT: u64 = 4;
+pub const TICKS_SLOT: u64 = 5;
+pub const TICK_BITMAP_SLOT: u64 = 6;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TickInfo {
+    pub liquidity_gross: u128,
+    pub liquidity_net: i128,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TickMap {
+    pub tick_spacing: i32,
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+    /// Initialized ticks within the words read.
+    pub ticks: BTreeMap<i32, TickInfo>,
+    /// Bitmap words read, inclusive.
+    pub words: (i16, i16),
+}
+
+/// Slot of `mapping(K => V)` at `slot` for a key already ABI-encoded.
+fn mapping_slot(key: B256, slot: u64) -> U256 {
+    let mut buf = [0u8; 64];
+    buf[..32].copy_from_slice(key.as_slice());
+    buf[32..].copy_from_slice(&U256::from(slot).to_be_bytes::<32>());
+    keccak256(buf).into()
+}
+
+fn signed_key(v: i32) -> B256 {
+    // Signed keys are sign-extended to 32 bytes.
+    let fill = if v < 0 { 0xff } else { 0 };
+    let mut key = [fill; 32];
+    key[28..].copy_from_slice(&v.to_be_bytes());
+    B256::from(key)
+}
+
+pub fn tick_slot(tick: i32) -> U256 {
+    mapping_slot(signed_key(tick), TICKS_SLOT)
+}
+
+pub fn bitmap_slot(word: i16) -> U256 {
+    mapping_slot(signed_key(word.into()), TICK_BITMAP_SLOT)
+}
+
+/// Bitmap word holding `tick`.
+pub fn word_of(tick: i32, tick_spacing: i32) -> i16 {
+    (tick.div_euclid(tick_spacing) >> 8) as i16
+}
+
+/// `sqrtPriceX96` and `tick` packed into `slot0`.
+pub fn decode_slot0(word: U256) -> (U256, i32) {
+    let sqrt_price = word & ((U256::ONE << 160) - U256::ONE);
+    let tick = (word >> 160usize).as_limbs()[0] as u32;
+    // int24, sign-extended from bit 23.
+    let tick = ((tick << 8) as i32) >> 8;
+    (sqrt_price, tick)
+}
+
+/// First slot of `Tick.Info`: `liquidityGross` low, `liquidityNet` high.
+pub fn decode_tick(word: U256) -> TickInfo {
+    let limbs = word.as_limbs();
+    TickInfo {
+        liquidity_gross: (limbs[1] as u128) << 64 | limbs[0] as u128,
+        liquidity_net: ((limbs[3] as u128) << 64 | limbs[2] as u128) as i128,
+    }
+}
+
+/// Ticks flagged in bitmap `word` number `pos`.
+pub fn initialized_ticks(pos: i16, word: U256, tick_spacing: i32) -> impl Iterator<Item = i32> {
+    (0..256)
+        .filter(move |&bit| word.bit(bit))
+        .map(move |bit| ((i32::from(pos) << 8) + bit as i32) * tick_spacing)
+}
+
+pub struct V3StorageReader<P> {
+    pub provider: P,
+    /// Bitmap words read on each side of the current tick's word.
+    pub words_around: i16,
+}
+
+impl<P: Provider> V3StorageReader<P> {
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider,
+            words_around: 2,
+        }
+    }
+
+    pub fn with_words_around(mut self, words_around: i16) -> Self {
+        self.words_around = words_around.max(0);
+        self
+    }
+
+    /// Rebuilds the tick map of the pool at `pool` as of `block`.
+    pub async fn read_pool(
+        &self,
+        pool: Address,
+        tick_spacing: i32,
+        block: u64,
+    ) -> Result<TickMap, WayfinderError> {
+        let (sqrt_price_x96, tick) =
+            decode_slot0(self.storage(pool, U256::from(SLOT0_SLOT), block).await?);
+        let liquidity = self
+            .storage(pool, U256::from(LIQUIDITY_SLOT), block)
+            .await?
+            .saturating_to();
+
+        let center = word_of(tick, tick_spacing);
+        let words = (
+            center.saturating_sub(self.words_around),
+            center.saturating_add(self.words_around),
+        );
+        let mut ticks = BTreeMap::new();
+        for pos in words.0..=words.1 {
+            let word = self.storage(pool, bitmap_slot(pos), block).await?;
+            for t in initialized_ticks(pos, word, tick_spacing) {
+                let info = decode_tick(self.storage(pool, tick_slot(t), block).await?);
+                ticks.insert(t, info);
+            }
+        }
+        Ok(TickMap {
+            tick_spacing,
+            sqrt_price_x96,
+            tick,
+            liquidity,
+            ticks,
+            words,
+        })
+    }
+
+    async fn storage(&self, pool: Address, slot: U256, block: u64) -> Result<U256, WayfinderError> {
+        self.provider
+            .get_storage_at(pool, slot)
+            .block_id(block.into())
+            .await
+            .map_err(|e| WayfinderError::Rpc(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_provider::{ProviderBuilder, mock::Asserter};
+
+    #[test]
+    fn slot_layout_and_packing() {
+        // keccak256(abi.encode(int24(-60), uint256(5)))
+        let mut buf = [0xffu8; 64];
+        buf[28..32].copy_from_slice(&(-60i32).to_be_bytes());
+        buf[32..].copy_from_slice(&U256::from(5u64).to_be_bytes::<32>());
+        assert_eq!(tick_slot(-60), U256::from_be_bytes(keccak256(buf).0));
+
+        assert_eq!(word_of(-1, 60), -1);
+        assert_eq!(word_of(60 * 256, 60), 1);
+        let ticks: Vec<_> = initialized_ticks(-1, U256::from(0b101u64), 10).collect();
+        assert_eq!(ticks, vec![-2560, -2540]);
+
+        let tick_bits = U256::from(0xff_fff6u64) << 160; // int24(-10)
+        assert_eq!(
+            decode_slot0(tick_bits | U256::from(42u64)),
+            (U256::from(42u64), -10)
+        );
+
+        let word = (U256::from((-5i128) as u128) << 128) | U256::from(7u64);
+        assert_eq!(
+            decode_tick(word),
+            TickInfo {
+                liquidity_gross: 7,
+                liquidity_net: -5
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_tick_map_from_storage() {
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+        let reader = V3StorageReader::new(provider).with_words_around(1);
+
+        asserter.push_success(&(U256::from(1u64) << 96)); // slot0, tick 0
+        asserter.push_success(&U256::from(1_000u64));
+        asserter.push_success(&U256::ZERO); // word -1
+        asserter.push_success(&U256::from(0b11u64)); // word 0: ticks 0, 60
+        asserter.push_success(&U256::from(10u64));
+        asserter.push_success(&U256::from(20u64));
+        asserter.push_success(&U256::ZERO); // word 1
+
+        let map = reader.read_pool(Address::ZERO, 60, 1).await.unwrap();
+        assert_eq!((map.tick, map.liquidity, map.words), (0, 1_000, (-1, 1)));
+        assert_eq!(
+            map.ticks
+                .iter()
+                .map(|(&t, i)| (t, i.liquidity_gross))
+                .collect::<Vec<_>>(),
+            vec![(0, 10), (60, 20)]
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use crate::{
+    engine::Engine,
+    ids::{PoolId, SwapDirection},
+    num::MathError,
+    pool::Pool,
+    timeline::Timeline,
+    world::{StateView, World},
+};
+use alloy_primitives::U256;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ObservedSwap {
+    pub block: u64,
+    pub log_index: u64,
+    pub pool: PoolId,
+    pub dir: SwapDirection,
+    pub amt_in: U256,
+    pub amt_out: U256,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    MissingPool,
+    MissingState,
+    Unsupported,
+    Math(MathError),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolDeviation {
+    pub swaps: u64,
+    pub exact: u64,
+    pub max_dev_bps: u64,
+    pub sum_dev_bps: u64,
+    pub worst: Option<(u64, u64)>,
+}
+
+impl PoolDeviation {
+    pub fn mean_dev_bps(&self) -> u64 {
+        self.sum_dev_bps.checked_div(self.swaps).unwrap_or_default()
+    }
+
+    fn record(&mut self, swap: &ObservedSwap, simulated: U256) {
+        let dev = deviation_bps(swap.amt_out, simulated);
+        self.swaps += 1;
+        self.sum_dev_bps = self.sum_dev_bps.saturating_add(dev);
+        if dev == 0 && simulated == swap.amt_out {
+            self.exact += 1;
+        }
+        if dev > self.max_dev_bps || self.worst.is_none() {
+            self.max_dev_bps = self.max_dev_bps.max(dev);
+            self.worst = Some((swap.block, swap.log_index));
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    pub pools: HashMap<PoolId, PoolDeviation>,
+    pub skipped: Vec<(ObservedSwap, SkipReason)>,
+}
+
+impl ValidationReport {
+    pub fn worst_pools(&self, n: usize) -> Vec<(PoolId, PoolDeviation)> {
+        let mut all: Vec<_> = self.pools.iter().map(|(&p, &d)| (p, d)).collect();
+        all.sort_by(|a, b| b.1.max_dev_bps.cmp(&a.1.max_dev_bps).then(a.0.cmp(&b.0)));
+        all.truncate(n);
+        all
+    }
+}
+
+pub fn deviation_bps(observed: U256, simulated: U256) -> u64 {
+    let diff = observed.abs_diff(simulated);
+    if diff.is_zero() {
+        return 0;
+    }
+    if observed.is_zero() {
+        return 10_000;
+    }
+    let bps = diff.saturating_mul(U256::from(10_000u64)) / observed;
+    bps.try_into().unwrap_or(u64::MAX)
+}
+
+fn sorted(swaps: &[ObservedSwap]) -> Vec<ObservedSwap> {
+    let mut swaps = swaps.to_vec();
+    swaps.sort_by_key(|s| (s.block, s.log_index));
+    swaps
+}
+
+fn replay_one<P: Pool>(
+    engine: &Engine<'_, P>,
+    world: &mut World<P::State>,
+    swap: &ObservedSwap,
+    report: &mut ValidationReport,
+) {
+    let Some(pool) = engine.pools.get(&swap.pool) else {
+        report.skipped.push((*swap, SkipReason::MissingPool));
+        return;
+    };
+    if !pool.supports(swap.dir) {
+        report.skipped.push((*swap, SkipReason::Unsupported));
+        return;
+    }
+    let ctx = world.block();
+    let Some(st) = world.pool_states.get_mut(&swap.pool) else {
+        report.skipped.push((*swap, SkipReason::MissingState));
+        return;
+    };
+    let simulated = match pool.swap(st, &ctx, swap.dir, swap.amt_in) {
+        Ok(out) => out,
+        Err(e) => {
+            report.skipped.push((*swap, SkipReason::Math(e)));
+            return;
+        }
+    };
+    world.touch(swap.pool);
+    report
+        .pools
+        .entry(swap.pool)
+        .or_default()
+        .record(swap, simulated);
+}
+
+pub fn replay<P: Pool>(
+    engine: &Engine<'_, P>,
+    world: &mut World<P::State>,
+    swaps: &[ObservedSwap],
+) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    for swap in sorted(swaps) {
+        world.block.number = swap.block;
+        replay_one(engine, world, &swap, &mut report);
+    }
+    report
+}
+
+pub fn replay_timeline<P: Pool>(
+    engine: &Engine<'_, P>,
+    timeline: &Timeline<P::State>,
+    swaps: &[ObservedSwap],
+) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let mut current: Option<(u64, World<P::State>)> = None;
+
+    for swap in sorted(swaps) {
+        if current.as_ref().is_none_or(|(b, _)| *b != swap.block) {
+            let pre = swap
+                .block
+                .checked_sub(1)
+                .and_then(|b| timeline.at(b))
+                .map(|v| v.materialize());
+            current = pre.map(|mut w| {
+                w.block.number = swap.block;
+                (swap.block, w)
+            });
+        }
+        match current.as_mut() {
+            Some((_, world)) => replay_one(engine, world, &swap, &mut report),
+            None => report.skipped.push((swap, SkipReason::MissingState)),
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ids::TokenId;
+    use crate::test_utils::{Cp, reserves};
+    use crate::world::{BlockContext, WorldDiff};
+
+    fn observed(block: u64, log_index: u64, amt_in: u64, amt_out: U256) -> ObservedSwap {
+        ObservedSwap {
+            block,
+            log_index,
+            pool: PoolId(1),
+            dir: SwapDirection::new(TokenId(1), TokenId(2)).unwrap(),
+            amt_in: U256::from(amt_in),
+            amt_out,
+        }
+    }
+
+    fn chain_swaps(truth: &Cp, mut st: (U256, U256)) -> Vec<ObservedSwap> {
+        let dir = SwapDirection::new(TokenId(1), TokenId(2)).unwrap();
+        let ctx = BlockContext::default();
+        (0..4u64)
+            .map(|i| {
+                let out = truth
+                    .swap(&mut st, &ctx, dir, U256::from(1_000 * (i + 1)))
+                    .unwrap();
+                observed(10 + i / 2, i, 1_000 * (i + 1), out)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matching_model_replays_exactly() {
+        let truth = Cp::new(1, 1, 2).with_fee(30);
+        let swaps = chain_swaps(&truth, reserves(1_000_000, 1_000_000));
+
+        let mut pools = HashMap::new();
+        pools.insert(PoolId(1), Cp::new(1, 1, 2).with_fee(30));
+        let mut world = World::default();
+        world
+            .pool_states
+            .insert(PoolId(1), reserves(1_000_000, 1_000_000));
+
+        let mut rev = swaps.clone();
+        rev.reverse();
+        let report = replay(&Engine::new(&pools), &mut world, &rev);
+        let dev = report.pools[&PoolId(1)];
+        assert_eq!(dev.swaps, 4);
+        assert_eq!(dev.exact, 4);
+        assert_eq!(dev.max_dev_bps, 0);
+        assert!(report.skipped.is_empty());
+    }
+
+    #[test]
+    fn fee_bug_shows_up_as_deviation() {
+        let truth = Cp::new(1, 1, 2).with_fee(30);
+        let swaps = chain_swaps(&truth, reserves(1_000_000, 1_000_000));
+
+        let mut pools = HashMap::new();
+        pools.insert(PoolId(1), Cp::new(1, 1, 2));
+        let mut base = World::default();
+        base.pool_states
+            .insert(PoolId(1), reserves(1_000_000, 1_000_000));
+        let mut tl = Timeline::new();
+        tl.insert_snapshot(9, base);
+        tl.insert_diff(10, WorldDiff::default());
+
+        let mut with_unknown: Vec<_> = swaps.into_iter().filter(|s| s.block == 10).collect();
+        with_unknown.push(ObservedSwap {
+            pool: PoolId(2),
+            ..observed(10, 9, 1, U256::from(1u64))
+        });
+        let report = replay_timeline(&Engine::new(&pools), &tl, &with_unknown);
+        let dev = report.pools[&PoolId(1)];
+        assert_eq!((dev.swaps, dev.exact), (2, 0));
+        assert!((29..=31).contains(&dev.max_dev_bps), "{dev:?}");
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].1, SkipReason::MissingPool);
+        assert_eq!(report.worst_pools(5)[0].0, PoolId(1));
+        assert_eq!(deviation_bps(U256::from(100u64), U256::from(99u64)), 100);
+    }
+}
@@ -0,0 +1,258 @@
+//! Work done once after startup, so the first quote costs what later ones
+//! do: a compact graph, lookup tables over it, and a pass over the hot code.
+
+use crate::{
+    graph::{AMMGraph, NodeKind},
+    ids::{PoolId, TokenId},
+    pool::Pool,
+    router::Router,
+};
+use alloy_primitives::U256;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+
+/// Tokens [`RouteIndex::build`] treats as connectors.
+pub const WARM_UP_CONNECTORS: usize = 8;
+
+#[derive(Clone, Debug, Default)]
+pub struct RouteIndex {
+    /// Pools trading each pair, keyed smaller token first.
+    pub pairs: HashMap<(TokenId, TokenId), Vec<PoolId>>,
+    /// The tokens in most pools, most first.
+    pub connectors: Vec<TokenId>,
+    /// The connectors each token shares a pool with.
+    pub connector_adjacency: HashMap<TokenId, Vec<TokenId>>,
+}
+
+impl RouteIndex {
+    pub fn build(graph: &AMMGraph, connectors: usize) -> Self {
+        let mut pairs: HashMap<(TokenId, TokenId), Vec<PoolId>> = HashMap::new();
+        let mut degree: HashMap<TokenId, usize> = HashMap::new();
+        for (&pid, &pix) in &graph.pool_idx {
+            let tokens: BTreeSet<TokenId> = graph
+                .g
+                .neighbors_undirected(pix)
+                .filter_map(|n| match graph.g[n] {
+                    NodeKind::Token(t) => Some(t),
+                    NodeKind::Pool(_) => None,
+                })
+                .collect();
+            for &t in &tokens {
+                *degree.entry(t).or_default() += 1;
+            }
+            for (i, &a) in tokens.iter().enumerate() {
+                for &b in tokens.iter().skip(i + 1) {
+                    pairs.entry((a, b)).or_default().push(pid);
+                }
+            }
+        }
+        for pools in pairs.values_mut() {
+            pools.sort();
+        }
+
+        let mut ranked: Vec<_> = degree.into_iter().collect();
+        ranked.sort_by_key(|&(t, d)| (Reverse(d), t));
+        let connectors: Vec<TokenId> = ranked
+            .into_iter()
+            .take(connectors)
+            .map(|(t, _)| t)
+            .collect();
+        let mut connector_adjacency: HashMap<TokenId, Vec<TokenId>> = HashMap::new();
+        for &(a, b) in pairs.keys() {
+            for (t, other) in [(a, b), (b, a)] {
+                if connectors.contains(&other) {
+                    connector_adjacency.entry(t).or_default().push(other);
+                }
+            }
+        }
+        for adj in connector_adjacency.values_mut() {
+            adj.sort();
+        }
+        Self {
+            pairs,
+            connectors,
+            connector_adjacency,
+        }
+    }
+
+    pub fn pools_between(&self, a: TokenId, b: TokenId) -> &[PoolId] {
+        self.pairs
+            .get(&(a.min(b), a.max(b)))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct WarmUpReport {
+    /// Each stage and how long it took, in the order they ran.
+    pub stages: Vec<(&'static str, Duration)>,
+    /// Connector pairs quoted to prime the hot path.
+    pub primed: usize,
+    /// Hop results in the router's memo once priming is done.
+    pub cached: usize,
+}
+
+impl WarmUpReport {
+    pub fn total(&self) -> Duration {
+        self.stages.iter().map(|(_, d)| *d).sum()
+    }
+}
+
+impl AMMGraph {
+    /// A copy with nodes renumbered densely, tokens then pools in id order,
+    /// so removed nodes leave no holes and iteration order is stable.
+    pub fn frozen(&self) -> AMMGraph {
+        let mut out = AMMGraph::new();
+        let mut tokens: Vec<_> = self.token_idx.keys().copied().collect();
+        tokens.sort();
+        for t in tokens {
+            out.add_token(t);
+        }
+        let mut pools: Vec<_> = self.pool_idx.iter().map(|(&p, &ix)| (p, ix)).collect();
+        pools.sort_by_key(|&(p, _)| p);
+        for (pid, pix) in pools {
+            out.add_pool(pid);
+            let mut ins: Vec<_> =
+                self.token_ids(self.g.neighbors_directed(pix, petgraph::Incoming));
+            let mut outs: Vec<_> =
+                self.token_ids(self.g.neighbors_directed(pix, petgraph::Outgoing));
+            ins.sort();
+            outs.sort();
+            for t in ins {
+                out.connect_token_to_pool(t, pid);
+            }
+            for t in outs {
+                out.connect_pool_to_token(pid, t);
+            }
+        }
+        out
+    }
+
+    fn token_ids(&self, nodes: impl Iterator<Item = petgraph::graph::NodeIndex>) -> Vec<TokenId> {
+        nodes
+            .filter_map(|n| match self.g[n] {
+                NodeKind::Token(t) => Some(t),
+                NodeKind::Pool(_) => None,
+            })
+            .collect()
+    }
+}
+
+impl<P: Pool> Router<P> {
+    /// Freezes the graph, builds the [`RouteIndex`], and quotes one whole
+    /// token (by registry decimals, 18 if unknown) each way between every
+    /// pair of connectors, timing each stage. The quotes fill the router's
+    /// memo, so unit-price quotes between connectors are served from it
+    /// until their pools move. Adding a pool drops the index until the next
+    /// warm-up.
+    pub fn warm_up(&mut self) -> WarmUpReport {
+        let mut report = WarmUpReport::default();
+        let mut stage = |name, started: Instant| report.stages.push((name, started.elapsed()));
+
+        let t = Instant::now();
+        let frozen = self.graph().frozen();
+        self.set_graph(frozen);
+        stage("freeze_graph", t);
+
+        let t = Instant::now();
+        let index = RouteIndex::build(self.graph(), WARM_UP_CONNECTORS);
+        stage("route_index", t);
+
+        let t = Instant::now();
+        let mut primed = 0;
+        for (i, &a) in index.connectors.iter().enumerate() {
+            for &b in &index.connectors[i + 1..] {
+                self.quote(a, b, self.whole_token(a));
+                self.quote(b, a, self.whole_token(b));
+                primed += 1;
+            }
+        }
+        stage("prime", t);
+
+        self.set_index(Some(index));
+        report.primed = primed;
+        report.cached = self.memo().stats().entries;
+        report
+    }
+
+    /// One whole `t`, the amount unit prices are quoted in.
+    pub fn whole_token(&self, t: TokenId) -> U256 {
+        let decimals = self.registry().token(t).map_or(18, |m| m.decimals);
+        U256::from(10u64).pow(U256::from(decimals))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::Registry;
+    use crate::test_utils::{Cp, reserves};
+    use crate::world::World;
+
+    #[test]
+    fn warm_up_freezes_the_graph_and_indexes_pairs_and_connectors() {
+        let mut graph = AMMGraph::new();
+        let mut pools = HashMap::new();
+        let mut world = World::default();
+        // Token 1 is in the most pools; 5 is only reachable through it.
+        for (id, t0, t1) in [
+            (1, 1, 2),
+            (2, 1, 3),
+            (3, 2, 3),
+            (4, 1, 2),
+            (5, 1, 5),
+            (6, 4, 9),
+        ] {
+            pools.insert(PoolId(id), Cp::new(id, t0, t1));
+            graph.connect_bidirectional_pair(PoolId(id), TokenId(t0), TokenId(t1));
+            world.set_pool_state(PoolId(id), reserves(1_000_000, 1_000_000));
+        }
+        let graph = graph.filter_pools(|p| p != PoolId(6));
+        let mut router = Router::new(Registry::default(), graph, pools, world);
+        let before = router.quote(TokenId(5), TokenId(3), U256::from(1_000u64));
+
+        let report = router.warm_up();
+        let names: Vec<_> = report.stages.iter().map(|(n, _)| *n).collect();
+        assert_eq!(names, ["freeze_graph", "route_index", "prime"]);
+        assert!(report.total() >= report.stages[0].1);
+
+        let g = router.graph();
+        assert_eq!(g.g.node_count(), g.token_idx.len() + g.pool_idx.len());
+        assert_eq!(
+            router.quote(TokenId(5), TokenId(3), U256::from(1_000u64)),
+            before
+        );
+
+        let index = router.index().unwrap();
+        assert_eq!(
+            index.pools_between(TokenId(2), TokenId(1)),
+            [PoolId(1), PoolId(4)]
+        );
+        assert!(index.pools_between(TokenId(2), TokenId(5)).is_empty());
+        assert_eq!(index.connectors[0], TokenId(1));
+        assert_eq!(index.connector_adjacency[&TokenId(5)], [TokenId(1)]);
+        assert_eq!(
+            report.primed,
+            index.connectors.len() * (index.connectors.len() - 1) / 2
+        );
+
+        // Unit-price quotes between connectors now come from the memo.
+        assert!(report.cached > 0);
+        let unit = router.whole_token(TokenId(1));
+        let cold = Router::new(
+            Registry::default(),
+            router.graph().clone(),
+            router.pools().clone(),
+            router.world().clone(),
+        );
+        assert_eq!(
+            router.quote(TokenId(1), TokenId(2), unit),
+            cold.quote(TokenId(1), TokenId(2), unit)
+        );
+        let misses = router.memo().stats().misses;
+        router.quote(TokenId(1), TokenId(2), unit);
+        assert_eq!(router.memo().stats().misses, misses);
+        assert!(cold.memo().stats().misses > 0);
+    }
+}
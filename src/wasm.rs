@@ -0,0 +1,199 @@
+use crate::{
+    arb::{ScanConfig, Scanner},
+    engine::{Engine, Path},
+    graph::AMMGraph,
+    ids::{PoolId, SwapDirection, TokenId},
+    registry::Registry,
+    univ2::{UniV2Pool, UniV2State, pools_from_registry},
+    wire::RouteDto,
+    world::{BlockContext, World, WorldDiff},
+};
+use alloy_primitives::U256;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Quoter {
+    pools: HashMap<PoolId, UniV2Pool>,
+    graph: AMMGraph,
+    world: World<UniV2State>,
+}
+
+fn parse_amount(s: &str) -> Result<U256, String> {
+    s.parse().map_err(|_| format!("invalid amount {s}"))
+}
+
+fn pair(token0: TokenId, token1: TokenId) -> Result<SwapDirection, String> {
+    SwapDirection::new(token0, token1).ok_or_else(|| format!("pool pairs {token0} with itself"))
+}
+
+#[wasm_bindgen]
+impl Quoter {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[wasm_bindgen(js_name = fromRegistry)]
+    pub fn from_registry(json: &str) -> Result<Quoter, String> {
+        let reg: Registry = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let mut metas: Vec<_> = reg.pool_meta.iter().collect();
+        metas.sort_by_key(|&(pid, _)| *pid);
+        for (pid, meta) in metas {
+            pair(meta.token0, meta.token1).map_err(|e| format!("pool {pid}: {e}"))?;
+        }
+        let (pools, graph) = pools_from_registry(&reg);
+        Ok(Self {
+            pools,
+            graph,
+            world: World::default(),
+        })
+    }
+
+    #[wasm_bindgen(js_name = addPool)]
+    pub fn add_pool(
+        &mut self,
+        id: u64,
+        token0: u32,
+        token1: u32,
+        fee_bps: u32,
+    ) -> Result<(), String> {
+        let (pid, t0, t1) = (PoolId(id), TokenId(token0), TokenId(token1));
+        pair(t0, t1)?;
+        self.graph.connect_bidirectional_pair(pid, t0, t1);
+        self.pools
+            .insert(pid, UniV2Pool::new(pid, t0, t1).with_fee_bps(fee_bps));
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setReserves)]
+    pub fn set_reserves(&mut self, id: u64, reserve0: &str, reserve1: &str) -> Result<(), String> {
+        let st = UniV2State::new(parse_amount(reserve0)?, parse_amount(reserve1)?);
+        self.world.set_pool_state(PoolId(id), st);
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setBlock)]
+    pub fn set_block(&mut self, number: u64, timestamp: u64) {
+        self.world.block = BlockContext {
+            number,
+            timestamp,
+            ..self.world.block
+        };
+    }
+
+    /// Applies a JSON-encoded `WorldDiff` of reserve updates.
+    #[wasm_bindgen(js_name = applyUpdate)]
+    pub fn apply_update(&mut self, json: &str) -> Result<(), String> {
+        let diff: WorldDiff<UniV2State> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        self.world.apply(diff);
+        Ok(())
+    }
+
+    /// Best output amount as a decimal string, or `"0"` when no route exists.
+    pub fn quote(
+        &self,
+        from: u32,
+        to: u32,
+        amount: &str,
+        max_hops: usize,
+    ) -> Result<String, String> {
+        Ok(self
+            .best(from, to, amount, max_hops)?
+            .and_then(|p| p.steps.last().map(|s| s.amt_out))
+            .unwrap_or_default()
+            .to_string())
+    }
+
+    /// Best route as a JSON-encoded [`RouteDto`], or `null` when no route
+    /// exists.
+    pub fn route(
+        &self,
+        from: u32,
+        to: u32,
+        amount: &str,
+        max_hops: usize,
+    ) -> Result<String, String> {
+        let route = self
+            .best(from, to, amount, max_hops)?
+            .map(|p| RouteDto::from(&p));
+        serde_json::to_string(&route).map_err(|e| e.to_string())
+    }
+
+    fn best(
+        &self,
+        from: u32,
+        to: u32,
+        amount: &str,
+        max_hops: usize,
+    ) -> Result<Option<Path>, String> {
+        let amt_in = parse_amount(amount)?;
+        let engine = Engine::new(&self.pools);
+        let scanner = Scanner::new(&engine, &self.graph).with_config(ScanConfig {
+            max_hops,
+            ..ScanConfig::default()
+        });
+        Ok(scanner.best_route(&self.world, TokenId(from), TokenId(to), amt_in))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quoter() -> Quoter {
+        let mut q = Quoter::new();
+        q.add_pool(1, 1, 2, 30).unwrap();
+        q.add_pool(2, 2, 3, 30).unwrap();
+        q.set_reserves(1, "1000000", "2000000").unwrap();
+        q.set_reserves(2, "2000000", "2000000").unwrap();
+        q
+    }
+
+    #[test]
+    fn quotes_multi_hop_routes() {
+        let q = quoter();
+        let out: U256 = q.quote(1, 3, "1000", 2).unwrap().parse().unwrap();
+        assert!(out > U256::ZERO);
+        assert_eq!(q.quote(1, 3, "1000", 1).unwrap(), "0");
+
+        let route: RouteDto = serde_json::from_str(&q.route(1, 3, "1000", 2).unwrap()).unwrap();
+        assert_eq!(route.steps.len(), 2);
+        assert_eq!(route.amount_out, out);
+    }
+
+    #[test]
+    fn streamed_updates_move_quotes() {
+        let mut q = quoter();
+        let before = q.quote(1, 2, "1000", 1).unwrap();
+        q.apply_update(r#"{"block":{"number":5,"timestamp":0,"basefee":0},"pool_states":{"1":{"reserve0":"0xf4240","reserve1":"0x3d0900"}}}"#)
+            .unwrap();
+        assert_eq!(q.world.block.number, 5);
+        assert_ne!(q.quote(1, 2, "1000", 1).unwrap(), before);
+        assert!(q.quote(1, 2, "many", 1).is_err());
+    }
+
+    #[test]
+    fn degenerate_pairs_are_errors_not_traps() {
+        let mut q = quoter();
+        assert!(q.add_pool(3, 4, 4, 30).is_err());
+        assert!(!q.pools.contains_key(&PoolId(3)));
+
+        let mut reg = Registry::default();
+        reg.upsert_pool(
+            PoolId(9),
+            crate::registry::PoolMeta {
+                address: alloy_primitives::Address::repeat_byte(9),
+                kind: crate::registry::PoolKind::UniV2,
+                token0: TokenId(1),
+                token1: TokenId(1),
+                fee: 3_000,
+            },
+        );
+        let err = Quoter::from_registry(&serde_json::to_string(&reg).unwrap())
+            .err()
+            .unwrap();
+        assert!(err.contains("pool 9"), "{err}");
+    }
+}
@@ -0,0 +1,151 @@
+//! The JSON shape of quotes and routes shared by the HTTP server, the wasm
+//! quoter and external consumers. It mirrors the gRPC messages in
+//! `proto/wayfinder.proto`: amounts are base-10 strings of 256-bit unsigned
+//! integers, and token and pool ids are numbers.
+//!
+//! Responses carry [`WIRE_VERSION`]. Within a version fields are only added,
+//! never renamed, retyped or removed, so readers should ignore fields they do
+//! not know.
+
+use crate::{
+    engine::{HopFailure, Path},
+    ids::{PoolId, TokenId},
+};
+use alloy_primitives::U256;
+use serde::{Deserialize, Serialize};
+
+pub const WIRE_VERSION: u32 = 1;
+
+/// A quote or route query. Tokens are ids, addresses or symbols as
+/// [`Registry::resolve_token`](crate::registry::Registry::resolve_token)
+/// accepts them; `amount` is in raw units of `from`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuoteRequest {
+    pub from: String,
+    pub to: String,
+    pub amount: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_hops: Option<usize>,
+    /// How many routes to return, best first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepDto {
+    pub pool: PoolId,
+    pub token_in: TokenId,
+    pub token_out: TokenId,
+    #[serde(with = "decimal")]
+    pub amount_in: U256,
+    #[serde(with = "decimal")]
+    pub amount_out: U256,
+    /// Set on the hop that broke the route.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<HopFailure>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteDto {
+    #[serde(with = "decimal")]
+    pub amount_in: U256,
+    #[serde(with = "decimal")]
+    pub amount_out: U256,
+    pub steps: Vec<StepDto>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuoteResponse {
+    pub version: u32,
+    pub block: u64,
+    pub token_in: TokenId,
+    pub token_out: TokenId,
+    pub route: RouteDto,
+}
+
+impl From<&Path> for RouteDto {
+    fn from(path: &Path) -> Self {
+        Self {
+            amount_in: path.steps.first().map(|s| s.amt_in).unwrap_or_default(),
+            amount_out: path.steps.last().map(|s| s.amt_out).unwrap_or_default(),
+            steps: path
+                .steps
+                .iter()
+                .map(|s| StepDto {
+                    pool: s.pool,
+                    token_in: s.from,
+                    token_out: s.to,
+                    amount_in: s.amt_in,
+                    amount_out: s.amt_out,
+                    failure: s.failure,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl QuoteResponse {
+    pub fn new(block: u64, token_in: TokenId, token_out: TokenId, path: &Path) -> Self {
+        Self {
+            version: WIRE_VERSION,
+            block,
+            token_in,
+            token_out,
+            route: path.into(),
+        }
+    }
+}
+
+mod decimal {
+    use alloy_primitives::U256;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(v: &U256, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(v)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<U256, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(d)?;
+        s.parse()
+            .map_err(|_| D::Error::custom(format!("invalid amount {s}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use crate::test_utils::{Cp, hop, reserves};
+    use crate::world::World;
+    use std::collections::HashMap;
+
+    #[test]
+    fn quotes_round_trip_through_the_documented_shape() {
+        let pools = HashMap::from([(PoolId(1), Cp::new(1, 1, 2)), (PoolId(2), Cp::new(2, 2, 3))]);
+        let mut world = World::default();
+        world.set_pool_state(PoolId(1), reserves(1_000_000, 2_000_000));
+        world.set_pool_state(PoolId(2), reserves(2_000_000, 0));
+        let path = Engine::new(&pools).simulate_chained(
+            &world,
+            &[hop(1, 1, 2), hop(2, 2, 3)],
+            U256::from(1_000u64),
+        );
+
+        let quote = QuoteResponse::new(7, TokenId(1), TokenId(3), &path);
+        let json = serde_json::to_value(&quote).unwrap();
+        assert_eq!(json["version"], 1);
+        assert_eq!(json["route"]["amount_in"], "1000");
+        assert_eq!(json["route"]["steps"][0]["amount_out"], "1998");
+        assert_eq!(json["route"]["steps"][0]["pool"], 1);
+        assert!(json["route"]["steps"][0].get("failure").is_none());
+        assert_eq!(json["route"]["steps"][1]["failure"], "ZeroLiquidity");
+        assert_eq!(
+            serde_json::from_value::<QuoteResponse>(json).unwrap(),
+            quote
+        );
+
+        let req: QuoteRequest =
+            serde_json::from_str(r#"{"from":"WETH","to":"3","amount":"10","extra":1}"#).unwrap();
+        assert_eq!((req.max_hops, req.top), (None, None));
+    }
+}
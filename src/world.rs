@@ -1,7 +1,533 @@
-use crate::ids::PoolId;
-use std::collections::HashMap;
+use crate::ids::{AccountId, PoolId, TokenId};
+use crate::telemetry;
+use alloy_primitives::U256;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Source of [`World::version`]s, shared by every world in the process so
+/// two worlds never give different states the same version.
+static NEXT_VERSION: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct BlockContext {
+    pub number: u64,
+    pub timestamp: u64,
+    pub basefee: u64,
+}
 
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct World<S> {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub block: BlockContext,
     pub pool_states: HashMap<PoolId, S>,
+    pub holdings: HashMap<AccountId, HashMap<TokenId, U256>>,
+    /// Written as `(owner, spender, amounts)` entries, since JSON map keys
+    /// must be strings.
+    #[cfg_attr(feature = "serde", serde(with = "allowance_entries"))]
+    pub allowances: HashMap<(AccountId, AccountId), HashMap<TokenId, U256>>,
+    /// Bumped on every pool state change made through `World` methods, from
+    /// a counter shared by all worlds in the process.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub version: u64,
+    /// `version` at each pool's last change; pools written directly into
+    /// `pool_states` have none and are never memoized.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pool_versions: HashMap<PoolId, u64>,
+}
+
+#[cfg(feature = "serde")]
+mod allowance_entries {
+    use super::*;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    type Allowances = HashMap<(AccountId, AccountId), HashMap<TokenId, U256>>;
+
+    pub fn serialize<Z: Serializer>(allowances: &Allowances, ser: Z) -> Result<Z::Ok, Z::Error> {
+        let mut entries: Vec<_> = allowances
+            .iter()
+            .map(|(&(owner, spender), amounts)| (owner, spender, amounts))
+            .collect();
+        entries.sort_unstable_by_key(|&(owner, spender, _)| (owner, spender));
+        entries.serialize(ser)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<Allowances, D::Error> {
+        let entries = Vec::<(AccountId, AccountId, HashMap<TokenId, U256>)>::deserialize(de)?;
+        Ok(entries
+            .into_iter()
+            .map(|(owner, spender, amounts)| ((owner, spender), amounts))
+            .collect())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HoldingDelta {
+    pub before: U256,
+    pub after: U256,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WorldDelta {
+    pub added_pools: Vec<PoolId>,
+    pub removed_pools: Vec<PoolId>,
+    pub changed_pools: Vec<PoolId>,
+    pub holdings: Vec<(AccountId, TokenId, HoldingDelta)>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WorldStats {
+    pub pools: usize,
+    pub pool_capacity: usize,
+    pub pool_state_bytes: usize,
+    pub accounts: usize,
+}
+
+impl WorldDelta {
+    pub fn is_empty(&self) -> bool {
+        self.added_pools.is_empty()
+            && self.removed_pools.is_empty()
+            && self.changed_pools.is_empty()
+            && self.holdings.is_empty()
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorldDiff<S> {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub block: Option<BlockContext>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pool_states: HashMap<PoolId, S>,
+}
+
+impl<S> Default for WorldDiff<S> {
+    fn default() -> Self {
+        Self {
+            block: None,
+            pool_states: HashMap::new(),
+        }
+    }
+}
+
+impl<S> WorldDiff<S> {
+    pub fn set_pool_state(&mut self, pid: PoolId, st: S) {
+        self.pool_states.insert(pid, st);
+    }
+
+    pub fn merge(&mut self, later: WorldDiff<S>) {
+        self.block = later.block.or(self.block);
+        self.pool_states.extend(later.pool_states);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pool_states.is_empty()
+    }
+}
+
+pub trait StateView<S> {
+    fn block(&self) -> BlockContext;
+    fn pool_state(&self, pid: PoolId) -> Option<&S>;
+
+    /// Identifies the current state of `pid` for caching; `None` if the
+    /// view cannot vouch for it.
+    fn pool_version(&self, _pid: PoolId) -> Option<u64> {
+        None
+    }
+}
+
+impl<S> StateView<S> for World<S> {
+    fn block(&self) -> BlockContext {
+        self.block
+    }
+
+    fn pool_state(&self, pid: PoolId) -> Option<&S> {
+        self.pool_states.get(&pid)
+    }
+
+    fn pool_version(&self, pid: PoolId) -> Option<u64> {
+        self.pool_versions.get(&pid).copied()
+    }
+}
+
+pub struct WorldOverlay<'a, S> {
+    pub base: &'a World<S>,
+    pub pending: WorldDiff<S>,
+}
+
+impl<S> World<S> {
+    pub fn with_overlay(&self, pending: WorldDiff<S>) -> WorldOverlay<'_, S> {
+        WorldOverlay {
+            base: self,
+            pending,
+        }
+    }
+
+    pub fn apply(&mut self, diff: WorldDiff<S>) {
+        if let Some(block) = diff.block {
+            self.block = block;
+            telemetry::world_synced(&block);
+        }
+        for (pid, st) in diff.pool_states {
+            self.set_pool_state(pid, st);
+        }
+    }
+
+    pub fn set_pool_state(&mut self, pid: PoolId, st: S) {
+        self.pool_states.insert(pid, st);
+        self.touch(pid);
+    }
+
+    /// Marks `pid` as changed; call after mutating `pool_states` directly.
+    pub fn touch(&mut self, pid: PoolId) {
+        self.version = NEXT_VERSION.fetch_add(1, Ordering::Relaxed) + 1;
+        self.pool_versions.insert(pid, self.version);
+    }
+
+    /// Replaces every pool version with a fresh one, oldest first. Versions
+    /// loaded from elsewhere were drawn from another process's counter, so
+    /// loaders call this before the world meets a memo.
+    pub fn restamp(&mut self) {
+        let mut pools: Vec<_> = self
+            .pool_versions
+            .iter()
+            .map(|(&pid, &v)| (v, pid))
+            .collect();
+        pools.sort_unstable();
+        for (_, pid) in pools {
+            self.touch(pid);
+        }
+    }
+
+    pub fn prune<F: FnMut(PoolId, &S) -> bool>(&mut self, mut drop: F) -> Vec<PoolId> {
+        let mut dropped = Vec::new();
+        self.pool_states.retain(|&pid, st| {
+            let d = drop(pid, st);
+            if d {
+                dropped.push(pid);
+            }
+            !d
+        });
+        for pid in &dropped {
+            self.pool_versions.remove(pid);
+        }
+        self.pool_states.shrink_to_fit();
+        dropped
+    }
+
+    pub fn stats(&self) -> WorldStats {
+        self.stats_with(|_| 0)
+    }
+
+    pub fn stats_with<F: Fn(&S) -> usize>(&self, heap_bytes: F) -> WorldStats {
+        let slot = std::mem::size_of::<(PoolId, S)>();
+        WorldStats {
+            pools: self.pool_states.len(),
+            pool_capacity: self.pool_states.capacity(),
+            pool_state_bytes: self.pool_states.capacity() * slot
+                + self.pool_states.values().map(heap_bytes).sum::<usize>(),
+            accounts: self.holdings.len(),
+        }
+    }
+
+    pub fn holding(&self, account: AccountId, t: TokenId) -> U256 {
+        self.holdings
+            .get(&account)
+            .and_then(|h| h.get(&t))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn set_holding(&mut self, account: AccountId, t: TokenId, amt: U256) {
+        self.holdings.entry(account).or_default().insert(t, amt);
+    }
+
+    pub fn credit(&mut self, account: AccountId, t: TokenId, amt: U256) {
+        let bal = self
+            .holdings
+            .entry(account)
+            .or_default()
+            .entry(t)
+            .or_default();
+        *bal = bal.saturating_add(amt);
+    }
+
+    pub fn debit(&mut self, account: AccountId, t: TokenId, amt: U256) -> bool {
+        let Some(bal) = self.holdings.get_mut(&account).and_then(|h| h.get_mut(&t)) else {
+            return amt.is_zero();
+        };
+        match bal.checked_sub(amt) {
+            Some(rest) => {
+                *bal = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn allowance(&self, owner: AccountId, spender: AccountId, t: TokenId) -> U256 {
+        self.allowances
+            .get(&(owner, spender))
+            .and_then(|a| a.get(&t))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn approve(&mut self, owner: AccountId, spender: AccountId, t: TokenId, amt: U256) {
+        self.allowances
+            .entry((owner, spender))
+            .or_default()
+            .insert(t, amt);
+    }
+
+    pub fn spend_allowance(
+        &mut self,
+        owner: AccountId,
+        spender: AccountId,
+        t: TokenId,
+        amt: U256,
+    ) -> bool {
+        let current = self.allowance(owner, spender, t);
+        if current == U256::MAX {
+            return true;
+        }
+        match current.checked_sub(amt) {
+            Some(rest) => {
+                self.approve(owner, spender, t, rest);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn transfer(&mut self, from: AccountId, to: AccountId, t: TokenId, amt: U256) -> bool {
+        if !self.debit(from, t, amt) {
+            return false;
+        }
+        self.credit(to, t, amt);
+        true
+    }
+}
+
+impl<S: PartialEq> World<S> {
+    pub fn diff(&self, other: &World<S>) -> WorldDelta {
+        let mut delta = WorldDelta::default();
+
+        for (&pid, st) in &self.pool_states {
+            match other.pool_states.get(&pid) {
+                None => delta.removed_pools.push(pid),
+                Some(o) if o != st => delta.changed_pools.push(pid),
+                Some(_) => {}
+            }
+        }
+        delta.added_pools.extend(
+            other
+                .pool_states
+                .keys()
+                .filter(|pid| !self.pool_states.contains_key(pid)),
+        );
+
+        let keys: HashSet<(AccountId, TokenId)> = self
+            .holdings
+            .iter()
+            .chain(other.holdings.iter())
+            .flat_map(|(&a, h)| h.keys().map(move |&t| (a, t)))
+            .collect();
+        for (a, t) in keys {
+            let (before, after) = (self.holding(a, t), other.holding(a, t));
+            if before != after {
+                delta.holdings.push((a, t, HoldingDelta { before, after }));
+            }
+        }
+
+        delta.added_pools.sort();
+        delta.removed_pools.sort();
+        delta.changed_pools.sort();
+        delta.holdings.sort_by_key(|(a, t, _)| (*a, *t));
+        delta
+    }
+}
+
+impl<S> WorldOverlay<'_, S> {
+    pub fn is_shadowed(&self, pid: PoolId) -> bool {
+        self.pending.pool_states.contains_key(&pid)
+    }
+}
+
+impl<S: Clone> WorldOverlay<'_, S> {
+    pub fn materialize(&self) -> World<S> {
+        let mut world = self.base.clone();
+        world.apply(self.pending.clone());
+        world
+    }
+}
+
+impl<S> StateView<S> for WorldOverlay<'_, S> {
+    fn block(&self) -> BlockContext {
+        self.pending.block.unwrap_or(self.base.block)
+    }
+
+    fn pool_state(&self, pid: PoolId) -> Option<&S> {
+        self.pending
+            .pool_states
+            .get(&pid)
+            .or_else(|| self.base.pool_states.get(&pid))
+    }
+
+    fn pool_version(&self, pid: PoolId) -> Option<u64> {
+        if self.is_shadowed(pid) {
+            return None;
+        }
+        self.base.pool_version(pid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlay_shadows_confirmed_state() {
+        let mut world = World::default();
+        world.pool_states.insert(PoolId(1), 10u64);
+        world.pool_states.insert(PoolId(2), 20u64);
+
+        let mut pending = WorldDiff::default();
+        pending.set_pool_state(PoolId(2), 25);
+        pending.set_pool_state(PoolId(3), 30);
+
+        let view = world.with_overlay(pending);
+        assert_eq!(view.pool_state(PoolId(1)), Some(&10));
+        assert_eq!(view.pool_state(PoolId(2)), Some(&25));
+        assert_eq!(view.pool_state(PoolId(3)), Some(&30));
+        assert!(view.is_shadowed(PoolId(2)));
+        assert!(!view.is_shadowed(PoolId(1)));
+
+        assert_eq!(world.pool_state(PoolId(2)), Some(&20), "base untouched");
+    }
+
+    #[test]
+    fn later_diffs_win_on_merge() {
+        let mut a = WorldDiff::default();
+        a.set_pool_state(PoolId(1), 1u64);
+        let mut b = WorldDiff::default();
+        b.set_pool_state(PoolId(1), 2u64);
+        a.merge(b);
+
+        let world = World::default().with_overlay(a).materialize();
+        assert_eq!(world.pool_states[&PoolId(1)], 2);
+    }
+
+    #[test]
+    fn prune_drops_matching_pools_and_shrinks() {
+        let mut w = World::default();
+        for i in 0..64u64 {
+            w.pool_states.insert(PoolId(i), i % 4);
+        }
+        let before = w.stats();
+
+        let mut dropped = w.prune(|_, &liq| liq == 0);
+        dropped.sort();
+        assert_eq!(dropped.len(), 16);
+        assert_eq!(dropped[0], PoolId(0));
+        assert!(w.pool_states.values().all(|&liq| liq != 0));
+
+        let after = w.stats();
+        assert_eq!(after.pools, 48);
+        assert!(after.pool_state_bytes <= before.pool_state_bytes);
+        assert_eq!(
+            w.stats_with(|_| 10).pool_state_bytes,
+            after.pool_state_bytes + 480
+        );
+    }
+
+    #[cfg(feature = "rkyv")]
+    #[test]
+    fn world_round_trips_through_rkyv() {
+        let mut w = World::default();
+        w.block.number = 7;
+        w.pool_states.insert(PoolId(1), 11u64);
+        w.set_holding(AccountId(1), TokenId(2), U256::from(3u64));
+        w.approve(AccountId(1), AccountId(2), TokenId(2), U256::MAX);
+
+        let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&w).unwrap();
+        let back: World<u64> = rkyv::from_bytes::<_, rkyv::rancor::Error>(&bytes).unwrap();
+        assert!(w.diff(&back).is_empty());
+        assert_eq!(back.block, w.block);
+        assert_eq!(back.allowances, w.allowances);
+    }
+
+    #[test]
+    fn debit_refuses_overdraft() {
+        let mut w: World<u64> = World::default();
+        let (alice, t) = (AccountId(1), TokenId(1));
+        assert!(w.debit(alice, t, U256::ZERO));
+        assert!(!w.debit(alice, t, U256::from(1u64)));
+
+        w.credit(alice, t, U256::from(5u64));
+        assert!(!w.debit(alice, t, U256::from(6u64)));
+        assert!(w.debit(alice, t, U256::from(5u64)));
+        assert_eq!(w.holding(alice, t), U256::ZERO);
+    }
+
+    #[test]
+    fn diff_reports_pool_and_holding_changes() {
+        let mut a = World::default();
+        a.pool_states.insert(PoolId(1), 1u64);
+        a.pool_states.insert(PoolId(2), 2u64);
+        a.pool_states.insert(PoolId(3), 3u64);
+        a.set_holding(AccountId(1), TokenId(1), U256::from(100u64));
+
+        let mut b = a.clone();
+        assert!(a.diff(&b).is_empty());
+
+        b.pool_states.insert(PoolId(2), 20);
+        b.pool_states.remove(&PoolId(3));
+        b.pool_states.insert(PoolId(4), 4);
+        assert!(b.transfer(AccountId(1), AccountId(2), TokenId(1), U256::from(60u64)));
+        b.credit(AccountId(1), TokenId(2), U256::from(7u64));
+
+        let d = a.diff(&b);
+        assert_eq!(d.changed_pools, vec![PoolId(2)]);
+        assert_eq!(d.removed_pools, vec![PoolId(3)]);
+        assert_eq!(d.added_pools, vec![PoolId(4)]);
+        assert_eq!(
+            d.holdings,
+            vec![
+                (
+                    AccountId(1),
+                    TokenId(1),
+                    HoldingDelta {
+                        before: U256::from(100u64),
+                        after: U256::from(40u64)
+                    }
+                ),
+                (
+                    AccountId(1),
+                    TokenId(2),
+                    HoldingDelta {
+                        before: U256::ZERO,
+                        after: U256::from(7u64)
+                    }
+                ),
+                (
+                    AccountId(2),
+                    TokenId(1),
+                    HoldingDelta {
+                        before: U256::ZERO,
+                        after: U256::from(60u64)
+                    }
+                ),
+            ]
+        );
+    }
 }